@@ -0,0 +1,397 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Seek, Write};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::validate::PREVIOUS_PASS;
+use crate::{
+    CellPayload, MigrationError, NotebookContent, NotebookResponse, ShaderLanguage, migrate_content,
+};
+use crate::{file_stem, slug};
+
+/// File extension of notebook archives
+pub const ARCHIVE_EXTENSION: &str = "senra";
+
+/// Version of the archive layout written by `NotebookArchive::write`
+pub const ARCHIVE_FORMAT_VERSION: u32 = 1;
+
+/// Largest uncompressed size of a single file of an archive
+pub const MAX_ARCHIVE_FILE_SIZE: u64 = 32 * 1024 * 1024;
+
+/// Largest uncompressed size of all the files of an archive read together
+pub const MAX_ARCHIVE_SIZE: u64 = 64 * 1024 * 1024;
+
+const MANIFEST_PATH: &str = "manifest.json";
+const CONTENT_PATH: &str = "notebook.json";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("Invalid archive: {0}")]
+    Zip(#[from] zip::result::ZipError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid JSON in {path}: {source}")]
+    Json {
+        path: String,
+        source: serde_json::Error,
+    },
+
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
+
+    #[error("Missing file in archive: {0}")]
+    MissingFile(String),
+
+    #[error("Unsupported archive format version: {0}")]
+    UnsupportedVersion(u32),
+
+    #[error("Missing data of resource {0}")]
+    MissingResourceData(i64),
+
+    #[error("File too large in archive: {0}")]
+    TooLarge(String),
+}
+
+/// Self-contained notebook, stored as a `.senra` zip archive
+///
+/// The archive holds a `manifest.json`, the content as `notebook.json`, each shader as a
+/// `.wgsl` or `.glsl` file and each resource as a raw file with an optional JSON metadata
+/// sidecar.
+#[derive(Debug, Clone)]
+pub struct NotebookArchive {
+    pub title: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub visibility: String,
+    pub content: NotebookContent,
+    pub shaders: Vec<ArchiveShader>,
+    pub resources: Vec<ArchiveResource>,
+}
+
+/// Shader stored in an archive, `id` being the id referenced by the content
+#[derive(Debug, Clone)]
+pub struct ArchiveShader {
+    pub id: i64,
+    pub name: String,
+    pub shader_type: String,
+    pub code: String,
+}
+
+/// Resource stored in an archive, `id` being the id referenced by the content
+#[derive(Debug, Clone)]
+pub struct ArchiveResource {
+    pub id: i64,
+    pub name: String,
+    pub resource_type: String,
+    pub data: Vec<u8>,
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Manifest {
+    format_version: u32,
+    title: String,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    visibility: String,
+    #[serde(default)]
+    shaders: Vec<ManifestShader>,
+    #[serde(default)]
+    resources: Vec<ManifestResource>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestShader {
+    id: i64,
+    name: String,
+    shader_type: String,
+    path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ManifestResource {
+    id: i64,
+    name: String,
+    resource_type: String,
+    path: String,
+    #[serde(default)]
+    metadata_path: Option<String>,
+}
+
+impl NotebookArchive {
+    /// Builds an archive from a notebook as returned by the API, along with the downloaded
+    /// resource blobs keyed by hash
+    pub fn from_response(
+        notebook: &NotebookResponse,
+        blobs: &HashMap<String, Vec<u8>>,
+    ) -> Result<Self, ArchiveError> {
+        Ok(Self {
+            title: notebook.inner.title.clone(),
+            description: notebook.inner.description.clone(),
+            tags: notebook.inner.tags.clone(),
+            visibility: notebook.visibility.clone(),
+            content: parse_content(notebook.content.clone())?,
+            shaders: notebook
+                .shaders
+                .iter()
+                .map(|shader| ArchiveShader {
+                    id: shader.id,
+                    name: shader.name.clone(),
+                    shader_type: shader.shader_type.clone(),
+                    code: shader.code.clone(),
+                })
+                .collect(),
+            resources: notebook
+                .resources
+                .iter()
+                .map(|resource| {
+                    let data = blobs
+                        .get(&resource.data.hash)
+                        .ok_or(ArchiveError::MissingResourceData(resource.id))?;
+                    Ok(ArchiveResource {
+                        id: resource.id,
+                        name: resource.name.clone(),
+                        resource_type: resource.resource_type.clone(),
+                        data: data.clone(),
+                        metadata: resource.metadata.clone(),
+                    })
+                })
+                .collect::<Result<_, ArchiveError>>()?,
+        })
+    }
+
+    /// Reads an archive, upgrading its content to the current schema version
+    pub fn read<R: Read + Seek>(reader: R) -> Result<Self, ArchiveError> {
+        let mut zip = ZipArchive::new(reader)?;
+        // Uncompressed bytes left to read, archives being small but possibly inflating a lot
+        let mut budget = MAX_ARCHIVE_SIZE;
+
+        let manifest: Manifest = read_json(&mut zip, MANIFEST_PATH, &mut budget)?;
+        if manifest.format_version > ARCHIVE_FORMAT_VERSION {
+            return Err(ArchiveError::UnsupportedVersion(manifest.format_version));
+        }
+        let content = parse_content(read_json(&mut zip, CONTENT_PATH, &mut budget)?)?;
+
+        let mut shaders = Vec::new();
+        for shader in manifest.shaders {
+            let code = String::from_utf8(read_file(&mut zip, &shader.path, &mut budget)?).map_err(
+                |err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.utf8_error()),
+            )?;
+            shaders.push(ArchiveShader {
+                id: shader.id,
+                name: shader.name,
+                shader_type: shader.shader_type,
+                code,
+            });
+        }
+
+        let mut resources = Vec::new();
+        for resource in manifest.resources {
+            let metadata = match &resource.metadata_path {
+                Some(path) => Some(read_json(&mut zip, path, &mut budget)?),
+                None => None,
+            };
+            resources.push(ArchiveResource {
+                id: resource.id,
+                name: resource.name,
+                resource_type: resource.resource_type,
+                data: read_file(&mut zip, &resource.path, &mut budget)?,
+                metadata,
+            });
+        }
+
+        Ok(Self {
+            title: manifest.title,
+            description: manifest.description,
+            tags: manifest.tags,
+            visibility: manifest.visibility,
+            content,
+            shaders,
+            resources,
+        })
+    }
+
+    /// Reads an archive from memory
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ArchiveError> {
+        Self::read(Cursor::new(bytes))
+    }
+
+    /// Writes the archive, returning the underlying writer
+    pub fn write<W: Write + Seek>(&self, writer: W) -> Result<W, ArchiveError> {
+        let mut zip = ZipWriter::new(writer);
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+        let mut manifest = Manifest {
+            format_version: ARCHIVE_FORMAT_VERSION,
+            title: self.title.clone(),
+            description: self.description.clone(),
+            tags: self.tags.clone(),
+            visibility: self.visibility.clone(),
+            shaders: Vec::new(),
+            resources: Vec::new(),
+        };
+
+        for shader in &self.shaders {
+            let language: ShaderLanguage = shader.shader_type.parse().unwrap_or_default();
+            let path = format!(
+                "shaders/{}.{}",
+                file_stem(shader.id, &shader.name),
+                language.as_str()
+            );
+            zip.start_file(path.as_str(), options)?;
+            zip.write_all(shader.code.as_bytes())?;
+
+            manifest.shaders.push(ManifestShader {
+                id: shader.id,
+                name: shader.name.clone(),
+                shader_type: shader.shader_type.clone(),
+                path,
+            });
+        }
+
+        for resource in &self.resources {
+            let stem = file_stem(resource.id, &resource.name);
+            let path = format!("resources/{}", stem);
+            zip.start_file(path.as_str(), options)?;
+            zip.write_all(&resource.data)?;
+
+            let metadata_path = match &resource.metadata {
+                Some(metadata) => {
+                    let metadata_path = format!("resources/{}.meta.json", stem);
+                    zip.start_file(metadata_path.as_str(), options)?;
+                    zip.write_all(&to_json(&metadata_path, metadata)?)?;
+                    Some(metadata_path)
+                }
+                None => None,
+            };
+
+            manifest.resources.push(ManifestResource {
+                id: resource.id,
+                name: resource.name.clone(),
+                resource_type: resource.resource_type.clone(),
+                path,
+                metadata_path,
+            });
+        }
+
+        zip.start_file(CONTENT_PATH, options)?;
+        zip.write_all(&to_json(CONTENT_PATH, &self.content)?)?;
+
+        zip.start_file(MANIFEST_PATH, options)?;
+        zip.write_all(&to_json(MANIFEST_PATH, &manifest)?)?;
+
+        Ok(zip.finish()?)
+    }
+
+    /// Writes the archive to memory
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ArchiveError> {
+        Ok(self.write(Cursor::new(Vec::new()))?.into_inner())
+    }
+
+    /// Suggested file name for the archive
+    pub fn file_name(&self) -> String {
+        format!("{}.{}", slug(&self.title, "notebook"), ARCHIVE_EXTENSION)
+    }
+}
+
+impl NotebookContent {
+    /// Rewrites the shader and resource ids referenced by render cells, e.g. after importing
+    /// an archive into a server which assigned new ids
+    ///
+    /// Ids missing from the maps are kept as they are.
+    pub fn remap_ids(&mut self, shaders: &HashMap<i64, i64>, resources: &HashMap<i64, i64>) {
+        for cell in &mut self.cells {
+            let CellPayload::Render(config) = &mut cell.payload else {
+                continue;
+            };
+
+            for id in &mut config.shader_ids {
+                *id = shaders.get(id).copied().unwrap_or(*id);
+            }
+            for id in &mut config.resource_ids {
+                *id = resources.get(id).copied().unwrap_or(*id);
+            }
+
+            // Input textures can name a resource by its id
+            for pass in &mut config.pipeline.render_passes {
+                for input in &mut pass.input_textures {
+                    if input.texture_id == PREVIOUS_PASS {
+                        continue;
+                    }
+                    if let Some(id) = input
+                        .texture_id
+                        .parse::<i64>()
+                        .ok()
+                        .and_then(|id| resources.get(&id))
+                    {
+                        input.texture_id = id.to_string();
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn parse_content(mut content: Value) -> Result<NotebookContent, ArchiveError> {
+    migrate_content(&mut content)?;
+    serde_json::from_value(content).map_err(|source| ArchiveError::Json {
+        path: CONTENT_PATH.to_string(),
+        source,
+    })
+}
+
+/// Reads a file of the archive taking its size out of `budget`, refusing files larger than
+/// `MAX_ARCHIVE_FILE_SIZE` or the budget left
+fn read_file<R: Read + Seek>(
+    zip: &mut ZipArchive<R>,
+    path: &str,
+    budget: &mut u64,
+) -> Result<Vec<u8>, ArchiveError> {
+    let file = match zip.by_name(path) {
+        Ok(file) => file,
+        Err(zip::result::ZipError::FileNotFound) => {
+            return Err(ArchiveError::MissingFile(path.to_string()));
+        }
+        Err(err) => return Err(err.into()),
+    };
+
+    let limit = MAX_ARCHIVE_FILE_SIZE.min(*budget);
+    if file.size() > limit {
+        return Err(ArchiveError::TooLarge(path.to_string()));
+    }
+
+    // The declared size may be forged, so reading stops past the limit either way
+    let mut data = Vec::new();
+    file.take(limit + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > limit {
+        return Err(ArchiveError::TooLarge(path.to_string()));
+    }
+
+    *budget -= data.len() as u64;
+    Ok(data)
+}
+
+fn read_json<R: Read + Seek, T: serde::de::DeserializeOwned>(
+    zip: &mut ZipArchive<R>,
+    path: &str,
+    budget: &mut u64,
+) -> Result<T, ArchiveError> {
+    serde_json::from_slice(&read_file(zip, path, budget)?).map_err(|source| ArchiveError::Json {
+        path: path.to_string(),
+        source,
+    })
+}
+
+fn to_json<T: Serialize>(path: &str, value: &T) -> Result<Vec<u8>, ArchiveError> {
+    serde_json::to_vec_pretty(value).map_err(|source| ArchiveError::Json {
+        path: path.to_string(),
+        source,
+    })
+}
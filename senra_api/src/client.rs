@@ -8,18 +8,12 @@ pub struct Client {
     pub base_url: String,
     pub http_client: HttpClient,
     pub token: Option<String>,
+    pub encoding: Encoding,
 }
 
 impl Client {
     pub fn new(base_url: impl Into<String>) -> Self {
-        let mut headers = header::HeaderMap::new();
-        headers.insert(
-            header::CONTENT_TYPE,
-            header::HeaderValue::from_static("application/json"),
-        );
-
         let http_client = HttpClient::builder()
-            .default_headers(headers)
             .build()
             .expect("Failed to create HTTP client");
 
@@ -27,6 +21,7 @@ impl Client {
             base_url: base_url.into(),
             http_client,
             token: None,
+            encoding: Encoding::default(),
         }
     }
 
@@ -46,36 +41,117 @@ impl Client {
         self.token = None;
     }
 
+    /// Sets the encoding of request and response bodies
+    pub fn set_encoding(&mut self, encoding: Encoding) {
+        self.encoding = encoding;
+    }
+
     pub async fn request(&self, request: Request) -> Result<Response, ApiError> {
         Ok(match &request {
             Request::Auth(_) => self
                 .request_with::<TokenResponse>(request)
                 .await
                 .map(Response::Token)?,
-            Request::Login(_) | Request::Register(_) => self
+            Request::Login(_) => match self.request_with::<LoginResponse>(request).await? {
+                LoginResponse::Authenticated(auth) => Response::Auth(auth),
+                LoginResponse::Challenge(challenge) => Response::TwoFactorChallenge(challenge),
+            },
+            Request::LoginTwoFactor(_) | Request::Register(_) => self
                 .request_with::<AuthResponse>(request)
                 .await
                 .map(Response::Auth)?,
-            Request::GetSelf | Request::GetUser(_) | Request::EditUser(_) => self
+            Request::GetTwoFactor | Request::DisableTwoFactor(_) => self
+                .request_with::<TwoFactorStatusResponse>(request)
+                .await
+                .map(Response::TwoFactorStatus)?,
+            Request::SetupTwoFactor => self
+                .request_with::<TwoFactorSetupResponse>(request)
+                .await
+                .map(Response::TwoFactorSetup)?,
+            Request::EnableTwoFactor(_) | Request::RegenerateRecoveryCodes(_) => self
+                .request_with::<RecoveryCodesResponse>(request)
+                .await
+                .map(Response::RecoveryCodes)?,
+            Request::GetSelf | Request::GetUser(_) => self
                 .request_with::<UserResponse>(request)
                 .await
                 .map(Response::User)?,
-            Request::GetNotebookList { .. } => self
+            Request::EditUser(_) | Request::ResetAvatar => self
+                .request_with::<UserInfoResponse>(request)
+                .await
+                .map(Response::UserInfo)?,
+            Request::GetBlob(blob) => Response::Blob {
+                blob: blob.clone(),
+                data: self.download_blob(blob).await?,
+            },
+            Request::FollowUser(_) | Request::UnfollowUser(_) => self
+                .request_with::<FollowResponse>(request)
+                .await
+                .map(Response::Follow)?,
+            Request::GetNotebookList { .. } | Request::GetFollowingFeed { .. } => self
                 .request_with::<NotebookListResponse>(request)
                 .await
                 .map(Response::NotebookList)?,
             Request::CreateNotebook(_)
             | Request::GetNotebook(_)
             | Request::EditNotebook(_, _)
-            | Request::RemoveNotebook(_)
             | Request::UpdateShader { .. }
-            | Request::UpdateResource { .. }
-            | Request::LikeNotebook(_)
-            | Request::UnlikeNotebook(_) => self
+            | Request::UpdateResource { .. } => self
                 .request_with::<NotebookResponse>(request)
                 .await
                 .map(Response::Notebook)?,
-            Request::CreateComment(_, _) => self
+            Request::RemoveNotebook(_)
+            | Request::PurgeNotebook(_)
+            | Request::LikeNotebook(_)
+            | Request::UnlikeNotebook(_) => {
+                self.send(request).await?;
+                Response::Empty
+            }
+            Request::GetTrash { .. } => self
+                .request_with::<TrashListResponse>(request)
+                .await
+                .map(Response::Trash)?,
+            Request::RestoreNotebook(_) => self
+                .request_with::<NotebookInfo>(request)
+                .await
+                .map(Response::NotebookInfo)?,
+            Request::ExportShaders(_) => self
+                .request_with::<ShaderExportResponse>(request)
+                .await
+                .map(Response::ShaderExport)?,
+            Request::GetNotebookAnalytics { .. } => self
+                .request_with::<NotebookAnalyticsResponse>(request)
+                .await
+                .map(Response::NotebookAnalytics)?,
+            Request::GetLibraryModule { .. } => self
+                .request_with::<ShaderResponse>(request)
+                .await
+                .map(Response::Shader)?,
+            Request::CreateCollection(_)
+            | Request::GetCollection(_)
+            | Request::EditCollection(_, _)
+            | Request::AddToCollection(_, _)
+            | Request::RemoveFromCollection { .. } => self
+                .request_with::<CollectionResponse>(request)
+                .await
+                .map(Response::Collection)?,
+            Request::GetUserCollections { .. } => self
+                .request_with::<CollectionListResponse>(request)
+                .await
+                .map(Response::CollectionList)?,
+            Request::GetPackage(_) => self
+                .request_with::<PackageResponse>(request)
+                .await
+                .map(Response::Package)?,
+            Request::PublishPackage(_) | Request::GetPackageVersion { .. } => self
+                .request_with::<PackageVersionResponse>(request)
+                .await
+                .map(Response::PackageVersion)?,
+            Request::GetPackageUsedBy(_) => self
+                .request_with::<PackageUsedByResponse>(request)
+                .await
+                .map(Response::PackageUsedBy)?,
+            Request::CreateComment(_, _) | Request::EditComment { .. } => self
                 .request_with::<NotebookCommentResponse>(request)
                 .await
                 .map(Response::Comment)?,
@@ -83,10 +159,40 @@ impl Client {
                 .request_with::<NotebookCommentListResponse>(request)
                 .await
                 .map(Response::CommentList)?,
+            Request::GetNotifications { .. } => self
+                .request_with::<NotificationListResponse>(request)
+                .await
+                .map(Response::NotificationList)?,
+            Request::MarkNotificationsRead(_) => self
+                .request_with::<UnreadNotificationsResponse>(request)
+                .await
+                .map(Response::UnreadNotifications)?,
         })
     }
 
+    /// Downloads the bytes of a blob referenced by a response
+    pub async fn download_blob(&self, blob: &BlobRef) -> Result<Vec<u8>, ApiError> {
+        let response = self
+            .http_client
+            .get(format!("{}{}", self.base_url, blob.url))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(Self::response_error(response).await);
+        }
+
+        Ok(response.bytes().await?.to_vec())
+    }
+
     pub async fn request_with<T: DeserializeOwned>(&self, request: Request) -> Result<T, ApiError> {
+        let bytes = self.send(request).await?.bytes().await?;
+
+        self.encoding.decode(&bytes)
+    }
+
+    /// Sends a request, failing unless the response is successful
+    async fn send(&self, request: Request) -> Result<reqwest::Response, ApiError> {
         let endpoint: Endpoint = request.try_into()?;
         let url = format!("{}{}", self.base_url, endpoint.path);
 
@@ -112,10 +218,13 @@ impl Client {
             });
 
         let request_builder = if let Some(body) = endpoint.body {
-            request_builder.json(&body)
+            request_builder
+                .header(header::CONTENT_TYPE, self.encoding.mime_type())
+                .body(self.encoding.encode(&body)?)
         } else {
             request_builder
         };
+        let request_builder = request_builder.header(header::ACCEPT, self.encoding.mime_type());
 
         let request_builder = if let Some(token) = &self.token {
             request_builder.header(header::AUTHORIZATION, format!("Bearer {}", token))
@@ -126,14 +235,62 @@ impl Client {
         let response = request_builder.send().await?;
 
         if !response.status().is_success() {
-            return Err(ApiError::HttpError(format!(
-                "HTTP error: {}",
-                response.status()
-            )));
+            return Err(Self::response_error(response).await);
         }
 
-        let json = response.json::<serde_json::Value>().await?;
+        Ok(response)
+    }
+
+    /// Error of a failed response, with the message and request id the server answered with
+    async fn response_error(response: reqwest::Response) -> ApiError {
+        let status = response.status();
+        let header_id = response
+            .headers()
+            .get(REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let retry_after = (status == reqwest::StatusCode::TOO_MANY_REQUESTS).then(|| {
+            response
+                .headers()
+                .get(header::RETRY_AFTER)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1)
+        });
+        let encoding = response
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(Encoding::from_content_type);
 
-        Ok(serde_json::from_value(json)?)
+        let body = match (encoding, response.bytes().await) {
+            (Some(encoding), Ok(bytes)) => encoding.decode::<ErrorBody>(&bytes).ok(),
+            _ => None,
+        };
+        let (message, body_id) = match body {
+            Some(body) => (body.error, body.request_id),
+            None => (
+                status
+                    .canonical_reason()
+                    .unwrap_or("Unknown error")
+                    .to_string(),
+                None,
+            ),
+        };
+
+        let request_id = header_id.or(body_id);
+
+        match retry_after {
+            Some(retry_after) => ApiError::RateLimited {
+                retry_after,
+                message,
+                request_id,
+            },
+            None => ApiError::ResponseError {
+                status: status.as_u16(),
+                message,
+                request_id,
+            },
+        }
     }
 }
@@ -1,4 +1,4 @@
-use js_sys::{Promise, Uint8Array};
+use js_sys::Promise;
 use wasm_bindgen::prelude::*;
 
 use super::*;
@@ -31,9 +31,41 @@ impl JsUserInfoResponse {
         self.inner.email.clone()
     }
 
+    /// URL of the avatar relative to the API base URL
     #[wasm_bindgen(getter)]
-    pub fn avatar(&self) -> Uint8Array {
-        Uint8Array::from(self.inner.avatar.as_slice())
+    pub fn avatar(&self) -> Option<String> {
+        self.inner.avatar.as_ref().map(|avatar| avatar.url.clone())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn follower_count(&self) -> u32 {
+        self.inner.follower_count as u32
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn following_count(&self) -> u32 {
+        self.inner.following_count as u32
+    }
+}
+
+/// Second step a login resolves to when the account has two-factor authentication enabled,
+/// answered with `JsClient.login_two_factor`
+#[wasm_bindgen]
+pub struct JsTwoFactorChallenge {
+    inner: TwoFactorChallengeResponse,
+}
+
+#[wasm_bindgen]
+impl JsTwoFactorChallenge {
+    #[wasm_bindgen(getter)]
+    pub fn challenge(&self) -> String {
+        self.inner.challenge.clone()
+    }
+
+    /// Seconds the challenge can be answered in
+    #[wasm_bindgen(getter)]
+    pub fn expires_in(&self) -> u32 {
+        self.inner.expires_in as u32
     }
 }
 
@@ -76,6 +108,31 @@ impl JsClient {
         let storage = self.storage.clone();
         wasm_bindgen_futures::future_to_promise(async move {
             let request = Request::Login(LoginRequest { username, password });
+            let result = client.request_with::<LoginResponse>(request).await;
+            match result {
+                Ok(LoginResponse::Authenticated(AuthResponse { token, user })) => {
+                    if let Some(storage) = &storage {
+                        let _ = storage.set_item("token", &token);
+                    }
+                    client.set_token(token);
+                    let js_user = JsUserInfoResponse { inner: user };
+                    Ok(JsValue::from(js_user))
+                }
+                Ok(LoginResponse::Challenge(challenge)) => {
+                    Ok(JsValue::from(JsTwoFactorChallenge { inner: challenge }))
+                }
+                Err(err) => Err(err.into()),
+            }
+        })
+    }
+
+    /// Answers the challenge of a login with a code of the authenticator app or a recovery code
+    #[wasm_bindgen]
+    pub fn login_two_factor(&mut self, challenge: String, code: String) -> Promise {
+        let mut client = self.inner.clone();
+        let storage = self.storage.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let request = Request::LoginTwoFactor(TwoFactorLoginRequest { challenge, code });
             let result = client.request_with::<AuthResponse>(request).await;
             match result {
                 Ok(AuthResponse { token, user }) => {
@@ -91,6 +148,58 @@ impl JsClient {
         })
     }
 
+    /// Starts enrolling the signed in user, resolving to the secret, its provisioning URI and
+    /// the SVG QR code of the URI
+    #[wasm_bindgen]
+    pub fn setup_two_factor(&self) -> Promise {
+        let client = self.inner.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let setup = client
+                .request_with::<TwoFactorSetupResponse>(Request::SetupTwoFactor)
+                .await?;
+            Ok(serde_wasm_bindgen::to_value(&setup)?)
+        })
+    }
+
+    /// Confirms the enrollment with a first code, resolving to the recovery codes
+    #[wasm_bindgen]
+    pub fn enable_two_factor(&self, code: String) -> Promise {
+        let client = self.inner.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let request = Request::EnableTwoFactor(TwoFactorCodeRequest { code });
+            let codes = client
+                .request_with::<RecoveryCodesResponse>(request)
+                .await?;
+            Ok(serde_wasm_bindgen::to_value(&codes.recovery_codes)?)
+        })
+    }
+
+    #[wasm_bindgen]
+    pub fn disable_two_factor(&self, code: String) -> Promise {
+        let client = self.inner.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let request = Request::DisableTwoFactor(TwoFactorCodeRequest { code });
+            client
+                .request_with::<TwoFactorStatusResponse>(request)
+                .await?;
+            Ok(JsValue::from(true))
+        })
+    }
+
+    /// Replaces the recovery codes, confirmed by a code or a recovery code, resolving to the
+    /// new ones
+    #[wasm_bindgen]
+    pub fn regenerate_recovery_codes(&self, code: String) -> Promise {
+        let client = self.inner.clone();
+        wasm_bindgen_futures::future_to_promise(async move {
+            let request = Request::RegenerateRecoveryCodes(TwoFactorCodeRequest { code });
+            let codes = client
+                .request_with::<RecoveryCodesResponse>(request)
+                .await?;
+            Ok(serde_wasm_bindgen::to_value(&codes.recovery_codes)?)
+        })
+    }
+
     #[wasm_bindgen]
     pub fn register(&mut self, username: String, email: String, password: String) -> Promise {
         let mut client = self.inner.clone();
@@ -0,0 +1,75 @@
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+
+use super::ApiError;
+
+/// Format of request and response bodies, negotiated with `Content-Type` and `Accept`.
+/// Byte payloads are base64 strings in JSON and native byte strings in MessagePack
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Encoding {
+    #[default]
+    Json,
+    MessagePack,
+}
+
+impl Encoding {
+    pub const MESSAGE_PACK_MIME: &'static str = "application/msgpack";
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            Encoding::Json => "application/json",
+            Encoding::MessagePack => Self::MESSAGE_PACK_MIME,
+        }
+    }
+
+    /// Encoding of a `Content-Type` value, ignoring its parameters
+    pub fn from_content_type(value: &str) -> Option<Self> {
+        let mime = value.split(';').next().unwrap_or_default().trim();
+        if mime.eq_ignore_ascii_case("application/json") {
+            Some(Encoding::Json)
+        } else if mime.eq_ignore_ascii_case(Self::MESSAGE_PACK_MIME)
+            || mime.eq_ignore_ascii_case("application/x-msgpack")
+        {
+            Some(Encoding::MessagePack)
+        } else {
+            None
+        }
+    }
+
+    /// Encoding to answer with for an `Accept` value, JSON unless MessagePack is accepted
+    /// with a higher preference
+    pub fn from_accept(value: &str) -> Self {
+        let mut best = (Encoding::Json, 0.0);
+        for range in value.split(',') {
+            let mut parts = range.split(';');
+            let Some(encoding) = parts.next().and_then(Self::from_content_type) else {
+                continue;
+            };
+            let quality = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|quality| quality.parse::<f32>().ok())
+                .unwrap_or(1.0);
+            if quality > best.1 {
+                best = (encoding, quality);
+            }
+        }
+        best.0
+    }
+
+    pub fn encode<T: Serialize + ?Sized>(&self, value: &T) -> Result<Vec<u8>, ApiError> {
+        match self {
+            Encoding::Json => Ok(serde_json::to_vec(value)?),
+            Encoding::MessagePack => rmp_serde::to_vec_named(value)
+                .map_err(|err| ApiError::EncodingError(err.to_string())),
+        }
+    }
+
+    pub fn decode<T: DeserializeOwned>(&self, bytes: &[u8]) -> Result<T, ApiError> {
+        match self {
+            Encoding::Json => Ok(serde_json::from_slice(bytes)?),
+            Encoding::MessagePack => {
+                rmp_serde::from_slice(bytes).map_err(|err| ApiError::EncodingError(err.to_string()))
+            }
+        }
+    }
+}
@@ -0,0 +1,337 @@
+use std::collections::HashMap;
+
+use crate::shader::{naga_stage, write_module};
+use crate::{
+    BindGroupReflection, BindingReflection, BufferLayout, BufferMember, CellPayload, CompileTarget,
+    EntryPointReflection, FieldError, NotebookContent, PipelineReflection, RenderConfig,
+    ShaderExportFile, ShaderExportResponse, ShaderLanguage, ShaderReflection, ShaderSource,
+    ShaderStage, file_stem,
+};
+
+/// Shader of a notebook to export
+#[derive(Debug, Clone, Copy)]
+pub struct ExportShader<'a> {
+    pub id: i64,
+    pub name: &'a str,
+    pub source: ShaderSource<'a>,
+}
+
+type Compiled = Result<(naga::Module, naga::valid::ModuleInfo), String>;
+
+/// Cross-compiles the shaders of a notebook to `targets`, along with the bind group layout of
+/// every render cell pipeline
+///
+/// GLSL shaders are compiled once per stage the render cells bind them to, as a fragment
+/// shader when unbound. Shaders failing to compile are reported in `errors` and skipped.
+pub fn export_shaders(
+    content: &NotebookContent,
+    shaders: &[ExportShader<'_>],
+    targets: &[CompileTarget],
+) -> ShaderExportResponse {
+    let mut exporter = Exporter {
+        shaders,
+        modules: HashMap::new(),
+    };
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+
+    for shader in shaders {
+        let stages: Vec<Option<ShaderStage>> = match shader.source.language {
+            ShaderLanguage::Wgsl => vec![None],
+            ShaderLanguage::Glsl => {
+                let mut stages = bound_stages(content, shader.id);
+                if stages.is_empty() {
+                    stages.push(Some(ShaderStage::Fragment));
+                }
+                stages
+            }
+        };
+
+        for stage in stages {
+            let stem = match stage {
+                Some(stage) => format!(
+                    "{}.{}",
+                    file_stem(shader.id, shader.name),
+                    stage_name(stage)
+                ),
+                None => file_stem(shader.id, shader.name),
+            };
+
+            let (module, info) = match exporter.module(shader.id, stage) {
+                Some(Ok(compiled)) => compiled,
+                Some(Err(message)) => {
+                    errors.push(FieldError {
+                        path: format!("/shaders/{}", shader.id),
+                        message: message.clone(),
+                    });
+                    break;
+                }
+                None => continue,
+            };
+
+            for &target in targets {
+                // GLSL ES holds a single entry point per file
+                let entry_points: Vec<Option<&str>> = match target {
+                    CompileTarget::GlslEs => module
+                        .entry_points
+                        .iter()
+                        .map(|entry_point| Some(entry_point.name.as_str()))
+                        .collect(),
+                    _ => vec![None],
+                };
+
+                for entry_point in entry_points {
+                    let path = match entry_point {
+                        Some(entry_point) => format!(
+                            "{}/{}.{}.{}",
+                            target.as_str(),
+                            stem,
+                            entry_point,
+                            target.extension()
+                        ),
+                        None => format!("{}/{}.{}", target.as_str(), stem, target.extension()),
+                    };
+
+                    match write_module(module, info, target, entry_point) {
+                        Ok(output) => files.push(ShaderExportFile {
+                            shader_id: shader.id,
+                            target,
+                            path,
+                            output,
+                        }),
+                        Err(err) => errors.push(FieldError {
+                            path: format!("/shaders/{}/{}", shader.id, target.as_str()),
+                            message: format!("{}: {}", path, err),
+                        }),
+                    }
+                }
+            }
+        }
+    }
+
+    let pipelines = content
+        .cells
+        .iter()
+        .filter_map(|cell| match &cell.payload {
+            CellPayload::Render(config) => Some(exporter.reflect(&cell.id, config)),
+            _ => None,
+        })
+        .collect();
+
+    ShaderExportResponse {
+        files,
+        reflection: ShaderReflection { pipelines },
+        errors,
+    }
+}
+
+struct Exporter<'a, 'b> {
+    shaders: &'a [ExportShader<'b>],
+    /// Compiled modules by shader id and stage, WGSL modules being compiled once
+    modules: HashMap<(i64, Option<ShaderStage>), Compiled>,
+}
+
+impl Exporter<'_, '_> {
+    fn module(&mut self, id: i64, stage: Option<ShaderStage>) -> Option<&Compiled> {
+        let shader = self.shaders.iter().find(|shader| shader.id == id)?;
+        let stage = match shader.source.language {
+            ShaderLanguage::Wgsl => None,
+            ShaderLanguage::Glsl => Some(stage.unwrap_or(ShaderStage::Fragment)),
+        };
+
+        let compiled = self.modules.entry((id, stage)).or_insert_with(|| {
+            let module = shader
+                .source
+                .parse(naga_stage(&stage.unwrap_or(ShaderStage::Fragment)))
+                .map_err(|err| err.to_string())?;
+            let info = shader
+                .source
+                .validate_module(&module)
+                .map_err(|err| err.to_string())?;
+            Ok((module, info))
+        });
+        Some(compiled)
+    }
+
+    fn reflect(&mut self, cell_id: &str, config: &RenderConfig) -> PipelineReflection {
+        let mut entry_points = Vec::new();
+        // Bound entry points as (shader id, stage, index into the module entry points)
+        let mut bound = Vec::new();
+        for binding in &config.pipeline.shader_bindings {
+            let Some(&shader_id) = config.shader_ids.get(binding.shader_index) else {
+                continue;
+            };
+            entry_points.push(EntryPointReflection {
+                shader_id,
+                stage: binding.shader_stage,
+                entry_point: binding.entry_point.clone(),
+            });
+
+            if let Some(Ok((module, _))) = self.module(shader_id, Some(binding.shader_stage))
+                && let Some(index) = module.entry_points.iter().position(|entry_point| {
+                    entry_point.name == binding.entry_point
+                        && entry_point.stage == naga_stage(&binding.shader_stage)
+                })
+            {
+                bound.push((shader_id, binding.shader_stage, index));
+            }
+        }
+
+        let mut bind_groups: Vec<BindGroupReflection> = Vec::new();
+        for resource_binding in &config.pipeline.resource_bindings {
+            let mut reflection = BindingReflection {
+                binding: resource_binding.binding,
+                binding_type: resource_binding.binding_type.clone(),
+                resource_id: config
+                    .resource_ids
+                    .get(resource_binding.resource_index)
+                    .copied(),
+                name: None,
+                visibility: Vec::new(),
+                layout: None,
+            };
+
+            let slot = naga::ResourceBinding {
+                group: resource_binding.group,
+                binding: resource_binding.binding,
+            };
+            for &(shader_id, stage, index) in &bound {
+                let Some(Ok((module, info))) = self.module(shader_id, Some(stage)) else {
+                    continue;
+                };
+                let Some((handle, global)) = module
+                    .global_variables
+                    .iter()
+                    .find(|(_, global)| global.binding.as_ref() == Some(&slot))
+                else {
+                    continue;
+                };
+
+                if !info.get_entry_point(index)[handle].is_empty()
+                    && !reflection.visibility.contains(&stage)
+                {
+                    reflection.visibility.push(stage);
+                }
+                if reflection.name.is_none() {
+                    reflection.name = global.name.clone();
+                }
+                if reflection.layout.is_none() && global.space != naga::AddressSpace::Handle {
+                    reflection.layout = Some(buffer_layout(module, global.ty));
+                }
+            }
+
+            match bind_groups
+                .iter_mut()
+                .find(|group| group.group == resource_binding.group)
+            {
+                Some(group) => group.bindings.push(reflection),
+                None => bind_groups.push(BindGroupReflection {
+                    group: resource_binding.group,
+                    bindings: vec![reflection],
+                }),
+            }
+        }
+
+        bind_groups.sort_by_key(|group| group.group);
+        for group in &mut bind_groups {
+            group.bindings.sort_by_key(|binding| binding.binding);
+        }
+
+        PipelineReflection {
+            cell_id: cell_id.to_string(),
+            entry_points,
+            bind_groups,
+        }
+    }
+}
+
+/// Stages the render cells bind a shader to
+fn bound_stages(content: &NotebookContent, shader_id: i64) -> Vec<Option<ShaderStage>> {
+    let mut stages = Vec::new();
+    for cell in &content.cells {
+        let CellPayload::Render(config) = &cell.payload else {
+            continue;
+        };
+        for binding in &config.pipeline.shader_bindings {
+            let stage = Some(binding.shader_stage);
+            if config.shader_ids.get(binding.shader_index) == Some(&shader_id)
+                && !stages.contains(&stage)
+            {
+                stages.push(stage);
+            }
+        }
+    }
+    stages
+}
+
+fn stage_name(stage: ShaderStage) -> &'static str {
+    match stage {
+        ShaderStage::Vertex => "vertex",
+        ShaderStage::Fragment => "fragment",
+        ShaderStage::Compute => "compute",
+    }
+}
+
+fn buffer_layout(module: &naga::Module, ty: naga::Handle<naga::Type>) -> BufferLayout {
+    let members = match &module.types[ty].inner {
+        naga::TypeInner::Struct { members, .. } => members
+            .iter()
+            .map(|member| BufferMember {
+                name: member.name.clone().unwrap_or_default(),
+                type_name: type_name(module, member.ty),
+                offset: member.offset,
+                size: module.types[member.ty].inner.size(module.to_ctx()),
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    BufferLayout {
+        type_name: type_name(module, ty),
+        size: module.types[ty].inner.size(module.to_ctx()),
+        members,
+    }
+}
+
+/// WGSL spelling of a type
+fn type_name(module: &naga::Module, ty: naga::Handle<naga::Type>) -> String {
+    let ty = &module.types[ty];
+    if let Some(name) = &ty.name {
+        return name.clone();
+    }
+
+    match &ty.inner {
+        naga::TypeInner::Scalar(scalar) => scalar_name(scalar),
+        naga::TypeInner::Vector { size, scalar } => {
+            format!("vec{}<{}>", *size as u8, scalar_name(scalar))
+        }
+        naga::TypeInner::Matrix {
+            columns,
+            rows,
+            scalar,
+        } => format!(
+            "mat{}x{}<{}>",
+            *columns as u8,
+            *rows as u8,
+            scalar_name(scalar)
+        ),
+        naga::TypeInner::Atomic(scalar) => format!("atomic<{}>", scalar_name(scalar)),
+        naga::TypeInner::Array { base, size, .. } => match size {
+            naga::ArraySize::Constant(count) => {
+                format!("array<{}, {}>", type_name(module, *base), count)
+            }
+            naga::ArraySize::Dynamic => format!("array<{}>", type_name(module, *base)),
+        },
+        inner => format!("{:?}", inner),
+    }
+}
+
+fn scalar_name(scalar: &naga::Scalar) -> String {
+    match (scalar.kind, scalar.width) {
+        (naga::ScalarKind::Bool, _) => "bool".to_string(),
+        (naga::ScalarKind::Float, width) => format!("f{}", width * 8),
+        (naga::ScalarKind::Sint, width) => format!("i{}", width * 8),
+        (naga::ScalarKind::Uint, width) => format!("u{}", width * 8),
+        (kind, width) => format!("{:?}{}", kind, width * 8),
+    }
+}
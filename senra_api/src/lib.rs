@@ -1,27 +1,84 @@
+#[cfg(feature = "archive")]
+mod archive;
 mod client;
 #[cfg(target_arch = "wasm32")]
 mod client_wasm;
+mod encoding;
 mod endpoint;
+#[cfg(feature = "compile")]
+mod export;
+mod migration;
+mod package;
 mod payloads;
+mod preprocess;
+#[cfg(feature = "schema")]
+mod schema;
+mod shader;
+#[cfg(feature = "shadertoy")]
+mod shadertoy;
+mod validate;
 
 use http::Method;
 use serde::{Deserialize, Serialize};
-use serde_json::json;
 
+#[cfg(feature = "archive")]
+pub use archive::*;
 pub use client::*;
 #[cfg(target_arch = "wasm32")]
 pub use client_wasm::*;
+pub use encoding::*;
 pub use endpoint::*;
+#[cfg(feature = "compile")]
+pub use export::*;
+pub use migration::*;
+pub use package::*;
 pub use payloads::*;
+pub use preprocess::*;
+#[cfg(feature = "schema")]
+pub use schema::*;
+pub use shader::*;
+#[cfg(feature = "shadertoy")]
+pub use shadertoy::*;
+
+/// Header carrying the correlation id of a request, echoed back by the server
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Headers of rate limited responses: the size of the bucket, the requests left in it and the
+/// seconds until it is full again
+pub const RATE_LIMIT_LIMIT_HEADER: &str = "x-ratelimit-limit";
+pub const RATE_LIMIT_REMAINING_HEADER: &str = "x-ratelimit-remaining";
+pub const RATE_LIMIT_RESET_HEADER: &str = "x-ratelimit-reset";
 
 #[derive(Debug, thiserror::Error)]
 pub enum ApiError {
     #[error("HTTP error: {0}")]
     HttpError(String),
 
+    /// Error answered by the server, with the id to find the request in its logs
+    #[error(
+        "HTTP error {status}: {message}{}",
+        .request_id.as_ref().map(|id| format!(" (request id {})", id)).unwrap_or_default()
+    )]
+    ResponseError {
+        status: u16,
+        message: String,
+        request_id: Option<String>,
+    },
+
+    /// Too many requests were sent, or too many failed logins locked the account
+    #[error("Rate limited: {message}, retry in {retry_after} seconds")]
+    RateLimited {
+        retry_after: u64,
+        message: String,
+        request_id: Option<String>,
+    },
+
     #[error("Serialization error: {0}")]
     SerializationError(#[from] serde_json::Error),
 
+    #[error("Encoding error: {0}")]
+    EncodingError(String),
+
     #[error("Network error: {0}")]
     NetworkError(String),
 
@@ -29,6 +86,17 @@ pub enum ApiError {
     UnknownError(String),
 }
 
+impl ApiError {
+    /// Correlation id of the request the server failed, if the server answered
+    pub fn request_id(&self) -> Option<&str> {
+        match self {
+            ApiError::ResponseError { request_id, .. }
+            | ApiError::RateLimited { request_id, .. } => request_id.as_deref(),
+            _ => None,
+        }
+    }
+}
+
 impl From<reqwest::Error> for ApiError {
     fn from(err: reqwest::Error) -> Self {
         ApiError::NetworkError(err.to_string())
@@ -39,11 +107,28 @@ impl From<reqwest::Error> for ApiError {
 #[serde(tag = "type", content = "payload")]
 pub enum Request {
     Auth(AuthRequest),
+    /// Answered with `Response::Auth`, or `Response::TwoFactorChallenge` when the account has
+    /// two-factor authentication enabled
     Login(LoginRequest),
+    /// Second step of a login answering a `Response::TwoFactorChallenge`
+    LoginTwoFactor(TwoFactorLoginRequest),
     Register(RegisterRequest),
+    GetTwoFactor,
+    /// Generates a new secret, enabled once confirmed with `Request::EnableTwoFactor`
+    SetupTwoFactor,
+    EnableTwoFactor(TwoFactorCodeRequest),
+    DisableTwoFactor(TwoFactorCodeRequest),
+    RegenerateRecoveryCodes(TwoFactorCodeRequest),
     GetSelf,
     GetUser(u64),
     EditUser(EditUserRequest),
+    /// Replaces the avatar of the signed in user with their default one
+    ResetAvatar,
+    FollowUser(u64),
+    UnfollowUser(u64),
+
+    /// Downloads a blob, answered with `Response::Blob`
+    GetBlob(BlobRef),
 
     CreateNotebook(CreateNotebookRequest),
     GetNotebookList {
@@ -51,10 +136,53 @@ pub enum Request {
         limit: Option<u32>,
         category: Option<String>,
         search: Option<String>,
+        sort: Option<NotebookSort>,
+    },
+    GetFollowingFeed {
+        page: Option<u32>,
+        limit: Option<u32>,
     },
     GetNotebook(u64),
     EditNotebook(u64, EditNotebookRequest),
     RemoveNotebook(u64),
+    GetTrash {
+        page: Option<u32>,
+        per_page: Option<u32>,
+    },
+    RestoreNotebook(u64),
+    PurgeNotebook(u64),
+    ExportShaders(u64),
+    GetNotebookAnalytics {
+        id: u64,
+        days: Option<u32>,
+    },
+    GetLibraryModule {
+        notebook_id: u64,
+        name: String,
+    },
+
+    CreateCollection(CreateCollectionRequest),
+    GetCollection(u64),
+    EditCollection(u64, EditCollectionRequest),
+    /// Adds a notebook visible to the signed in user to one of their collections
+    AddToCollection(u64, AddCollectionNotebookRequest),
+    RemoveFromCollection {
+        collection_id: u64,
+        notebook_id: u64,
+    },
+    GetUserCollections {
+        user_id: u64,
+        page: Option<u32>,
+        limit: Option<u32>,
+    },
+
+    PublishPackage(PublishPackageRequest),
+    GetPackage(String),
+    GetPackageVersion {
+        name: String,
+        version: String,
+    },
+    GetPackageUsedBy(String),
 
     UpdateShader {
         notebook_id: i64,
@@ -64,6 +192,7 @@ pub enum Request {
     UpdateResource {
         notebook_id: i64,
         resource_id: i64,
+        #[serde(with = "payloads::bytes")]
         data: Vec<u8>,
         metadata: Option<serde_json::Value>,
     },
@@ -71,25 +200,68 @@ pub enum Request {
     LikeNotebook(u64),
     UnlikeNotebook(u64),
 
-    CreateComment(u64, String),
+    CreateComment(u64, CreateNotebookCommentRequest),
+    EditComment {
+        notebook_id: u64,
+        comment_id: u64,
+        content: String,
+    },
     GetCommentList {
+        id: u64,
+        page: Option<u32>,
+        limit: Option<u32>,
+    },
+
+    GetNotifications {
         page: Option<u32>,
         limit: Option<u32>,
+        unread: bool,
     },
+    MarkNotificationsRead(MarkNotificationsReadRequest),
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 pub enum Response {
+    /// Success of a request answered with an empty body
+    Empty,
     Token(TokenResponse),
     User(UserResponse),
+    UserInfo(UserInfoResponse),
     Auth(AuthResponse),
+    TwoFactorChallenge(TwoFactorChallengeResponse),
+    TwoFactorSetup(TwoFactorSetupResponse),
+    TwoFactorStatus(TwoFactorStatusResponse),
+    RecoveryCodes(RecoveryCodesResponse),
+    Follow(FollowResponse),
+    Blob {
+        blob: BlobRef,
+        #[serde(with = "payloads::bytes")]
+        data: Vec<u8>,
+    },
 
     Notebook(NotebookResponse),
     NotebookList(NotebookListResponse),
+    NotebookInfo(NotebookInfo),
+    Trash(TrashListResponse),
+    ShaderExport(ShaderExportResponse),
+    NotebookAnalytics(NotebookAnalyticsResponse),
+    Shader(ShaderResponse),
+
+    Collection(CollectionResponse),
+    CollectionList(CollectionListResponse),
+
+    Package(PackageResponse),
+    PackageVersion(PackageVersionResponse),
+    PackageUsedBy(PackageUsedByResponse),
 
     Comment(NotebookCommentResponse),
     CommentList(NotebookCommentListResponse),
+
+    /// Pushed over the WebSocket as notifications are created
+    Notification(NotificationResponse),
+    NotificationList(NotificationListResponse),
+    UnreadNotifications(UnreadNotificationsResponse),
 }
 
 impl TryFrom<Request> for Endpoint {
@@ -103,14 +275,35 @@ impl TryFrom<Request> for Endpoint {
             Request::Login(req) => Endpoint::new("/auth/login")
                 .with_method(Method::POST)
                 .with_body(req)?,
+            Request::LoginTwoFactor(req) => Endpoint::new("/auth/login/2fa")
+                .with_method(Method::POST)
+                .with_body(req)?,
             Request::Register(req) => Endpoint::new("/auth/register")
                 .with_method(Method::POST)
                 .with_body(req)?,
+            Request::GetTwoFactor => Endpoint::new("/auth/2fa"),
+            Request::SetupTwoFactor => Endpoint::new("/auth/2fa/setup").with_method(Method::POST),
+            Request::EnableTwoFactor(req) => Endpoint::new("/auth/2fa/enable")
+                .with_method(Method::POST)
+                .with_body(req)?,
+            Request::DisableTwoFactor(req) => Endpoint::new("/auth/2fa/disable")
+                .with_method(Method::POST)
+                .with_body(req)?,
+            Request::RegenerateRecoveryCodes(req) => Endpoint::new("/auth/2fa/recovery-codes")
+                .with_method(Method::POST)
+                .with_body(req)?,
             Request::GetSelf => Endpoint::new("/user"),
             Request::GetUser(id) => Endpoint::new("/user/{id}").with_param("id", id),
             Request::EditUser(req) => Endpoint::new("/user")
                 .with_method(Method::PATCH)
                 .with_body(req)?,
+            Request::ResetAvatar => Endpoint::new("/user/avatar").with_method(Method::DELETE),
+            Request::FollowUser(id) => Endpoint::new("/user/{id}/follow")
+                .with_method(Method::POST)
+                .with_param("id", id),
+            Request::UnfollowUser(id) => Endpoint::new("/user/{id}/follow")
+                .with_method(Method::DELETE)
+                .with_param("id", id),
 
             Request::CreateNotebook(req) => Endpoint::new("/notebooks")
                 .with_method(Method::POST)
@@ -120,6 +313,7 @@ impl TryFrom<Request> for Endpoint {
                 limit,
                 category,
                 search,
+                sort,
             } => {
                 let mut endpoint = Endpoint::new("/notebooks");
                 if let Some(page) = page {
@@ -134,6 +328,9 @@ impl TryFrom<Request> for Endpoint {
                 if let Some(search) = search {
                     endpoint = endpoint.with_query("search", search);
                 }
+                if let Some(sort) = sort {
+                    endpoint = endpoint.with_query("sort", sort.as_str());
+                }
                 endpoint
             }
             Request::GetNotebook(id) => Endpoint::new("/notebooks/{id}").with_param("id", id),
@@ -144,6 +341,95 @@ impl TryFrom<Request> for Endpoint {
             Request::RemoveNotebook(id) => Endpoint::new("/notebooks/{id}")
                 .with_method(Method::DELETE)
                 .with_param("id", id),
+            Request::GetFollowingFeed { page, limit } => {
+                let mut endpoint = Endpoint::new("/notebooks/following");
+                if let Some(page) = page {
+                    endpoint = endpoint.with_query("page", page);
+                }
+                if let Some(limit) = limit {
+                    endpoint = endpoint.with_query("per_page", limit);
+                }
+                endpoint
+            }
+            Request::GetTrash { page, per_page } => {
+                let mut endpoint = Endpoint::new("/user/trash");
+                if let Some(page) = page {
+                    endpoint = endpoint.with_query("page", page);
+                }
+                if let Some(per_page) = per_page {
+                    endpoint = endpoint.with_query("per_page", per_page);
+                }
+                endpoint
+            }
+            Request::RestoreNotebook(id) => Endpoint::new("/user/trash/{id}/restore")
+                .with_method(Method::POST)
+                .with_param("id", id),
+            Request::PurgeNotebook(id) => Endpoint::new("/user/trash/{id}")
+                .with_method(Method::DELETE)
+                .with_param("id", id),
+            Request::ExportShaders(id) => {
+                Endpoint::new("/notebooks/{id}/export/shaders").with_param("id", id)
+            }
+            Request::GetNotebookAnalytics { id, days } => {
+                let mut endpoint = Endpoint::new("/notebooks/{id}/analytics").with_param("id", id);
+                if let Some(days) = days {
+                    endpoint = endpoint.with_query("days", days);
+                }
+                endpoint
+            }
+            Request::GetLibraryModule { notebook_id, name } => {
+                Endpoint::new("/notebooks/{id}/modules/{name}")
+                    .with_param("id", notebook_id)
+                    .with_param("name", name)
+            }
+
+            Request::CreateCollection(req) => Endpoint::new("/collections")
+                .with_method(Method::POST)
+                .with_body(req)?,
+            Request::GetCollection(id) => Endpoint::new("/collections/{id}").with_param("id", id),
+            Request::EditCollection(id, req) => Endpoint::new("/collections/{id}")
+                .with_method(Method::PATCH)
+                .with_body(req)?
+                .with_param("id", id),
+            Request::AddToCollection(id, req) => Endpoint::new("/collections/{id}/notebooks")
+                .with_method(Method::POST)
+                .with_body(req)?
+                .with_param("id", id),
+            Request::RemoveFromCollection {
+                collection_id,
+                notebook_id,
+            } => Endpoint::new("/collections/{id}/notebooks/{notebook_id}")
+                .with_method(Method::DELETE)
+                .with_param("id", collection_id)
+                .with_param("notebook_id", notebook_id),
+            Request::GetUserCollections {
+                user_id,
+                page,
+                limit,
+            } => {
+                let mut endpoint =
+                    Endpoint::new("/user/{id}/collections").with_param("id", user_id);
+                if let Some(page) = page {
+                    endpoint = endpoint.with_query("page", page);
+                }
+                if let Some(limit) = limit {
+                    endpoint = endpoint.with_query("per_page", limit);
+                }
+                endpoint
+            }
+
+            Request::PublishPackage(req) => Endpoint::new("/packages")
+                .with_method(Method::POST)
+                .with_body(req)?,
+            Request::GetPackage(name) => Endpoint::new("/packages/{name}").with_param("name", name),
+            Request::GetPackageVersion { name, version } => {
+                Endpoint::new("/packages/{name}/versions/{version}")
+                    .with_param("name", name)
+                    .with_param("version", version)
+            }
+            Request::GetPackageUsedBy(name) => {
+                Endpoint::new("/packages/{name}/used-by").with_param("name", name)
+            }
 
             Request::LikeNotebook(id) => Endpoint::new("/notebooks/{id}/like")
                 .with_method(Method::POST)
@@ -152,12 +438,21 @@ impl TryFrom<Request> for Endpoint {
                 .with_method(Method::POST)
                 .with_param("id", id),
 
-            Request::CreateComment(id, content) => Endpoint::new("/notebooks/{id}/comments")
+            Request::CreateComment(id, req) => Endpoint::new("/notebooks/{id}/comments")
                 .with_method(Method::POST)
-                .with_body(json!({ "comment": content }))?
+                .with_body(req)?
                 .with_param("id", id),
-            Request::GetCommentList { page, limit } => {
-                let mut endpoint = Endpoint::new("/notebooks/{id}/comments");
+            Request::EditComment {
+                notebook_id,
+                comment_id,
+                content,
+            } => Endpoint::new("/notebooks/{id}/comments/{comment_id}")
+                .with_method(Method::PATCH)
+                .with_body(UpdateNotebookCommentRequest { content })?
+                .with_param("id", notebook_id)
+                .with_param("comment_id", comment_id),
+            Request::GetCommentList { id, page, limit } => {
+                let mut endpoint = Endpoint::new("/notebooks/{id}/comments").with_param("id", id);
                 if let Some(page) = page {
                     endpoint = endpoint.with_query("page", page);
                 }
@@ -167,7 +462,55 @@ impl TryFrom<Request> for Endpoint {
                 endpoint
             }
 
+            Request::GetNotifications {
+                page,
+                limit,
+                unread,
+            } => {
+                let mut endpoint = Endpoint::new("/notifications");
+                if let Some(page) = page {
+                    endpoint = endpoint.with_query("page", page);
+                }
+                if let Some(limit) = limit {
+                    endpoint = endpoint.with_query("per_page", limit);
+                }
+                if unread {
+                    endpoint = endpoint.with_query("unread", unread);
+                }
+                endpoint
+            }
+            Request::MarkNotificationsRead(req) => Endpoint::new("/notifications/read")
+                .with_method(Method::POST)
+                .with_body(req)?,
+
             _ => Err(ApiError::UnknownError("Invalid Http Endpoint".to_string()))?,
         })
     }
 }
+
+/// Stable, filesystem friendly file name for an archived or exported shader or resource
+#[cfg(any(feature = "archive", feature = "compile"))]
+pub(crate) fn file_stem(id: i64, name: &str) -> String {
+    format!("{}-{}", id, slug(name, "unnamed"))
+}
+
+#[cfg(any(feature = "archive", feature = "compile"))]
+pub(crate) fn slug(name: &str, fallback: &str) -> String {
+    let slug: String = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '.' || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let slug = slug.trim_matches(['.', '_']);
+
+    if slug.is_empty() {
+        fallback.to_string()
+    } else {
+        slug.to_string()
+    }
+}
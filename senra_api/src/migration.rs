@@ -0,0 +1,137 @@
+use serde_json::{Map, Value, json};
+
+/// Version written by documents following the current `NotebookContent` schema
+pub const CURRENT_CONTENT_VERSION: &str = "1.1";
+
+/// Version assumed for documents saved before the version field was populated
+pub const LEGACY_CONTENT_VERSION: &str = "0";
+
+#[derive(Debug, thiserror::Error)]
+pub enum MigrationError {
+    #[error("Notebook content must be a JSON object")]
+    NotAnObject,
+
+    #[error("Unknown notebook content version: {0}")]
+    UnknownVersion(String),
+
+    #[error("Invalid notebook content at {path}: {message}")]
+    InvalidContent { path: String, message: String },
+}
+
+/// A single upgrade step between two consecutive content versions
+pub struct ContentMigration {
+    pub from: &'static str,
+    pub to: &'static str,
+    pub description: &'static str,
+    pub apply: fn(&mut Map<String, Value>) -> Result<(), MigrationError>,
+}
+
+/// Registered migrations, ordered from the oldest to the newest version
+pub static CONTENT_MIGRATIONS: &[ContentMigration] = &[
+    ContentMigration {
+        from: LEGACY_CONTENT_VERSION,
+        to: "1.0",
+        description: "Fill in notebook metadata, cell ids and cell metadata",
+        apply: migrate_legacy_to_1_0,
+    },
+    ContentMigration {
+        from: "1.0",
+        to: "1.1",
+        description: "Store render cell configurations inline instead of as JSON strings",
+        apply: migrate_1_0_to_1_1,
+    },
+];
+
+/// Returns the schema version of a content document
+pub fn content_version(content: &Value) -> &str {
+    content
+        .get("version")
+        .and_then(Value::as_str)
+        .unwrap_or(LEGACY_CONTENT_VERSION)
+}
+
+/// Upgrades a content document step by step to `CURRENT_CONTENT_VERSION`
+///
+/// Returns whether the document was modified.
+pub fn migrate_content(content: &mut Value) -> Result<bool, MigrationError> {
+    let mut version = content_version(content).to_string();
+    let mut changed = false;
+
+    while version != CURRENT_CONTENT_VERSION {
+        let migration = CONTENT_MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or_else(|| MigrationError::UnknownVersion(version.clone()))?;
+
+        let document = content.as_object_mut().ok_or(MigrationError::NotAnObject)?;
+        (migration.apply)(document)?;
+        document.insert("version".to_string(), json!(migration.to));
+
+        version = migration.to.to_string();
+        changed = true;
+    }
+
+    Ok(changed)
+}
+
+fn cells_mut(
+    document: &mut Map<String, Value>,
+) -> Result<impl Iterator<Item = (usize, &mut Value)>, MigrationError> {
+    match document.get_mut("cells") {
+        Some(Value::Array(cells)) => Ok(cells.iter_mut().enumerate()),
+        _ => Err(MigrationError::InvalidContent {
+            path: "cells".to_string(),
+            message: "expected an array".to_string(),
+        }),
+    }
+}
+
+fn migrate_legacy_to_1_0(document: &mut Map<String, Value>) -> Result<(), MigrationError> {
+    document
+        .entry("cells")
+        .or_insert_with(|| Value::Array(Vec::new()));
+    if document.get("metadata").is_none_or(Value::is_null) {
+        document.insert("metadata".to_string(), json!({}));
+    }
+
+    for (index, cell) in cells_mut(document)? {
+        let Some(cell) = cell.as_object_mut() else {
+            return Err(MigrationError::InvalidContent {
+                path: format!("cells[{}]", index),
+                message: "expected an object".to_string(),
+            });
+        };
+
+        let id = match cell.get("id") {
+            Some(Value::String(id)) => id.clone(),
+            Some(Value::Number(id)) => id.to_string(),
+            _ => format!("cell-{}", index),
+        };
+        cell.insert("id".to_string(), json!(id));
+
+        if cell.get("metadata").is_none_or(Value::is_null) {
+            cell.insert("metadata".to_string(), json!({ "collapsed": false }));
+        }
+    }
+
+    Ok(())
+}
+
+fn migrate_1_0_to_1_1(document: &mut Map<String, Value>) -> Result<(), MigrationError> {
+    for (index, cell) in cells_mut(document)? {
+        if cell.get("cell_type").and_then(Value::as_str) != Some("render") {
+            continue;
+        }
+
+        if let Some(Value::String(encoded)) = cell.get("content") {
+            let config: Value =
+                serde_json::from_str(encoded).map_err(|err| MigrationError::InvalidContent {
+                    path: format!("cells[{}].content", index),
+                    message: err.to_string(),
+                })?;
+            cell["content"] = config;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,274 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+#[cfg(doc)]
+use crate::preprocess;
+use crate::preprocess::preprocess_with;
+use crate::{ImportError, PreprocessedShader, shader_imports};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PackageError {
+    #[error("Invalid version `{0}`, expected `MAJOR.MINOR.PATCH`")]
+    InvalidVersion(String),
+
+    #[error(
+        "Invalid package name `{0}`, expected lowercase letters, digits, `-` or `_` starting with a letter"
+    )]
+    InvalidName(String),
+
+    #[error("Package `{0}` is not published")]
+    NotFound(String),
+
+    #[error("Package `{name}` is required at incompatible versions {}", join_versions(.versions))]
+    Conflict {
+        name: String,
+        versions: Vec<Version>,
+    },
+}
+
+/// Semantic version of a published package, `MAJOR.MINOR.PATCH`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl Version {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+        }
+    }
+
+    /// Whether both versions may replace each other, sharing their major version, or their
+    /// minor version before `1.0.0`
+    pub fn is_compatible(&self, other: &Version) -> bool {
+        match self.major {
+            0 => other.major == 0 && self.minor == other.minor,
+            major => other.major == major,
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = PackageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || PackageError::InvalidVersion(s.to_string());
+        let mut parts = s.split('.').map(|part| {
+            // Leading zeros and signs would give several spellings of a version
+            let canonical = part == "0" || (!part.starts_with('0') && !part.is_empty());
+            match canonical && part.bytes().all(|b| b.is_ascii_digit()) {
+                true => part.parse::<u64>().map_err(|_| invalid()),
+                false => Err(invalid()),
+            }
+        });
+
+        let version = Version {
+            major: parts.next().ok_or_else(invalid)??,
+            minor: parts.next().ok_or_else(invalid)??,
+            patch: parts.next().ok_or_else(invalid)??,
+        };
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(version),
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Import of a published package version, written `"name@MAJOR.MINOR.PATCH"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackageImport<'a> {
+    pub name: &'a str,
+    pub version: Version,
+}
+
+impl<'a> PackageImport<'a> {
+    /// Parses an import name, returning `None` for imports of notebook or library modules
+    pub fn parse(import: &'a str) -> Option<Result<Self, PackageError>> {
+        let (name, version) = import.split_once('@')?;
+        Some(check_package_name(name).and_then(|_| {
+            Ok(Self {
+                name,
+                version: version.parse()?,
+            })
+        }))
+    }
+}
+
+impl fmt::Display for PackageImport<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.name, self.version)
+    }
+}
+
+/// Checks that a package name is made of lowercase letters, digits, `-` or `_`, starting with
+/// a letter
+pub fn check_package_name(name: &str) -> Result<(), PackageError> {
+    let valid = name.len() <= 64
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_');
+
+    match valid {
+        true => Ok(()),
+        false => Err(PackageError::InvalidName(name.to_string())),
+    }
+}
+
+/// Package versions imported by shader code, skipping malformed package imports
+pub fn package_imports(code: &str) -> Vec<PackageImport<'_>> {
+    shader_imports(code)
+        .into_iter()
+        .filter_map(|import| PackageImport::parse(import)?.ok())
+        .collect()
+}
+
+/// Published package version available to the resolution
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PackageSource<'a> {
+    pub name: &'a str,
+    pub version: Version,
+    pub code: &'a str,
+}
+
+/// Package version selected by a resolution
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedPackage {
+    pub version: Version,
+    pub code: String,
+}
+
+/// Versions of the packages a set of shaders depends on, directly or through other packages
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackageLock {
+    pub packages: BTreeMap<String, LockedPackage>,
+}
+
+impl PackageLock {
+    /// Resolves the package imports of `sources` against the `available` package versions
+    ///
+    /// Every version reachable from the imports is required and the highest required version
+    /// of each package is selected, so the outcome only depends on the versions the shaders
+    /// and packages pin, not on the order they are listed or on later publications. Required
+    /// versions of a package must be compatible with each other.
+    pub fn resolve(
+        sources: &[&str],
+        available: &[PackageSource<'_>],
+    ) -> Result<Self, PackageError> {
+        let mut required: BTreeMap<&str, BTreeSet<Version>> = BTreeMap::new();
+        let mut pending = Vec::new();
+        for code in sources {
+            pending.extend(parse_package_imports(code)?);
+        }
+
+        while let Some(import) = pending.pop() {
+            let source = available
+                .iter()
+                .find(|source| source.name == import.name && source.version == import.version)
+                .ok_or_else(|| PackageError::NotFound(import.to_string()))?;
+            if required
+                .entry(source.name)
+                .or_default()
+                .insert(source.version)
+            {
+                pending.extend(parse_package_imports(source.code)?);
+            }
+        }
+
+        let mut selected = BTreeMap::new();
+        for (&name, versions) in &required {
+            let (Some(lowest), Some(highest)) = (versions.first(), versions.last()) else {
+                continue;
+            };
+            if !lowest.is_compatible(highest) {
+                return Err(PackageError::Conflict {
+                    name: name.to_string(),
+                    versions: versions.iter().copied().collect(),
+                });
+            }
+            selected.insert(name, *highest);
+        }
+
+        let packages = available
+            .iter()
+            .filter(|source| selected.get(source.name) == Some(&source.version))
+            .map(|source| {
+                let package = LockedPackage {
+                    version: source.version,
+                    code: source.code.to_string(),
+                };
+                (source.name.to_string(), package)
+            })
+            .collect();
+
+        Ok(PackageLock { packages })
+    }
+
+    /// Expands the imports of the `module` shader code like [`preprocess`], package imports
+    /// being redirected to the selected versions so that a package required at several
+    /// versions is included once
+    pub fn preprocess<'a>(
+        &'a self,
+        module: &str,
+        code: &str,
+        resolve: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<PreprocessedShader, ImportError> {
+        preprocess_with(
+            module,
+            code,
+            |name| self.module(name).or_else(|| resolve(name)),
+            |import| self.locked_import(import),
+        )
+    }
+
+    /// Import name of the selected version of an imported package
+    pub fn locked_import(&self, import: &str) -> Option<String> {
+        let import = PackageImport::parse(import)?.ok()?;
+        let package = self.packages.get(import.name)?;
+        Some(format!("{}@{}", import.name, package.version))
+    }
+
+    /// Code of the package version imported as `import`, when selected by the resolution
+    pub fn module(&self, import: &str) -> Option<&str> {
+        let import = PackageImport::parse(import)?.ok()?;
+        self.packages
+            .get(import.name)
+            .filter(|package| package.version == import.version)
+            .map(|package| package.code.as_str())
+    }
+
+    /// Selected package versions, as `name@version` imports
+    pub fn imports(&self) -> impl Iterator<Item = PackageImport<'_>> {
+        self.packages.iter().map(|(name, package)| PackageImport {
+            name,
+            version: package.version,
+        })
+    }
+}
+
+fn parse_package_imports(code: &str) -> Result<Vec<PackageImport<'_>>, PackageError> {
+    shader_imports(code)
+        .into_iter()
+        .filter_map(PackageImport::parse)
+        .collect()
+}
+
+fn join_versions(versions: &[Version]) -> String {
+    versions
+        .iter()
+        .map(Version::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
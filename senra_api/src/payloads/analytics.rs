@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+/// Activity on a notebook during one UTC day
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DailyStatsResponse {
+    /// Day formatted as `YYYY-MM-DD`
+    pub day: String,
+    pub view_count: i64,
+    pub like_count: i64,
+    pub comment_count: i64,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReferrerResponse {
+    /// Host of the referring page, `None` for direct views
+    pub referrer: Option<String>,
+    pub view_count: i64,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotebookAnalyticsResponse {
+    pub notebook_id: i64,
+    /// One entry per day of the period, oldest first
+    pub daily: Vec<DailyStatsResponse>,
+    /// Referrers over the period, most views first
+    pub referrers: Vec<ReferrerResponse>,
+}
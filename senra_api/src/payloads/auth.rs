@@ -35,3 +35,62 @@ pub struct AuthResponse {
     pub user: UserInfoResponse,
     pub token: String,
 }
+
+/// Outcome of a login, a challenge to answer with a second factor when the account has
+/// two-factor authentication enabled
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Authenticated(AuthResponse),
+    Challenge(TwoFactorChallengeResponse),
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorChallengeResponse {
+    /// Token to send back along with the code, only good for the second step of the login
+    pub challenge: String,
+    /// Seconds the challenge can be answered in
+    pub expires_in: u64,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge: String,
+    /// Code of the authenticator app, or one of the recovery codes
+    pub code: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    /// Code of the authenticator app, or one of the recovery codes
+    pub code: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorSetupResponse {
+    /// Base32 encoded secret, for authenticator apps that can't scan the QR code
+    pub secret: String,
+    /// `otpauth://` URI of the secret
+    pub provisioning_uri: String,
+    /// SVG image of the QR code of the provisioning URI
+    pub qr_code: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    /// Single use codes standing in for the authenticator app, only ever shown once
+    pub recovery_codes: Vec<String>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TwoFactorStatusResponse {
+    pub enabled: bool,
+    pub recovery_codes_left: u32,
+}
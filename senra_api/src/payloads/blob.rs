@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// Content-addressed blob, downloadable from `url` relative to the API base URL
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BlobRef {
+    /// Lowercase hex SHA-256 of the blob bytes
+    pub hash: String,
+    pub url: String,
+}
+
+impl BlobRef {
+    pub fn new(hash: impl Into<String>) -> Self {
+        let hash = hash.into();
+        let url = format!("/blobs/{}", hash);
+        Self { hash, url }
+    }
+}
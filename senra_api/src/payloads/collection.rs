@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use super::notebook::NotebookPreviewResponse;
+use super::user::UserPreviewResponse;
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCollectionRequest {
+    pub title: String,
+    pub description: Option<String>,
+    /// Either `public` or `private`
+    pub visibility: String,
+    /// Notebooks of the collection in order
+    #[serde(default)]
+    pub notebook_ids: Vec<i64>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EditCollectionRequest {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub visibility: Option<String>,
+    /// Replaces the notebooks of the collection, also reordering them
+    #[serde(default)]
+    pub notebook_ids: Option<Vec<i64>>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddCollectionNotebookRequest {
+    pub notebook_id: i64,
+    /// Index to insert the notebook at, the end of the collection when omitted
+    #[serde(default)]
+    pub position: Option<u32>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionInfo {
+    pub id: i64,
+    pub title: String,
+    pub description: Option<String>,
+    pub visibility: String,
+    /// Number of notebooks of the collection visible to the requesting user
+    pub notebook_count: i64,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionResponse {
+    #[serde(flatten)]
+    pub inner: CollectionInfo,
+    pub author: UserPreviewResponse,
+    pub notebooks: Vec<NotebookPreviewResponse>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CollectionListResponse {
+    pub collections: Vec<CollectionInfo>,
+    pub total: i64,
+}
@@ -0,0 +1,163 @@
+use serde::{Deserialize, Serialize};
+
+use super::notebook_content::{Cell, CellPayload, NotebookContent, RenderConfig};
+
+/// Options of the embedded player, read from the query of `/embed/{id}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbedOptions {
+    /// Id of the render cell to play, the first render cell when unset
+    pub cell: Option<String>,
+    /// Whether the shader starts playing once loaded
+    pub autoplay: bool,
+    /// Whether the title, author and controls are shown over the player
+    pub ui: bool,
+    /// Seconds the shader clock starts at
+    pub time: f32,
+}
+
+impl Default for EmbedOptions {
+    fn default() -> Self {
+        Self {
+            cell: None,
+            autoplay: true,
+            ui: true,
+            time: 0.0,
+        }
+    }
+}
+
+impl EmbedOptions {
+    /// Parses `cell`, `autoplay`, `ui` and `t` out of a query string, with or without its
+    /// leading `?`. Unknown keys and invalid values are ignored
+    pub fn from_query(query: &str) -> Self {
+        let mut options = Self::default();
+        for pair in query.trim_start_matches('?').split('&') {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let value = percent_decode(value);
+            match key {
+                "cell" if !value.is_empty() => options.cell = Some(value),
+                "autoplay" => options.autoplay = parse_flag(&value).unwrap_or(options.autoplay),
+                "ui" => options.ui = parse_flag(&value).unwrap_or(options.ui),
+                "t" => {
+                    options.time = value
+                        .parse::<f32>()
+                        .ok()
+                        .filter(|time| time.is_finite() && *time >= 0.0)
+                        .unwrap_or(options.time)
+                }
+                _ => {}
+            }
+        }
+        options
+    }
+
+    /// Query string of the options differing from the defaults, empty if none do
+    pub fn to_query(&self) -> String {
+        let defaults = Self::default();
+        let mut pairs = Vec::new();
+        if let Some(cell) = &self.cell {
+            pairs.push(format!("cell={}", percent_encode(cell)));
+        }
+        if self.autoplay != defaults.autoplay {
+            pairs.push(format!("autoplay={}", self.autoplay as u8));
+        }
+        if self.ui != defaults.ui {
+            pairs.push(format!("ui={}", self.ui as u8));
+        }
+        if self.time != defaults.time {
+            pairs.push(format!("t={}", self.time));
+        }
+
+        if pairs.is_empty() {
+            String::new()
+        } else {
+            format!("?{}", pairs.join("&"))
+        }
+    }
+
+    /// Render cell of a notebook the options select, along with its configuration
+    pub fn render_cell<'a>(
+        &self,
+        content: &'a NotebookContent,
+    ) -> Option<(&'a Cell, &'a RenderConfig)> {
+        content.cells.iter().find_map(|cell| match &cell.payload {
+            CellPayload::Render(config) if self.cell.as_ref().is_none_or(|id| *id == cell.id) => {
+                Some((cell, config.as_ref()))
+            }
+            _ => None,
+        })
+    }
+}
+
+/// oEmbed response of a notebook, see <https://oembed.com>
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OEmbedResponse {
+    /// Always `rich`
+    #[serde(rename = "type")]
+    pub kind: String,
+    /// Always `1.0`
+    pub version: String,
+    pub title: String,
+    pub author_name: String,
+    pub author_url: String,
+    pub provider_name: String,
+    pub provider_url: String,
+    /// `iframe` snippet hosting the player
+    pub html: String,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_height: Option<u32>,
+}
+
+fn parse_flag(value: &str) -> Option<bool> {
+    match value {
+        "" | "1" | "true" | "yes" => Some(true),
+        "0" | "false" | "no" => Some(false),
+        _ => None,
+    }
+}
+
+fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
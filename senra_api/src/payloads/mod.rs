@@ -1,13 +1,30 @@
+mod analytics;
 mod auth;
+mod blob;
+pub(crate) mod bytes;
+mod collection;
+mod embed;
+mod error;
 mod notebook;
 mod notebook_content;
+mod notification;
+mod package;
 mod resource;
 mod shader;
 mod user;
+mod validation;
 
+pub use analytics::*;
 pub use auth::*;
+pub use blob::*;
+pub use collection::*;
+pub use embed::*;
+pub use error::*;
 pub use notebook::*;
 pub use notebook_content::*;
+pub use notification::*;
+pub use package::*;
 pub use resource::*;
 pub use shader::*;
 pub use user::*;
+pub use validation::*;
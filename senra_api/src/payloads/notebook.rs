@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::blob::BlobRef;
 use super::resource::{CreateResourceRequest, ResourceResponse};
 use super::shader::{CreateShaderRequest, ShaderResponse};
 use super::user::UserPreviewResponse;
+use super::validation::FieldError;
 
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -14,6 +16,8 @@ pub struct CreateNotebookRequest {
     pub resources: Vec<CreateResourceRequest>,
     pub shaders: Vec<CreateShaderRequest>,
     pub tags: Vec<String>,
+    #[serde(default, with = "super::bytes::option")]
+    #[cfg_attr(feature = "docs", schema(value_type = Option<String>, format = Byte))]
     pub preview: Option<Vec<u8>>,
     pub visibility: String,
 }
@@ -25,6 +29,8 @@ pub struct EditNotebookRequest {
     pub description: Option<String>,
     pub content: Option<Value>,
     pub tags: Option<Vec<String>>,
+    #[serde(default, with = "super::bytes::option")]
+    #[cfg_attr(feature = "docs", schema(value_type = Option<String>, format = Byte))]
     pub preview: Option<Vec<u8>>,
     pub visibility: Option<String>,
 }
@@ -33,6 +39,30 @@ pub struct EditNotebookRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateNotebookCommentRequest {
     pub content: String,
+    /// Comment to reply to, in the same notebook
+    #[serde(default)]
+    pub parent_id: Option<i64>,
+    #[serde(default)]
+    pub anchor: Option<CommentAnchor>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateNotebookCommentRequest {
+    pub content: String,
+}
+
+/// Points a comment at a cell of the notebook content, optionally at a range of its lines
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommentAnchor {
+    pub cell_id: String,
+    /// First line of the range, starting at 1
+    #[serde(default)]
+    pub start_line: Option<u32>,
+    /// Last line of the range, inclusive
+    #[serde(default)]
+    pub end_line: Option<u32>,
 }
 
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
@@ -62,7 +92,7 @@ pub struct NotebookPreviewResponse {
     pub inner: NotebookInfo,
     pub author: UserPreviewResponse,
     pub stats: NotebookStats,
-    pub preview: Option<Vec<u8>>,
+    pub preview: Option<BlobRef>,
 }
 
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
@@ -86,6 +116,59 @@ pub struct NotebookListResponse {
     pub total: i64,
 }
 
+/// Feed the public notebooks are listed from
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum NotebookSort {
+    /// Trending notebooks, favoring recent activity
+    #[default]
+    Hot,
+    /// Most engagement over the last seven days
+    TopWeek,
+    /// Most engagement ever
+    TopAllTime,
+    /// Most recently created first
+    New,
+}
+
+impl NotebookSort {
+    /// Feeds materialized by the ranking job
+    pub const RANKED: [NotebookSort; 3] = [
+        NotebookSort::Hot,
+        NotebookSort::TopWeek,
+        NotebookSort::TopAllTime,
+    ];
+
+    /// Key used in the `sort` query parameter and as the ranking feed
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotebookSort::Hot => "hot",
+            NotebookSort::TopWeek => "top-week",
+            NotebookSort::TopAllTime => "top-all-time",
+            NotebookSort::New => "new",
+        }
+    }
+}
+
+/// Notebook in its owner's trash
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashedNotebookResponse {
+    #[serde(flatten)]
+    pub inner: NotebookInfo,
+    pub deleted_at: String,
+    /// When the notebook will be permanently deleted unless restored
+    pub purge_at: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashListResponse {
+    pub notebooks: Vec<TrashedNotebookResponse>,
+    pub total: i64,
+}
+
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotebookVersionResponse {
@@ -103,6 +186,13 @@ pub struct NotebookVersionListResponse {
     pub total: i64,
 }
 
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShadertoyImportResponse {
+    pub notebook: NotebookResponse,
+    pub warnings: Vec<FieldError>,
+}
+
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotebookCommentResponse {
@@ -113,12 +203,18 @@ pub struct NotebookCommentResponse {
     pub created_at: String,
     pub updated_at: String,
     pub author: String,
-    pub author_avatar: Option<Vec<u8>>,
+    pub author_avatar: Option<BlobRef>,
+    pub parent_id: Option<i64>,
+    /// When the content was last edited, if ever
+    pub edited_at: Option<String>,
+    pub anchor: Option<CommentAnchor>,
 }
 
+/// A page of top-level comments followed by all their replies
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotebookCommentListResponse {
     pub comments: Vec<NotebookCommentResponse>,
+    /// Number of top-level comments
     pub total: i64,
 }
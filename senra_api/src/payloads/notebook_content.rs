@@ -1,12 +1,14 @@
-use serde::{Deserialize, Serialize};
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize};
 use serde_json::Value;
 
 /// Notebook content protocol for ShaderLab, optimized for WebGPU rendering
 /// Similar to Jupyter notebook format but with specialized structures for shader rendering
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NotebookContent {
-    /// Version string for compatibility support
+    /// Schema version, see `CURRENT_CONTENT_VERSION`
     pub version: String,
     /// List of cells in the notebook
     pub cells: Vec<Cell>,
@@ -17,21 +19,49 @@ pub struct NotebookContent {
 
 /// Represents a single cell in the notebook
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cell {
     /// Unique identifier for the cell
     pub id: String,
-    /// Type of cell (markdown, code, or render)
-    pub cell_type: CellType,
-    /// Content of the cell, format depends on cell_type
-    pub content: String,
+    /// Typed content of the cell, serialized as `cell_type` and `content`
+    #[serde(flatten)]
+    pub payload: CellPayload,
     /// Metadata for the cell
+    #[serde(default)]
     pub metadata: CellMetadata,
 }
 
-/// Types of cells supported in the notebook
+impl Cell {
+    /// Returns the type of the cell payload
+    pub fn cell_type(&self) -> CellType {
+        match self.payload {
+            CellPayload::Markdown(_) => CellType::Markdown,
+            CellPayload::Code(_) => CellType::Code,
+            CellPayload::Render(_) => CellType::Render,
+        }
+    }
+}
+
+/// Content of a cell, tagged by its cell type
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "cell_type", content = "content", rename_all = "lowercase")]
+pub enum CellPayload {
+    /// Markdown source text
+    Markdown(String),
+    /// Shader source code (e.g., WGSL)
+    Code(String),
+    /// Render configuration, also accepted as a JSON encoded string
+    #[serde(deserialize_with = "deserialize_render_config")]
+    Render(Box<RenderConfig>),
+}
+
+/// Types of cells supported in the notebook
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum CellType {
     /// Markdown text content
@@ -44,7 +74,8 @@ pub enum CellType {
 
 /// Metadata for a cell
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct CellMetadata {
     /// Whether the cell is collapsed in the UI
     #[serde(default)]
@@ -53,6 +84,7 @@ pub struct CellMetadata {
 
 /// Configuration for WebGPU rendering in a render cell
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenderConfig {
     /// Canvas width in pixels
@@ -73,6 +105,7 @@ pub struct RenderConfig {
 
 /// Configuration for the WebGPU rendering pipeline
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PipelineConfig {
     /// Configuration of shader bindings, specifying which shaders to use and their stages
@@ -88,6 +121,7 @@ pub struct PipelineConfig {
 
 /// Configuration for binding a shader to a specific stage in the pipeline
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShaderBinding {
     /// Index into the shader_ids array to reference a specific shader
@@ -100,7 +134,8 @@ pub struct ShaderBinding {
 
 /// Available shader stages in the WebGPU pipeline
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ShaderStage {
     /// Vertex processing stage
@@ -113,20 +148,90 @@ pub enum ShaderStage {
 
 /// Configuration for a vertex attribute in the pipeline
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VertexAttribute {
     /// Name of the attribute
     pub name: String,
     /// Format of the attribute data (e.g., "float32x3" for a vec3)
-    pub format: String,
+    pub format: VertexFormat,
     /// Byte offset within the vertex buffer
     pub offset: u64,
     /// Byte stride between consecutive vertices
     pub stride: u64,
 }
 
+/// Vertex attribute formats as named by WebGPU
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VertexFormat {
+    /// Two 8-bit unsigned integers
+    Uint8x2,
+    /// Four 8-bit unsigned integers
+    Uint8x4,
+    /// Two 8-bit signed integers
+    Sint8x2,
+    /// Four 8-bit signed integers
+    Sint8x4,
+    /// Two 8-bit unsigned normalized values
+    Unorm8x2,
+    /// Four 8-bit unsigned normalized values
+    Unorm8x4,
+    /// Two 8-bit signed normalized values
+    Snorm8x2,
+    /// Four 8-bit signed normalized values
+    Snorm8x4,
+    /// Two 16-bit unsigned integers
+    Uint16x2,
+    /// Four 16-bit unsigned integers
+    Uint16x4,
+    /// Two 16-bit signed integers
+    Sint16x2,
+    /// Four 16-bit signed integers
+    Sint16x4,
+    /// Two 16-bit unsigned normalized values
+    Unorm16x2,
+    /// Four 16-bit unsigned normalized values
+    Unorm16x4,
+    /// Two 16-bit signed normalized values
+    Snorm16x2,
+    /// Four 16-bit signed normalized values
+    Snorm16x4,
+    /// Two 16-bit floats
+    Float16x2,
+    /// Four 16-bit floats
+    Float16x4,
+    /// One 32-bit float
+    Float32,
+    /// Two 32-bit floats
+    Float32x2,
+    /// Three 32-bit floats
+    Float32x3,
+    /// Four 32-bit floats
+    Float32x4,
+    /// One 32-bit unsigned integer
+    Uint32,
+    /// Two 32-bit unsigned integers
+    Uint32x2,
+    /// Three 32-bit unsigned integers
+    Uint32x3,
+    /// Four 32-bit unsigned integers
+    Uint32x4,
+    /// One 32-bit signed integer
+    Sint32,
+    /// Two 32-bit signed integers
+    Sint32x2,
+    /// Three 32-bit signed integers
+    Sint32x3,
+    /// Four 32-bit signed integers
+    Sint32x4,
+}
+
 /// Configuration for binding a resource to the pipeline
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResourceBinding {
     /// Index into the resource_ids array to reference a specific resource
@@ -141,6 +246,7 @@ pub struct ResourceBinding {
 
 /// Types of bindings available in WebGPU
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum BindingType {
@@ -156,6 +262,7 @@ pub enum BindingType {
 
 /// Configuration for a render pass in the pipeline
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RenderPassConfig {
     /// Unique identifier for the pass, defaults to "main"
@@ -167,6 +274,9 @@ pub struct RenderPassConfig {
     /// Optional description of the pass purpose
     #[serde(default)]
     pub description: Option<String>,
+    /// Indices into the pipeline shader bindings used by this pass, all of them when empty
+    #[serde(default)]
+    pub shader_bindings: Vec<usize>,
     /// Input texture bindings, used to receive textures from previous passes or resources
     #[serde(default)]
     pub input_textures: Vec<InputTextureBinding>,
@@ -195,6 +305,7 @@ pub struct RenderPassConfig {
 
 /// Types of render passes supported in the pipeline
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Default, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum RenderPassType {
@@ -211,6 +322,7 @@ pub enum RenderPassType {
 
 /// Configuration for binding an input texture to a render pass
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InputTextureBinding {
     /// Texture ID, can be a pass ID, resource ID, or special value (e.g., "previous")
@@ -226,32 +338,62 @@ pub struct InputTextureBinding {
 
 /// Configuration for a texture sampler
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SamplerConfig {
     /// Magnification filter ("linear" or "nearest")
-    #[serde(default = "default_filter_linear")]
-    pub mag_filter: String,
+    #[serde(default)]
+    pub mag_filter: FilterMode,
     /// Minification filter ("linear" or "nearest")
-    #[serde(default = "default_filter_linear")]
-    pub min_filter: String,
+    #[serde(default)]
+    pub min_filter: FilterMode,
     /// Texture addressing mode for U coordinate
-    #[serde(default = "default_address_clamp")]
-    pub address_mode_u: String,
+    #[serde(default)]
+    pub address_mode_u: AddressMode,
     /// Texture addressing mode for V coordinate
-    #[serde(default = "default_address_clamp")]
-    pub address_mode_v: String,
+    #[serde(default)]
+    pub address_mode_v: AddressMode,
+}
+
+/// Texture filtering modes
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FilterMode {
+    /// Interpolate between neighboring texels
+    #[default]
+    Linear,
+    /// Use the nearest texel
+    Nearest,
+}
+
+/// Texture addressing modes for coordinates outside [0, 1]
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressMode {
+    /// Clamp coordinates to the edge texels
+    #[default]
+    ClampToEdge,
+    /// Repeat the texture
+    Repeat,
+    /// Repeat the texture, mirroring every other repetition
+    MirrorRepeat,
 }
 
 /// Configuration for an output texture in a render pass
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutputTextureConfig {
     /// Texture ID for referencing in subsequent passes
     #[serde(default = "default_output_id")]
     pub id: String,
     /// Texture format (e.g., "rgba8unorm")
-    #[serde(default = "default_texture_format")]
-    pub format: String,
+    #[serde(default)]
+    pub format: TextureFormat,
     /// Width scale relative to the renderer width
     #[serde(default = "default_one_float")]
     pub width_scale: f32,
@@ -263,21 +405,122 @@ pub struct OutputTextureConfig {
     pub blend: Option<BlendConfig>,
 }
 
+/// Texture formats usable as render targets or sampled inputs
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TextureFormat {
+    /// One 8-bit unsigned normalized channel
+    R8Unorm,
+    /// One 16-bit float channel
+    R16Float,
+    /// One 32-bit float channel
+    R32Float,
+    /// Two 8-bit unsigned normalized channels
+    Rg8Unorm,
+    /// Two 16-bit float channels
+    Rg16Float,
+    /// Two 32-bit float channels
+    Rg32Float,
+    /// Four 8-bit unsigned normalized channels
+    #[default]
+    Rgba8Unorm,
+    /// Four 8-bit unsigned normalized channels, the color ones sRGB encoded
+    #[serde(rename = "rgba8unorm-srgb")]
+    Rgba8UnormSrgb,
+    /// Four 8-bit unsigned normalized channels in blue, green, red, alpha order
+    Bgra8Unorm,
+    /// Four 8-bit unsigned normalized channels in blue, green, red, alpha order, the color ones sRGB encoded
+    #[serde(rename = "bgra8unorm-srgb")]
+    Bgra8UnormSrgb,
+    /// 10-bit unsigned normalized color channels with a 2-bit alpha
+    Rgb10a2Unorm,
+    /// Four 16-bit float channels
+    Rgba16Float,
+    /// Four 32-bit float channels
+    Rgba32Float,
+    /// 16-bit unsigned normalized depth
+    Depth16Unorm,
+    /// Depth of at least 24 bits
+    Depth24Plus,
+    /// Depth of at least 24 bits with an 8-bit stencil
+    #[serde(rename = "depth24plus-stencil8")]
+    Depth24PlusStencil8,
+    /// 32-bit float depth
+    Depth32Float,
+}
+
 /// Configuration for blending in render targets
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BlendConfig {
     /// Source blend factor
-    pub src_factor: String,
+    pub src_factor: BlendFactor,
     /// Destination blend factor
-    pub dst_factor: String,
+    pub dst_factor: BlendFactor,
     /// Blend operation
-    #[serde(default = "default_blend_op")]
-    pub operation: String,
+    #[serde(default)]
+    pub operation: BlendOperation,
+}
+
+/// Blend factors applied to source or destination colors
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendFactor {
+    /// 0
+    Zero,
+    /// 1
+    One,
+    /// Color of the fragment
+    Src,
+    /// 1 minus the color of the fragment
+    OneMinusSrc,
+    /// Alpha of the fragment
+    SrcAlpha,
+    /// 1 minus the alpha of the fragment
+    OneMinusSrcAlpha,
+    /// Color already in the target
+    Dst,
+    /// 1 minus the color already in the target
+    OneMinusDst,
+    /// Alpha already in the target
+    DstAlpha,
+    /// 1 minus the alpha already in the target
+    OneMinusDstAlpha,
+    /// Alpha of the fragment, at most 1 minus the alpha already in the target
+    SrcAlphaSaturated,
+    /// Blend constant of the pass
+    Constant,
+    /// 1 minus the blend constant of the pass
+    OneMinusConstant,
+}
+
+/// Operations combining source and destination colors
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlendOperation {
+    /// Fragment term plus target term
+    #[default]
+    Add,
+    /// Fragment term minus target term
+    Subtract,
+    /// Target term minus fragment term
+    ReverseSubtract,
+    /// Smaller of the fragment and target values, ignoring the factors
+    Min,
+    /// Larger of the fragment and target values, ignoring the factors
+    Max,
 }
 
 /// Configuration for geometry in a render pass
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase", tag = "type")]
 pub enum GeometryConfig {
@@ -306,6 +549,7 @@ pub enum GeometryConfig {
 
 /// Configuration for a 3D camera
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CameraConfig {
     /// Camera position in 3D space
@@ -324,6 +568,7 @@ pub struct CameraConfig {
 
 /// Performance configuration for the renderer
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PerformanceConfig {
     /// Whether to use hardware acceleration
@@ -352,10 +597,6 @@ fn default_output_id() -> String {
     "output".to_string()
 }
 
-fn default_texture_format() -> String {
-    "rgba8unorm".to_string()
-}
-
 fn default_clear_color() -> [f32; 4] {
     [0.0, 0.0, 0.0, 1.0]
 }
@@ -372,14 +613,14 @@ fn default_one_u32() -> u32 {
     1
 }
 
-fn default_filter_linear() -> String {
-    "linear".to_string()
-}
-
-fn default_address_clamp() -> String {
-    "clamp-to-edge".to_string()
-}
-
-fn default_blend_op() -> String {
-    "add".to_string()
+/// Accepts a render configuration either inline or as a JSON encoded string,
+/// which is how render cells were stored before the payload was typed
+fn deserialize_render_config<'de, D>(deserializer: D) -> Result<Box<RenderConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match Value::deserialize(deserializer)? {
+        Value::String(encoded) => serde_json::from_str(&encoded).map_err(D::Error::custom),
+        value => serde_json::from_value(value).map_err(D::Error::custom),
+    }
 }
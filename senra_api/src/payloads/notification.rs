@@ -0,0 +1,75 @@
+use serde::{Deserialize, Serialize};
+
+/// Activity a notification reports to its recipient
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationKind {
+    /// Someone liked one of the recipient's notebooks
+    Like,
+    /// Someone commented on one of the recipient's notebooks
+    Comment,
+    /// Someone replied to one of the recipient's comments
+    Reply,
+    /// Someone mentioned the recipient with `@username` in a comment
+    Mention,
+}
+
+impl NotificationKind {
+    pub const ALL: [NotificationKind; 4] = [
+        NotificationKind::Like,
+        NotificationKind::Comment,
+        NotificationKind::Reply,
+        NotificationKind::Mention,
+    ];
+
+    /// Key stored as the notification `kind`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Like => "like",
+            NotificationKind::Comment => "comment",
+            NotificationKind::Reply => "reply",
+            NotificationKind::Mention => "mention",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == key)
+    }
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationResponse {
+    pub id: i64,
+    pub kind: NotificationKind,
+    pub actor_id: i64,
+    pub actor: String,
+    pub notebook_id: i64,
+    pub notebook_title: String,
+    pub comment_id: Option<i64>,
+    pub read: bool,
+    pub created_at: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationListResponse {
+    pub notifications: Vec<NotificationResponse>,
+    pub total: i64,
+    pub unread: i64,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkNotificationsReadRequest {
+    /// Notifications to mark as read, all of them when omitted
+    #[serde(default)]
+    pub ids: Option<Vec<i64>>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UnreadNotificationsResponse {
+    pub unread: i64,
+}
@@ -0,0 +1,62 @@
+use serde::{Deserialize, Serialize};
+
+/// Publishes the current code of a WGSL shader as a package version
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishPackageRequest {
+    pub shader_id: i64,
+    pub name: String,
+    /// Semantic version, `MAJOR.MINOR.PATCH`
+    pub version: String,
+    pub description: Option<String>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageResponse {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    /// Published versions, from the oldest to the latest
+    pub versions: Vec<String>,
+    pub created_at: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageVersionResponse {
+    pub name: String,
+    pub version: String,
+    pub shader_type: String,
+    pub code: String,
+    /// Package versions imported by the code, as `name@version`
+    pub dependencies: Vec<String>,
+    pub created_at: String,
+}
+
+/// Notebooks and package versions depending on a package
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageUsedByResponse {
+    pub notebooks: Vec<PackageNotebookDependent>,
+    pub packages: Vec<PackageVersionDependent>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageNotebookDependent {
+    pub notebook_id: i64,
+    pub title: String,
+    /// Version of the package the notebook resolved to
+    pub version: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PackageVersionDependent {
+    pub name: String,
+    pub version: String,
+    /// Version of the package imported by the dependent version
+    pub dependency_version: String,
+}
@@ -1,12 +1,16 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::blob::BlobRef;
+
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateResourceRequest {
     pub notebook_id: i64,
     pub name: String,
     pub resource_type: String,
+    #[serde(with = "super::bytes")]
+    #[cfg_attr(feature = "docs", schema(value_type = String, format = Byte))]
     pub data: Vec<u8>,
     pub metadata: Option<Value>,
 }
@@ -15,6 +19,8 @@ pub struct CreateResourceRequest {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EditResourceRequest {
     pub name: Option<String>,
+    #[serde(default, with = "super::bytes::option")]
+    #[cfg_attr(feature = "docs", schema(value_type = Option<String>, format = Byte))]
     pub data: Option<Vec<u8>>,
     pub metadata: Option<Value>,
 }
@@ -26,7 +32,7 @@ pub struct ResourceResponse {
     pub notebook_id: i64,
     pub name: String,
     pub resource_type: String,
-    pub data: Vec<u8>,
+    pub data: BlobRef,
     pub metadata: Option<Value>,
     pub created_at: String,
 }
@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use super::notebook_content::{BindingType, ShaderStage};
+use super::validation::FieldError;
+use crate::shader::{CompileTarget, CompiledShader};
+
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateShaderRequest {
@@ -46,3 +50,85 @@ pub struct ShaderVersionListResponse {
     pub versions: Vec<ShaderVersionResponse>,
     pub total: i64,
 }
+
+/// Notebook shaders cross-compiled for other engines
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShaderExportResponse {
+    pub files: Vec<ShaderExportFile>,
+    pub reflection: ShaderReflection,
+    /// Shaders or targets which could not be compiled, the other files being exported
+    pub errors: Vec<FieldError>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShaderExportFile {
+    pub shader_id: i64,
+    pub target: CompileTarget,
+    /// Relative path of the file, e.g. `hlsl/3-blur.hlsl`
+    pub path: String,
+    pub output: CompiledShader,
+}
+
+/// Resource layout expected by the exported shaders
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ShaderReflection {
+    pub pipelines: Vec<PipelineReflection>,
+}
+
+/// Entry points and bind groups of the pipeline of a render cell
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineReflection {
+    pub cell_id: String,
+    pub entry_points: Vec<EntryPointReflection>,
+    pub bind_groups: Vec<BindGroupReflection>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EntryPointReflection {
+    pub shader_id: i64,
+    pub stage: ShaderStage,
+    pub entry_point: String,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BindGroupReflection {
+    pub group: u32,
+    pub bindings: Vec<BindingReflection>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BindingReflection {
+    pub binding: u32,
+    pub binding_type: BindingType,
+    pub resource_id: Option<i64>,
+    /// Name of the variable declared by the shaders for this binding
+    pub name: Option<String>,
+    /// Stages of the pipeline entry points using the binding
+    pub visibility: Vec<ShaderStage>,
+    /// Memory layout of uniform and storage buffers
+    pub layout: Option<BufferLayout>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferLayout {
+    pub type_name: String,
+    pub size: u32,
+    pub members: Vec<BufferMember>,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BufferMember {
+    pub name: String,
+    pub type_name: String,
+    pub offset: u32,
+    pub size: u32,
+}
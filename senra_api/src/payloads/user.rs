@@ -1,5 +1,6 @@
 use serde::{Deserialize, Serialize};
 
+use super::blob::BlobRef;
 use super::notebook::NotebookListResponse;
 
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
@@ -8,15 +9,25 @@ pub struct EditUserRequest {
     pub username: Option<String>,
     pub email: Option<String>,
     pub password: Option<String>,
+    #[serde(default, with = "super::bytes::option")]
+    #[cfg_attr(feature = "docs", schema(value_type = Option<String>, format = Byte))]
     pub avatar: Option<Vec<u8>>,
 }
 
+/// Avatar resized to a square of `size` pixels
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AvatarVariant {
+    pub size: u32,
+    pub blob: BlobRef,
+}
+
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UserPreviewResponse {
     pub id: i64,
     pub username: String,
-    pub avatar: Option<Vec<u8>>,
+    pub avatar: Option<BlobRef>,
 }
 
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
@@ -25,7 +36,11 @@ pub struct UserInfoResponse {
     pub id: i64,
     pub username: String,
     pub email: String,
-    pub avatar: Vec<u8>,
+    pub avatar: Option<BlobRef>,
+    /// Every stored size of the avatar, smallest first
+    pub avatar_variants: Vec<AvatarVariant>,
+    pub follower_count: i64,
+    pub following_count: i64,
 }
 
 #[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
@@ -33,7 +48,21 @@ pub struct UserInfoResponse {
 pub struct UserResponse {
     pub id: i64,
     pub username: String,
-    pub avatar: Option<Vec<u8>>,
+    pub avatar: Option<BlobRef>,
+    /// Every stored size of the avatar, smallest first
+    pub avatar_variants: Vec<AvatarVariant>,
     pub created_at: String,
     pub notebooks: NotebookListResponse,
+    pub follower_count: i64,
+    pub following_count: i64,
+    /// Whether the signed in user follows this user
+    pub is_following: bool,
+}
+
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FollowResponse {
+    pub user_id: i64,
+    pub is_following: bool,
+    pub follower_count: i64,
 }
@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+
+use crate::{PackageImport, ShaderLanguage};
+
+/// Directive importing a shader module, written `#import "name"` on its own line
+pub const IMPORT_DIRECTIVE: &str = "#import";
+
+/// Module the lines of the prelude come from, see [`ShaderLanguage::prelude`]
+pub const PRELUDE_MODULE: &str = "prelude";
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum ImportError {
+    #[error("Line {line} of `{module}`: malformed import, expected `#import \"name\"`")]
+    Malformed { module: String, line: usize },
+
+    #[error("Line {line} of `{module}`: module `{name}` not found")]
+    NotFound {
+        module: String,
+        line: usize,
+        name: String,
+    },
+
+    #[error("Import cycle {}", .0.join(" -> "))]
+    Cycle(Vec<String>),
+}
+
+/// Import of a shader published by another notebook, written `"{notebook_id}/{shader name}"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LibraryImport<'a> {
+    pub notebook_id: i64,
+    pub name: &'a str,
+}
+
+impl<'a> LibraryImport<'a> {
+    /// Parses an import name, returning `None` for modules of the importing notebook
+    pub fn parse(import: &'a str) -> Option<Self> {
+        let (notebook_id, name) = import.split_once('/')?;
+        let notebook_id = notebook_id.parse().ok()?;
+        (!name.is_empty()).then_some(Self { notebook_id, name })
+    }
+}
+
+/// Rewrites the imports of a module published by `notebook_id` to library imports, so that
+/// they keep pointing to the modules of that notebook once imported elsewhere
+///
+/// Package imports are left as is, they point to the same version from anywhere.
+pub fn qualify_imports(code: &str, notebook_id: i64) -> String {
+    code.lines()
+        .map(|line| match parse_import(line) {
+            Some(Ok(name))
+                if LibraryImport::parse(name).is_none() && PackageImport::parse(name).is_none() =>
+            {
+                format!("{} \"{}/{}\"", IMPORT_DIRECTIVE, notebook_id, name)
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Origin of a line of preprocessed code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub module: String,
+    /// 1-based line number in the module
+    pub line: usize,
+}
+
+/// Shader code with its imports expanded, along with the origin of every line
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PreprocessedShader {
+    /// Name of the preprocessed module
+    pub module: String,
+    pub code: String,
+    /// Origin of every line of `code`
+    pub lines: Vec<SourceLine>,
+}
+
+impl PreprocessedShader {
+    /// Prepends the prelude of `language`, errors in its lines pointing to the
+    /// [`PRELUDE_MODULE`] module
+    ///
+    /// A GLSL shader declaring its own `#version` keeps it as first line, in place of the one
+    /// of the prelude.
+    pub fn with_prelude(self, language: ShaderLanguage) -> Self {
+        let is_version = |line: &str| line.trim_start().starts_with("#version");
+        let header = match self.code.lines().next() {
+            Some(line) if is_version(line) => 1,
+            _ => 0,
+        };
+        let prelude: Vec<(usize, &str)> = language
+            .prelude()
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| header == 0 || !is_version(line))
+            .collect();
+
+        let mut code: Vec<&str> = self.code.lines().collect();
+        code.splice(header..header, prelude.iter().map(|(_, line)| *line));
+        let mut lines = self.lines;
+        let header = header.min(lines.len());
+        lines.splice(
+            header..header,
+            prelude.iter().map(|(index, _)| SourceLine {
+                module: PRELUDE_MODULE.to_string(),
+                line: index + 1,
+            }),
+        );
+
+        Self {
+            module: self.module,
+            code: code.into_iter().map(|line| format!("{}\n", line)).collect(),
+            lines,
+        }
+    }
+
+    /// Origin of a 1-based line of the preprocessed code
+    pub fn source_line(&self, line: usize) -> Option<&SourceLine> {
+        self.lines.get(line.checked_sub(1)?)
+    }
+
+    /// Describes a 1-based line of the preprocessed code, naming the module it was imported from
+    pub fn line_label(&self, line: usize) -> String {
+        match self.source_line(line) {
+            Some(source) if source.module == self.module => format!("Line {}", source.line),
+            Some(source) => format!("Line {} of `{}`", source.line, source.module),
+            None => format!("Line {}", line),
+        }
+    }
+
+    /// Maps the `Line N: ` prefixes of compiler messages back to the imported modules
+    pub fn map_lines(&self, message: &str) -> String {
+        message
+            .lines()
+            .map(|line| {
+                let Some((number, rest)) = line
+                    .strip_prefix("Line ")
+                    .and_then(|line| line.split_once(": "))
+                else {
+                    return line.to_string();
+                };
+                match number.parse::<usize>() {
+                    Ok(number) => format!("{}: {}", self.line_label(number), rest),
+                    Err(_) => line.to_string(),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Names imported by shader code, in order, skipping malformed imports
+pub fn shader_imports(code: &str) -> Vec<&str> {
+    code.lines()
+        .filter_map(|line| parse_import(line)?.ok())
+        .collect()
+}
+
+/// Expands the `#import "name"` directives of the `module` shader code
+///
+/// Imported modules are inlined in place of their first import, later imports of a module
+/// being dropped so that every declaration appears once. `resolve` returns the code of a module
+/// from its import name.
+pub fn preprocess<'a>(
+    module: &str,
+    code: &str,
+    resolve: impl Fn(&str) -> Option<&'a str>,
+) -> Result<PreprocessedShader, ImportError> {
+    preprocess_with(module, code, resolve, |_| None)
+}
+
+/// Expands imports like [`preprocess`], `redirect` returning the name a module is imported as
+/// in place of the written one
+pub(crate) fn preprocess_with<'a>(
+    module: &str,
+    code: &str,
+    resolve: impl Fn(&str) -> Option<&'a str>,
+    redirect: impl Fn(&str) -> Option<String>,
+) -> Result<PreprocessedShader, ImportError> {
+    let mut preprocessor = Preprocessor {
+        resolve,
+        redirect,
+        stack: Vec::new(),
+        included: HashSet::new(),
+        code: String::new(),
+        lines: Vec::new(),
+    };
+    preprocessor.expand(module, code)?;
+
+    Ok(PreprocessedShader {
+        module: module.to_string(),
+        code: preprocessor.code,
+        lines: preprocessor.lines,
+    })
+}
+
+struct Preprocessor<F, R> {
+    resolve: F,
+    redirect: R,
+    /// Modules being expanded, from the preprocessed module to the innermost import
+    stack: Vec<String>,
+    included: HashSet<String>,
+    code: String,
+    lines: Vec<SourceLine>,
+}
+
+impl<'a, F, R> Preprocessor<F, R>
+where
+    F: Fn(&str) -> Option<&'a str>,
+    R: Fn(&str) -> Option<String>,
+{
+    fn expand(&mut self, module: &str, code: &str) -> Result<(), ImportError> {
+        self.stack.push(module.to_string());
+        self.included.insert(module.to_string());
+
+        for (index, line) in code.lines().enumerate() {
+            let Some(import) = parse_import(line) else {
+                self.code.push_str(line);
+                self.code.push('\n');
+                self.lines.push(SourceLine {
+                    module: module.to_string(),
+                    line: index + 1,
+                });
+                continue;
+            };
+
+            let name = import.map_err(|_| ImportError::Malformed {
+                module: module.to_string(),
+                line: index + 1,
+            })?;
+            let redirected = (self.redirect)(name);
+            let name = redirected.as_deref().unwrap_or(name);
+
+            if let Some(position) = self.stack.iter().position(|entry| entry == name) {
+                let mut cycle = self.stack[position..].to_vec();
+                cycle.push(name.to_string());
+                return Err(ImportError::Cycle(cycle));
+            }
+            if self.included.contains(name) {
+                continue;
+            }
+
+            let imported = (self.resolve)(name).ok_or_else(|| ImportError::NotFound {
+                module: module.to_string(),
+                line: index + 1,
+                name: name.to_string(),
+            })?;
+            self.expand(name, imported)?;
+        }
+
+        self.stack.pop();
+        Ok(())
+    }
+}
+
+/// Parses an import directive, `None` meaning the line is not an import
+fn parse_import(line: &str) -> Option<Result<&str, ()>> {
+    let rest = line.trim().strip_prefix(IMPORT_DIRECTIVE)?;
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) && !rest.starts_with('"') {
+        // Another directive sharing the prefix
+        return None;
+    }
+
+    let rest = rest.trim_start();
+    let name = rest
+        .strip_prefix('"')
+        .and_then(|rest| rest.split_once('"'))
+        .filter(|(name, tail)| {
+            let tail = tail.trim_start();
+            !name.is_empty() && (tail.is_empty() || tail.starts_with("//"))
+        })
+        .map(|(name, _)| name);
+
+    Some(name.ok_or(()))
+}
@@ -0,0 +1,56 @@
+use serde_json::{Value, json};
+
+use super::NotebookContent;
+
+/// Tagged unions in the content format and the field carrying their tag
+const TAGGED_DEFINITIONS: &[(&str, &str)] = &[("Cell", "cell_type"), ("GeometryConfig", "type")];
+
+/// Generates the JSON Schema of the current `NotebookContent` format
+pub fn notebook_content_schema() -> Value {
+    let mut schema = schemars::schema_for!(NotebookContent).to_value();
+
+    for (name, tag) in TAGGED_DEFINITIONS {
+        if let Some(definition) = schema.pointer_mut(&format!("/$defs/{}", name)) {
+            discriminate_variants(definition, tag);
+        }
+    }
+
+    schema
+}
+
+/// Rewrites a `oneOf` over tagged variants into `if`/`then` branches selected by the tag,
+/// so validation errors point at fields of the matching variant instead of the whole union
+fn discriminate_variants(definition: &mut Value, tag: &str) {
+    let Some(Value::Array(variants)) = definition
+        .as_object_mut()
+        .and_then(|definition| definition.remove("oneOf"))
+    else {
+        return;
+    };
+
+    let mut tags = Vec::new();
+    let mut branches = Vec::new();
+    for variant in variants {
+        let Some(value) = variant
+            .pointer(&format!("/properties/{}/const", tag))
+            .cloned()
+        else {
+            continue;
+        };
+        branches.push(json!({
+            "if": {
+                "properties": { tag: { "const": value } },
+                "required": [tag],
+            },
+            "then": variant,
+        }));
+        tags.push(value);
+    }
+
+    definition["properties"][tag] = json!({ "enum": tags });
+    match definition.get_mut("required") {
+        Some(Value::Array(required)) => required.push(json!(tag)),
+        _ => definition["required"] = json!([tag]),
+    }
+    definition["allOf"] = Value::Array(branches);
+}
@@ -0,0 +1,361 @@
+use std::fmt;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{PreprocessedShader, ShaderStage};
+
+/// Bind group of the viewer holding the input channels, binding `2 * n` being the texture of
+/// channel `n` and `2 * n + 1` its sampler
+pub const VIEWER_CHANNEL_GROUP: u32 = 1;
+
+/// Number of input channels bound by the viewer
+pub const VIEWER_CHANNEL_COUNT: u32 = 4;
+
+/// Language a shader is written in, stored as the shader `shader_type`
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShaderLanguage {
+    #[default]
+    Wgsl,
+    /// GLSL 4.50, compiled for a single stage with a `main` entry point
+    Glsl,
+}
+
+impl ShaderLanguage {
+    pub const ALL: [ShaderLanguage; 2] = [ShaderLanguage::Wgsl, ShaderLanguage::Glsl];
+
+    /// Entry point naga assigns to GLSL shaders
+    pub const GLSL_ENTRY_POINT: &str = "main";
+
+    /// Fragment entry point the viewer draws WGSL shaders with
+    pub const WGSL_ENTRY_POINT: &str = "fs_main";
+
+    /// Key stored as the shader `shader_type`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShaderLanguage::Wgsl => "wgsl",
+            ShaderLanguage::Glsl => "glsl",
+        }
+    }
+
+    /// Declarations of the uniforms bound by the viewer, prepended to shaders before they are
+    /// compiled, see [`PreprocessedShader::with_prelude`]
+    pub fn prelude(&self) -> &'static str {
+        match self {
+            ShaderLanguage::Wgsl => include_str!("shaders/shared_uniforms.wgsl"),
+            ShaderLanguage::Glsl => include_str!("shaders/shared_uniforms.glsl"),
+        }
+    }
+}
+
+impl fmt::Display for ShaderLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderLanguage::Wgsl => write!(f, "WGSL"),
+            ShaderLanguage::Glsl => write!(f, "GLSL"),
+        }
+    }
+}
+
+impl FromStr for ShaderLanguage {
+    type Err = ShaderCompileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ShaderLanguage::ALL
+            .into_iter()
+            .find(|language| language.as_str() == s)
+            .ok_or_else(|| ShaderCompileError::UnknownLanguage(s.to_string()))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShaderCompileError {
+    #[error("Unknown shader language `{0}`, expected `wgsl` or `glsl`")]
+    UnknownLanguage(String),
+
+    #[error("Unknown compile target `{0}`")]
+    UnknownTarget(String),
+
+    #[error("{0}")]
+    Parse(String),
+
+    #[error("{0}")]
+    Validation(String),
+
+    #[error("{0}")]
+    Output(String),
+}
+
+/// Output language of `ShaderSource::compile`
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CompileTarget {
+    Wgsl,
+    #[serde(rename = "spirv")]
+    SpirV,
+    /// GLSL ES 3.0, holding a single entry point
+    GlslEs,
+    /// HLSL for shader model 5.1
+    Hlsl,
+    /// Metal Shading Language 1.2
+    Msl,
+}
+
+impl CompileTarget {
+    /// Targets of the shader export
+    pub const EXPORT: [CompileTarget; 4] = [
+        CompileTarget::GlslEs,
+        CompileTarget::Hlsl,
+        CompileTarget::Msl,
+        CompileTarget::SpirV,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CompileTarget::Wgsl => "wgsl",
+            CompileTarget::SpirV => "spirv",
+            CompileTarget::GlslEs => "glsl_es",
+            CompileTarget::Hlsl => "hlsl",
+            CompileTarget::Msl => "msl",
+        }
+    }
+
+    /// File extension of compiled shaders
+    pub fn extension(&self) -> &'static str {
+        match self {
+            CompileTarget::Wgsl => "wgsl",
+            CompileTarget::SpirV => "spv",
+            CompileTarget::GlslEs => "glsl",
+            CompileTarget::Hlsl => "hlsl",
+            CompileTarget::Msl => "metal",
+        }
+    }
+}
+
+impl FromStr for CompileTarget {
+    type Err = ShaderCompileError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        [CompileTarget::Wgsl]
+            .into_iter()
+            .chain(CompileTarget::EXPORT)
+            .find(|target| target.as_str() == s)
+            .ok_or_else(|| ShaderCompileError::UnknownTarget(s.to_string()))
+    }
+}
+
+/// Compiled shader, SPIR-V being kept as words
+#[cfg_attr(feature = "docs", derive(utoipa::ToSchema))]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "output", rename_all = "lowercase")]
+pub enum CompiledShader {
+    Source(String),
+    #[serde(rename = "spirv")]
+    SpirV(Vec<u32>),
+}
+
+impl CompiledShader {
+    /// Contents of the compiled shader file, SPIR-V words being little endian
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            CompiledShader::Source(source) => source.as_bytes().to_vec(),
+            CompiledShader::SpirV(words) => {
+                words.iter().flat_map(|word| word.to_le_bytes()).collect()
+            }
+        }
+    }
+}
+
+/// Shader code together with the language it is written in
+#[derive(Debug, Clone, Copy)]
+pub struct ShaderSource<'a> {
+    pub code: &'a str,
+    pub language: ShaderLanguage,
+    /// Origin of the lines of code with expanded imports, used to report errors
+    pub source_map: Option<&'a PreprocessedShader>,
+}
+
+impl<'a> ShaderSource<'a> {
+    pub fn new(code: &'a str, language: ShaderLanguage) -> Self {
+        Self {
+            code,
+            language,
+            source_map: None,
+        }
+    }
+
+    /// Shader code with expanded imports, errors pointing to the modules lines come from
+    pub fn preprocessed(shader: &'a PreprocessedShader, language: ShaderLanguage) -> Self {
+        Self {
+            code: &shader.code,
+            language,
+            source_map: Some(shader),
+        }
+    }
+
+    pub fn wgsl(code: &'a str) -> Self {
+        Self::new(code, ShaderLanguage::Wgsl)
+    }
+
+    pub fn glsl(code: &'a str) -> Self {
+        Self::new(code, ShaderLanguage::Glsl)
+    }
+
+    /// Parses the shader into a naga module
+    ///
+    /// WGSL modules declare their own stages, GLSL is parsed as a `stage` shader.
+    pub(crate) fn parse(
+        &self,
+        stage: naga::ShaderStage,
+    ) -> Result<naga::Module, ShaderCompileError> {
+        match self.language {
+            ShaderLanguage::Wgsl => naga::front::wgsl::parse_str(self.code).map_err(|err| {
+                let message = match err.location(self.code) {
+                    Some(location) => format!(
+                        "{}: {}",
+                        self.line_label(location.line_number as usize),
+                        err.message()
+                    ),
+                    None => err.message().to_string(),
+                };
+                ShaderCompileError::Parse(message)
+            }),
+            ShaderLanguage::Glsl => {
+                let options = naga::front::glsl::Options::from(stage);
+                naga::front::glsl::Frontend::default()
+                    .parse(&options, self.code)
+                    .map_err(|errors| {
+                        let messages: Vec<String> = errors
+                            .into_iter()
+                            .map(|error| {
+                                let line = error.meta.location(self.code).line_number;
+                                format!("{}: {}", self.line_label(line as usize), error.kind)
+                            })
+                            .collect();
+                        ShaderCompileError::Parse(messages.join("\n"))
+                    })
+            }
+        }
+    }
+
+    fn line_label(&self, line: usize) -> String {
+        match self.source_map {
+            Some(source_map) => source_map.line_label(line),
+            None => format!("Line {}", line),
+        }
+    }
+
+    /// Compiles the shader for `stage` to `target`, validating it on the way
+    ///
+    /// GLSL ES holds a single entry point, the first one of `stage`.
+    #[cfg(feature = "compile")]
+    pub fn compile(
+        &self,
+        stage: &ShaderStage,
+        target: CompileTarget,
+    ) -> Result<CompiledShader, ShaderCompileError> {
+        let module = self.parse(naga_stage(stage))?;
+        let info = self.validate_module(&module)?;
+        let entry_point = module
+            .entry_points
+            .iter()
+            .find(|entry_point| entry_point.stage == naga_stage(stage))
+            .map(|entry_point| entry_point.name.as_str());
+
+        write_module(&module, &info, target, entry_point)
+    }
+
+    #[cfg(feature = "compile")]
+    pub(crate) fn validate_module(
+        &self,
+        module: &naga::Module,
+    ) -> Result<naga::valid::ModuleInfo, ShaderCompileError> {
+        naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(module)
+        .map_err(|err| ShaderCompileError::Validation(err.emit_to_string(self.code)))
+    }
+}
+
+/// Writes a validated module in `target`, `entry_point` selecting the entry point of
+/// single entry point targets
+#[cfg(feature = "compile")]
+pub(crate) fn write_module(
+    module: &naga::Module,
+    info: &naga::valid::ModuleInfo,
+    target: CompileTarget,
+    entry_point: Option<&str>,
+) -> Result<CompiledShader, ShaderCompileError> {
+    let output_error = |err: &dyn std::error::Error| ShaderCompileError::Output(err.to_string());
+
+    match target {
+        CompileTarget::Wgsl => {
+            naga::back::wgsl::write_string(module, info, naga::back::wgsl::WriterFlags::empty())
+                .map(CompiledShader::Source)
+                .map_err(|err| output_error(&err))
+        }
+        CompileTarget::SpirV => naga::back::spv::write_vec(module, info, &Default::default(), None)
+            .map(CompiledShader::SpirV)
+            .map_err(|err| output_error(&err)),
+        CompileTarget::GlslEs => {
+            let entry_point = module
+                .entry_points
+                .iter()
+                .find(|candidate| Some(candidate.name.as_str()) == entry_point)
+                .ok_or_else(|| {
+                    ShaderCompileError::Output("GLSL ES needs an entry point".to_string())
+                })?;
+            let options = naga::back::glsl::Options {
+                version: naga::back::glsl::Version::new_gles(300),
+                ..Default::default()
+            };
+            let pipeline_options = naga::back::glsl::PipelineOptions {
+                shader_stage: entry_point.stage,
+                entry_point: entry_point.name.clone(),
+                multiview: None,
+            };
+            let mut output = String::new();
+            naga::back::glsl::Writer::new(
+                &mut output,
+                module,
+                info,
+                &options,
+                &pipeline_options,
+                Default::default(),
+            )
+            .and_then(|mut writer| writer.write())
+            .map_err(|err| output_error(&err))?;
+            Ok(CompiledShader::Source(output))
+        }
+        CompileTarget::Hlsl => {
+            let options = naga::back::hlsl::Options::default();
+            let mut output = String::new();
+            naga::back::hlsl::Writer::new(&mut output, &options)
+                .write(module, info)
+                .map_err(|err| output_error(&err))?;
+            Ok(CompiledShader::Source(output))
+        }
+        CompileTarget::Msl => {
+            let options = naga::back::msl::Options {
+                lang_version: (1, 2),
+                ..Default::default()
+            };
+            naga::back::msl::write_string(module, info, &options, &Default::default())
+                .map(|(output, _)| CompiledShader::Source(output))
+                .map_err(|err| output_error(&err))
+        }
+    }
+}
+
+pub(crate) fn naga_stage(stage: &ShaderStage) -> naga::ShaderStage {
+    match stage {
+        ShaderStage::Vertex => naga::ShaderStage::Vertex,
+        ShaderStage::Fragment => naga::ShaderStage::Fragment,
+        ShaderStage::Compute => naga::ShaderStage::Compute,
+    }
+}
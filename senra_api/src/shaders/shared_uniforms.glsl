@@ -0,0 +1,9 @@
+#version 450
+
+layout(set = 0, binding = 0, std140) uniform Uniforms {
+    mat4 transform;
+    vec2 position;
+    vec2 scale;
+    vec2 mouse;
+    float time;
+} uniforms;
@@ -0,0 +1,11 @@
+
+void main() {
+    // Shadertoy places the origin at the bottom left corner of the viewport
+    vec2 fragCoord = vec2(
+        gl_FragCoord.x - uniforms.position.x,
+        iResolution.y - gl_FragCoord.y + uniforms.position.y
+    );
+    vec4 fragColor = vec4(0.0);
+    mainImage(fragColor, fragCoord);
+    shadertoy_FragColor = fragColor;
+}
@@ -0,0 +1,558 @@
+use serde::{Deserialize, Deserializer};
+use serde_json::{Value, json};
+
+use crate::{
+    AddressMode, ArchiveResource, ArchiveShader, CURRENT_CONTENT_VERSION, CameraConfig, Cell,
+    CellMetadata, CellPayload, FieldError, FilterMode, GeometryConfig, InputTextureBinding,
+    NotebookArchive, NotebookContent, OutputTextureConfig, PerformanceConfig, PipelineConfig,
+    RenderConfig, RenderPassConfig, RenderPassType, SamplerConfig, ShaderBinding, ShaderLanguage,
+    ShaderStage, TextureFormat, VIEWER_CHANNEL_COUNT, VIEWER_CHANNEL_GROUP,
+};
+
+/// Shadertoy inputs derived from the viewer uniforms and the output, declared after the viewer
+/// prelude before the code of every pass
+const PRELUDE: &str = include_str!("prelude.glsl");
+
+/// Entry point calling Shadertoy's `mainImage`, appended after the code of every pass
+const EPILOGUE: &str = include_str!("epilogue.glsl");
+
+/// Vertex shader drawing a full screen triangle, shared by all passes
+const FULLSCREEN_SHADER: &str = include_str!("fullscreen.wgsl");
+
+/// Buffer pass ids used by Shadertoy, by output id
+const BUFFER_OUTPUTS: &[(&str, &str)] = &[
+    ("4dXGR8", "buffer_a"),
+    ("XsXGR8", "buffer_b"),
+    ("4sXGR8", "buffer_c"),
+    ("XdfGR8", "buffer_d"),
+    ("257", "buffer_a"),
+    ("258", "buffer_b"),
+    ("259", "buffer_c"),
+    ("260", "buffer_d"),
+];
+
+const SHADERTOY_URL: &str = "https://www.shadertoy.com";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ShadertoyError {
+    #[error("Invalid Shadertoy export: {0}")]
+    InvalidExport(String),
+
+    #[error("Shadertoy export has no image pass")]
+    MissingImagePass,
+
+    #[error("Shadertoy code could not be translated to WGSL")]
+    Untranslatable(Vec<FieldError>),
+}
+
+/// Result of a Shadertoy import
+#[derive(Debug, Clone)]
+pub struct ShadertoyImport {
+    /// Imported notebook, with one fragment shader per pass
+    pub archive: NotebookArchive,
+    /// Parts of the export which could not be carried over as is
+    pub warnings: Vec<FieldError>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Shader {
+    info: Info,
+    renderpass: Vec<Pass>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Info {
+    #[serde(default)]
+    id: String,
+    name: String,
+    #[serde(default)]
+    username: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Pass {
+    #[serde(default)]
+    inputs: Vec<Input>,
+    #[serde(default)]
+    outputs: Vec<Output>,
+    code: String,
+    #[serde(default)]
+    name: String,
+    #[serde(rename = "type")]
+    pass_type: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Input {
+    #[serde(deserialize_with = "deserialize_id")]
+    id: String,
+    #[serde(default, alias = "filepath")]
+    src: String,
+    ctype: String,
+    channel: u32,
+    #[serde(default)]
+    sampler: Option<Sampler>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Output {
+    #[serde(deserialize_with = "deserialize_id")]
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Sampler {
+    #[serde(default)]
+    filter: String,
+    #[serde(default)]
+    wrap: String,
+}
+
+/// Ids are strings in current exports and numbers in older ones
+fn deserialize_id<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(match Value::deserialize(deserializer)? {
+        Value::String(id) => id,
+        id => id.to_string(),
+    })
+}
+
+/// Maps a Shadertoy JSON export onto a notebook
+///
+/// Accepts the `{"Shader": ...}` object returned by the Shadertoy API, an array of them as
+/// produced by bulk exports, or the bare shader object. Buffer passes become intermediate
+/// passes, the image pass becomes the main pass and `iChannel` inputs become input texture
+/// bindings. GLSL is translated to WGSL through naga, and every error is reported with the
+/// path of the pass in the export and the line in its code or in the Common tab.
+pub fn import_shadertoy(export: &Value) -> Result<ShadertoyImport, ShadertoyError> {
+    let shader = match export {
+        Value::Array(shaders) if shaders.len() == 1 => &shaders[0],
+        Value::Array(shaders) => {
+            return Err(ShadertoyError::InvalidExport(format!(
+                "expected a single shader, found {}",
+                shaders.len()
+            )));
+        }
+        export => export,
+    };
+    let shader = shader.get("Shader").unwrap_or(shader);
+    let shader = Shader::deserialize(shader)
+        .map_err(|err| ShadertoyError::InvalidExport(err.to_string()))?;
+
+    Importer::default().import(shader)
+}
+
+#[derive(Default)]
+struct Importer {
+    shaders: Vec<ArchiveShader>,
+    resources: Vec<ArchiveResource>,
+    shader_bindings: Vec<ShaderBinding>,
+    warnings: Vec<FieldError>,
+    errors: Vec<FieldError>,
+}
+
+impl Importer {
+    fn import(mut self, shader: Shader) -> Result<ShadertoyImport, ShadertoyError> {
+        let common = shader
+            .renderpass
+            .iter()
+            .find(|pass| pass.pass_type == "common")
+            .map(|pass| pass.code.as_str())
+            .unwrap_or_default();
+
+        self.add_shader("fullscreen", FULLSCREEN_SHADER.to_string());
+        self.shader_bindings.push(ShaderBinding {
+            shader_index: 0,
+            shader_stage: ShaderStage::Vertex,
+            entry_point: "vs_main".to_string(),
+        });
+
+        // Shadertoy renders the buffers in order, then the image
+        let mut passes: Vec<(usize, &Pass)> = shader
+            .renderpass
+            .iter()
+            .enumerate()
+            .filter(|(_, pass)| pass.pass_type == "buffer")
+            .collect();
+        passes.sort_by_key(|(_, pass)| buffer_pass_id(pass));
+        match shader
+            .renderpass
+            .iter()
+            .enumerate()
+            .find(|(_, pass)| pass.pass_type == "image")
+        {
+            Some(image) => passes.push(image),
+            None => return Err(ShadertoyError::MissingImagePass),
+        }
+
+        for (index, pass) in shader.renderpass.iter().enumerate() {
+            if !matches!(pass.pass_type.as_str(), "image" | "buffer" | "common") {
+                self.warnings.push(FieldError {
+                    path: format!("/renderpass/{}", index),
+                    message: format!("{} passes are not supported", pass.pass_type),
+                });
+            }
+        }
+
+        let render_passes: Vec<RenderPassConfig> = passes
+            .into_iter()
+            .map(|(index, pass)| self.import_pass(index, pass, common))
+            .collect();
+
+        if !self.errors.is_empty() {
+            return Err(ShadertoyError::Untranslatable(self.errors));
+        }
+
+        let config = RenderConfig {
+            width: 640,
+            height: 360,
+            shader_ids: self.shaders.iter().map(|shader| shader.id).collect(),
+            resource_ids: self.resources.iter().map(|resource| resource.id).collect(),
+            pipeline: PipelineConfig {
+                shader_bindings: self.shader_bindings,
+                vertex_attributes: Vec::new(),
+                resource_bindings: Vec::new(),
+                render_passes,
+            },
+            camera: CameraConfig {
+                position: [0.0, 0.0, 1.0],
+                target: [0.0, 0.0, 0.0],
+                up: [0.0, 1.0, 0.0],
+                fov: 45.0,
+                near: 0.1,
+                far: 100.0,
+            },
+            performance: PerformanceConfig {
+                hardware_acceleration: true,
+                antialias: true,
+                adaptive_resolution: true,
+                max_fps: 0,
+            },
+        };
+
+        let mut attribution = format!("# {}\n\n", shader.info.name);
+        if !shader.info.description.is_empty() {
+            attribution.push_str(&format!("{}\n\n", shader.info.description));
+        }
+        attribution.push_str(&format!(
+            "Imported from [Shadertoy]({}/view/{}), created by {}.",
+            SHADERTOY_URL, shader.info.id, shader.info.username
+        ));
+
+        let content = NotebookContent {
+            version: CURRENT_CONTENT_VERSION.to_string(),
+            cells: vec![
+                Cell {
+                    id: "about".to_string(),
+                    payload: CellPayload::Markdown(attribution),
+                    metadata: CellMetadata::default(),
+                },
+                Cell {
+                    id: "render".to_string(),
+                    payload: CellPayload::Render(Box::new(config)),
+                    metadata: CellMetadata::default(),
+                },
+            ],
+            metadata: json!({ "shadertoy": { "id": shader.info.id } }),
+        };
+
+        Ok(ShadertoyImport {
+            archive: NotebookArchive {
+                title: shader.info.name,
+                description: Some(shader.info.description).filter(|d| !d.is_empty()),
+                tags: shader.info.tags,
+                visibility: "private".to_string(),
+                content,
+                shaders: self.shaders,
+                resources: self.resources,
+            },
+            warnings: self.warnings,
+        })
+    }
+
+    fn import_pass(&mut self, index: usize, pass: &Pass, common: &str) -> RenderPassConfig {
+        let is_image = pass.pass_type == "image";
+        let id = if is_image {
+            "image".to_string()
+        } else {
+            buffer_pass_id(pass)
+        };
+
+        let mut input_textures = Vec::new();
+        for (input_index, input) in pass.inputs.iter().enumerate() {
+            let path = format!("/renderpass/{}/inputs/{}", index, input_index);
+            if input.channel >= VIEWER_CHANNEL_COUNT {
+                self.warnings.push(FieldError {
+                    path,
+                    message: format!("Channel {} does not exist", input.channel),
+                });
+                continue;
+            }
+
+            let texture_id = match input.ctype.as_str() {
+                "buffer" => match buffer_id(&input.id) {
+                    Some(buffer) => buffer.to_string(),
+                    None => {
+                        self.warnings.push(FieldError {
+                            path,
+                            message: format!("Unknown buffer `{}`", input.id),
+                        });
+                        continue;
+                    }
+                },
+                "texture" => {
+                    let (resource_id, name) = self.add_texture(input);
+                    self.warnings.push(FieldError {
+                        path,
+                        message: format!(
+                            "Texture `{}` is not downloaded, upload it to resource `{}`",
+                            input.src, name
+                        ),
+                    });
+                    resource_id.to_string()
+                }
+                ctype => {
+                    self.warnings.push(FieldError {
+                        path,
+                        message: format!(
+                            "{} inputs are not supported, iChannel{} is left unbound",
+                            ctype, input.channel
+                        ),
+                    });
+                    continue;
+                }
+            };
+
+            input_textures.push(InputTextureBinding {
+                texture_id,
+                group: VIEWER_CHANNEL_GROUP,
+                binding: input.channel * 2,
+                sampler_config: input.sampler.as_ref().map(|sampler| {
+                    let filter = match sampler.filter.as_str() {
+                        "nearest" => FilterMode::Nearest,
+                        _ => FilterMode::Linear,
+                    };
+                    let address_mode = match sampler.wrap.as_str() {
+                        "repeat" => AddressMode::Repeat,
+                        _ => AddressMode::ClampToEdge,
+                    };
+                    SamplerConfig {
+                        mag_filter: filter,
+                        min_filter: filter,
+                        address_mode_u: address_mode,
+                        address_mode_v: address_mode,
+                    }
+                }),
+            });
+        }
+
+        let code_path = format!("/renderpass/{}/code", index);
+        if let Some(code) = self.translate(&code_path, &pass.code, common) {
+            let shader_index = self.add_shader(&id, code);
+            self.shader_bindings.push(ShaderBinding {
+                shader_index,
+                shader_stage: ShaderStage::Fragment,
+                entry_point: ShaderLanguage::WGSL_ENTRY_POINT.to_string(),
+            });
+        }
+
+        RenderPassConfig {
+            id: id.clone(),
+            pass_type: if is_image {
+                RenderPassType::Main
+            } else {
+                RenderPassType::Intermediate
+            },
+            description: Some(pass.name.clone()).filter(|name| !name.is_empty()),
+            shader_bindings: vec![0, self.shader_bindings.len() - 1],
+            input_textures,
+            output_textures: if is_image {
+                Vec::new()
+            } else {
+                // Buffers are sampled back with linear filtering, which WebGPU only allows on
+                // 32-bit float textures behind the `float32-filterable` feature
+                vec![OutputTextureConfig {
+                    id,
+                    format: TextureFormat::Rgba16Float,
+                    width_scale: 1.0,
+                    height_scale: 1.0,
+                    blend: None,
+                }]
+            },
+            geometry: Some(GeometryConfig::NonIndexed {
+                vertex_count: 3,
+                instance_count: 1,
+            }),
+            clear_color: [0.0, 0.0, 0.0, 1.0],
+            depth_enabled: false,
+            clear_depth: 1.0,
+            clear_stencil: 0,
+            shader_parameters: Value::Null,
+        }
+    }
+
+    /// Compiles the code of a pass to WGSL, recording errors against `path`
+    ///
+    /// The pass is compiled on top of the viewer prelude, whose declarations are left out of
+    /// the WGSL as the viewer prepends them again.
+    fn translate(&mut self, path: &str, code: &str, common: &str) -> Option<String> {
+        let mut source = format!("{}\n{}", ShaderLanguage::Glsl.prelude(), PRELUDE);
+        for channel in 0..VIEWER_CHANNEL_COUNT {
+            source.push_str(&format!(
+                "layout(set = {group}, binding = {texture}) uniform texture2D iChannel{channel}_texture;\n\
+                 layout(set = {group}, binding = {sampler}) uniform sampler iChannel{channel}_sampler;\n\
+                 #define iChannel{channel} sampler2D(iChannel{channel}_texture, iChannel{channel}_sampler)\n",
+                group = VIEWER_CHANNEL_GROUP,
+                texture = channel * 2,
+                sampler = channel * 2 + 1,
+            ));
+        }
+        let common_line = source.lines().count() + 1;
+        source.push_str(common);
+        source.push('\n');
+        let code_line = source.lines().count() + 1;
+        source.push_str(code);
+        source.push('\n');
+        let epilogue_line = source.lines().count() + 1;
+        source.push_str(EPILOGUE);
+
+        // Reports a location in the generated source as a line of the Common tab or the pass
+        let locate = |line: usize| {
+            if (code_line..epilogue_line).contains(&line) {
+                format!("Line {}: ", line - code_line + 1)
+            } else if (common_line..code_line).contains(&line) {
+                format!("Common line {}: ", line - common_line + 1)
+            } else {
+                String::new()
+            }
+        };
+
+        let options = naga::front::glsl::Options::from(naga::ShaderStage::Fragment);
+        let mut module = match naga::front::glsl::Frontend::default().parse(&options, &source) {
+            Ok(module) => module,
+            Err(errors) => {
+                self.errors.extend(errors.into_iter().map(|error| {
+                    let line = error.meta.location(&source).line_number as usize;
+                    FieldError {
+                        path: path.to_string(),
+                        message: format!("{}{}", locate(line), error.kind),
+                    }
+                }));
+                return None;
+            }
+        };
+
+        for entry_point in &mut module.entry_points {
+            entry_point.name = ShaderLanguage::WGSL_ENTRY_POINT.to_string();
+        }
+
+        let info = naga::valid::Validator::new(
+            naga::valid::ValidationFlags::all(),
+            naga::valid::Capabilities::empty(),
+        )
+        .validate(&module);
+        let info = match info {
+            Ok(info) => info,
+            Err(error) => {
+                let location = error
+                    .location(&source)
+                    .map(|location| locate(location.line_number as usize))
+                    .unwrap_or_default();
+                self.errors.push(FieldError {
+                    path: path.to_string(),
+                    message: format!("{}{}", location, error.as_inner()),
+                });
+                return None;
+            }
+        };
+
+        match naga::back::wgsl::write_string(&module, &info, naga::back::wgsl::WriterFlags::empty())
+        {
+            Ok(wgsl) => Some(strip_prelude(wgsl)),
+            Err(error) => {
+                self.errors.push(FieldError {
+                    path: path.to_string(),
+                    message: error.to_string(),
+                });
+                None
+            }
+        }
+    }
+
+    /// Adds a shader to the archive, returning its index in `shader_ids`
+    fn add_shader(&mut self, name: &str, code: String) -> usize {
+        self.shaders.push(ArchiveShader {
+            id: self.shaders.len() as i64 + 1,
+            name: name.to_string(),
+            shader_type: "wgsl".to_string(),
+            code,
+        });
+        self.shaders.len() - 1
+    }
+
+    /// Adds a placeholder resource for a Shadertoy media texture, shared between passes,
+    /// returning its id and name
+    fn add_texture(&mut self, input: &Input) -> (i64, String) {
+        let name = input
+            .src
+            .rsplit('/')
+            .next()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(&input.id)
+            .to_string();
+
+        if let Some(resource) = self.resources.iter().find(|r| r.name == name) {
+            return (resource.id, name);
+        }
+
+        let id = self.resources.len() as i64 + 1;
+        self.resources.push(ArchiveResource {
+            id,
+            name: name.clone(),
+            resource_type: "texture".to_string(),
+            data: Vec::new(),
+            metadata: Some(json!({ "source": format!("{}{}", SHADERTOY_URL, input.src) })),
+        });
+        (id, name)
+    }
+}
+
+/// Removes the declarations of the WGSL viewer prelude from a translated pass
+fn strip_prelude(mut wgsl: String) -> String {
+    let prelude = naga::front::wgsl::parse_str(ShaderLanguage::Wgsl.prelude())
+        .expect("viewer prelude is valid WGSL");
+    let info = naga::valid::Validator::new(
+        naga::valid::ValidationFlags::all(),
+        naga::valid::Capabilities::empty(),
+    )
+    .validate(&prelude)
+    .expect("viewer prelude is valid WGSL");
+    let prelude =
+        naga::back::wgsl::write_string(&prelude, &info, naga::back::wgsl::WriterFlags::empty())
+            .expect("viewer prelude is valid WGSL");
+
+    // The writer separates the declarations of the prelude with blank lines
+    for declaration in prelude.split("\n\n").filter(|item| !item.is_empty()) {
+        wgsl = wgsl.replacen(&format!("{}\n", declaration), "", 1);
+    }
+    wgsl.trim_start().to_string()
+}
+
+fn buffer_id(output_id: &str) -> Option<&'static str> {
+    BUFFER_OUTPUTS
+        .iter()
+        .find(|(id, _)| *id == output_id)
+        .map(|(_, buffer)| *buffer)
+}
+
+/// Pass id of a buffer, derived from its output so that inputs can refer to it
+fn buffer_pass_id(pass: &Pass) -> String {
+    pass.outputs
+        .first()
+        .and_then(|output| buffer_id(&output.id))
+        .map(str::to_string)
+        .unwrap_or_else(|| pass.name.to_lowercase().replace(' ', "_"))
+}
@@ -0,0 +1,11 @@
+#define iResolution vec3(uniforms.scale, 1.0)
+#define iTime uniforms.time
+#define iTimeDelta (1.0 / 60.0)
+#define iFrameRate 60.0
+#define iFrame int(uniforms.time * 60.0)
+#define iMouse vec4(uniforms.mouse.x - uniforms.position.x, uniforms.scale.y - uniforms.mouse.y + uniforms.position.y, 0.0, 0.0)
+#define iDate vec4(0.0, 0.0, 0.0, uniforms.time)
+#define iSampleRate 44100.0
+#define iChannelResolution vec3[4](iResolution, iResolution, iResolution, iResolution)
+
+layout(location = 0) out vec4 shadertoy_FragColor;
@@ -0,0 +1,339 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::shader::naga_stage;
+use crate::{CellPayload, FieldError, NotebookContent, RenderConfig, ShaderLanguage, ShaderSource};
+
+/// Special `texture_id` referring to the output of the preceding pass
+pub(crate) const PREVIOUS_PASS: &str = "previous";
+
+impl NotebookContent {
+    /// Checks the cross-references of every render cell
+    ///
+    /// `shader_code` resolves a shader id from `RenderConfig::shader_ids` to its source.
+    /// Paths are JSON pointers into the content document.
+    pub fn validate<'a>(
+        &self,
+        shader_code: impl Fn(i64) -> Option<ShaderSource<'a>>,
+    ) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        for (index, cell) in self.cells.iter().enumerate() {
+            let CellPayload::Render(config) = &cell.payload else {
+                continue;
+            };
+            if let Err(cell_errors) = config.validate(&shader_code) {
+                let prefix = format!("/cells/{}/content", index);
+                errors.extend(cell_errors.into_iter().map(|error| FieldError {
+                    path: format!("{}{}", prefix, error.path),
+                    message: error.message,
+                }));
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+impl RenderConfig {
+    /// Checks that shader and resource indices, pass inputs and binding slots refer to
+    /// something that exists, and that the bound entry points are declared in the shaders
+    ///
+    /// `shader_code` resolves a shader id from `shader_ids` to its source. GLSL shaders are
+    /// compiled once per stage they are bound to.
+    /// Paths are JSON pointers relative to the configuration.
+    pub fn validate<'a>(
+        &self,
+        shader_code: impl Fn(i64) -> Option<ShaderSource<'a>>,
+    ) -> Result<(), Vec<FieldError>> {
+        let mut errors = Vec::new();
+
+        self.validate_shaders(&shader_code, &mut errors);
+        self.validate_resource_bindings(&mut errors);
+        self.validate_render_passes(&mut errors);
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    fn validate_shaders<'a>(
+        &self,
+        shader_code: &impl Fn(i64) -> Option<ShaderSource<'a>>,
+        errors: &mut Vec<FieldError>,
+    ) {
+        // Parsed modules by shader index and stage, WGSL modules declaring their stages
+        // themselves are stored once without a stage
+        let mut modules = HashMap::new();
+        let mut languages = HashMap::new();
+        for (index, &id) in self.shader_ids.iter().enumerate() {
+            let path = format!("/shader_ids/{}", index);
+            let Some(source) = shader_code(id) else {
+                errors.push(field_error(path, format!("Shader {} does not exist", id)));
+                continue;
+            };
+            languages.insert(index, source.language);
+
+            let stages: Vec<Option<naga::ShaderStage>> = match source.language {
+                ShaderLanguage::Wgsl => vec![None],
+                ShaderLanguage::Glsl => {
+                    let mut stages = Vec::new();
+                    for binding in &self.pipeline.shader_bindings {
+                        let stage = Some(naga_stage(&binding.shader_stage));
+                        if binding.shader_index == index && !stages.contains(&stage) {
+                            stages.push(stage);
+                        }
+                    }
+                    stages
+                }
+            };
+
+            for stage in stages {
+                match source.parse(stage.unwrap_or(naga::ShaderStage::Fragment)) {
+                    Ok(module) => {
+                        modules.insert((index, stage), module);
+                    }
+                    Err(err) => {
+                        errors.push(field_error(
+                            path,
+                            format!("Shader {} is not valid {}: {}", id, source.language, err),
+                        ));
+                        break;
+                    }
+                }
+            }
+        }
+
+        for (index, binding) in self.pipeline.shader_bindings.iter().enumerate() {
+            let path = format!("/pipeline/shader_bindings/{}", index);
+            if binding.shader_index >= self.shader_ids.len() {
+                errors.push(field_error(
+                    format!("{}/shader_index", path),
+                    format!(
+                        "Shader index {} is out of range, {} shaders are referenced",
+                        binding.shader_index,
+                        self.shader_ids.len()
+                    ),
+                ));
+                continue;
+            }
+
+            let stage = naga_stage(&binding.shader_stage);
+            let Some(module) = modules
+                .get(&(binding.shader_index, None))
+                .or_else(|| modules.get(&(binding.shader_index, Some(stage))))
+            else {
+                continue;
+            };
+            let entry_point = module
+                .entry_points
+                .iter()
+                .find(|entry_point| entry_point.name == binding.entry_point);
+            match entry_point {
+                None if languages.get(&binding.shader_index) == Some(&ShaderLanguage::Glsl) => {
+                    errors.push(field_error(
+                        format!("{}/entry_point", path),
+                        format!(
+                            "Entry point of GLSL shader {} must be `{}`",
+                            self.shader_ids[binding.shader_index],
+                            ShaderLanguage::GLSL_ENTRY_POINT
+                        ),
+                    ))
+                }
+                None => errors.push(field_error(
+                    format!("{}/entry_point", path),
+                    format!(
+                        "Entry point `{}` is not declared in shader {}",
+                        binding.entry_point, self.shader_ids[binding.shader_index]
+                    ),
+                )),
+                Some(entry_point) if entry_point.stage != stage => errors.push(field_error(
+                    format!("{}/entry_point", path),
+                    format!(
+                        "Entry point `{}` is a {:?} entry point, expected {:?}",
+                        binding.entry_point, entry_point.stage, stage
+                    ),
+                )),
+                Some(_) => {}
+            }
+        }
+    }
+
+    fn validate_resource_bindings(&self, errors: &mut Vec<FieldError>) {
+        let mut slots = HashSet::new();
+        for (index, binding) in self.pipeline.resource_bindings.iter().enumerate() {
+            let path = format!("/pipeline/resource_bindings/{}", index);
+            if binding.resource_index >= self.resource_ids.len() {
+                errors.push(field_error(
+                    format!("{}/resource_index", path),
+                    format!(
+                        "Resource index {} is out of range, {} resources are referenced",
+                        binding.resource_index,
+                        self.resource_ids.len()
+                    ),
+                ));
+            }
+            if !slots.insert((binding.group, binding.binding)) {
+                errors.push(slot_error(path, binding.group, binding.binding));
+            }
+        }
+    }
+
+    fn validate_render_passes(&self, errors: &mut Vec<FieldError>) {
+        let passes = &self.pipeline.render_passes;
+        let resource_slots: HashSet<_> = self
+            .pipeline
+            .resource_bindings
+            .iter()
+            .map(|binding| (binding.group, binding.binding))
+            .collect();
+
+        let mut pass_ids = HashSet::new();
+        for (index, pass) in passes.iter().enumerate() {
+            if !pass_ids.insert(pass.id.as_str()) {
+                errors.push(field_error(
+                    format!("/pipeline/render_passes/{}/id", index),
+                    format!("Pass id `{}` is used by more than one pass", pass.id),
+                ));
+            }
+            for (binding_index, &shader_binding) in pass.shader_bindings.iter().enumerate() {
+                if shader_binding >= self.pipeline.shader_bindings.len() {
+                    errors.push(field_error(
+                        format!(
+                            "/pipeline/render_passes/{}/shader_bindings/{}",
+                            index, binding_index
+                        ),
+                        format!(
+                            "Shader binding {} is out of range, {} shader bindings are declared",
+                            shader_binding,
+                            self.pipeline.shader_bindings.len()
+                        ),
+                    ));
+                }
+            }
+        }
+
+        // Edges from each pass to the passes producing its inputs
+        let mut dependencies = vec![Vec::new(); passes.len()];
+        for (index, pass) in passes.iter().enumerate() {
+            let mut slots = resource_slots.clone();
+            for (input_index, input) in pass.input_textures.iter().enumerate() {
+                let path = format!(
+                    "/pipeline/render_passes/{}/input_textures/{}",
+                    index, input_index
+                );
+                if !slots.insert((input.group, input.binding)) {
+                    errors.push(slot_error(path.clone(), input.group, input.binding));
+                }
+
+                if input.texture_id == PREVIOUS_PASS {
+                    if index == 0 {
+                        errors.push(field_error(
+                            format!("{}/texture_id", path),
+                            "The first pass has no previous pass to read from".to_string(),
+                        ));
+                    }
+                    continue;
+                }
+
+                let producers: Vec<usize> = passes
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, producer)| {
+                        producer.id == input.texture_id
+                            || producer
+                                .output_textures
+                                .iter()
+                                .any(|output| output.id == input.texture_id)
+                    })
+                    .map(|(producer_index, _)| producer_index)
+                    .collect();
+                let is_resource = self
+                    .resource_ids
+                    .iter()
+                    .any(|id| id.to_string() == input.texture_id);
+
+                if producers.is_empty() && !is_resource {
+                    errors.push(field_error(
+                        format!("{}/texture_id", path),
+                        format!(
+                            "Texture `{}` is not output by any pass nor a referenced resource",
+                            input.texture_id
+                        ),
+                    ));
+                }
+
+                // A pass sampling its own output reads the previous frame, which is not a cycle
+                for producer in producers {
+                    if producer != index {
+                        dependencies[index].push((producer, input_index));
+                    }
+                }
+            }
+        }
+
+        for (index, edges) in dependencies.iter().enumerate() {
+            for &(producer, input_index) in edges {
+                if let Some(cycle) = find_path(&dependencies, producer, index) {
+                    let names: Vec<&str> = std::iter::once(index)
+                        .chain(cycle)
+                        .map(|pass| passes[pass].id.as_str())
+                        .collect();
+                    errors.push(field_error(
+                        format!(
+                            "/pipeline/render_passes/{}/input_textures/{}/texture_id",
+                            index, input_index
+                        ),
+                        format!("Passes form a cycle: {}", names.join(" -> ")),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Finds the shortest chain of dependencies leading from `from` to `to`, both included
+fn find_path(dependencies: &[Vec<(usize, usize)>], from: usize, to: usize) -> Option<Vec<usize>> {
+    let mut parents = vec![None; dependencies.len()];
+    let mut visited = vec![false; dependencies.len()];
+    let mut queue = VecDeque::from([from]);
+    visited[from] = true;
+
+    while let Some(pass) = queue.pop_front() {
+        if pass == to {
+            let mut path = vec![pass];
+            let mut current = pass;
+            while let Some(parent) = parents[current] {
+                path.push(parent);
+                current = parent;
+            }
+            path.reverse();
+            return Some(path);
+        }
+        for &(next, _) in &dependencies[pass] {
+            if !visited[next] {
+                visited[next] = true;
+                parents[next] = Some(pass);
+                queue.push_back(next);
+            }
+        }
+    }
+
+    None
+}
+
+fn field_error(path: String, message: String) -> FieldError {
+    FieldError { path, message }
+}
+
+fn slot_error(path: String, group: u32, binding: u32) -> FieldError {
+    field_error(
+        path,
+        format!("Group {} binding {} is already in use", group, binding),
+    )
+}
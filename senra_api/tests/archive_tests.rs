@@ -0,0 +1,241 @@
+use std::collections::HashMap;
+use std::io::{Cursor, Read, Write};
+
+use senra_api::*;
+use serde_json::json;
+use zip::write::SimpleFileOptions;
+
+fn archive() -> NotebookArchive {
+    let content: NotebookContent = serde_json::from_value(json!({
+        "version": CURRENT_CONTENT_VERSION,
+        "cells": [
+            { "id": "intro", "cell_type": "markdown", "content": "# Archived" },
+            {
+                "id": "render",
+                "cell_type": "render",
+                "content": {
+                    "width": 320,
+                    "height": 240,
+                    "shader_ids": [4],
+                    "resource_ids": [9],
+                    "pipeline": {
+                        "shader_bindings": [],
+                        "vertex_attributes": [],
+                        "resource_bindings": [],
+                        "render_passes": [{
+                            "id": "main",
+                            "input_textures": [
+                                { "texture_id": "9", "group": 0, "binding": 0 }
+                            ]
+                        }]
+                    },
+                    "camera": {
+                        "position": [0.0, 0.0, 3.0],
+                        "target": [0.0, 0.0, 0.0],
+                        "up": [0.0, 1.0, 0.0],
+                        "fov": 45.0,
+                        "near": 0.1,
+                        "far": 100.0
+                    },
+                    "performance": {}
+                }
+            }
+        ]
+    }))
+    .unwrap();
+
+    NotebookArchive {
+        title: "Ray marching / basics".to_string(),
+        description: Some("Spheres".to_string()),
+        tags: vec!["sdf".to_string()],
+        visibility: "public".to_string(),
+        content,
+        shaders: vec![ArchiveShader {
+            id: 4,
+            name: "main shader".to_string(),
+            shader_type: "wgsl".to_string(),
+            code: "@fragment fn fs_main() {}".to_string(),
+        }],
+        resources: vec![
+            ArchiveResource {
+                id: 9,
+                name: "noise.png".to_string(),
+                resource_type: "texture".to_string(),
+                data: vec![0, 1, 2, 3],
+                metadata: Some(json!({ "width": 2, "height": 2 })),
+            },
+            ArchiveResource {
+                id: 10,
+                name: "lut".to_string(),
+                resource_type: "buffer".to_string(),
+                data: vec![255; 16],
+                metadata: None,
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_archive_round_trip() {
+    let original = archive();
+    let bytes = original.to_bytes().unwrap();
+
+    let mut zip = zip::ZipArchive::new(Cursor::new(bytes.as_slice())).unwrap();
+    let mut names: Vec<&str> = zip.file_names().collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "manifest.json",
+            "notebook.json",
+            "resources/10-lut",
+            "resources/9-noise.png",
+            "resources/9-noise.png.meta.json",
+            "shaders/4-main_shader.wgsl"
+        ]
+    );
+
+    let mut code = String::new();
+    zip.by_name("shaders/4-main_shader.wgsl")
+        .unwrap()
+        .read_to_string(&mut code)
+        .unwrap();
+    assert_eq!(code, original.shaders[0].code);
+
+    let archive = NotebookArchive::from_bytes(&bytes).unwrap();
+    assert_eq!(archive.title, original.title);
+    assert_eq!(archive.description, original.description);
+    assert_eq!(archive.tags, original.tags);
+    assert_eq!(archive.shaders[0].code, original.shaders[0].code);
+    assert_eq!(archive.resources[0].data, original.resources[0].data);
+    assert_eq!(
+        archive.resources[0].metadata,
+        original.resources[0].metadata
+    );
+    assert_eq!(archive.resources[1].metadata, None);
+    assert_eq!(
+        serde_json::to_value(&archive.content).unwrap(),
+        serde_json::to_value(&original.content).unwrap()
+    );
+    assert_eq!(archive.file_name(), "Ray_marching___basics.senra");
+}
+
+#[test]
+fn test_archive_content_is_migrated() {
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default();
+    zip.start_file("manifest.json", options).unwrap();
+    zip.write_all(
+        json!({ "format_version": 1, "title": "Legacy", "visibility": "private" })
+            .to_string()
+            .as_bytes(),
+    )
+    .unwrap();
+    zip.start_file("notebook.json", options).unwrap();
+    zip.write_all(
+        json!({ "cells": [{ "cell_type": "markdown", "content": "# Old" }] })
+            .to_string()
+            .as_bytes(),
+    )
+    .unwrap();
+    let bytes = zip.finish().unwrap().into_inner();
+
+    let archive = NotebookArchive::from_bytes(&bytes).unwrap();
+    assert_eq!(archive.content.version, CURRENT_CONTENT_VERSION);
+    assert_eq!(archive.content.cells[0].id, "cell-0");
+    assert!(archive.shaders.is_empty());
+}
+
+#[test]
+fn test_invalid_archives_are_rejected() {
+    assert!(matches!(
+        NotebookArchive::from_bytes(b"not a zip"),
+        Err(ArchiveError::Zip(_))
+    ));
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    zip.start_file("manifest.json", SimpleFileOptions::default())
+        .unwrap();
+    zip.write_all(
+        json!({ "format_version": 1, "title": "Empty", "visibility": "public" })
+            .to_string()
+            .as_bytes(),
+    )
+    .unwrap();
+    let bytes = zip.finish().unwrap().into_inner();
+
+    assert!(matches!(
+        NotebookArchive::from_bytes(&bytes),
+        Err(ArchiveError::MissingFile(path)) if path == "notebook.json"
+    ));
+}
+
+#[test]
+fn test_oversized_archives_are_rejected() {
+    // Zeros deflate to almost nothing, the way zip bombs do
+    let archive_with = |resources: &[(&str, u64)]| {
+        let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+        let options = SimpleFileOptions::default();
+        let manifest_resources: Vec<_> = resources
+            .iter()
+            .enumerate()
+            .map(|(id, (path, _))| {
+                json!({ "id": id, "name": path, "resource_type": "texture", "path": path })
+            })
+            .collect();
+        zip.start_file("manifest.json", options).unwrap();
+        zip.write_all(
+            json!({
+                "format_version": 1,
+                "title": "Bomb",
+                "visibility": "public",
+                "resources": manifest_resources
+            })
+            .to_string()
+            .as_bytes(),
+        )
+        .unwrap();
+        zip.start_file("notebook.json", options).unwrap();
+        zip.write_all(json!({ "cells": [] }).to_string().as_bytes())
+            .unwrap();
+        for (path, size) in resources {
+            zip.start_file(*path, options).unwrap();
+            std::io::copy(&mut std::io::repeat(0).take(*size), &mut zip).unwrap();
+        }
+        zip.finish().unwrap().into_inner()
+    };
+
+    let bytes = archive_with(&[("big.bin", MAX_ARCHIVE_FILE_SIZE + 1)]);
+    assert!(bytes.len() < 1024 * 1024);
+    assert!(matches!(
+        NotebookArchive::from_bytes(&bytes),
+        Err(ArchiveError::TooLarge(path)) if path == "big.bin"
+    ));
+
+    // Files under the limit each still can't add up past the archive limit
+    let bytes = archive_with(&[
+        ("a.bin", MAX_ARCHIVE_FILE_SIZE),
+        ("b.bin", MAX_ARCHIVE_FILE_SIZE),
+        ("c.bin", MAX_ARCHIVE_FILE_SIZE),
+    ]);
+    assert!(matches!(
+        NotebookArchive::from_bytes(&bytes),
+        Err(ArchiveError::TooLarge(path)) if path == "b.bin"
+    ));
+}
+
+#[test]
+fn test_remap_ids() {
+    let mut content = archive().content;
+    content.remap_ids(&HashMap::from([(4, 1)]), &HashMap::from([(9, 2)]));
+
+    let CellPayload::Render(config) = &content.cells[1].payload else {
+        panic!("expected a render payload");
+    };
+    assert_eq!(config.shader_ids, [1]);
+    assert_eq!(config.resource_ids, [2]);
+    assert_eq!(
+        config.pipeline.render_passes[0].input_textures[0].texture_id,
+        "2"
+    );
+}
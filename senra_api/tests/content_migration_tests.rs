@@ -0,0 +1,88 @@
+use senra_api::*;
+use serde_json::{Value, json};
+
+fn fixture(source: &str) -> Value {
+    serde_json::from_str(source).unwrap()
+}
+
+fn assert_current(content: &Value) -> NotebookContent {
+    assert_eq!(content_version(content), CURRENT_CONTENT_VERSION);
+    let notebook: NotebookContent = serde_json::from_value(content.clone()).unwrap();
+    assert_eq!(notebook.version, CURRENT_CONTENT_VERSION);
+    notebook
+}
+
+#[test]
+fn test_migrate_legacy_content() {
+    let mut content = fixture(include_str!("fixtures/content_v0.json"));
+    assert_eq!(content_version(&content), LEGACY_CONTENT_VERSION);
+
+    assert!(migrate_content(&mut content).unwrap());
+    let notebook = assert_current(&content);
+
+    assert_eq!(notebook.metadata, json!({}));
+    assert_eq!(notebook.cells[0].id, "1");
+    assert_eq!(notebook.cells[1].id, "cell-1");
+    assert!(!notebook.cells[1].metadata.collapsed);
+    assert!(notebook.cells[2].metadata.collapsed);
+    assert!(content["cells"][2]["content"].is_object());
+    assert!(matches!(
+        &notebook.cells[2].payload,
+        CellPayload::Render(config) if config.width == 640
+    ));
+}
+
+#[test]
+fn test_migrate_1_0_content() {
+    let mut content = fixture(include_str!("fixtures/content_v1_0.json"));
+    assert_eq!(content_version(&content), "1.0");
+
+    assert!(migrate_content(&mut content).unwrap());
+    assert_current(&content);
+
+    assert_eq!(content, fixture(include_str!("fixtures/content_v1_1.json")));
+}
+
+#[test]
+fn test_current_content_is_unchanged() {
+    let mut content = fixture(include_str!("fixtures/content_v1_1.json"));
+    let original = content.clone();
+
+    assert!(!migrate_content(&mut content).unwrap());
+    assert_current(&content);
+    assert_eq!(content, original);
+}
+
+#[test]
+fn test_migrations_form_a_chain() {
+    let mut version = LEGACY_CONTENT_VERSION;
+    for migration in CONTENT_MIGRATIONS {
+        assert_eq!(migration.from, version);
+        version = migration.to;
+    }
+    assert_eq!(version, CURRENT_CONTENT_VERSION);
+}
+
+#[test]
+fn test_invalid_content_is_rejected() {
+    let mut content = json!({ "version": "9.0", "cells": [] });
+    assert!(matches!(
+        migrate_content(&mut content),
+        Err(MigrationError::UnknownVersion(version)) if version == "9.0"
+    ));
+
+    let mut content = json!([]);
+    assert!(matches!(
+        migrate_content(&mut content),
+        Err(MigrationError::NotAnObject)
+    ));
+
+    let mut content = json!({
+        "version": "1.0",
+        "cells": [{ "id": "render", "cell_type": "render", "content": "{ not json" }]
+    });
+    assert!(matches!(
+        migrate_content(&mut content),
+        Err(MigrationError::InvalidContent { path, .. }) if path == "cells[0].content"
+    ));
+}
@@ -0,0 +1,32 @@
+use senra_api::*;
+use serde_json::json;
+
+#[test]
+fn test_tagged_unions_are_discriminated() {
+    let schema = notebook_content_schema();
+
+    let cell = &schema["$defs"]["Cell"];
+    assert!(cell.get("oneOf").is_none());
+    assert_eq!(
+        cell["properties"]["cell_type"]["enum"],
+        json!(["markdown", "code", "render"])
+    );
+    assert!(
+        cell["required"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("cell_type"))
+    );
+
+    let branches = cell["allOf"].as_array().unwrap();
+    assert_eq!(branches.len(), 3);
+    assert_eq!(
+        branches[2]["if"]["properties"]["cell_type"]["const"],
+        "render"
+    );
+    assert!(branches[2]["then"]["properties"]["content"].is_object());
+
+    let geometry = &schema["$defs"]["GeometryConfig"];
+    assert!(geometry.get("oneOf").is_none());
+    assert!(geometry["allOf"].is_array());
+}
@@ -0,0 +1,130 @@
+use senra_api::*;
+use serde_json::{Value, json};
+
+fn create_request() -> CreateNotebookRequest {
+    CreateNotebookRequest {
+        title: "Encoded".to_string(),
+        description: None,
+        content: json!({ "cells": [] }),
+        resources: vec![CreateResourceRequest {
+            notebook_id: 0,
+            name: "noise.png".to_string(),
+            resource_type: "texture".to_string(),
+            data: (0..=255).collect(),
+            metadata: Some(json!({ "width": 16, "height": 16 })),
+        }],
+        shaders: Vec::new(),
+        tags: vec!["bytes".to_string()],
+        preview: Some(vec![0, 1, 2, 254, 255]),
+        visibility: "public".to_string(),
+    }
+}
+
+#[test]
+fn test_round_trip_both_encodings() {
+    let request = create_request();
+
+    for encoding in [Encoding::Json, Encoding::MessagePack] {
+        let bytes = encoding.encode(&request).unwrap();
+        let decoded: CreateNotebookRequest = encoding.decode(&bytes).unwrap();
+
+        assert_eq!(decoded.title, request.title);
+        assert_eq!(decoded.preview, request.preview);
+        assert_eq!(decoded.resources[0].data, request.resources[0].data);
+        assert_eq!(decoded.resources[0].metadata, request.resources[0].metadata);
+        assert_eq!(decoded.content, request.content);
+    }
+
+    // MessagePack keeps bytes as they are, JSON grows them by a third
+    let json = Encoding::Json.encode(&request).unwrap();
+    let msgpack = Encoding::MessagePack.encode(&request).unwrap();
+    assert!(msgpack.len() < json.len());
+    assert!(json.len() < 256 * 4 / 3 + 512);
+}
+
+#[test]
+fn test_json_bytes_are_base64() {
+    let value = serde_json::to_value(create_request()).unwrap();
+    assert_eq!(value["preview"], "AAEC/v8=");
+    assert!(value["resources"][0]["data"].is_string());
+
+    let edit: EditUserRequest = serde_json::from_value(json!({ "avatar": "AAEC/v8=" })).unwrap();
+    assert_eq!(edit.avatar, Some(vec![0, 1, 2, 254, 255]));
+
+    // Integer arrays from older clients are still accepted
+    let edit: EditUserRequest =
+        serde_json::from_value(json!({ "avatar": [0, 1, 2, 254, 255] })).unwrap();
+    assert_eq!(edit.avatar, Some(vec![0, 1, 2, 254, 255]));
+
+    // Absent and null byte fields are both none
+    let edit: EditUserRequest = serde_json::from_value(json!({ "username": "user" })).unwrap();
+    assert_eq!(edit.avatar, None);
+    let edit: EditUserRequest = serde_json::from_value(json!({ "avatar": null })).unwrap();
+    assert_eq!(edit.avatar, None);
+
+    let invalid = serde_json::from_value::<EditUserRequest>(json!({ "avatar": "not base64!" }));
+    assert!(invalid.is_err());
+}
+
+#[test]
+fn test_tagged_enums_round_trip() {
+    let request = Request::UpdateResource {
+        notebook_id: 1,
+        resource_id: 2,
+        data: vec![9, 8, 7],
+        metadata: None,
+    };
+    let response = Response::Blob {
+        blob: BlobRef::new("ab".repeat(32)),
+        data: vec![1, 2, 3],
+    };
+
+    for encoding in [Encoding::Json, Encoding::MessagePack] {
+        let decoded: Request = encoding
+            .decode(&encoding.encode(&request).unwrap())
+            .unwrap();
+        assert!(matches!(
+            decoded,
+            Request::UpdateResource { data, .. } if data == vec![9, 8, 7]
+        ));
+
+        let decoded: Response = encoding
+            .decode(&encoding.encode(&response).unwrap())
+            .unwrap();
+        let Response::Blob { blob, data } = decoded else {
+            panic!("Expected a blob response");
+        };
+        assert_eq!(blob.hash, "ab".repeat(32));
+        assert_eq!(data, vec![1, 2, 3]);
+    }
+
+    let value: Value = serde_json::to_value(&response).unwrap();
+    assert_eq!(value["payload"]["data"], "AQID");
+}
+
+#[test]
+fn test_negotiation_headers() {
+    assert_eq!(
+        Encoding::from_content_type("application/msgpack"),
+        Some(Encoding::MessagePack)
+    );
+    assert_eq!(
+        Encoding::from_content_type("application/json; charset=utf-8"),
+        Some(Encoding::Json)
+    );
+    assert_eq!(Encoding::from_content_type("text/html"), None);
+
+    assert_eq!(Encoding::from_accept("*/*"), Encoding::Json);
+    assert_eq!(
+        Encoding::from_accept("application/msgpack"),
+        Encoding::MessagePack
+    );
+    assert_eq!(
+        Encoding::from_accept("application/json;q=0.5, application/msgpack"),
+        Encoding::MessagePack
+    );
+    assert_eq!(
+        Encoding::from_accept("application/json, application/msgpack;q=0.9"),
+        Encoding::Json
+    );
+}
@@ -0,0 +1,131 @@
+use senra_api::*;
+use serde_json::json;
+
+fn render_config() -> serde_json::Value {
+    json!({
+        "width": 800,
+        "height": 600,
+        "shader_ids": [1, 2],
+        "resource_ids": [],
+        "pipeline": {
+            "shader_bindings": [
+                { "shader_index": 0, "shader_stage": "vertex", "entry_point": "vs_main" },
+                { "shader_index": 1, "shader_stage": "fragment", "entry_point": "fs_main" }
+            ],
+            "vertex_attributes": [
+                { "name": "position", "format": "float32x3", "offset": 0, "stride": 20 }
+            ],
+            "resource_bindings": [],
+            "render_passes": [{
+                "id": "main",
+                "input_textures": [{
+                    "texture_id": "previous",
+                    "group": 1,
+                    "binding": 0,
+                    "sampler_config": { "mag_filter": "nearest", "address_mode_u": "mirror-repeat" }
+                }],
+                "output_textures": [{
+                    "format": "rgba8unorm-srgb",
+                    "blend": { "src_factor": "src-alpha", "dst_factor": "one-minus-src-alpha" }
+                }]
+            }]
+        },
+        "camera": {
+            "position": [0.0, 0.0, 3.0],
+            "target": [0.0, 0.0, 0.0],
+            "up": [0.0, 1.0, 0.0],
+            "fov": 45.0,
+            "near": 0.1,
+            "far": 100.0
+        },
+        "performance": {}
+    })
+}
+
+#[test]
+fn test_cell_payload_tagging() {
+    let content: NotebookContent = serde_json::from_value(json!({
+        "version": "1.0",
+        "cells": [
+            { "id": "a", "cell_type": "markdown", "content": "# Title", "metadata": {} },
+            { "id": "b", "cell_type": "code", "content": "fn main() {}", "metadata": { "collapsed": true } },
+            { "id": "c", "cell_type": "render", "content": render_config() }
+        ]
+    }))
+    .unwrap();
+
+    assert_eq!(content.cells[0].cell_type(), CellType::Markdown);
+    assert!(matches!(&content.cells[0].payload, CellPayload::Markdown(text) if text == "# Title"));
+    assert_eq!(content.cells[1].cell_type(), CellType::Code);
+    assert!(content.cells[1].metadata.collapsed);
+    assert_eq!(content.cells[2].cell_type(), CellType::Render);
+    assert!(!content.cells[2].metadata.collapsed);
+
+    let value = serde_json::to_value(&content).unwrap();
+    assert_eq!(value["cells"][1]["cell_type"], "code");
+    assert_eq!(value["cells"][1]["content"], "fn main() {}");
+    assert_eq!(value["cells"][2]["cell_type"], "render");
+    assert!(value["cells"][2]["content"].is_object());
+}
+
+#[test]
+fn test_render_config_from_encoded_string() {
+    let encoded = render_config().to_string();
+    let cell: Cell = serde_json::from_value(json!({
+        "id": "render",
+        "cell_type": "render",
+        "content": encoded,
+        "metadata": { "collapsed": false }
+    }))
+    .unwrap();
+
+    let CellPayload::Render(config) = &cell.payload else {
+        panic!("expected a render payload");
+    };
+    assert_eq!(config.width, 800);
+    assert_eq!(
+        config.pipeline.vertex_attributes[0].format,
+        VertexFormat::Float32x3
+    );
+
+    let pass = &config.pipeline.render_passes[0];
+    let sampler = pass.input_textures[0].sampler_config.as_ref().unwrap();
+    assert_eq!(sampler.mag_filter, FilterMode::Nearest);
+    assert_eq!(sampler.min_filter, FilterMode::Linear);
+    assert_eq!(sampler.address_mode_u, AddressMode::MirrorRepeat);
+    assert_eq!(sampler.address_mode_v, AddressMode::ClampToEdge);
+
+    let output = &pass.output_textures[0];
+    assert_eq!(output.format, TextureFormat::Rgba8UnormSrgb);
+    let blend = output.blend.as_ref().unwrap();
+    assert_eq!(blend.src_factor, BlendFactor::SrcAlpha);
+    assert_eq!(blend.dst_factor, BlendFactor::OneMinusSrcAlpha);
+    assert_eq!(blend.operation, BlendOperation::Add);
+
+    // Re-serializing stores the configuration inline
+    let value = serde_json::to_value(&cell).unwrap();
+    assert_eq!(
+        value["content"]["pipeline"]["render_passes"][0]["output_textures"][0]["format"],
+        "rgba8unorm-srgb"
+    );
+}
+
+#[test]
+fn test_invalid_enum_values_are_rejected() {
+    let mut config = render_config();
+    config["pipeline"]["vertex_attributes"][0]["format"] = json!("vec3");
+
+    let result = serde_json::from_value::<Cell>(json!({
+        "id": "render",
+        "cell_type": "render",
+        "content": config,
+    }));
+    assert!(result.is_err());
+
+    let result = serde_json::from_value::<Cell>(json!({
+        "id": "unknown",
+        "cell_type": "python",
+        "content": "print()",
+    }));
+    assert!(result.is_err());
+}
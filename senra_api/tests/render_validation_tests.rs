@@ -0,0 +1,256 @@
+use senra_api::*;
+use serde_json::{Value, json};
+
+const VERTEX_SHADER: &str = r#"
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(f32(index), 0.0, 0.0, 1.0);
+}
+"#;
+
+const FRAGMENT_SHADER: &str = r#"
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+const GLSL_FRAGMENT_SHADER: &str = r#"#version 450
+layout(location = 0) out vec4 color;
+
+void main() {
+    color = vec4(1.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+fn shader_code(id: i64) -> Option<ShaderSource<'static>> {
+    match id {
+        1 => Some(ShaderSource::wgsl(VERTEX_SHADER)),
+        2 => Some(ShaderSource::wgsl(FRAGMENT_SHADER)),
+        3 => Some(ShaderSource::wgsl("fn broken(")),
+        10 => Some(ShaderSource::glsl(GLSL_FRAGMENT_SHADER)),
+        11 => Some(ShaderSource::glsl(
+            "#version 450\nvoid main() {\n    float x = ;\n}\n",
+        )),
+        _ => None,
+    }
+}
+
+fn render_config(pipeline: Value) -> RenderConfig {
+    serde_json::from_value(json!({
+        "width": 800,
+        "height": 600,
+        "shader_ids": [1, 2],
+        "resource_ids": [7],
+        "pipeline": pipeline,
+        "camera": {
+            "position": [0.0, 0.0, 3.0],
+            "target": [0.0, 0.0, 0.0],
+            "up": [0.0, 1.0, 0.0],
+            "fov": 45.0,
+            "near": 0.1,
+            "far": 100.0
+        },
+        "performance": {}
+    }))
+    .unwrap()
+}
+
+fn valid_pipeline() -> Value {
+    json!({
+        "shader_bindings": [
+            { "shader_index": 0, "shader_stage": "vertex", "entry_point": "vs_main" },
+            { "shader_index": 1, "shader_stage": "fragment", "entry_point": "fs_main" }
+        ],
+        "vertex_attributes": [],
+        "resource_bindings": [
+            { "resource_index": 0, "group": 0, "binding": 0, "binding_type": "uniform" }
+        ],
+        "render_passes": [
+            {
+                "id": "buffer",
+                "pass_type": "intermediate",
+                "input_textures": [
+                    { "texture_id": "buffer", "group": 1, "binding": 0 },
+                    { "texture_id": "7", "group": 1, "binding": 1 }
+                ],
+                "output_textures": [{ "id": "buffer_color" }]
+            },
+            {
+                "id": "main",
+                "input_textures": [
+                    { "texture_id": "buffer_color", "group": 1, "binding": 0 },
+                    { "texture_id": "previous", "group": 1, "binding": 1 }
+                ]
+            }
+        ]
+    })
+}
+
+fn error_paths(result: Result<(), Vec<FieldError>>) -> Vec<String> {
+    result
+        .unwrap_err()
+        .into_iter()
+        .map(|error| error.path)
+        .collect()
+}
+
+#[test]
+fn test_valid_config() {
+    assert_eq!(
+        render_config(valid_pipeline()).validate(shader_code),
+        Ok(())
+    );
+}
+
+#[test]
+fn test_out_of_range_indices() {
+    let mut pipeline = valid_pipeline();
+    pipeline["shader_bindings"][1]["shader_index"] = json!(2);
+    pipeline["resource_bindings"][0]["resource_index"] = json!(1);
+
+    assert_eq!(
+        error_paths(render_config(pipeline).validate(shader_code)),
+        [
+            "/pipeline/shader_bindings/1/shader_index",
+            "/pipeline/resource_bindings/0/resource_index"
+        ]
+    );
+}
+
+#[test]
+fn test_entry_points() {
+    let mut pipeline = valid_pipeline();
+    pipeline["shader_bindings"][0]["entry_point"] = json!("main");
+    pipeline["shader_bindings"][1]["shader_stage"] = json!("vertex");
+
+    let errors = render_config(pipeline).validate(shader_code).unwrap_err();
+    assert_eq!(errors[0].path, "/pipeline/shader_bindings/0/entry_point");
+    assert!(errors[0].message.contains("`main` is not declared"));
+    assert_eq!(errors[1].path, "/pipeline/shader_bindings/1/entry_point");
+    assert_eq!(errors.len(), 2);
+
+    let mut config = render_config(valid_pipeline());
+    config.shader_ids = vec![3, 4];
+    assert_eq!(
+        error_paths(config.validate(shader_code)),
+        ["/shader_ids/0", "/shader_ids/1"]
+    );
+}
+
+#[test]
+fn test_unknown_textures_and_cycles() {
+    let mut pipeline = valid_pipeline();
+    pipeline["render_passes"][0]["input_textures"][1]["texture_id"] = json!("main");
+    pipeline["render_passes"][1]["output_textures"] = json!([{ "id": "final" }]);
+    pipeline["render_passes"][0]["input_textures"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({ "texture_id": "missing", "group": 1, "binding": 2 }));
+
+    let errors = render_config(pipeline).validate(shader_code).unwrap_err();
+    assert_eq!(
+        errors.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+        [
+            "/pipeline/render_passes/0/input_textures/2/texture_id",
+            "/pipeline/render_passes/0/input_textures/1/texture_id",
+            "/pipeline/render_passes/1/input_textures/0/texture_id"
+        ]
+    );
+    assert_eq!(
+        errors[1].message,
+        "Passes form a cycle: buffer -> main -> buffer"
+    );
+}
+
+#[test]
+fn test_binding_slots_and_pass_ids() {
+    let mut pipeline = valid_pipeline();
+    pipeline["render_passes"][0]["input_textures"][1]["group"] = json!(0);
+    pipeline["render_passes"][0]["input_textures"][1]["binding"] = json!(0);
+    pipeline["render_passes"][1]["id"] = json!("buffer");
+    pipeline["resource_bindings"]
+        .as_array_mut()
+        .unwrap()
+        .push(json!({ "resource_index": 0, "group": 0, "binding": 0, "binding_type": "texture" }));
+
+    let paths = error_paths(render_config(pipeline).validate(shader_code));
+    assert!(paths.contains(&"/pipeline/resource_bindings/1".to_string()));
+    assert!(paths.contains(&"/pipeline/render_passes/0/input_textures/1".to_string()));
+    assert!(paths.contains(&"/pipeline/render_passes/1/id".to_string()));
+
+    let mut pipeline = valid_pipeline();
+    pipeline["render_passes"][0]["input_textures"][0]["texture_id"] = json!("previous");
+    assert_eq!(
+        error_paths(render_config(pipeline).validate(shader_code)),
+        ["/pipeline/render_passes/0/input_textures/0/texture_id"]
+    );
+}
+
+#[test]
+fn test_notebook_paths() {
+    let config = render_config(json!({
+        "shader_bindings": [
+            { "shader_index": 5, "shader_stage": "vertex", "entry_point": "vs_main" }
+        ],
+        "vertex_attributes": [],
+        "resource_bindings": []
+    }));
+    let content = NotebookContent {
+        version: CURRENT_CONTENT_VERSION.to_string(),
+        cells: vec![
+            Cell {
+                id: "text".to_string(),
+                payload: CellPayload::Markdown("# Title".to_string()),
+                metadata: CellMetadata::default(),
+            },
+            Cell {
+                id: "render".to_string(),
+                payload: CellPayload::Render(Box::new(config)),
+                metadata: CellMetadata::default(),
+            },
+        ],
+        metadata: json!({}),
+    };
+
+    assert_eq!(
+        error_paths(content.validate(shader_code)),
+        ["/cells/1/content/pipeline/shader_bindings/0/shader_index"]
+    );
+}
+
+#[test]
+fn test_glsl_shaders() {
+    let mut pipeline = valid_pipeline();
+    pipeline["shader_bindings"][1]["entry_point"] = json!("main");
+    let mut config = render_config(pipeline.clone());
+    config.shader_ids = vec![1, 10];
+    assert_eq!(config.validate(shader_code), Ok(()));
+
+    // GLSL shaders are always entered through `main`
+    pipeline["shader_bindings"][1]["entry_point"] = json!("fs_main");
+    let mut config = render_config(pipeline.clone());
+    config.shader_ids = vec![1, 10];
+    let errors = config.validate(shader_code).unwrap_err();
+    assert_eq!(errors[0].path, "/pipeline/shader_bindings/1/entry_point");
+    assert!(errors[0].message.contains("`main`"));
+
+    pipeline["shader_bindings"][1]["entry_point"] = json!("main");
+    let mut config = render_config(pipeline);
+    config.shader_ids = vec![1, 11];
+    let errors = config.validate(shader_code).unwrap_err();
+    assert_eq!(errors.len(), 1);
+    assert_eq!(errors[0].path, "/shader_ids/1");
+    assert!(errors[0].message.contains("not valid GLSL: Line 3"));
+}
+
+#[test]
+fn test_shader_language_keys() {
+    for language in ShaderLanguage::ALL {
+        assert_eq!(
+            language.as_str().parse::<ShaderLanguage>().unwrap(),
+            language
+        );
+    }
+    assert!("hlsl".parse::<ShaderLanguage>().is_err());
+}
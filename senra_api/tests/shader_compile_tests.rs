@@ -0,0 +1,215 @@
+use senra_api::*;
+
+const GLSL_FRAGMENT_SHADER: &str = r#"#version 450
+layout(set = 0, binding = 0) uniform Uniforms {
+    float time;
+};
+layout(location = 0) out vec4 color;
+
+void main() {
+    color = vec4(sin(time), 0.0, 0.0, 1.0);
+}
+"#;
+
+const SPIRV_MAGIC: u32 = 0x0723_0203;
+
+#[test]
+fn test_glsl_to_wgsl() {
+    let compiled = ShaderSource::glsl(GLSL_FRAGMENT_SHADER)
+        .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+        .unwrap();
+    let CompiledShader::Source(wgsl) = compiled else {
+        panic!("expected WGSL output");
+    };
+    assert!(wgsl.contains("@fragment"));
+    assert!(wgsl.contains("fn main("));
+
+    // The translation is itself valid WGSL
+    ShaderSource::wgsl(&wgsl)
+        .compile(&ShaderStage::Fragment, CompileTarget::SpirV)
+        .unwrap();
+}
+
+#[test]
+fn test_spirv_output() {
+    for source in [
+        ShaderSource::glsl(GLSL_FRAGMENT_SHADER),
+        ShaderSource::wgsl(
+            "@fragment fn fs_main() -> @location(0) vec4<f32> { return vec4<f32>(1.0); }",
+        ),
+    ] {
+        let CompiledShader::SpirV(words) = source
+            .compile(&ShaderStage::Fragment, CompileTarget::SpirV)
+            .unwrap()
+        else {
+            panic!("expected SPIR-V output");
+        };
+        assert_eq!(words[0], SPIRV_MAGIC);
+    }
+}
+
+#[test]
+fn test_compile_errors() {
+    let error = ShaderSource::glsl("#version 450\nvoid main() { undefined_call(); }\n")
+        .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+        .unwrap_err();
+    assert!(matches!(error, ShaderCompileError::Parse(_)));
+    assert!(error.to_string().starts_with("Line 2:"));
+}
+
+const WGSL_SHADER: &str = r#"
+struct Uniforms {
+    transform: mat4x4<f32>,
+    time: f32,
+    tint: vec3<f32>,
+}
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    return vec4<f32>(f32(index), 0.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(uniforms.tint * sin(uniforms.time), 1.0);
+}
+"#;
+
+fn export_content() -> NotebookContent {
+    serde_json::from_value(serde_json::json!({
+        "version": CURRENT_CONTENT_VERSION,
+        "cells": [{
+            "id": "render",
+            "cell_type": "render",
+            "content": {
+                "width": 800,
+                "height": 600,
+                "shader_ids": [1],
+                "resource_ids": [7],
+                "pipeline": {
+                    "shader_bindings": [
+                        { "shader_index": 0, "shader_stage": "vertex", "entry_point": "vs_main" },
+                        { "shader_index": 0, "shader_stage": "fragment", "entry_point": "fs_main" }
+                    ],
+                    "vertex_attributes": [],
+                    "resource_bindings": [
+                        { "resource_index": 0, "group": 0, "binding": 0, "binding_type": "uniform" }
+                    ]
+                },
+                "camera": {
+                    "position": [0.0, 0.0, 3.0],
+                    "target": [0.0, 0.0, 0.0],
+                    "up": [0.0, 1.0, 0.0],
+                    "fov": 45.0,
+                    "near": 0.1,
+                    "far": 100.0
+                },
+                "performance": {}
+            }
+        }]
+    }))
+    .unwrap()
+}
+
+#[test]
+fn test_export_files() {
+    let shaders = [
+        ExportShader {
+            id: 1,
+            name: "Main shader",
+            source: ShaderSource::wgsl(WGSL_SHADER),
+        },
+        ExportShader {
+            id: 2,
+            name: "glow",
+            source: ShaderSource::glsl(GLSL_FRAGMENT_SHADER),
+        },
+        ExportShader {
+            id: 3,
+            name: "broken",
+            source: ShaderSource::wgsl("fn broken("),
+        },
+    ];
+    let export = export_shaders(&export_content(), &shaders, &CompileTarget::EXPORT);
+
+    let paths: Vec<&str> = export.files.iter().map(|file| file.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "glsl_es/1-Main_shader.vs_main.glsl",
+            "glsl_es/1-Main_shader.fs_main.glsl",
+            "hlsl/1-Main_shader.hlsl",
+            "msl/1-Main_shader.metal",
+            "spirv/1-Main_shader.spv",
+            "glsl_es/2-glow.fragment.main.glsl",
+            "hlsl/2-glow.fragment.hlsl",
+            "msl/2-glow.fragment.metal",
+            "spirv/2-glow.fragment.spv",
+        ]
+    );
+
+    let CompiledShader::Source(glsl) = &export.files[1].output else {
+        panic!("expected GLSL output");
+    };
+    assert!(glsl.starts_with("#version 300 es"));
+    assert_eq!(
+        export.files[4].output.to_bytes()[..4],
+        SPIRV_MAGIC.to_le_bytes()
+    );
+
+    assert_eq!(export.errors.len(), 1);
+    assert_eq!(export.errors[0].path, "/shaders/3");
+}
+
+#[test]
+fn test_export_reflection() {
+    let shaders = [ExportShader {
+        id: 1,
+        name: "main",
+        source: ShaderSource::wgsl(WGSL_SHADER),
+    }];
+    let export = export_shaders(&export_content(), &shaders, &[]);
+    assert!(export.files.is_empty());
+
+    let [pipeline] = &export.reflection.pipelines[..] else {
+        panic!("expected a single pipeline");
+    };
+    assert_eq!(pipeline.cell_id, "render");
+    assert_eq!(pipeline.entry_points.len(), 2);
+
+    let [group] = &pipeline.bind_groups[..] else {
+        panic!("expected a single bind group");
+    };
+    let [binding] = &group.bindings[..] else {
+        panic!("expected a single binding");
+    };
+    assert_eq!(binding.resource_id, Some(7));
+    assert_eq!(binding.name.as_deref(), Some("uniforms"));
+    // Only the fragment entry point reads the uniforms
+    assert_eq!(binding.visibility, [ShaderStage::Fragment]);
+
+    let layout = binding.layout.as_ref().unwrap();
+    assert_eq!(layout.type_name, "Uniforms");
+    assert_eq!(layout.size, 96);
+    let members: Vec<(&str, &str, u32)> = layout
+        .members
+        .iter()
+        .map(|member| {
+            (
+                member.name.as_str(),
+                member.type_name.as_str(),
+                member.offset,
+            )
+        })
+        .collect();
+    assert_eq!(
+        members,
+        [
+            ("transform", "mat4x4<f32>", 0),
+            ("time", "f32", 64),
+            ("tint", "vec3<f32>", 80),
+        ]
+    );
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+
+use senra_api::*;
+
+const NOISE: &str = r#"fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}"#;
+
+const SDF: &str = r#"#import "noise"
+fn sd_circle(p: vec2<f32>, r: f32) -> f32 {
+    return length(p) - r + hash(p) * 0.01;
+}"#;
+
+const MAIN: &str = r#"#import "noise"
+#import "sdf" // shapes
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(sd_circle(position.xy, 0.5));
+}"#;
+
+fn modules() -> HashMap<String, String> {
+    HashMap::from([
+        ("noise".to_string(), NOISE.to_string()),
+        ("sdf".to_string(), SDF.to_string()),
+    ])
+}
+
+#[test]
+fn test_expand_imports() {
+    let modules = modules();
+    let shader = preprocess("main", MAIN, |name| modules.get(name).map(String::as_str)).unwrap();
+
+    // Modules are included once, before their first import
+    assert_eq!(shader.code.matches("fn hash").count(), 1);
+    assert!(shader.code.find("fn hash") < shader.code.find("fn sd_circle"));
+    assert!(!shader.code.contains(IMPORT_DIRECTIVE));
+    assert_eq!(shader.lines.len(), shader.code.lines().count());
+
+    assert_eq!(
+        shader.source_line(4),
+        Some(&SourceLine {
+            module: "sdf".to_string(),
+            line: 2
+        })
+    );
+    assert_eq!(shader.line_label(7), "Line 3");
+    assert_eq!(
+        shader.map_lines("Line 5: unknown identifier\nnote"),
+        "Line 3 of `sdf`: unknown identifier\nnote"
+    );
+
+    ShaderSource::preprocessed(&shader, ShaderLanguage::Wgsl)
+        .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+        .unwrap_or_else(|err| panic!("{}", err));
+}
+
+#[test]
+fn test_import_errors() {
+    let mut modules = modules();
+    let resolve = |modules: &HashMap<String, String>, code| {
+        preprocess("main", code, |name| modules.get(name).map(String::as_str))
+    };
+
+    assert_eq!(
+        resolve(&modules, "fn a() {}\n#import noise"),
+        Err(ImportError::Malformed {
+            module: "main".to_string(),
+            line: 2
+        })
+    );
+    assert_eq!(
+        resolve(&modules, "#import \"sdf\"\n#import \"missing\""),
+        Err(ImportError::NotFound {
+            module: "main".to_string(),
+            line: 2,
+            name: "missing".to_string()
+        })
+    );
+
+    modules.insert("noise".to_string(), "#import \"sdf\"".to_string());
+    let error = resolve(&modules, MAIN).unwrap_err();
+    assert_eq!(
+        error,
+        ImportError::Cycle(vec![
+            "noise".to_string(),
+            "sdf".to_string(),
+            "noise".to_string()
+        ])
+    );
+    assert_eq!(error.to_string(), "Import cycle noise -> sdf -> noise");
+
+    assert!(matches!(
+        resolve(&modules, "#import \"main\""),
+        Err(ImportError::Cycle(cycle)) if cycle == ["main", "main"]
+    ));
+}
+
+#[test]
+fn test_error_lines() {
+    let modules = HashMap::from([(
+        "broken".to_string(),
+        "fn ok() {}\nfn broken() { let x = ; }".to_string(),
+    )]);
+    let shader = preprocess("main", "#import \"broken\"\nfn main() {}", |name| {
+        modules.get(name).map(String::as_str)
+    })
+    .unwrap();
+
+    let error = ShaderSource::preprocessed(&shader, ShaderLanguage::Wgsl)
+        .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+        .unwrap_err();
+    assert!(error.to_string().starts_with("Line 2 of `broken`: "));
+}
+
+#[test]
+fn test_library_imports() {
+    assert_eq!(shader_imports(MAIN), ["noise", "sdf"]);
+    assert_eq!(
+        LibraryImport::parse("12/palette"),
+        Some(LibraryImport {
+            notebook_id: 12,
+            name: "palette"
+        })
+    );
+    assert_eq!(LibraryImport::parse("palette"), None);
+    assert_eq!(LibraryImport::parse("12/"), None);
+
+    assert_eq!(
+        qualify_imports(SDF, 12).lines().next(),
+        Some("#import \"12/noise\"")
+    );
+    assert_eq!(
+        qualify_imports("#import \"3/noise\"", 12),
+        "#import \"3/noise\""
+    );
+}
+
+#[test]
+fn test_prelude() {
+    let wgsl = r#"@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(fract(uniforms.time));
+}"#;
+    let shader = preprocess("main", wgsl, |_| None)
+        .unwrap()
+        .with_prelude(ShaderLanguage::Wgsl);
+    assert!(shader.code.ends_with(&format!("{}\n", wgsl)));
+    ShaderSource::preprocessed(&shader, ShaderLanguage::Wgsl)
+        .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+        .unwrap();
+
+    // Errors point to the lines of the shader, not of the prelude
+    let shader = preprocess("main", "fn ok() {}\nfn broken() { let x = ; }", |_| None)
+        .unwrap()
+        .with_prelude(ShaderLanguage::Wgsl);
+    let error = ShaderSource::preprocessed(&shader, ShaderLanguage::Wgsl)
+        .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+        .unwrap_err();
+    assert!(error.to_string().starts_with("Line 2: "));
+
+    // The `#version` of GLSL shaders stays first
+    let glsl = r#"#version 450
+layout(location = 0) out vec4 color;
+
+void main() {
+    color = vec4(fract(uniforms.time));
+}"#;
+    let shader = preprocess("main", glsl, |_| None)
+        .unwrap()
+        .with_prelude(ShaderLanguage::Glsl);
+    assert_eq!(shader.code.matches("#version").count(), 1);
+    assert!(shader.code.starts_with("#version 450\n"));
+    assert_eq!(shader.line_label(1), "Line 1");
+    assert_eq!(shader.line_label(2), "Line 2 of `prelude`");
+    ShaderSource::preprocessed(&shader, ShaderLanguage::Glsl)
+        .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+        .unwrap();
+}
@@ -0,0 +1,132 @@
+use senra_api::*;
+
+const NOISE_1_0: &str =
+    "fn hash(p: vec2<f32>) -> f32 {\n    return fract(sin(p.x) * 43758.5453);\n}";
+const NOISE_1_2: &str = "fn hash(p: vec2<f32>) -> f32 {\n    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);\n}";
+const NOISE_2_0: &str =
+    "fn hash2(p: vec2<f32>) -> vec2<f32> {\n    return fract(sin(p) * 43758.5453);\n}";
+const SDF_1_0: &str = "#import \"noise@1.2.0\"\nfn sd_circle(p: vec2<f32>, r: f32) -> f32 {\n    return length(p) - r + hash(p) * 0.01;\n}";
+
+fn available() -> Vec<PackageSource<'static>> {
+    vec![
+        PackageSource {
+            name: "noise",
+            version: Version::new(1, 0, 0),
+            code: NOISE_1_0,
+        },
+        PackageSource {
+            name: "noise",
+            version: Version::new(1, 2, 0),
+            code: NOISE_1_2,
+        },
+        PackageSource {
+            name: "noise",
+            version: Version::new(2, 0, 0),
+            code: NOISE_2_0,
+        },
+        PackageSource {
+            name: "sdf",
+            version: Version::new(1, 0, 0),
+            code: SDF_1_0,
+        },
+    ]
+}
+
+#[test]
+fn test_versions() {
+    assert_eq!("1.12.0".parse(), Ok(Version::new(1, 12, 0)));
+    assert_eq!(Version::new(0, 3, 1).to_string(), "0.3.1");
+    for invalid in ["1.2", "1.2.3.4", "01.2.3", "1.-2.3", "1.2.x", ""] {
+        assert_eq!(
+            invalid.parse::<Version>(),
+            Err(PackageError::InvalidVersion(invalid.to_string()))
+        );
+    }
+
+    assert!(Version::new(1, 0, 0) < Version::new(1, 0, 10));
+    assert!(Version::new(1, 2, 0).is_compatible(&Version::new(1, 9, 3)));
+    assert!(!Version::new(1, 2, 0).is_compatible(&Version::new(2, 0, 0)));
+    assert!(!Version::new(0, 1, 0).is_compatible(&Version::new(0, 2, 0)));
+
+    assert_eq!(
+        PackageImport::parse("noise@1.2.0"),
+        Some(Ok(PackageImport {
+            name: "noise",
+            version: Version::new(1, 2, 0)
+        }))
+    );
+    assert_eq!(PackageImport::parse("12/noise"), None);
+    assert!(matches!(
+        PackageImport::parse("Noise@1.2.0"),
+        Some(Err(PackageError::InvalidName(_)))
+    ));
+}
+
+#[test]
+fn test_resolve_highest_required_version() {
+    let available = available();
+    let main = "#import \"noise@1.0.0\"\n#import \"sdf@1.0.0\"";
+
+    let lock = PackageLock::resolve(&[main], &available).unwrap();
+    let selected: Vec<String> = lock.imports().map(|import| import.to_string()).collect();
+    assert_eq!(selected, ["noise@1.2.0", "sdf@1.0.0"]);
+
+    // Resolution does not depend on the order of sources or available versions
+    let mut reversed = available.clone();
+    reversed.reverse();
+    assert_eq!(
+        PackageLock::resolve(&["#import \"sdf@1.0.0\"", main], &reversed),
+        Ok(lock.clone())
+    );
+
+    // The package imported at both versions is included once, at the selected version
+    let shader = lock.preprocess("main", main, |_| None).unwrap();
+    assert_eq!(shader.code.matches("fn hash").count(), 1);
+    assert!(shader.code.contains("dot(p"));
+    assert_eq!(shader.line_label(1), "Line 1 of `noise@1.2.0`");
+}
+
+#[test]
+fn test_resolve_errors() {
+    let available = available();
+
+    assert_eq!(
+        PackageLock::resolve(&["#import \"noise@1.1.0\""], &available),
+        Err(PackageError::NotFound("noise@1.1.0".to_string()))
+    );
+
+    let error = PackageLock::resolve(
+        &["#import \"noise@2.0.0\"\n#import \"sdf@1.0.0\""],
+        &available,
+    )
+    .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "Package `noise` is required at incompatible versions 1.2.0, 2.0.0"
+    );
+
+    // Unlocked package imports are left to the module resolution
+    let lock = PackageLock::default();
+    assert!(matches!(
+        lock.preprocess("main", "#import \"noise@1.0.0\"", |_| None),
+        Err(ImportError::NotFound { name, .. }) if name == "noise@1.0.0"
+    ));
+}
+
+#[test]
+fn test_package_imports() {
+    let code = "#import \"noise@1.2.0\"\n#import \"palette\"\n#import \"bad@1\"";
+    assert_eq!(
+        package_imports(code),
+        [PackageImport {
+            name: "noise",
+            version: Version::new(1, 2, 0)
+        }]
+    );
+    assert_eq!(
+        qualify_imports(code, 3).lines().next(),
+        Some("#import \"noise@1.2.0\"")
+    );
+    assert_eq!(check_package_name("sdf-2d"), Ok(()));
+    assert!(check_package_name("2d").is_err());
+}
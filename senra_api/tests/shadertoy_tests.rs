@@ -0,0 +1,219 @@
+use senra_api::*;
+use serde_json::{Value, json};
+
+fn export() -> Value {
+    json!({
+        "Shader": {
+            "ver": "0.1",
+            "info": {
+                "id": "XsBXWt",
+                "name": "Feedback",
+                "username": "someone",
+                "description": "Trails",
+                "tags": ["feedback", "2d"]
+            },
+            "renderpass": [
+                {
+                    "inputs": [
+                        {
+                            "id": "4dXGR8",
+                            "src": "/media/previz/buffer00.png",
+                            "ctype": "buffer",
+                            "channel": 0,
+                            "sampler": { "filter": "linear", "wrap": "clamp" }
+                        },
+                        {
+                            "id": "XdX3Rn",
+                            "src": "/media/a/noise.png",
+                            "ctype": "texture",
+                            "channel": 1,
+                            "sampler": { "filter": "nearest", "wrap": "repeat" }
+                        },
+                        { "id": "4dXGRr", "src": "", "ctype": "keyboard", "channel": 2 }
+                    ],
+                    "outputs": [{ "id": "4dfGRr", "channel": 0 }],
+                    "code": "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    vec2 uv = fragCoord / iResolution.xy;\n    fragColor = texture(iChannel0, uv) + tint() * texture(iChannel1, uv).r;\n}",
+                    "name": "Image",
+                    "description": "",
+                    "type": "image"
+                },
+                {
+                    "inputs": [
+                        { "id": 257, "src": "", "ctype": "buffer", "channel": 0 }
+                    ],
+                    "outputs": [{ "id": 257, "channel": 0 }],
+                    "code": "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    vec2 uv = fragCoord / iResolution.xy;\n    float d = length(uv - iMouse.xy / iResolution.xy);\n    fragColor = max(texture(iChannel0, uv) * 0.98, vec4(smoothstep(0.05, 0.0, d)));\n}",
+                    "name": "Buffer A",
+                    "description": "",
+                    "type": "buffer"
+                },
+                {
+                    "inputs": [],
+                    "outputs": [],
+                    "code": "vec4 tint() {\n    return vec4(0.5 + 0.5 * sin(iTime), 0.2, 0.8, 1.0);\n}",
+                    "name": "Common",
+                    "description": "",
+                    "type": "common"
+                }
+            ]
+        }
+    })
+}
+
+/// Shaders of an archive by id, with the viewer prelude prepended
+fn with_prelude(archive: &NotebookArchive) -> Vec<(i64, PreprocessedShader)> {
+    archive
+        .shaders
+        .iter()
+        .map(|shader| {
+            let preprocessed = preprocess(&shader.name, &shader.code, |_| None)
+                .unwrap()
+                .with_prelude(ShaderLanguage::Wgsl);
+            (shader.id, preprocessed)
+        })
+        .collect()
+}
+
+fn render_config(archive: &NotebookArchive) -> &RenderConfig {
+    match &archive.content.cells[1].payload {
+        CellPayload::Render(config) => config,
+        _ => panic!("expected a render payload"),
+    }
+}
+
+#[test]
+fn test_import_passes_and_inputs() {
+    let import = import_shadertoy(&export()).unwrap();
+    let archive = &import.archive;
+
+    assert_eq!(archive.title, "Feedback");
+    assert_eq!(archive.tags, ["feedback", "2d"]);
+    assert_eq!(archive.shaders.len(), 3);
+    assert!(archive.shaders[1].code.contains("fn fs_main("));
+
+    let config = render_config(archive);
+    let passes = &config.pipeline.render_passes;
+    assert_eq!(passes.len(), 2);
+
+    assert_eq!(passes[0].id, "buffer_a");
+    assert!(matches!(passes[0].pass_type, RenderPassType::Intermediate));
+    assert_eq!(passes[0].output_textures[0].id, "buffer_a");
+    assert_eq!(
+        passes[0].output_textures[0].format,
+        TextureFormat::Rgba16Float
+    );
+    assert_eq!(passes[0].input_textures[0].texture_id, "buffer_a");
+
+    assert_eq!(passes[1].id, "image");
+    assert!(matches!(passes[1].pass_type, RenderPassType::Main));
+    let inputs = &passes[1].input_textures;
+    assert_eq!(inputs.len(), 2);
+    assert_eq!(inputs[0].texture_id, "buffer_a");
+    assert_eq!((inputs[0].group, inputs[0].binding), (1, 0));
+    assert_eq!(inputs[1].texture_id, archive.resources[0].id.to_string());
+    assert_eq!((inputs[1].group, inputs[1].binding), (1, 2));
+    let sampler = inputs[1].sampler_config.as_ref().unwrap();
+    assert_eq!(sampler.mag_filter, FilterMode::Nearest);
+    assert_eq!(sampler.address_mode_u, AddressMode::Repeat);
+
+    assert_eq!(archive.resources[0].name, "noise.png");
+    assert!(archive.resources[0].data.is_empty());
+
+    // The keyboard is not carried over, and the missing texture data is pointed out
+    let warnings: Vec<&str> = import.warnings.iter().map(|w| w.path.as_str()).collect();
+    assert_eq!(
+        warnings,
+        ["/renderpass/0/inputs/1", "/renderpass/0/inputs/2"]
+    );
+    assert!(import.warnings[1].message.contains("keyboard"));
+
+    // The imported configuration references its own shaders and entry points
+    let shaders = with_prelude(archive);
+    let result = config.validate(|id| {
+        let (_, shader) = shaders.iter().find(|(shader_id, _)| *shader_id == id)?;
+        Some(ShaderSource::preprocessed(shader, ShaderLanguage::Wgsl))
+    });
+    assert_eq!(result, Ok(()));
+}
+
+#[test]
+fn test_imported_passes_fit_the_viewer() {
+    let import = import_shadertoy(&export()).unwrap();
+    let shaders = with_prelude(&import.archive);
+
+    // Every pass draws with the viewer uniforms and channels, at the viewer entry point
+    for (_, shader) in &shaders[1..] {
+        let CompiledShader::Source(wgsl) = ShaderSource::preprocessed(shader, ShaderLanguage::Wgsl)
+            .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+            .unwrap()
+        else {
+            panic!("expected WGSL source");
+        };
+        let module = naga::front::wgsl::parse_str(&wgsl).unwrap();
+
+        assert!(module.entry_points.iter().any(|entry_point| {
+            entry_point.name == ShaderLanguage::WGSL_ENTRY_POINT
+                && entry_point.stage == naga::ShaderStage::Fragment
+        }));
+        for (_, global) in module.global_variables.iter() {
+            let Some(binding) = &global.binding else {
+                continue;
+            };
+            match (binding.group, binding.binding) {
+                (0, 0) => assert_eq!(global.name.as_deref(), Some("uniforms")),
+                (group, binding) => assert!(
+                    group == VIEWER_CHANNEL_GROUP && binding < VIEWER_CHANNEL_COUNT * 2,
+                    "{:?} is not bound by the viewer",
+                    global.name
+                ),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_untranslatable_code_is_reported() {
+    let mut export = export();
+    export["Shader"]["renderpass"][1]["code"] = json!(
+        "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    fragColor = undefined_function(fragCoord);\n}"
+    );
+
+    let Err(ShadertoyError::Untranslatable(errors)) = import_shadertoy(&export) else {
+        panic!("expected a translation error");
+    };
+    assert_eq!(errors[0].path, "/renderpass/1/code");
+    assert!(
+        errors[0].message.starts_with("Line 2: "),
+        "{}",
+        errors[0].message
+    );
+
+    let mut export = json!([export["Shader"].clone()]);
+    export[0]["renderpass"][1]["code"] =
+        json!("void mainImage(out vec4 c, in vec2 p) { c = vec4(1.0); }");
+    export[0]["renderpass"][2]["code"] = json!("vec4 tint() {\n    return vec4(1.0)\n}");
+
+    let Err(ShadertoyError::Untranslatable(errors)) = import_shadertoy(&export) else {
+        panic!("expected a translation error");
+    };
+    assert!(
+        errors[0].message.starts_with("Common line 3: "),
+        "{}",
+        errors[0].message
+    );
+}
+
+#[test]
+fn test_invalid_exports_are_rejected() {
+    assert!(matches!(
+        import_shadertoy(&json!({ "Shader": { "info": {} } })),
+        Err(ShadertoyError::InvalidExport(_))
+    ));
+
+    let mut export = export();
+    export["Shader"]["renderpass"][0]["type"] = json!("sound");
+    assert!(matches!(
+        import_shadertoy(&export),
+        Err(ShadertoyError::MissingImagePass)
+    ));
+}
@@ -1,21 +1,26 @@
 use iced::widget::{button, checkbox, column, container, row, text, text_input};
 use iced::{Alignment, Color, Element, Length, Task};
-use senra_api::{LoginRequest, RegisterRequest};
+use senra_api::{LoginRequest, RegisterRequest, TwoFactorChallengeResponse, TwoFactorLoginRequest};
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ErrorRequest(String),
+    TwoFactorChallengeRequest(TwoFactorChallengeResponse),
 
     LoginRespond(LoginRequest),
     RegisterRespond(RegisterRequest),
+    TwoFactorRespond(TwoFactorLoginRequest),
 
     Switch(AuthState),
     InputUsername(String),
     InputEmail(String),
     InputPassword(String),
+    InputCode(String),
     ToggleShowPassword,
     ClickRegister,
     ClickLogin,
+    ClickVerify,
+    CancelTwoFactor,
     Clear,
 }
 
@@ -32,6 +37,9 @@ pub struct AuthPage {
     email: String,
     password: String,
     show_password: bool,
+    /// Challenge of a login waiting for its second factor
+    challenge: Option<String>,
+    code: String,
     error_message: Option<String>,
 }
 
@@ -44,6 +52,8 @@ impl AuthPage {
                 email: Default::default(),
                 password: Default::default(),
                 show_password: false,
+                challenge: None,
+                code: Default::default(),
                 error_message: None,
             },
             Task::none(),
@@ -56,6 +66,12 @@ impl AuthPage {
                 self.error_message = Some(error);
                 Task::none()
             }
+            Message::TwoFactorChallengeRequest(challenge) => {
+                self.challenge = Some(challenge.challenge);
+                self.code.clear();
+                self.error_message = None;
+                Task::none()
+            }
             Message::Switch(state) => {
                 self.state = state;
                 self.error_message = None;
@@ -76,6 +92,11 @@ impl AuthPage {
                 self.error_message = None;
                 Task::none()
             }
+            Message::InputCode(code) => {
+                self.code = code;
+                self.error_message = None;
+                Task::none()
+            }
             Message::ToggleShowPassword => {
                 self.show_password = !self.show_password;
                 Task::none()
@@ -103,10 +124,33 @@ impl AuthPage {
                     password: self.password.clone(),
                 }))
             }
+            Message::ClickVerify => {
+                let Some(challenge) = &self.challenge else {
+                    return Task::none();
+                };
+                if self.code.trim().is_empty() {
+                    self.error_message = Some("Authentication code is required".to_string());
+                    return Task::none();
+                }
+                self.error_message = None;
+                Task::done(Message::TwoFactorRespond(TwoFactorLoginRequest {
+                    challenge: challenge.clone(),
+                    code: self.code.trim().to_string(),
+                }))
+            }
+            Message::CancelTwoFactor => {
+                self.challenge = None;
+                self.code.clear();
+                self.password.clear();
+                self.error_message = None;
+                Task::none()
+            }
             Message::Clear => {
                 self.username.clear();
                 self.email.clear();
                 self.password.clear();
+                self.challenge = None;
+                self.code.clear();
                 self.error_message = None;
                 Task::none()
             }
@@ -115,6 +159,10 @@ impl AuthPage {
     }
 
     pub fn view(&self) -> Element<Message> {
+        if self.challenge.is_some() {
+            return self.two_factor_view();
+        }
+
         let state_switch = row![
             button(text("Register").align_x(Alignment::Center))
                 .width(Length::FillPortion(1))
@@ -199,4 +247,48 @@ impl AuthPage {
             .align_top(Length::Fill)
             .into()
     }
+
+    fn two_factor_view(&self) -> Element<Message> {
+        let form = column![
+            text("Two-factor authentication").size(20),
+            text("Enter the code of your authenticator app, or one of your recovery codes")
+                .size(14),
+            text_input("Authentication code", &self.code)
+                .on_input(Message::InputCode)
+                .on_submit(Message::ClickVerify)
+                .width(Length::Fill)
+                .padding([8, 12]),
+        ]
+        .push_maybe(
+            self.error_message
+                .as_ref()
+                .map(|error| text(error).size(14).color(Color::from_rgb(1.0, 0.0, 0.0))),
+        )
+        .spacing(12);
+
+        let buttons = row![
+            button(text("Back").align_x(Alignment::Center))
+                .width(Length::FillPortion(1))
+                .padding([8, 12])
+                .on_press(Message::CancelTwoFactor)
+                .style(button::secondary),
+            button(text("Verify").align_x(Alignment::Center))
+                .width(Length::FillPortion(1))
+                .padding([8, 12])
+                .on_press(Message::ClickVerify)
+                .style(button::primary),
+        ]
+        .spacing(6)
+        .width(Length::Fill);
+
+        let content = column![form, buttons]
+            .spacing(24)
+            .padding([24, 0])
+            .max_width(350);
+
+        container(content)
+            .center_x(Length::Fill)
+            .align_top(Length::Fill)
+            .into()
+    }
 }
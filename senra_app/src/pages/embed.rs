@@ -0,0 +1,332 @@
+use std::collections::{HashMap, HashSet};
+
+use iced::widget::{Shader, button, center, column, container, mouse_area, row, text};
+use iced::{Alignment, Element, Length, Task};
+use senra_api::{
+    CellPayload, EmbedOptions, ImportError, LibraryImport, NotebookContent, NotebookResponse,
+    PackageError, PackageLock, PackageSource, PackageVersionResponse, ShaderLanguage,
+    ShaderResponse, ShaderSource, ShaderStage, qualify_imports,
+};
+
+use crate::widgets::viewer::Viewer;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ErrorRequest(String),
+    GetNotebookRequest(Box<NotebookResponse>),
+    GetLibraryModuleRequest(ShaderResponse),
+    GetPackageVersionRequest(PackageVersionResponse),
+
+    GetNotebookRespond(u64),
+    GetLibraryModuleRespond(u64, String),
+    GetPackageVersionRespond(String, String),
+
+    TogglePlay,
+}
+
+/// Player of a single render cell, shown instead of the whole app on `/embed/{id}` pages
+pub enum EmbedPage {
+    Loading(EmbedOptions),
+    /// Notebook fetched, waiting for the library modules and package versions it imports
+    Resolving {
+        options: EmbedOptions,
+        notebook: Box<NotebookResponse>,
+        /// Library modules fetched for imports, keyed by import name
+        library: HashMap<String, String>,
+        /// Package versions fetched for imports
+        packages: Vec<PackageVersionResponse>,
+        /// Library modules and package versions requested
+        requested: HashSet<String>,
+    },
+    Page {
+        title: String,
+        author: String,
+        viewer: Viewer,
+        ui: bool,
+    },
+    Error(String),
+}
+
+/// Reason the viewer of an embed can't be built, missing imports being fetched before trying
+/// again
+#[derive(Debug, thiserror::Error)]
+enum EmbedError {
+    #[error(transparent)]
+    Import(#[from] ImportError),
+    #[error(transparent)]
+    Package(#[from] PackageError),
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl EmbedPage {
+    pub fn new(id: u64, options: EmbedOptions) -> (Self, Task<Message>) {
+        (
+            Self::Loading(options),
+            Task::done(Message::GetNotebookRespond(id)),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::GetNotebookRequest(response) => {
+                if let Self::Loading(options) = self {
+                    *self = Self::Resolving {
+                        options: options.clone(),
+                        notebook: response,
+                        library: HashMap::new(),
+                        packages: Vec::new(),
+                        requested: HashSet::new(),
+                    };
+                }
+                self.build_viewer()
+            }
+            Message::GetLibraryModuleRequest(shader) => {
+                if let Self::Resolving { library, .. } = self {
+                    let import = format!("{}/{}", shader.notebook_id, shader.name);
+                    library.insert(import, qualify_imports(&shader.code, shader.notebook_id));
+                }
+                self.build_viewer()
+            }
+            Message::GetPackageVersionRequest(package_version) => {
+                if let Self::Resolving { packages, .. } = self {
+                    packages.push(package_version);
+                }
+                self.build_viewer()
+            }
+            Message::TogglePlay => {
+                if let Self::Page { viewer, .. } = self {
+                    if viewer.is_paused() {
+                        viewer.resume();
+                    } else {
+                        viewer.pause();
+                    }
+                }
+                Task::none()
+            }
+            Message::ErrorRequest(error) => {
+                *self = Self::Error(error);
+                Task::none()
+            }
+            _ => Task::none(),
+        }
+    }
+
+    /// Builds the viewer once the notebook and everything it imports are fetched, requesting
+    /// the next missing library module or package version otherwise
+    fn build_viewer(&mut self) -> Task<Message> {
+        let Self::Resolving {
+            options,
+            notebook,
+            library,
+            packages,
+            requested,
+        } = self
+        else {
+            return Task::none();
+        };
+
+        let error = match fragment_viewer(notebook, options, library, packages) {
+            Ok(viewer) => {
+                *self = Self::Page {
+                    title: notebook.inner.title.clone(),
+                    author: notebook.author.username.clone(),
+                    viewer,
+                    ui: options.ui,
+                };
+                return Task::none();
+            }
+            Err(error) => error,
+        };
+        let missing = match &error {
+            EmbedError::Package(PackageError::NotFound(import))
+            | EmbedError::Import(ImportError::NotFound { name: import, .. }) => Some(import),
+            _ => None,
+        };
+        let fetch = missing
+            .filter(|import| !requested.contains(*import))
+            .and_then(|import| Some((import.clone(), fetch_import(import)?)));
+        match fetch {
+            Some((import, message)) => {
+                requested.insert(import);
+                Task::done(message)
+            }
+            None => {
+                *self = Self::Error(error.to_string());
+                Task::none()
+            }
+        }
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        match self {
+            Self::Loading(_) | Self::Resolving { .. } => center(text("Loading...").size(16)).into(),
+            Self::Error(error) => center(
+                text(error)
+                    .size(16)
+                    .color(iced::Color::from_rgb(1.0, 0.0, 0.0)),
+            )
+            .into(),
+            Self::Page {
+                title,
+                author,
+                viewer,
+                ui,
+            } => {
+                let player =
+                    mouse_area(Shader::new(viewer).width(Length::Fill).height(Length::Fill))
+                        .on_press(Message::TogglePlay);
+                if !ui {
+                    return player.into();
+                }
+
+                let controls = row![
+                    button(if viewer.is_paused() { "▶" } else { "⏸" })
+                        .padding([4, 10])
+                        .on_press(Message::TogglePlay)
+                        .style(button::primary),
+                    text(title).size(14),
+                    text(format!("by {}", author)).size(12),
+                ]
+                .spacing(10)
+                .padding(8)
+                .align_y(Alignment::Center);
+
+                column![player, container(controls).width(Length::Fill)].into()
+            }
+        }
+    }
+}
+
+/// Notebook and embed options of the current `/embed/{id}` page, on the web only
+pub fn current_embed() -> Option<(u64, EmbedOptions)> {
+    #[cfg(target_arch = "wasm32")]
+    {
+        let location = web_sys::window()?.location();
+        let id = location
+            .pathname()
+            .ok()?
+            .trim_end_matches('/')
+            .rsplit_once("/embed/")?
+            .1
+            .parse()
+            .ok()?;
+        Some((id, EmbedOptions::from_query(&location.search().ok()?)))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    None
+}
+
+/// Message fetching a missing library module or package version from its import
+fn fetch_import(import: &str) -> Option<Message> {
+    if let Some(library_import) = LibraryImport::parse(import) {
+        return Some(Message::GetLibraryModuleRespond(
+            library_import.notebook_id as u64,
+            library_import.name.to_string(),
+        ));
+    }
+    let (name, version) = import.split_once('@')?;
+    Some(Message::GetPackageVersionRespond(
+        name.to_string(),
+        version.to_string(),
+    ))
+}
+
+/// Builds a viewer for the fragment shader of the render cell selected by `options`
+///
+/// Shader imports are expanded with the fetched `library` modules and `packages`, which are
+/// resolved like the server does for the notebook shaders, and the prelude of the viewer is
+/// prepended, so the shaders are checked and drawn as they were saved.
+fn fragment_viewer(
+    notebook: &NotebookResponse,
+    options: &EmbedOptions,
+    library: &HashMap<String, String>,
+    packages: &[PackageVersionResponse],
+) -> Result<Viewer, EmbedError> {
+    let content: NotebookContent = serde_json::from_value(notebook.content.clone())
+        .map_err(|err| EmbedError::Invalid(err.to_string()))?;
+    let code_cells: HashMap<&str, &str> = content
+        .cells
+        .iter()
+        .filter_map(|cell| match &cell.payload {
+            CellPayload::Code(code) => Some((cell.id.as_str(), code.as_str())),
+            _ => None,
+        })
+        .collect();
+
+    let sources: Vec<&str> = notebook
+        .shaders
+        .iter()
+        .map(|shader| shader.code.as_str())
+        .chain(code_cells.values().copied())
+        .chain(library.values().map(String::as_str))
+        .collect();
+    let available: Vec<PackageSource> = packages
+        .iter()
+        .filter_map(|package| {
+            Some(PackageSource {
+                name: &package.name,
+                version: package.version.parse().ok()?,
+                code: &package.code,
+            })
+        })
+        .collect();
+    let lock = PackageLock::resolve(&sources, &available)?;
+    let resolve = |name: &str| {
+        library
+            .get(name)
+            .map(String::as_str)
+            .or_else(|| {
+                let shader = notebook.shaders.iter().find(|shader| shader.name == name)?;
+                Some(shader.code.as_str())
+            })
+            .or_else(|| code_cells.get(name).copied())
+    };
+    let mut shaders = HashMap::new();
+    for shader in &notebook.shaders {
+        // Shaders stored before their language was recorded are WGSL
+        let language: ShaderLanguage = shader.shader_type.parse().unwrap_or_default();
+        let preprocessed = lock
+            .preprocess(&shader.name, &shader.code, resolve)?
+            .with_prelude(language);
+        shaders.insert(shader.id, (preprocessed, language));
+    }
+
+    let invalid = |errors: Vec<String>| EmbedError::Invalid(errors.join("\n"));
+    // Checks the configuration of every render cell before a pipeline is built from one
+    content
+        .validate(|id| {
+            let (shader, language) = shaders.get(&id)?;
+            Some(ShaderSource::preprocessed(shader, *language))
+        })
+        .map_err(|errors| {
+            invalid(
+                errors
+                    .into_iter()
+                    .map(|error| format!("{}: {}", error.path, error.message))
+                    .collect(),
+            )
+        })?;
+    let (_, config) = options
+        .render_cell(&content)
+        .ok_or_else(|| EmbedError::Invalid("Notebook has no render cell to embed".to_string()))?;
+
+    let (shader, language) = config
+        .pipeline
+        .shader_bindings
+        .iter()
+        .find(|binding| binding.shader_stage == ShaderStage::Fragment)
+        .and_then(|binding| config.shader_ids.get(binding.shader_index))
+        .and_then(|shader_id| shaders.get(shader_id))
+        .ok_or_else(|| EmbedError::Invalid("Render cell has no fragment shader".to_string()))?;
+
+    let mut viewer = Viewer::compile(shader, *language)
+        .map_err(|errors| invalid(errors.into_iter().map(|error| error.message).collect()))?;
+    viewer.seek(options.time);
+    if !options.autoplay {
+        viewer.pause();
+    }
+
+    Ok(viewer)
+}
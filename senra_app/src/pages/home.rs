@@ -2,7 +2,7 @@ use iced::widget::{
     button, center, column, container, horizontal_space, mouse_area, row, scrollable, text,
 };
 use iced::{Alignment, Element, Length, Task};
-use senra_api::{NotebookListResponse, NotebookResponse};
+use senra_api::{BlobRef, NotebookListResponse, NotebookResponse};
 use tracing::{debug, info};
 
 #[derive(Debug, Clone)]
@@ -24,7 +24,7 @@ struct NotebookCard {
     title: String,
     author: String,
     likes: i64,
-    preview: Option<Vec<u8>>,
+    preview: Option<BlobRef>,
     category: String,
 }
 
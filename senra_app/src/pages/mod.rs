@@ -1,17 +1,21 @@
 mod auth;
+mod embed;
 mod home;
 mod notebook;
+mod security;
 mod user;
 
 use iced::advanced::image::Handle;
 use iced::widget::{button, center, column, container, image, row, text, text_input};
 use iced::{Alignment, Element, Length, Renderer, Task, Theme};
-use senra_api::{Request, Response, UserInfoResponse};
+use senra_api::{BlobRef, MarkNotificationsReadRequest, Request, Response, UserInfoResponse};
 use tracing::{debug, info};
 
 use auth::{AuthPage, Message as AuthMessage};
+use embed::{EmbedPage, Message as EmbedMessage};
 use home::{HomePage, Message as HomeMessage};
 use notebook::{Message as NotebookMessage, NotebookPage};
+use security::{Message as SecurityMessage, SecurityPage};
 use user::{Message as UserMessage, UserPage};
 
 use crate::widgets::menu::{Item, Menu, MenuBar};
@@ -21,7 +25,9 @@ use crate::{Protocol, StorageMessage};
 pub struct User {
     id: u64,
     username: String,
-    avatar: Vec<u8>,
+    avatar: Option<BlobRef>,
+    /// Avatar image, once downloaded from the blob store
+    avatar_data: Option<Vec<u8>>,
 }
 
 impl From<UserInfoResponse> for User {
@@ -30,6 +36,7 @@ impl From<UserInfoResponse> for User {
             id: message.id as u64,
             username: message.username,
             avatar: message.avatar,
+            avatar_data: None,
         }
     }
 }
@@ -40,8 +47,10 @@ pub enum Message {
     ShowHomeRequest,
     ShowNotebookRequest(Option<u64>),
     ShowUserRequest(Option<u64>),
+    ShowSecurityRequest,
 
     LogoutRespond,
+    ReadNotificationsRespond,
     Noop,
 
     Send(Protocol, Request),
@@ -51,34 +60,50 @@ pub enum Message {
     SearchSubmit,
 
     Auth(AuthMessage),
+    Embed(EmbedMessage),
     Home(HomeMessage),
     Notebook(NotebookMessage),
+    Security(SecurityMessage),
     User(UserMessage),
 }
 
 pub enum PageState {
     Login(AuthPage),
+    Embed(Box<EmbedPage>),
     Home(HomePage),
     Notebook(NotebookPage),
+    Security(Box<SecurityPage>),
     User(UserPage),
 }
 
 pub struct Page {
     state: PageState,
     current_user: Option<User>,
+    /// Unread notifications of the current user, shown as a badge
+    unread_notifications: i64,
     search_input: String,
 }
 
 impl Page {
     pub fn new() -> (Self, Task<Message>) {
-        let (page, task) = HomePage::new();
+        let (state, task) = match embed::current_embed() {
+            Some((id, options)) => {
+                let (page, task) = EmbedPage::new(id, options);
+                (PageState::Embed(Box::new(page)), task.map(Message::Embed))
+            }
+            None => {
+                let (page, task) = HomePage::new();
+                (PageState::Home(page), task.map(Message::Home))
+            }
+        };
         (
             Self {
-                state: PageState::Home(page),
+                state,
                 current_user: None,
+                unread_notifications: 0,
                 search_input: String::new(),
             },
-            task.map(Message::Home),
+            task,
         )
     }
 
@@ -108,21 +133,150 @@ impl Page {
                     Task::none()
                 }
             }
+            Message::ShowSecurityRequest => {
+                if self.current_user.is_none() {
+                    return Task::none();
+                }
+                let (page, task) = SecurityPage::new();
+                self.state = PageState::Security(Box::new(page));
+                task.map(Message::Security)
+            }
             Message::LogoutRespond => {
                 self.current_user = None;
+                self.unread_notifications = 0;
                 let (page, task) = HomePage::new();
                 self.state = PageState::Home(page);
                 task.map(Message::Home)
             }
+            Message::ReadNotificationsRespond => {
+                let request =
+                    Request::MarkNotificationsRead(MarkNotificationsReadRequest { ids: None });
+                Task::done(Message::Send(Protocol::Http, request))
+            }
             Message::Receive(response) => {
                 debug!("Received response: {:?}", response);
                 match response {
                     Response::Auth(auth) => {
+                        let avatar = auth.user.avatar.clone();
                         self.current_user = Some(auth.user.into());
                         let (page, task) = HomePage::new();
                         self.state = PageState::Home(page);
-                        task.map(Message::Home)
+                        let request = Request::GetNotifications {
+                            page: None,
+                            limit: Some(1),
+                            unread: true,
+                        };
+                        Task::batch([
+                            task.map(Message::Home),
+                            Task::done(Message::Send(Protocol::Http, request)),
+                        ])
+                        .chain(match avatar {
+                            Some(avatar) => {
+                                Task::done(Message::Send(Protocol::Http, Request::GetBlob(avatar)))
+                            }
+                            None => Task::none(),
+                        })
+                    }
+                    Response::TwoFactorChallenge(challenge) => match &mut self.state {
+                        PageState::Login(page) => page
+                            .update(AuthMessage::TwoFactorChallengeRequest(challenge))
+                            .map(Message::Auth),
+                        _ => Task::none(),
+                    },
+                    Response::TwoFactorStatus(status) => match &mut self.state {
+                        PageState::Security(page) => page
+                            .update(SecurityMessage::GetTwoFactorRequest(status))
+                            .map(Message::Security),
+                        _ => Task::none(),
+                    },
+                    Response::TwoFactorSetup(setup) => match &mut self.state {
+                        PageState::Security(page) => page
+                            .update(SecurityMessage::TwoFactorSetupRequest(setup))
+                            .map(Message::Security),
+                        _ => Task::none(),
+                    },
+                    Response::RecoveryCodes(codes) => match &mut self.state {
+                        PageState::Security(page) => page
+                            .update(SecurityMessage::RecoveryCodesRequest(codes))
+                            .map(Message::Security),
+                        _ => Task::none(),
+                    },
+                    Response::Blob { blob, data } => {
+                        if let Some(user) = &mut self.current_user
+                            && user.avatar.as_ref() == Some(&blob)
+                        {
+                            user.avatar_data = Some(data);
+                        }
+                        Task::none()
+                    }
+                    Response::NotificationList(list) => {
+                        self.unread_notifications = list.unread;
+                        Task::none()
                     }
+                    Response::UnreadNotifications(unread) => {
+                        self.unread_notifications = unread.unread;
+                        Task::none()
+                    }
+                    Response::Notification(notification) => {
+                        if !notification.read {
+                            self.unread_notifications += 1;
+                        }
+                        Task::none()
+                    }
+                    Response::ShaderExport(export) => match &mut self.state {
+                        PageState::Notebook(page) => page
+                            .update(NotebookMessage::ExportShadersRequest(export))
+                            .map(Message::Notebook),
+                        _ => Task::none(),
+                    },
+                    Response::Shader(shader) => match &mut self.state {
+                        PageState::Notebook(page) => page
+                            .update(NotebookMessage::GetLibraryModuleRequest(shader))
+                            .map(Message::Notebook),
+                        PageState::Embed(page) => page
+                            .update(EmbedMessage::GetLibraryModuleRequest(shader))
+                            .map(Message::Embed),
+                        _ => Task::none(),
+                    },
+                    Response::PackageVersion(package_version) => match &mut self.state {
+                        PageState::Notebook(page) => page
+                            .update(NotebookMessage::GetPackageVersionRequest(package_version))
+                            .map(Message::Notebook),
+                        PageState::Embed(page) => page
+                            .update(EmbedMessage::GetPackageVersionRequest(package_version))
+                            .map(Message::Embed),
+                        _ => Task::none(),
+                    },
+                    Response::CommentList(list) => match &mut self.state {
+                        PageState::Notebook(page) => page
+                            .update(NotebookMessage::GetCommentListRequest(list))
+                            .map(Message::Notebook),
+                        _ => Task::none(),
+                    },
+                    Response::Notebook(notebook) => match &mut self.state {
+                        PageState::Embed(page) => page
+                            .update(EmbedMessage::GetNotebookRequest(Box::new(notebook)))
+                            .map(Message::Embed),
+                        _ => Task::none(),
+                    },
+                    Response::User(user) => match &mut self.state {
+                        PageState::User(page) => page
+                            .update(UserMessage::GetUserRequest(user))
+                            .map(Message::User),
+                        _ => Task::none(),
+                    },
+                    Response::CollectionList(list) => match &mut self.state {
+                        PageState::User(page) => page
+                            .update(UserMessage::GetCollectionsRequest(list))
+                            .map(Message::User),
+                        _ => Task::none(),
+                    },
+                    Response::Comment(comment) => match &mut self.state {
+                        PageState::Notebook(page) => page
+                            .update(NotebookMessage::CommentRequest(comment))
+                            .map(Message::Notebook),
+                        _ => Task::none(),
+                    },
                     _ => Task::none(),
                 }
             }
@@ -141,12 +295,43 @@ impl Page {
                             let request = Request::Register(request.to_owned());
                             Task::done(Message::Send(Protocol::Http, request))
                         }
+                        AuthMessage::TwoFactorRespond(request) => {
+                            let request = Request::LoginTwoFactor(request.to_owned());
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
                         _ => Task::none(),
                     },
                     page.update(message).map(Message::Auth),
                 ]),
                 _ => Task::none(),
             },
+            Message::Embed(message) => match &mut self.state {
+                PageState::Embed(page) => Task::batch([
+                    match &message {
+                        EmbedMessage::GetNotebookRespond(id) => {
+                            let request = Request::GetNotebook(*id);
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        EmbedMessage::GetLibraryModuleRespond(notebook_id, name) => {
+                            let request = Request::GetLibraryModule {
+                                notebook_id: *notebook_id,
+                                name: name.to_owned(),
+                            };
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        EmbedMessage::GetPackageVersionRespond(name, version) => {
+                            let request = Request::GetPackageVersion {
+                                name: name.to_owned(),
+                                version: version.to_owned(),
+                            };
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        _ => Task::none(),
+                    },
+                    page.update(message).map(Message::Embed),
+                ]),
+                _ => Task::none(),
+            },
             Message::Home(message) => match &mut self.state {
                 PageState::Home(page) => Task::batch([
                     match &message {
@@ -170,12 +355,77 @@ impl Page {
                             let request = Request::CreateNotebook(request.to_owned());
                             Task::done(Message::Send(Protocol::Http, request))
                         }
+                        NotebookMessage::ExportShadersRespond(id) => {
+                            let request = Request::ExportShaders(*id);
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        NotebookMessage::GetLibraryModuleRespond(notebook_id, name) => {
+                            let request = Request::GetLibraryModule {
+                                notebook_id: *notebook_id,
+                                name: name.to_owned(),
+                            };
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        NotebookMessage::GetPackageVersionRespond(name, version) => {
+                            let request = Request::GetPackageVersion {
+                                name: name.to_owned(),
+                                version: version.to_owned(),
+                            };
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        NotebookMessage::GetCommentListRespond(id) => {
+                            let request = Request::GetCommentList {
+                                id: *id,
+                                page: None,
+                                limit: None,
+                            };
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        NotebookMessage::CreateCommentRespond(id, request) => {
+                            let request = Request::CreateComment(*id, request.to_owned());
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        NotebookMessage::EditCommentRespond(notebook_id, comment_id, content) => {
+                            let request = Request::EditComment {
+                                notebook_id: *notebook_id,
+                                comment_id: *comment_id,
+                                content: content.to_owned(),
+                            };
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
                         _ => Task::none(),
                     },
                     page.update(message).map(Message::Notebook),
                 ]),
                 _ => Task::none(),
             },
+            Message::Security(message) => match &mut self.state {
+                PageState::Security(page) => Task::batch([
+                    match &message {
+                        SecurityMessage::GetTwoFactorRespond => {
+                            Task::done(Message::Send(Protocol::Http, Request::GetTwoFactor))
+                        }
+                        SecurityMessage::SetupTwoFactorRespond => {
+                            Task::done(Message::Send(Protocol::Http, Request::SetupTwoFactor))
+                        }
+                        SecurityMessage::EnableTwoFactorRespond(request) => {
+                            let request = Request::EnableTwoFactor(request.to_owned());
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        SecurityMessage::DisableTwoFactorRespond(request) => {
+                            let request = Request::DisableTwoFactor(request.to_owned());
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        SecurityMessage::RegenerateRecoveryCodesRespond(request) => {
+                            let request = Request::RegenerateRecoveryCodes(request.to_owned());
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
+                        _ => Task::none(),
+                    },
+                    page.update(message).map(Message::Security),
+                ]),
+                _ => Task::none(),
+            },
             Message::User(message) => match &mut self.state {
                 PageState::User(page) => Task::batch([
                     match &message {
@@ -183,6 +433,14 @@ impl Page {
                             let request = Request::GetUser(*id);
                             Task::done(Message::Send(Protocol::Http, request))
                         }
+                        UserMessage::GetCollectionsRespond(id) => {
+                            let request = Request::GetUserCollections {
+                                user_id: *id,
+                                page: None,
+                                limit: None,
+                            };
+                            Task::done(Message::Send(Protocol::Http, request))
+                        }
                         _ => Task::none(),
                     },
                     page.update(message).map(Message::User),
@@ -194,6 +452,11 @@ impl Page {
     }
 
     pub fn view(&self) -> Element<Message> {
+        // Embedded players take the whole page
+        if let PageState::Embed(page) = &self.state {
+            return page.view().map(Message::Embed);
+        }
+
         // Title bar
         let left_bar = MenuBar::<Message, Theme, Renderer>::new(vec![
             Item::new(
@@ -230,6 +493,13 @@ impl Page {
                             .on_press(Message::ShowAuthRequest)
                             .style(button::primary),
                     ),
+                    Item::new(
+                        button("Export shaders")
+                            .width(Length::Fill)
+                            .padding([6, 12])
+                            .on_press(Message::Notebook(NotebookMessage::ClickExportShaders))
+                            .style(button::primary),
+                    ),
                 ])
                 .max_width(180.0)
                 .offset(16.0)
@@ -254,13 +524,42 @@ impl Page {
         ])
         .spacing(6);
 
+        let notifications = self.current_user.as_ref().map(|_| {
+            let label = match self.unread_notifications {
+                0 => "🔔".to_string(),
+                unread => format!("🔔 {}", unread),
+            };
+            button(text(label))
+                .width(Length::Shrink)
+                .padding([6, 12])
+                .on_press(Message::ReadNotificationsRespond)
+                .style(if self.unread_notifications > 0 {
+                    button::danger
+                } else {
+                    button::primary
+                })
+        });
+
+        let security = self.current_user.as_ref().map(|_| {
+            button("Security")
+                .width(Length::Shrink)
+                .padding([6, 12])
+                .on_press(Message::ShowSecurityRequest)
+                .style(button::primary)
+        });
+
         let right_bar = row![]
+            .push_maybe(notifications)
+            .push_maybe(security)
             .push(match &self.current_user {
-                Some(user) => button(
-                    image(Handle::from_bytes(user.avatar.clone()))
-                        .width(Length::Fixed(24.0))
-                        .height(Length::Fixed(24.0)),
-                )
+                Some(user) => match &user.avatar_data {
+                    Some(avatar) => button(
+                        image(Handle::from_bytes(avatar.clone()))
+                            .width(Length::Fixed(24.0))
+                            .height(Length::Fixed(24.0)),
+                    ),
+                    None => button(text(user.username.clone())).padding([6, 12]),
+                }
                 .width(Length::Shrink)
                 .on_press(Message::ShowHomeRequest)
                 .style(button::primary),
@@ -300,8 +599,12 @@ impl Page {
         // Main content
         let content = match &self.state {
             PageState::Login(page) => page.view().map(Message::Auth),
+            PageState::Embed(_) => unreachable!("embedded players are shown without the menu bar"),
             PageState::Home(page) => page.view().map(Message::Home),
-            PageState::Notebook(page) => page.view().map(Message::Notebook),
+            PageState::Notebook(page) => page
+                .view(self.current_user.as_ref().map(|user| user.id))
+                .map(Message::Notebook),
+            PageState::Security(page) => page.view().map(Message::Security),
             PageState::User(page) => page.view().map(Message::User),
         };
 
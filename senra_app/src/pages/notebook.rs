@@ -1,28 +1,48 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use iced::widget::{button, center, column, container, mouse_area, row, scrollable, text};
 use iced::{Element, Length, Task};
-use senra_api::{CreateNotebookRequest, EditNotebookRequest, NotebookResponse};
+use senra_api::{
+    CreateNotebookCommentRequest, CreateNotebookRequest, EditNotebookRequest, ImportError,
+    LibraryImport, NotebookCommentListResponse, NotebookCommentResponse, NotebookResponse,
+    PackageError, PackageLock, PackageSource, PackageVersionResponse, ShaderExportResponse,
+    ShaderLanguage, ShaderResponse, qualify_imports,
+};
 use serde_json::json;
+use tracing::info;
 
-use crate::widgets::{Cell, CellMessage, CellType};
+use crate::widgets::{Cell, CellMessage, CellType, Comments, CommentsMessage};
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ErrorRequest(String),
     GetNotebookRequest(NotebookResponse),
+    ExportShadersRequest(ShaderExportResponse),
+    ExportShadersDone(String),
+    GetLibraryModuleRequest(ShaderResponse),
+    GetPackageVersionRequest(PackageVersionResponse),
+    GetCommentListRequest(NotebookCommentListResponse),
+    CommentRequest(NotebookCommentResponse),
 
     GetNotebookRespond(u64),
     SaveNotebookRespond(CreateNotebookRequest),
     EditNotebookRespond(EditNotebookRequest),
+    ExportShadersRespond(u64),
+    GetLibraryModuleRespond(u64, String),
+    GetPackageVersionRespond(String, String),
+    GetCommentListRespond(u64),
+    CreateCommentRespond(u64, CreateNotebookCommentRequest),
+    EditCommentRespond(u64, u64, String),
 
     CreateCell(CellType, Option<u32>),
     RemoveCell(u32),
     MoveUp(u32),
     MoveDown(u32),
     Cell(u32, CellMessage),
+    Comments(CommentsMessage),
     ShowButtons(Option<u32>),
     ClickSave,
+    ClickExportShaders,
 }
 
 pub enum NotebookPage {
@@ -37,9 +57,23 @@ pub enum NotebookPage {
         selected: Option<u32>,
         hovered: Option<u32>,
         error: Option<String>,
+        imports: Box<Imports>,
+        comments: Box<Comments>,
     },
 }
 
+/// Library modules and package versions fetched for the imports of shader cells
+#[derive(Default)]
+pub(crate) struct Imports {
+    /// Library modules, keyed by import name
+    library: HashMap<String, String>,
+    packages: Vec<PackageVersionResponse>,
+    /// Library modules and package versions requested and the cells to compile once they are
+    /// fetched
+    requested: HashSet<String>,
+    pending: Vec<u32>,
+}
+
 impl NotebookPage {
     pub fn new(id: Option<u64>) -> (Self, Task<Message>) {
         match id {
@@ -55,6 +89,8 @@ impl NotebookPage {
                     selected: None,
                     hovered: None,
                     error: None,
+                    imports: Box::default(),
+                    comments: Box::default(),
                 },
                 Task::none(),
             ),
@@ -73,8 +109,9 @@ impl NotebookPage {
                 _ => Task::none(),
             },
             Message::GetNotebookRequest(response) => {
+                let id = response.inner.id as u64;
                 *self = Self::Page {
-                    id: Some(response.inner.id as u64),
+                    id: Some(id),
                     title: response.inner.title,
                     description: response.inner.description,
                     cells: HashMap::new(),
@@ -83,9 +120,43 @@ impl NotebookPage {
                     selected: None,
                     hovered: None,
                     error: None,
+                    imports: Box::default(),
+                    comments: Box::default(),
                 };
-                Task::none()
+                Task::done(Message::GetCommentListRespond(id))
             }
+            Message::GetCommentListRequest(list) => match self {
+                Self::Page { comments, .. } => {
+                    comments.set_list(list);
+                    Task::none()
+                }
+                _ => Task::none(),
+            },
+            Message::CommentRequest(comment) => match self {
+                Self::Page { comments, .. } => {
+                    comments.insert(comment);
+                    Task::none()
+                }
+                _ => Task::none(),
+            },
+            Message::Comments(CommentsMessage::Create(request)) => match self {
+                Self::Page { id: Some(id), .. } => {
+                    Task::done(Message::CreateCommentRespond(*id, request))
+                }
+                _ => Task::none(),
+            },
+            Message::Comments(CommentsMessage::Edit(comment_id, content)) => match self {
+                Self::Page { id: Some(id), .. } => {
+                    Task::done(Message::EditCommentRespond(*id, comment_id as u64, content))
+                }
+                _ => Task::none(),
+            },
+            Message::Comments(comments_message) => match self {
+                Self::Page { comments, .. } => {
+                    comments.update(comments_message).map(Message::Comments)
+                }
+                _ => Task::none(),
+            },
             Message::CreateCell(cell_type, position) => match self {
                 Self::Page {
                     cells,
@@ -96,7 +167,7 @@ impl NotebookPage {
                 } => {
                     let id = *next_id;
                     *next_id += 1;
-                    let (cell, task) = Cell::new(cell_type, None);
+                    let (cell, task) = Cell::new(format!("cell{}", id), cell_type, None);
                     cells.insert(id, cell);
 
                     if let Some(pos) = position {
@@ -158,6 +229,25 @@ impl NotebookPage {
                 }
                 _ => Task::none(),
             },
+            Message::Cell(id, CellMessage::CompileShader) => self.compile_cell(id),
+            Message::GetLibraryModuleRequest(shader) => match self {
+                Self::Page { imports, .. } => {
+                    let import = format!("{}/{}", shader.notebook_id, shader.name);
+                    let code = qualify_imports(&shader.code, shader.notebook_id);
+                    imports.library.insert(import, code);
+                    let pending = std::mem::take(&mut imports.pending);
+                    Task::batch(pending.into_iter().map(|id| self.compile_cell(id)))
+                }
+                _ => Task::none(),
+            },
+            Message::GetPackageVersionRequest(package_version) => match self {
+                Self::Page { imports, .. } => {
+                    imports.packages.push(package_version);
+                    let pending = std::mem::take(&mut imports.pending);
+                    Task::batch(pending.into_iter().map(|id| self.compile_cell(id)))
+                }
+                _ => Task::none(),
+            },
             Message::Cell(id, cell_message) => match self {
                 Self::Page { cells, .. } => {
                     if let Some(cell) = cells.get_mut(&id) {
@@ -207,20 +297,135 @@ impl NotebookPage {
                 }
                 _ => Task::none(),
             },
+            Message::ClickExportShaders => match self {
+                Self::Page { id: Some(id), .. } => Task::done(Message::ExportShadersRespond(*id)),
+                Self::Page { error, .. } => {
+                    *error = Some("Save the notebook before exporting its shaders".to_string());
+                    Task::none()
+                }
+                _ => Task::none(),
+            },
+            Message::ExportShadersRequest(export) => match self {
+                Self::Page { id: Some(id), .. } => {
+                    let dir = format!("exports/notebook-{}", id);
+                    Task::perform(write_export(dir, export), |result| match result {
+                        Ok(dir) => Message::ExportShadersDone(dir),
+                        Err(err) => Message::ErrorRequest(err),
+                    })
+                }
+                _ => Task::none(),
+            },
+            Message::ExportShadersDone(dir) => {
+                info!("Exported shaders to {}", dir);
+                Task::none()
+            }
             _ => Task::none(),
         }
     }
 
-    pub fn view(&self) -> Element<Message> {
+    /// Compiles a shader cell, fetching the library modules and package versions it imports
+    /// first
+    ///
+    /// Packages are resolved for all the cells at once, like the server does for the notebook
+    /// shaders, so that every cell renders with the same package versions.
+    fn compile_cell(&mut self, id: u32) -> Task<Message> {
+        let Self::Page { cells, imports, .. } = self else {
+            return Task::none();
+        };
+        let Imports {
+            library,
+            packages,
+            requested,
+            pending,
+        } = &mut **imports;
+
+        let modules: HashMap<String, String> = cells
+            .iter()
+            .filter(|(cell_id, _)| **cell_id != id)
+            .filter_map(|(_, cell)| Some((cell.name().to_string(), cell.shader_code()?)))
+            .collect();
+        let Some(cell) = cells.get_mut(&id) else {
+            return Task::none();
+        };
+
+        let code = cell.shader_code().unwrap_or_default();
+        let sources: Vec<&str> = std::iter::once(code.as_str())
+            .chain(modules.values().map(String::as_str))
+            .chain(library.values().map(String::as_str))
+            .collect();
+        let available: Vec<PackageSource> = packages
+            .iter()
+            .filter_map(|package| {
+                Some(PackageSource {
+                    name: &package.name,
+                    version: package.version.parse().ok()?,
+                    code: &package.code,
+                })
+            })
+            .collect();
+        let lock = match PackageLock::resolve(&sources, &available) {
+            Ok(lock) => lock,
+            Err(PackageError::NotFound(import)) if !requested.contains(&import) => {
+                let Some((name, version)) = import.split_once('@') else {
+                    return Task::none();
+                };
+                let task = Task::done(Message::GetPackageVersionRespond(
+                    name.to_string(),
+                    version.to_string(),
+                ));
+                cell.set_error(format!("Fetching package `{}`", import));
+                requested.insert(import);
+                pending.push(id);
+                return task;
+            }
+            Err(err) => {
+                cell.set_error(err.to_string());
+                return Task::none();
+            }
+        };
+
+        let result = cell.compile(&lock, |name| {
+            library
+                .get(name)
+                .or_else(|| modules.get(name))
+                .map(String::as_str)
+        });
+        match result {
+            Ok(()) => Task::none(),
+            Err(ImportError::NotFound { name, .. }) if !requested.contains(&name) => {
+                let Some(import) = LibraryImport::parse(&name) else {
+                    cell.set_error(format!("Module `{}` not found", name));
+                    return Task::none();
+                };
+                let task = Task::done(Message::GetLibraryModuleRespond(
+                    import.notebook_id as u64,
+                    import.name.to_string(),
+                ));
+                cell.set_error(format!("Fetching module `{}`", name));
+                requested.insert(name);
+                pending.push(id);
+                task
+            }
+            Err(err) => {
+                cell.set_error(err.to_string());
+                Task::none()
+            }
+        }
+    }
+
+    /// Renders the notebook, `user_id` being the signed in user if any
+    pub fn view(&self, user_id: Option<u64>) -> Element<Message> {
         match self {
             Self::Loading => center(text("Loading...").size(24)).into(),
             Self::Page {
+                id,
                 title,
                 description,
                 cells,
                 cell_order,
                 hovered,
                 error,
+                comments,
                 ..
             } => {
                 let mut content = column![].spacing(20).padding(10);
@@ -253,8 +458,17 @@ impl NotebookPage {
                                 button("+ Markdown")
                                     .on_press(Message::CreateCell(CellType::Markdown, Some(id)))
                                     .padding(5),
-                                button("+ Shader")
-                                    .on_press(Message::CreateCell(CellType::Shader, Some(id)))
+                                button("+ WGSL")
+                                    .on_press(Message::CreateCell(
+                                        CellType::Shader(ShaderLanguage::Wgsl),
+                                        Some(id),
+                                    ))
+                                    .padding(5),
+                                button("+ GLSL")
+                                    .on_press(Message::CreateCell(
+                                        CellType::Shader(ShaderLanguage::Glsl),
+                                        Some(id),
+                                    ))
                                     .padding(5),
                             ]
                             .spacing(10)
@@ -294,8 +508,17 @@ impl NotebookPage {
                     button("+ Markdown")
                         .on_press(Message::CreateCell(CellType::Markdown, last_id))
                         .padding(5),
-                    button("+ Shader")
-                        .on_press(Message::CreateCell(CellType::Shader, last_id))
+                    button("+ WGSL")
+                        .on_press(Message::CreateCell(
+                            CellType::Shader(ShaderLanguage::Wgsl),
+                            last_id,
+                        ))
+                        .padding(5),
+                    button("+ GLSL")
+                        .on_press(Message::CreateCell(
+                            CellType::Shader(ShaderLanguage::Glsl),
+                            last_id,
+                        ))
                         .padding(5),
                 ]
                 .spacing(10)
@@ -309,6 +532,12 @@ impl NotebookPage {
                         .height(Length::Shrink),
                 );
 
+                // Comments of saved notebooks
+                if id.is_some() {
+                    content = content
+                        .push(container(comments.view(user_id).map(Message::Comments)).padding(10));
+                }
+
                 scrollable(content)
                     .width(Length::Fill)
                     .height(Length::Fill)
@@ -317,3 +546,41 @@ impl NotebookPage {
         }
     }
 }
+
+/// Writes the compiled shaders and `reflection.json` of an export under `dir`
+#[cfg(not(target_arch = "wasm32"))]
+async fn write_export(dir: String, export: ShaderExportResponse) -> Result<String, String> {
+    use std::path::Path;
+
+    for file in &export.files {
+        let path = Path::new(&dir).join(&file.path);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent)
+                .await
+                .map_err(|err| err.to_string())?;
+        }
+        tokio::fs::write(&path, file.output.to_bytes())
+            .await
+            .map_err(|err| err.to_string())?;
+    }
+
+    let reflection =
+        serde_json::to_vec_pretty(&export.reflection).map_err(|err| err.to_string())?;
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|err| err.to_string())?;
+    tokio::fs::write(Path::new(&dir).join("reflection.json"), reflection)
+        .await
+        .map_err(|err| err.to_string())?;
+
+    for error in &export.errors {
+        tracing::warn!("Shader export error at {}: {}", error.path, error.message);
+    }
+
+    Ok(dir)
+}
+
+#[cfg(target_arch = "wasm32")]
+async fn write_export(_dir: String, _export: ShaderExportResponse) -> Result<String, String> {
+    Err("Exporting shaders to files is not supported on the web".to_string())
+}
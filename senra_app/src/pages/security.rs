@@ -0,0 +1,213 @@
+use iced::widget::{button, column, container, row, text, text_input};
+use iced::{Alignment, Color, Element, Length, Task};
+use senra_api::{
+    RecoveryCodesResponse, TwoFactorCodeRequest, TwoFactorSetupResponse, TwoFactorStatusResponse,
+};
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    ErrorRequest(String),
+    GetTwoFactorRequest(TwoFactorStatusResponse),
+    TwoFactorSetupRequest(TwoFactorSetupResponse),
+    RecoveryCodesRequest(RecoveryCodesResponse),
+
+    GetTwoFactorRespond,
+    SetupTwoFactorRespond,
+    EnableTwoFactorRespond(TwoFactorCodeRequest),
+    DisableTwoFactorRespond(TwoFactorCodeRequest),
+    RegenerateRecoveryCodesRespond(TwoFactorCodeRequest),
+
+    InputCode(String),
+    ClickSetup,
+    ClickEnable,
+    ClickDisable,
+    ClickRegenerate,
+}
+
+/// Second factor settings of the signed in user
+#[derive(Debug, Clone)]
+pub struct SecurityPage {
+    /// Unknown until fetched
+    status: Option<TwoFactorStatusResponse>,
+    /// Secret being enrolled, waiting for its first code
+    setup: Option<TwoFactorSetupResponse>,
+    /// Recovery codes just generated, the only time they are shown
+    recovery_codes: Vec<String>,
+    code: String,
+    error_message: Option<String>,
+}
+
+impl SecurityPage {
+    pub fn new() -> (Self, Task<Message>) {
+        (
+            Self {
+                status: None,
+                setup: None,
+                recovery_codes: Vec::new(),
+                code: Default::default(),
+                error_message: None,
+            },
+            Task::done(Message::GetTwoFactorRespond),
+        )
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::ErrorRequest(error) => {
+                self.error_message = Some(error);
+                Task::none()
+            }
+            Message::GetTwoFactorRequest(status) => {
+                if status.enabled {
+                    self.setup = None;
+                } else {
+                    self.recovery_codes.clear();
+                }
+                self.status = Some(status);
+                self.code.clear();
+                self.error_message = None;
+                Task::none()
+            }
+            Message::TwoFactorSetupRequest(setup) => {
+                self.setup = Some(setup);
+                self.code.clear();
+                self.error_message = None;
+                Task::none()
+            }
+            Message::RecoveryCodesRequest(codes) => {
+                self.status = Some(TwoFactorStatusResponse {
+                    enabled: true,
+                    recovery_codes_left: codes.recovery_codes.len() as u32,
+                });
+                self.setup = None;
+                self.recovery_codes = codes.recovery_codes;
+                self.code.clear();
+                self.error_message = None;
+                Task::none()
+            }
+            Message::InputCode(code) => {
+                self.code = code;
+                self.error_message = None;
+                Task::none()
+            }
+            Message::ClickSetup => {
+                self.error_message = None;
+                Task::done(Message::SetupTwoFactorRespond)
+            }
+            Message::ClickEnable => self.code_request().map_or(Task::none(), |request| {
+                Task::done(Message::EnableTwoFactorRespond(request))
+            }),
+            Message::ClickDisable => self.code_request().map_or(Task::none(), |request| {
+                Task::done(Message::DisableTwoFactorRespond(request))
+            }),
+            Message::ClickRegenerate => self.code_request().map_or(Task::none(), |request| {
+                Task::done(Message::RegenerateRecoveryCodesRespond(request))
+            }),
+            _ => Task::none(),
+        }
+    }
+
+    /// Request confirming an action with the entered code, if there is one
+    fn code_request(&mut self) -> Option<TwoFactorCodeRequest> {
+        if self.code.trim().is_empty() {
+            self.error_message = Some("Authentication code is required".to_string());
+            return None;
+        }
+        self.error_message = None;
+        Some(TwoFactorCodeRequest {
+            code: self.code.trim().to_string(),
+        })
+    }
+
+    pub fn view(&self) -> Element<'_, Message> {
+        let code_input = |on_submit| {
+            text_input("Authentication code", &self.code)
+                .on_input(Message::InputCode)
+                .on_submit(on_submit)
+                .width(Length::Fill)
+                .padding([8, 12])
+        };
+
+        let form: Element<Message> = match (&self.status, &self.setup) {
+            (None, _) => text("Loading...").size(14).into(),
+            (Some(status), _) if status.enabled => column![
+                text(format!(
+                    "Enabled, {} recovery codes left",
+                    status.recovery_codes_left
+                ))
+                .size(14),
+                text("Confirm with an authenticator or recovery code").size(14),
+                code_input(Message::ClickRegenerate),
+                row![
+                    button(text("New recovery codes").align_x(Alignment::Center))
+                        .width(Length::FillPortion(1))
+                        .padding([8, 12])
+                        .on_press(Message::ClickRegenerate)
+                        .style(button::primary),
+                    button(text("Disable").align_x(Alignment::Center))
+                        .width(Length::FillPortion(1))
+                        .padding([8, 12])
+                        .on_press(Message::ClickDisable)
+                        .style(button::danger),
+                ]
+                .spacing(6),
+            ]
+            .spacing(12)
+            .into(),
+            // The QR code is an SVG image the app doesn't render, so the secret is typed in
+            (Some(_), Some(setup)) => column![
+                text("Add this secret to your authenticator app, then enter its first code")
+                    .size(14),
+                text(&setup.secret).size(16),
+                text(&setup.provisioning_uri).size(12),
+                code_input(Message::ClickEnable),
+                button(text("Enable").align_x(Alignment::Center))
+                    .width(Length::Fill)
+                    .padding([8, 12])
+                    .on_press(Message::ClickEnable)
+                    .style(button::primary),
+            ]
+            .spacing(12)
+            .into(),
+            (Some(_), None) => column![
+                text("Disabled").size(14),
+                button(text("Set up").align_x(Alignment::Center))
+                    .width(Length::Fill)
+                    .padding([8, 12])
+                    .on_press(Message::ClickSetup)
+                    .style(button::primary),
+            ]
+            .spacing(12)
+            .into(),
+        };
+
+        let recovery_codes = (!self.recovery_codes.is_empty()).then(|| {
+            column![
+                text("Recovery codes, keep them somewhere safe as they won't be shown again")
+                    .size(14)
+            ]
+            .extend(
+                self.recovery_codes
+                    .iter()
+                    .map(|code| text(code).size(16).into()),
+            )
+            .spacing(6)
+        });
+
+        let content = column![text("Two-factor authentication").size(20), form]
+            .push_maybe(recovery_codes)
+            .push_maybe(
+                self.error_message
+                    .as_ref()
+                    .map(|error| text(error).size(14).color(Color::from_rgb(1.0, 0.0, 0.0))),
+            )
+            .spacing(24)
+            .padding([24, 0])
+            .max_width(350);
+
+        container(content)
+            .center_x(Length::Fill)
+            .align_top(Length::Fill)
+            .into()
+    }
+}
@@ -2,14 +2,16 @@ use iced::widget::{
     button, center, column, container, horizontal_space, mouse_area, row, scrollable, text,
 };
 use iced::{Alignment, Element, Length, Task};
-use senra_api::{NotebookListResponse, UserResponse};
+use senra_api::{BlobRef, CollectionListResponse, UserResponse};
 
 #[derive(Debug, Clone)]
 pub enum Message {
     ErrorRequest(String),
     GetUserRequest(UserResponse),
+    GetCollectionsRequest(CollectionListResponse),
 
     GetUserRespond(u64),
+    GetCollectionsRespond(u64),
     GetNotebookRespond(u64),
 
     LoadUser(u64),
@@ -21,7 +23,7 @@ struct NotebookCard {
     id: u64,
     title: String,
     likes: i64,
-    preview: Option<Vec<u8>>,
+    preview: Option<BlobRef>,
 }
 
 impl NotebookCard {
@@ -51,14 +53,50 @@ impl NotebookCard {
     }
 }
 
+#[derive(Debug, Clone)]
+pub(crate) struct CollectionCard {
+    title: String,
+    description: Option<String>,
+    notebook_count: i64,
+    is_private: bool,
+}
+
+impl CollectionCard {
+    fn view(&self) -> Element<Message> {
+        let title = if self.is_private {
+            format!("🔒 {}", self.title)
+        } else {
+            self.title.clone()
+        };
+
+        container(
+            column![
+                text(title).size(16).width(Length::Fixed(200.0)),
+                text(self.description.clone().unwrap_or_default())
+                    .size(12)
+                    .width(Length::Fixed(200.0)),
+                row![
+                    horizontal_space(),
+                    text(format!("{} notebooks", self.notebook_count)).size(12),
+                ]
+                .width(Length::Fixed(200.0))
+            ]
+            .spacing(8),
+        )
+        .padding(8)
+        .into()
+    }
+}
+
 pub enum UserPage {
     Loading,
     Page {
         user_id: u64,
         username: String,
-        avatar: Option<Vec<u8>>,
+        avatar: Option<BlobRef>,
         created_at: String,
         notebooks: Vec<NotebookCard>,
+        collections: Vec<CollectionCard>,
         error: Option<String>,
     },
 }
@@ -87,8 +125,24 @@ impl UserPage {
                             preview: notebook.preview,
                         })
                         .collect(),
+                    collections: Vec::new(),
                     error: None,
                 };
+                Task::done(Message::GetCollectionsRespond(response.id as u64))
+            }
+            Message::GetCollectionsRequest(response) => {
+                if let Self::Page { collections, .. } = self {
+                    *collections = response
+                        .collections
+                        .into_iter()
+                        .map(|collection| CollectionCard {
+                            title: collection.title,
+                            description: collection.description,
+                            notebook_count: collection.notebook_count,
+                            is_private: collection.visibility == "private",
+                        })
+                        .collect();
+                }
                 Task::none()
             }
             Message::LoadUser(id) => Task::done(Message::GetUserRespond(id)),
@@ -114,6 +168,7 @@ impl UserPage {
                 avatar,
                 created_at,
                 notebooks,
+                collections,
                 error,
                 ..
             } => {
@@ -145,7 +200,18 @@ impl UserPage {
                         .iter()
                         .fold(notebooks_grid, |row, notebook| row.push(notebook.view()));
 
-                    container(column![header, notebooks_grid].spacing(30).padding(20))
+                    let mut page = column![header, notebooks_grid].spacing(30).padding(20);
+                    if !collections.is_empty() {
+                        let collections_grid = collections.iter().fold(
+                            row![].spacing(20).padding(20).width(Length::Fill),
+                            |row, collection| row.push(collection.view()),
+                        );
+                        page = page
+                            .push(text("Collections").size(24))
+                            .push(collections_grid);
+                    }
+
+                    container(page)
                 };
 
                 scrollable(content)
@@ -1,5 +1,8 @@
-use iced::widget::{Shader, button, column, container, markdown, pane_grid, row, scrollable};
+use iced::widget::{
+    Shader, button, column, container, markdown, pane_grid, row, scrollable, text, text_input,
+};
 use iced::{Alignment, Element, Length, Task, Theme};
+use senra_api::{ImportError, PackageLock, ShaderLanguage};
 
 use super::editor::{Editor, Message as EditorMessage, Syntax};
 use super::viewer::Viewer;
@@ -7,6 +10,7 @@ use super::viewer::Viewer;
 #[derive(Debug, Clone)]
 pub enum Message {
     SelectType(CellType),
+    Rename(String),
 
     MoveUp,
     MoveDown,
@@ -20,7 +24,7 @@ pub enum Message {
 #[derive(Debug, Clone, PartialEq)]
 pub enum CellType {
     Markdown,
-    Shader,
+    Shader(ShaderLanguage),
 }
 
 pub enum CellPreview {
@@ -34,13 +38,25 @@ pub enum CellPane {
 }
 
 pub struct Cell {
+    /// Module name other cells import the cell code with
+    name: String,
     panes: pane_grid::State<CellPane>,
     editor: Editor,
     preview: CellPreview,
+    language: ShaderLanguage,
+    error: Option<String>,
 }
 
 impl Cell {
-    pub fn new(cell_type: CellType, content: Option<String>) -> (Self, Task<Message>) {
+    pub fn new(
+        name: String,
+        cell_type: CellType,
+        content: Option<String>,
+    ) -> (Self, Task<Message>) {
+        let language = match cell_type {
+            CellType::Shader(language) => language,
+            CellType::Markdown => ShaderLanguage::default(),
+        };
         let (editor, preview, task) = match cell_type {
             CellType::Markdown => {
                 let markdown = content.as_ref().map_or(Vec::new(), |content| {
@@ -50,8 +66,8 @@ impl Cell {
                 let preview = CellPreview::Markdown(markdown);
                 (editor, preview, Task::<Message>::none())
             }
-            CellType::Shader => {
-                let editor = Editor::new(Syntax::Wgsl, content);
+            CellType::Shader(language) => {
+                let editor = Editor::new(shader_syntax(language), content);
                 let preview = CellPreview::Renderer(Viewer::default());
                 (editor, preview, Task::<Message>::none())
             }
@@ -65,9 +81,12 @@ impl Cell {
 
         (
             Self {
+                name,
                 panes,
                 editor,
                 preview,
+                language,
+                error: None,
             },
             task,
         )
@@ -76,15 +95,22 @@ impl Cell {
     pub fn update(&mut self, message: Message) -> Task<Message> {
         match message {
             Message::SelectType(cell_type) => {
-                self.preview = match cell_type {
+                let syntax = match cell_type {
                     CellType::Markdown => {
                         let markdown = markdown::parse(&self.editor.content()).collect();
-                        CellPreview::Markdown(markdown)
+                        self.preview = CellPreview::Markdown(markdown);
+                        Syntax::Markdown
+                    }
+                    CellType::Shader(language) => {
+                        self.preview = CellPreview::Renderer(Viewer::default());
+                        self.language = language;
+                        shader_syntax(language)
                     }
-                    CellType::Shader => CellPreview::Renderer(Viewer::default()),
                 };
 
-                Task::none()
+                self.editor
+                    .update(EditorMessage::SwitchSyntax(syntax))
+                    .map(Message::Editor)
             }
             Message::Editor(message) => {
                 if let EditorMessage::ActionPerformed(action) = &message {
@@ -97,17 +123,61 @@ impl Cell {
                 }
                 self.editor.update(message).map(Message::Editor)
             }
-            Message::CompileShader => {
-                let viewer = Viewer::new(self.editor.content());
-                self.preview = CellPreview::Renderer(viewer);
+            Message::Rename(name) => {
+                self.name = name;
                 Task::none()
             }
             _ => Task::none(),
         }
     }
 
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Code of shader cells, importable by other cells
+    pub fn shader_code(&self) -> Option<String> {
+        match self.preview {
+            CellPreview::Renderer(_) => Some(self.editor.content()),
+            CellPreview::Markdown(_) => None,
+        }
+    }
+
+    /// Compiles the shader after expanding its imports with the resolved `packages` and
+    /// `resolve`
+    ///
+    /// Import errors are returned for the caller to fetch missing modules, compile errors are
+    /// shown in the cell.
+    pub fn compile<'a>(
+        &mut self,
+        packages: &'a PackageLock,
+        resolve: impl Fn(&str) -> Option<&'a str>,
+    ) -> Result<(), ImportError> {
+        let shader = packages
+            .preprocess(&self.name, &self.editor.content(), resolve)?
+            .with_prelude(self.language);
+        match Viewer::compile(&shader, self.language) {
+            Ok(viewer) => {
+                self.preview = CellPreview::Renderer(viewer);
+                self.error = None;
+            }
+            Err(errors) => {
+                let messages: Vec<String> = errors.into_iter().map(|error| error.message).collect();
+                self.error = Some(messages.join("\n"));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_error(&mut self, error: String) {
+        self.error = Some(error);
+    }
+
     pub fn view(&self) -> Element<Message> {
         let title_bar = row![
+            text_input("Module name", &self.name)
+                .on_input(Message::Rename)
+                .width(Length::Fixed(160.0)),
             button(" ↑ ").on_press(Message::MoveUp),
             button(" ↓ ").on_press(Message::MoveDown),
             button(" x ").on_press(Message::Delete),
@@ -140,13 +210,28 @@ impl Cell {
             },
         });
 
-        column![
-            title_bar,
-            container(pane_grid)
-                .width(Length::Fill)
-                .height(Length::Fixed(300.0))
-                .padding(10)
-        ]
-        .into()
+        column![title_bar]
+            .push_maybe(self.error.as_ref().map(|error| {
+                container(
+                    text(error)
+                        .size(14)
+                        .color(iced::Color::from_rgb(1.0, 0.0, 0.0)),
+                )
+                .padding([0, 10])
+            }))
+            .push(
+                container(pane_grid)
+                    .width(Length::Fill)
+                    .height(Length::Fixed(300.0))
+                    .padding(10),
+            )
+            .into()
+    }
+}
+
+fn shader_syntax(language: ShaderLanguage) -> Syntax {
+    match language {
+        ShaderLanguage::Wgsl => Syntax::Wgsl,
+        ShaderLanguage::Glsl => Syntax::Glsl,
     }
 }
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use iced::widget::{Column, button, column, container, markdown, row, text, text_input};
+use iced::{Alignment, Element, Length, Task, Theme};
+use senra_api::{
+    CommentAnchor, CreateNotebookCommentRequest, NotebookCommentListResponse,
+    NotebookCommentResponse,
+};
+
+/// Replies deeper than this are no longer indented further
+const MAX_INDENT_DEPTH: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    InputChanged(String),
+    ReplyTo(Option<i64>),
+    StartEdit(i64),
+    EditChanged(String),
+    CancelEdit,
+    Submit,
+    SubmitEdit,
+    Markdown(markdown::Url),
+
+    /// Comment to create, handled by the page
+    Create(CreateNotebookCommentRequest),
+    /// Edited content of a comment, handled by the page
+    Edit(i64, String),
+}
+
+/// Comments panel rendering the threads of a notebook
+#[derive(Default)]
+pub struct Comments {
+    comments: Vec<NotebookCommentResponse>,
+    /// Parsed content of the comments, keyed by comment ID
+    markdown: HashMap<i64, Vec<markdown::Item>>,
+    total: i64,
+    input: String,
+    reply_to: Option<i64>,
+    editing: Option<(i64, String)>,
+}
+
+impl Comments {
+    pub fn set_list(&mut self, list: NotebookCommentListResponse) {
+        self.markdown = list
+            .comments
+            .iter()
+            .map(|comment| (comment.id, markdown::parse(&comment.content).collect()))
+            .collect();
+        self.comments = list.comments;
+        self.total = list.total;
+    }
+
+    /// Adds a created comment or replaces an edited one
+    pub fn insert(&mut self, comment: NotebookCommentResponse) {
+        self.markdown
+            .insert(comment.id, markdown::parse(&comment.content).collect());
+
+        if let Some(existing) = self.comments.iter_mut().find(|c| c.id == comment.id) {
+            *existing = comment;
+        } else if comment.parent_id.is_some() {
+            self.comments.push(comment);
+        } else {
+            self.total += 1;
+            self.comments.insert(0, comment);
+        }
+    }
+
+    pub fn update(&mut self, message: Message) -> Task<Message> {
+        match message {
+            Message::InputChanged(input) => {
+                self.input = input;
+                Task::none()
+            }
+            Message::ReplyTo(comment_id) => {
+                self.reply_to = comment_id;
+                Task::none()
+            }
+            Message::StartEdit(comment_id) => {
+                self.editing = self
+                    .comments
+                    .iter()
+                    .find(|comment| comment.id == comment_id)
+                    .map(|comment| (comment_id, comment.content.clone()));
+                Task::none()
+            }
+            Message::EditChanged(content) => {
+                if let Some((_, editing)) = &mut self.editing {
+                    *editing = content;
+                }
+                Task::none()
+            }
+            Message::CancelEdit => {
+                self.editing = None;
+                Task::none()
+            }
+            Message::Submit => {
+                if self.input.trim().is_empty() {
+                    return Task::none();
+                }
+                let request = CreateNotebookCommentRequest {
+                    content: std::mem::take(&mut self.input),
+                    parent_id: self.reply_to.take(),
+                    anchor: None,
+                };
+                Task::done(Message::Create(request))
+            }
+            Message::SubmitEdit => match self.editing.take() {
+                Some((comment_id, content)) if !content.trim().is_empty() => {
+                    Task::done(Message::Edit(comment_id, content))
+                }
+                _ => Task::none(),
+            },
+            _ => Task::none(),
+        }
+    }
+
+    /// Renders the threads, offering to edit the comments written by `user_id`
+    pub fn view(&self, user_id: Option<u64>) -> Element<'_, Message> {
+        let mut replies: HashMap<Option<i64>, Vec<&NotebookCommentResponse>> = HashMap::new();
+        for comment in &self.comments {
+            replies.entry(comment.parent_id).or_default().push(comment);
+        }
+
+        let mut threads = column![text(format!("Comments ({})", self.total)).size(20)].spacing(10);
+        for comment in replies.get(&None).into_iter().flatten() {
+            threads = self.view_thread(threads, comment, &replies, 0, user_id);
+        }
+
+        let replying_to = self.reply_to.and_then(|reply_to| {
+            let comment = self.comments.iter().find(|c| c.id == reply_to)?;
+            Some(
+                row![
+                    text(format!("Replying to {}", comment.author)).size(14),
+                    button("Cancel").on_press(Message::ReplyTo(None)),
+                ]
+                .spacing(10)
+                .align_y(Alignment::Center),
+            )
+        });
+        let input = row![
+            text_input("Write a comment (Markdown)", &self.input)
+                .on_input(Message::InputChanged)
+                .on_submit(Message::Submit)
+                .padding([6, 10]),
+            button("Send").on_press(Message::Submit),
+        ]
+        .spacing(10)
+        .align_y(Alignment::Center);
+
+        threads
+            .push_maybe(replying_to)
+            .push(input)
+            .width(Length::Fill)
+            .into()
+    }
+
+    fn view_thread<'a>(
+        &'a self,
+        threads: Column<'a, Message>,
+        comment: &'a NotebookCommentResponse,
+        replies: &HashMap<Option<i64>, Vec<&'a NotebookCommentResponse>>,
+        depth: usize,
+        user_id: Option<u64>,
+    ) -> Column<'a, Message> {
+        let mut header = row![text(&comment.author).size(14)]
+            .spacing(10)
+            .align_y(Alignment::Center);
+        if let Some(anchor) = &comment.anchor {
+            header = header.push(text(anchor_label(anchor)).size(12));
+        }
+        if comment.edited_at.is_some() {
+            header = header.push(text("(edited)").size(12));
+        }
+        header = header.push(button("Reply").on_press(Message::ReplyTo(Some(comment.id))));
+        if user_id == Some(comment.user_id as u64) {
+            header = header.push(button("Edit").on_press(Message::StartEdit(comment.id)));
+        }
+
+        let body: Element<Message> = match &self.editing {
+            Some((editing_id, content)) if *editing_id == comment.id => row![
+                text_input("Comment", content)
+                    .on_input(Message::EditChanged)
+                    .on_submit(Message::SubmitEdit)
+                    .padding([6, 10]),
+                button("Save").on_press(Message::SubmitEdit),
+                button("Cancel").on_press(Message::CancelEdit),
+            ]
+            .spacing(10)
+            .align_y(Alignment::Center)
+            .into(),
+            _ => markdown::view(
+                self.markdown.get(&comment.id).into_iter().flatten(),
+                markdown::Settings::default(),
+                markdown::Style::from_palette(Theme::TokyoNightStorm.palette()),
+            )
+            .map(Message::Markdown),
+        };
+
+        let indent = (depth.min(MAX_INDENT_DEPTH) * 24) as f32;
+        let mut threads = threads.push(
+            container(column![header, body].spacing(4))
+                .padding(iced::Padding::ZERO.left(indent))
+                .width(Length::Fill),
+        );
+        for reply in replies.get(&Some(comment.id)).into_iter().flatten() {
+            threads = self.view_thread(threads, reply, replies, depth + 1, user_id);
+        }
+        threads
+    }
+}
+
+fn anchor_label(anchor: &CommentAnchor) -> String {
+    match (anchor.start_line, anchor.end_line) {
+        (Some(start), Some(end)) if start == end => {
+            format!("on `{}` line {}", anchor.cell_id, start)
+        }
+        (Some(start), Some(end)) => format!("on `{}` lines {}-{}", anchor.cell_id, start, end),
+        _ => format!("on `{}`", anchor.cell_id),
+    }
+}
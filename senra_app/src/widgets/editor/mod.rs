@@ -18,6 +18,7 @@ pub enum Syntax {
     PlainText,
     Markdown,
     Wgsl,
+    Glsl,
 }
 
 impl Syntax {
@@ -26,6 +27,7 @@ impl Syntax {
             Syntax::PlainText => "plain_text",
             Syntax::Markdown => "markdown",
             Syntax::Wgsl => "wgsl",
+            Syntax::Glsl => "glsl",
         }
     }
 }
@@ -1,7 +1,9 @@
 pub mod cell;
+pub mod comments;
 pub mod editor;
 pub mod menu;
 pub mod viewer;
 
 pub use cell::{Cell, CellType, Message as CellMessage};
+pub use comments::{Comments, Message as CommentsMessage};
 pub use editor::{Editor, Message as EditorMessage};
@@ -3,26 +3,83 @@ mod primitive;
 mod uniforms;
 
 use std::sync::Arc;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use iced::advanced::Shell;
 use iced::widget::shader;
 use iced::{Point, Rectangle, event, mouse, window};
 use primitive::Primitive;
+use senra_api::{
+    CompileTarget, CompiledShader, FieldError, PreprocessedShader, ShaderLanguage, ShaderSource,
+    ShaderStage,
+};
 use uniforms::Uniforms;
 
 pub struct Viewer {
     start: Instant,
+    /// Shader clock while paused
+    paused: Option<Duration>,
+    /// Fragment shader drawn by the viewer, as a complete WGSL module
     pub last_valid_shader: Arc<String>,
+    pub entry_point: &'static str,
     pub version: usize,
 }
 
 impl Viewer {
-    pub fn new(shader_code: String) -> Self {
-        Self {
+    /// Builds a viewer drawing a fragment shader written in `language`, its imports expanded
+    /// and the prelude declaring the uniforms prepended
+    ///
+    /// The shader is validated through naga before its pipeline is built, GLSL being
+    /// translated to WGSL on the way. Error lines point to the modules they come from.
+    pub fn compile(
+        shader: &PreprocessedShader,
+        language: ShaderLanguage,
+    ) -> Result<Self, Vec<FieldError>> {
+        let compiled = ShaderSource::preprocessed(shader, language)
+            .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+            .map_err(|err| {
+                vec![FieldError {
+                    path: String::new(),
+                    message: err.to_string(),
+                }]
+            })?;
+        let last_valid_shader = match (language, compiled) {
+            (ShaderLanguage::Wgsl, _) => shader.code.clone(),
+            (ShaderLanguage::Glsl, CompiledShader::Source(wgsl)) => wgsl,
+            (ShaderLanguage::Glsl, _) => unreachable!("compiled to WGSL"),
+        };
+
+        Ok(Self {
             start: Instant::now(),
-            last_valid_shader: Arc::new(shader_code),
+            paused: None,
+            last_valid_shader: Arc::new(last_valid_shader),
+            entry_point: entry_point(language),
             version: 0,
+        })
+    }
+
+    /// Moves the shader clock to `seconds`
+    pub fn seek(&mut self, seconds: f32) {
+        let elapsed = Duration::from_secs_f32(seconds);
+        match &mut self.paused {
+            Some(paused) => *paused = elapsed,
+            None => self.start = started_before(elapsed),
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    pub fn pause(&mut self) {
+        if self.paused.is_none() {
+            self.paused = Some(self.start.elapsed());
+        }
+    }
+
+    pub fn resume(&mut self) {
+        if let Some(elapsed) = self.paused.take() {
+            self.start = started_before(elapsed);
         }
     }
 }
@@ -31,12 +88,33 @@ impl Default for Viewer {
     fn default() -> Self {
         Self {
             start: Instant::now(),
-            last_valid_shader: Arc::new(include_str!("shaders/default_frag.wgsl").to_string()),
+            paused: None,
+            last_valid_shader: Arc::new(
+                PreprocessedShader {
+                    code: include_str!("shaders/default_frag.wgsl").to_string(),
+                    ..Default::default()
+                }
+                .with_prelude(ShaderLanguage::Wgsl)
+                .code,
+            ),
+            entry_point: entry_point(ShaderLanguage::Wgsl),
             version: 0,
         }
     }
 }
 
+fn started_before(elapsed: Duration) -> Instant {
+    let now = Instant::now();
+    now.checked_sub(elapsed).unwrap_or(now)
+}
+
+fn entry_point(language: ShaderLanguage) -> &'static str {
+    match language {
+        ShaderLanguage::Wgsl => "fs_main",
+        ShaderLanguage::Glsl => ShaderLanguage::GLSL_ENTRY_POINT,
+    }
+}
+
 impl<Message> shader::Program<Message> for Viewer {
     type State = ();
     type Primitive = Primitive;
@@ -62,7 +140,7 @@ impl<Message> shader::Program<Message> for Viewer {
     ) -> Self::Primitive {
         Primitive {
             uniforms: Uniforms {
-                time: Instant::now() - self.start,
+                time: self.paused.unwrap_or_else(|| self.start.elapsed()),
                 mouse: match cursor {
                     mouse::Cursor::Available(pt) => pt,
                     mouse::Cursor::Unavailable => Point::new(-1.0, -1.0),
@@ -70,6 +148,7 @@ impl<Message> shader::Program<Message> for Viewer {
                 bounds,
             },
             shader: self.last_valid_shader.clone(),
+            entry_point: self.entry_point,
             version: self.version,
         }
     }
@@ -2,6 +2,7 @@ use std::borrow::Cow;
 
 use iced::Rectangle;
 use iced::widget::shader::wgpu;
+use senra_api::{ShaderLanguage, VIEWER_CHANNEL_COUNT, VIEWER_CHANNEL_GROUP};
 
 use super::uniforms;
 
@@ -9,6 +10,7 @@ pub struct Pipeline {
     pub version: usize,
     uniforms: wgpu::Buffer,
     bind_group: wgpu::BindGroup,
+    channel_bind_group: wgpu::BindGroup,
     pipeline: wgpu::RenderPipeline,
 }
 
@@ -20,6 +22,7 @@ impl Pipeline {
         device: &wgpu::Device,
         format: wgpu::TextureFormat,
         shader: &str,
+        entry_point: &str,
         version: usize,
     ) -> Self {
         let uniforms = device.create_buffer(&wgpu::BufferDescriptor {
@@ -52,31 +55,26 @@ impl Pipeline {
             }],
         });
 
+        let (channel_layout, channel_bind_group) = channel_bind_group(device);
+
         let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("pipeline.layout"),
-            bind_group_layouts: &[&layout],
+            bind_group_layouts: &[&layout, &channel_layout],
             push_constant_ranges: &[],
         });
 
         let vertex_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("pipeline.shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(concat!(
-                include_str!("shaders/shared_uniforms.wgsl"),
-                "\n",
+            source: wgpu::ShaderSource::Wgsl(Cow::Owned(format!(
+                "{}\n{}",
+                ShaderLanguage::Wgsl.prelude(),
                 include_str!("shaders/default_vert.wgsl"),
             ))),
         });
 
         let fragment_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("pipeline.fragment_shader"),
-            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(
-                format!(
-                    "{}\n{}",
-                    include_str!("shaders/shared_uniforms.wgsl"),
-                    shader
-                )
-                .as_str(),
-            )),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(shader)),
         });
 
         let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -92,7 +90,7 @@ impl Pipeline {
             multisample: Default::default(),
             fragment: Some(wgpu::FragmentState {
                 module: &fragment_shader,
-                entry_point: "fs_main",
+                entry_point,
                 targets: &[Some(wgpu::ColorTargetState {
                     format,
                     blend: Some(wgpu::BlendState::ALPHA_BLENDING),
@@ -106,6 +104,7 @@ impl Pipeline {
             version,
             uniforms,
             bind_group,
+            channel_bind_group,
             pipeline,
         }
     }
@@ -139,6 +138,79 @@ impl Pipeline {
 
         pass.set_pipeline(&self.pipeline);
         pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_bind_group(VIEWER_CHANNEL_GROUP, &self.channel_bind_group, &[]);
         pass.draw(0..6, 0..1);
     }
 }
+
+/// Bind group of the input channels, every channel sampling a black placeholder texture
+fn channel_bind_group(device: &wgpu::Device) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+    let texture = device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("pipeline.channel_texture"),
+        size: wgpu::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::TEXTURE_BINDING,
+        view_formats: &[],
+    });
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("pipeline.channel_sampler"),
+        ..Default::default()
+    });
+
+    let layout_entries: Vec<wgpu::BindGroupLayoutEntry> = (0..VIEWER_CHANNEL_COUNT)
+        .flat_map(|channel| {
+            [
+                wgpu::BindGroupLayoutEntry {
+                    binding: channel * 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: channel * 2 + 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ]
+        })
+        .collect();
+    let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("pipeline.channel_bind_group_layout"),
+        entries: &layout_entries,
+    });
+
+    let entries: Vec<wgpu::BindGroupEntry> = (0..VIEWER_CHANNEL_COUNT)
+        .flat_map(|channel| {
+            [
+                wgpu::BindGroupEntry {
+                    binding: channel * 2,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: channel * 2 + 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ]
+        })
+        .collect();
+    let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("pipeline.channel_bind_group"),
+        layout: &layout,
+        entries: &entries,
+    });
+
+    (layout, bind_group)
+}
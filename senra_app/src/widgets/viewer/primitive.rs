@@ -13,6 +13,7 @@ use super::uniforms::Uniforms;
 pub struct Primitive {
     pub uniforms: Uniforms,
     pub shader: Arc<String>,
+    pub entry_point: &'static str,
     pub version: usize,
 }
 
@@ -32,7 +33,13 @@ impl shader::Primitive for Primitive {
             .unwrap_or(true);
 
         if should_store {
-            storage.store(Pipeline::new(device, format, &self.shader, self.version));
+            storage.store(Pipeline::new(
+                device,
+                format,
+                &self.shader,
+                self.entry_point,
+                self.version,
+            ));
         }
 
         let pipeline = storage.get_mut::<Pipeline>().unwrap();
@@ -0,0 +1,26 @@
+-- User-owned ordered lists of notebooks
+CREATE TABLE IF NOT EXISTS collections (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id         INTEGER NOT NULL,
+    title           TEXT NOT NULL,
+    description     TEXT,
+    visibility      TEXT NOT NULL DEFAULT 'public' CHECK (visibility IN ('public', 'private')),
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_collections_user ON collections(user_id, updated_at);
+
+CREATE TABLE IF NOT EXISTS collection_notebooks (
+    collection_id   INTEGER NOT NULL,
+    notebook_id     INTEGER NOT NULL,
+    -- Order of the notebook in the collection starting at 0
+    position        INTEGER NOT NULL,
+    added_at        TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (collection_id, notebook_id),
+    FOREIGN KEY (collection_id) REFERENCES collections(id) ON DELETE CASCADE,
+    FOREIGN KEY (notebook_id) REFERENCES notebooks(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_notebooks_position ON collection_notebooks(collection_id, position);
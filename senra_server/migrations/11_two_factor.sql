@@ -0,0 +1,22 @@
+-- TOTP second factor of users, enabled once a first code was confirmed
+CREATE TABLE IF NOT EXISTS user_two_factor (
+    user_id         INTEGER PRIMARY KEY,
+    -- Base32 encoded shared secret
+    secret          TEXT NOT NULL,
+    enabled_at      TIMESTAMP,
+    -- Time step of the last accepted code, so a code can't be replayed
+    last_step       INTEGER,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Single use codes standing in for the second factor, only their hash is kept
+CREATE TABLE IF NOT EXISTS user_recovery_codes (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id         INTEGER NOT NULL,
+    code_hash       TEXT NOT NULL,
+    used_at         TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_recovery_codes_user ON user_recovery_codes(user_id, code_hash);
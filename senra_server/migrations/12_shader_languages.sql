@@ -0,0 +1,15 @@
+-- `shader_type` names the shader language since GLSL shaders are supported, shaders stored
+-- before held their stage, written in WGSL
+UPDATE shaders SET shader_type = 'wgsl' WHERE shader_type NOT IN ('wgsl', 'glsl');
+
+-- Published versions are otherwise immutable, so the trigger guarding them is lifted meanwhile
+DROP TRIGGER IF EXISTS shader_package_versions_immutable;
+
+UPDATE shader_package_versions SET shader_type = 'wgsl' WHERE shader_type NOT IN ('wgsl', 'glsl');
+
+CREATE TRIGGER IF NOT EXISTS shader_package_versions_immutable
+BEFORE UPDATE OF package_id, version, major, minor, patch, shader_type, code
+ON shader_package_versions
+BEGIN
+    SELECT RAISE(ABORT, 'Published package versions are immutable');
+END;
@@ -0,0 +1,8 @@
+-- Published versions are immutable and may be depended upon by notebooks of other users, so
+-- their publishers are kept rather than cascading the deletion to their packages
+CREATE TRIGGER IF NOT EXISTS users_publishing_packages
+BEFORE DELETE ON users
+WHEN EXISTS (SELECT 1 FROM shader_packages WHERE user_id = OLD.id)
+BEGIN
+    SELECT RAISE(ABORT, 'Users who published packages cannot be deleted');
+END;
@@ -0,0 +1,52 @@
+-- Create shader_packages table
+CREATE TABLE IF NOT EXISTS shader_packages (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id         INTEGER NOT NULL,
+    name            TEXT NOT NULL UNIQUE,
+    description     TEXT,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+-- Create shader_package_versions table
+CREATE TABLE IF NOT EXISTS shader_package_versions (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    package_id      INTEGER NOT NULL,
+    version         TEXT NOT NULL,
+    major           INTEGER NOT NULL,
+    minor           INTEGER NOT NULL,
+    patch           INTEGER NOT NULL,
+    shader_type     TEXT NOT NULL,
+    code            TEXT NOT NULL,
+    shader_id       INTEGER,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (package_id) REFERENCES shader_packages(id) ON DELETE CASCADE,
+    FOREIGN KEY (shader_id) REFERENCES shaders(id) ON DELETE SET NULL,
+    UNIQUE (package_id, version)
+);
+
+-- Published versions are immutable, only the shader they were published from may be unlinked
+CREATE TRIGGER IF NOT EXISTS shader_package_versions_immutable
+BEFORE UPDATE OF package_id, version, major, minor, patch, shader_type, code
+ON shader_package_versions
+BEGIN
+    SELECT RAISE(ABORT, 'Published package versions are immutable');
+END;
+
+-- Create shader_package_dependencies table, package versions imported by package versions
+CREATE TABLE IF NOT EXISTS shader_package_dependencies (
+    package_version_id      INTEGER NOT NULL,
+    dependency_version_id   INTEGER NOT NULL,
+    PRIMARY KEY (package_version_id, dependency_version_id),
+    FOREIGN KEY (package_version_id) REFERENCES shader_package_versions(id) ON DELETE CASCADE,
+    FOREIGN KEY (dependency_version_id) REFERENCES shader_package_versions(id) ON DELETE CASCADE
+);
+
+-- Create notebook_package_dependencies table, package versions resolved for notebooks
+CREATE TABLE IF NOT EXISTS notebook_package_dependencies (
+    notebook_id             INTEGER NOT NULL,
+    package_version_id      INTEGER NOT NULL,
+    PRIMARY KEY (notebook_id, package_version_id),
+    FOREIGN KEY (notebook_id) REFERENCES notebooks(id) ON DELETE CASCADE,
+    FOREIGN KEY (package_version_id) REFERENCES shader_package_versions(id) ON DELETE CASCADE
+);
@@ -0,0 +1,4 @@
+-- Deleted notebooks stay in their owner's trash until restored or purged
+ALTER TABLE notebooks ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX IF NOT EXISTS idx_notebooks_deleted_at ON notebooks(deleted_at);
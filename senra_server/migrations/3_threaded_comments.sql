@@ -0,0 +1,13 @@
+-- Replies point at the comment they answer and are removed with it
+ALTER TABLE notebook_comments ADD COLUMN parent_id INTEGER REFERENCES notebook_comments(id) ON DELETE CASCADE;
+
+-- Set when the author edits the comment content
+ALTER TABLE notebook_comments ADD COLUMN edited_at TIMESTAMP;
+
+-- Optional anchor to a cell of the notebook content and a line range of its code
+ALTER TABLE notebook_comments ADD COLUMN anchor_cell_id TEXT;
+ALTER TABLE notebook_comments ADD COLUMN anchor_start_line INTEGER;
+ALTER TABLE notebook_comments ADD COLUMN anchor_end_line INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_notebook_comments_notebook_id ON notebook_comments(notebook_id);
+CREATE INDEX IF NOT EXISTS idx_notebook_comments_parent_id ON notebook_comments(parent_id);
@@ -0,0 +1,17 @@
+-- Activity on a user's notebooks and comments, shown in their notification center
+CREATE TABLE IF NOT EXISTS notifications (
+    id              INTEGER PRIMARY KEY AUTOINCREMENT,
+    user_id         INTEGER NOT NULL,
+    actor_id        INTEGER NOT NULL,
+    kind            TEXT NOT NULL CHECK (kind IN ('like', 'comment', 'reply', 'mention')),
+    notebook_id     INTEGER NOT NULL,
+    comment_id      INTEGER,
+    read_at         TIMESTAMP,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (actor_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (notebook_id) REFERENCES notebooks(id) ON DELETE CASCADE,
+    FOREIGN KEY (comment_id) REFERENCES notebook_comments(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_notifications_user_id ON notifications(user_id, read_at);
@@ -0,0 +1,12 @@
+-- Users following the notebooks of other users
+CREATE TABLE IF NOT EXISTS user_follows (
+    follower_id     INTEGER NOT NULL,
+    followee_id     INTEGER NOT NULL,
+    created_at      TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (follower_id, followee_id),
+    CHECK (follower_id != followee_id),
+    FOREIGN KEY (follower_id) REFERENCES users(id) ON DELETE CASCADE,
+    FOREIGN KEY (followee_id) REFERENCES users(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_user_follows_followee_id ON user_follows(followee_id);
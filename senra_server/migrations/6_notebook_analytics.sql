@@ -0,0 +1,32 @@
+-- Recent views of notebooks, only kept long enough to deduplicate views of the same viewer
+CREATE TABLE IF NOT EXISTS notebook_view_log (
+    notebook_id     INTEGER NOT NULL,
+    -- `user:<id>` for signed in viewers, `anon:<fingerprint>` otherwise
+    viewer          TEXT NOT NULL,
+    viewed_at       TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (notebook_id, viewer),
+    FOREIGN KEY (notebook_id) REFERENCES notebooks(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_notebook_view_log_viewed_at ON notebook_view_log(viewed_at);
+
+-- Views, new likes and new comments of notebooks per UTC day
+CREATE TABLE IF NOT EXISTS notebook_daily_stats (
+    notebook_id     INTEGER NOT NULL,
+    day             DATE NOT NULL,
+    view_count      INTEGER NOT NULL DEFAULT 0,
+    like_count      INTEGER NOT NULL DEFAULT 0,
+    comment_count   INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (notebook_id, day),
+    FOREIGN KEY (notebook_id) REFERENCES notebooks(id) ON DELETE CASCADE
+);
+
+-- Counted views of notebooks per UTC day and referring host, empty for direct views
+CREATE TABLE IF NOT EXISTS notebook_daily_referrers (
+    notebook_id     INTEGER NOT NULL,
+    day             DATE NOT NULL,
+    referrer        TEXT NOT NULL DEFAULT '',
+    view_count      INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (notebook_id, day, referrer),
+    FOREIGN KEY (notebook_id) REFERENCES notebooks(id) ON DELETE CASCADE
+);
@@ -0,0 +1,15 @@
+-- Scores of public notebooks per feed, materialized periodically by the ranking job
+CREATE TABLE IF NOT EXISTS notebook_rankings (
+    feed            TEXT NOT NULL CHECK (feed IN ('hot', 'top-week', 'top-all-time')),
+    notebook_id     INTEGER NOT NULL,
+    score           REAL NOT NULL,
+    -- Position in the feed starting at 1, ties broken by the newest notebook
+    rank            INTEGER NOT NULL,
+    computed_at     TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (feed, notebook_id),
+    FOREIGN KEY (notebook_id) REFERENCES notebooks(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_notebook_rankings_rank ON notebook_rankings(feed, rank);
+
+CREATE INDEX IF NOT EXISTS idx_notebooks_created_at ON notebooks(created_at, id);
@@ -0,0 +1,5 @@
+-- Blobs move to the content-addressed blob store, rows only keep their SHA-256 hash.
+-- The inline columns are emptied as their blobs are moved on startup
+ALTER TABLE resources ADD COLUMN data_hash TEXT;
+ALTER TABLE notebooks ADD COLUMN preview_hash TEXT;
+ALTER TABLE users ADD COLUMN avatar_hash TEXT;
@@ -0,0 +1,9 @@
+-- Resized variants of user avatars, `users.avatar_hash` pointing at the default size
+CREATE TABLE IF NOT EXISTS user_avatars (
+    user_id         INTEGER NOT NULL,
+    -- Width and height in pixels
+    size            INTEGER NOT NULL,
+    hash            TEXT NOT NULL,
+    PRIMARY KEY (user_id, size),
+    FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+);
@@ -0,0 +1,121 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Cursor;
+
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::io::{Limits, Reader};
+use image::{ColorType, DynamicImage, ImageBuffer, ImageFormat, Rgba, RgbaImage};
+
+use crate::config::AvatarConfig;
+use crate::errors::{AppError, Result, UserError};
+
+/// Widths and heights in pixels of the variants stored for each avatar
+pub const SIZES: [u32; 3] = [64, 128, 256];
+
+/// Size of the variant shown wherever a single avatar is returned
+pub const DEFAULT_SIZE: u32 = 128;
+
+/// Formats accepted for uploaded avatars
+const FORMATS: [ImageFormat; 4] = [
+    ImageFormat::Png,
+    ImageFormat::Jpeg,
+    ImageFormat::Gif,
+    ImageFormat::WebP,
+];
+
+/// Encoded WebP variant of an avatar
+pub struct AvatarVariant {
+    pub size: u32,
+    pub data: Vec<u8>,
+}
+
+/// Validates an uploaded image and turns it into the avatar variants, cropped to a square.
+/// Variants are encoded from the decoded pixels so no metadata of the upload is kept
+pub fn process(data: &[u8], config: &AvatarConfig) -> Result<Vec<AvatarVariant>> {
+    if data.len() > config.max_bytes {
+        return Err(UserError::AvatarTooLarge(config.max_bytes).into());
+    }
+
+    let format = image::guess_format(data)
+        .ok()
+        .filter(|format| FORMATS.contains(format))
+        .ok_or_else(|| UserError::InvalidAvatar("Unsupported image format".to_string()))?;
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(config.max_dimension);
+    limits.max_image_height = Some(config.max_dimension);
+
+    let mut reader = Reader::with_format(Cursor::new(data), format);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|err| UserError::InvalidAvatar(err.to_string()))?;
+
+    let side = image.width().min(image.height());
+    if side == 0 {
+        return Err(UserError::InvalidAvatar("Empty image".to_string()).into());
+    }
+    let square = image.crop_imm(
+        (image.width() - side) / 2,
+        (image.height() - side) / 2,
+        side,
+        side,
+    );
+
+    variants(&square, FilterType::Lanczos3)
+}
+
+/// Default avatar of a user, a symmetric pattern derived from their username
+pub fn identicon(seed: &str) -> Result<Vec<AvatarVariant>> {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    let seed = hasher.finish();
+
+    let mut img: RgbaImage = ImageBuffer::new(64, 64);
+
+    let color = Rgba([
+        ((seed >> 16) & 0xFF) as u8,
+        ((seed >> 8) & 0xFF) as u8,
+        (seed & 0xFF) as u8,
+        255,
+    ]);
+
+    let grid_size = 5;
+    let cell_size = 12;
+    let padding = (64 - grid_size * cell_size) / 2;
+
+    for y in 0..grid_size {
+        for x in 0..(grid_size / 2 + 1) {
+            let pattern = (seed >> (y * 3 + x)) & 0x7;
+            if pattern.is_multiple_of(2) {
+                for px in padding + x * cell_size..padding + (x + 1) * cell_size {
+                    for py in padding + y * cell_size..padding + (y + 1) * cell_size {
+                        *img.get_pixel_mut(px as u32, py as u32) = color;
+                        if x < grid_size / 2 {
+                            *img.get_pixel_mut((64 - px - 1) as u32, py as u32) = color;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Sizes are multiples of the grid so nearest scaling keeps the cells sharp
+    variants(&DynamicImage::ImageRgba8(img), FilterType::Nearest)
+}
+
+fn variants(image: &DynamicImage, filter: FilterType) -> Result<Vec<AvatarVariant>> {
+    SIZES
+        .iter()
+        .map(|&size| {
+            let resized = image.resize_exact(size, size, filter).to_rgba8();
+
+            let mut data = Vec::new();
+            WebPEncoder::new_lossless(&mut data)
+                .encode(&resized, size, size, ColorType::Rgba8)
+                .map_err(|err| AppError::InternalError(err.to_string()))?;
+
+            Ok(AvatarVariant { size, data })
+        })
+        .collect()
+}
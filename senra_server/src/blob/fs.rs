@@ -0,0 +1,129 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use tokio::io::AsyncReadExt;
+
+use super::{BlobBackend, BlobReader, is_valid_hash};
+
+/// Suffix of temporary files, unique among concurrent writes of the same blob
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Stores each blob as a file named by its hash, sharded by the first two hash characters
+pub struct FsBackend {
+    root: PathBuf,
+}
+
+impl FsBackend {
+    pub fn new(root: impl Into<PathBuf>) -> io::Result<Self> {
+        let root = root.into();
+        fs::create_dir_all(&root)?;
+        Ok(Self { root })
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(hash)
+    }
+}
+
+impl BlobBackend for FsBackend {
+    fn put(&self, hash: &str, data: &[u8]) -> io::Result<()> {
+        let path = self.path(hash);
+        if path.exists() {
+            return Ok(());
+        }
+
+        let dir = path.parent().expect("Blob paths should have a parent");
+        fs::create_dir_all(dir)?;
+
+        // Readers never see partially written blobs
+        let temp_path = dir.join(format!(
+            "{}.{}.{}.tmp",
+            hash,
+            std::process::id(),
+            TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = File::create(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(data)?;
+                file.sync_all()
+            })
+            .and_then(|_| fs::rename(&temp_path, &path));
+
+        match result {
+            Ok(()) => Ok(()),
+            // Another write of the same content got there first
+            Err(_) if path.exists() => {
+                let _ = fs::remove_file(&temp_path);
+                Ok(())
+            }
+            Err(err) => {
+                let _ = fs::remove_file(&temp_path);
+                Err(err)
+            }
+        }
+    }
+
+    fn size(&self, hash: &str) -> io::Result<Option<u64>> {
+        match fs::metadata(self.path(hash)) {
+            Ok(metadata) => Ok(Some(metadata.len())),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    fn read(&self, hash: &str, range: Range<u64>) -> io::Result<Option<Vec<u8>>> {
+        let mut file = match File::open(self.path(hash)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        file.seek(SeekFrom::Start(range.start))?;
+        let mut data = vec![0; (range.end - range.start) as usize];
+        file.read_exact(&mut data)?;
+        Ok(Some(data))
+    }
+
+    fn open(&self, hash: &str, range: Range<u64>) -> io::Result<Option<BlobReader>> {
+        let mut file = match File::open(self.path(hash)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err),
+        };
+
+        file.seek(SeekFrom::Start(range.start))?;
+        let file = tokio::fs::File::from_std(file).take(range.end - range.start);
+        Ok(Some(Box::new(file)))
+    }
+
+    fn delete(&self, hash: &str) -> io::Result<()> {
+        match fs::remove_file(self.path(hash)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        let mut hashes = Vec::new();
+        for shard in fs::read_dir(&self.root)? {
+            let shard = shard?;
+            if !shard.file_type()?.is_dir() {
+                continue;
+            }
+            for entry in fs::read_dir(shard.path())? {
+                // Leaves out the blobs still being written
+                if let Some(name) = entry?
+                    .file_name()
+                    .to_str()
+                    .filter(|name| is_valid_hash(name))
+                {
+                    hashes.push(name.to_string());
+                }
+            }
+        }
+        Ok(hashes)
+    }
+}
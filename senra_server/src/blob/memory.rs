@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+use std::io;
+use std::ops::Range;
+use std::sync::RwLock;
+
+use super::{BlobBackend, BlobReader};
+
+/// Keeps the blobs in memory, for tests and ephemeral servers
+#[derive(Default)]
+pub struct MemoryBackend {
+    blobs: RwLock<HashMap<String, Vec<u8>>>,
+}
+
+impl MemoryBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl BlobBackend for MemoryBackend {
+    fn put(&self, hash: &str, data: &[u8]) -> io::Result<()> {
+        let mut blobs = self.blobs.write().map_err(|_| poisoned())?;
+        blobs
+            .entry(hash.to_string())
+            .or_insert_with(|| data.to_vec());
+        Ok(())
+    }
+
+    fn size(&self, hash: &str) -> io::Result<Option<u64>> {
+        let blobs = self.blobs.read().map_err(|_| poisoned())?;
+        Ok(blobs.get(hash).map(|data| data.len() as u64))
+    }
+
+    fn read(&self, hash: &str, range: Range<u64>) -> io::Result<Option<Vec<u8>>> {
+        let blobs = self.blobs.read().map_err(|_| poisoned())?;
+        blobs
+            .get(hash)
+            .map(|data| {
+                data.get(range.start as usize..range.end as usize)
+                    .map(<[u8]>::to_vec)
+                    .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))
+            })
+            .transpose()
+    }
+
+    fn open(&self, hash: &str, range: Range<u64>) -> io::Result<Option<BlobReader>> {
+        Ok(self
+            .read(hash, range)?
+            .map(|data| Box::new(io::Cursor::new(data)) as BlobReader))
+    }
+
+    fn delete(&self, hash: &str) -> io::Result<()> {
+        let mut blobs = self.blobs.write().map_err(|_| poisoned())?;
+        blobs.remove(hash);
+        Ok(())
+    }
+
+    fn list(&self) -> io::Result<Vec<String>> {
+        let blobs = self.blobs.read().map_err(|_| poisoned())?;
+        Ok(blobs.keys().cloned().collect())
+    }
+}
+
+fn poisoned() -> io::Error {
+    io::Error::other("Blob store lock poisoned")
+}
@@ -0,0 +1,126 @@
+mod fs;
+mod memory;
+
+use std::io;
+use std::ops::Range;
+use std::sync::Arc;
+
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncRead;
+
+pub use fs::FsBackend;
+pub use memory::MemoryBackend;
+
+/// Bytes of a blob read as they are sent
+pub type BlobReader = Box<dyn AsyncRead + Send + Unpin>;
+
+/// Storage of blobs by their SHA-256 hash, implemented by the blob store backends
+pub trait BlobBackend: Send + Sync + 'static {
+    /// Stores a blob under its hash, keeping the existing one if already stored
+    fn put(&self, hash: &str, data: &[u8]) -> io::Result<()>;
+
+    /// Size of a blob in bytes, `None` when not stored
+    fn size(&self, hash: &str) -> io::Result<Option<u64>>;
+
+    /// Reads a range of bytes of a blob, `None` when not stored
+    fn read(&self, hash: &str, range: Range<u64>) -> io::Result<Option<Vec<u8>>>;
+
+    /// Opens a range of bytes of a blob to stream it, `None` when not stored
+    fn open(&self, hash: &str, range: Range<u64>) -> io::Result<Option<BlobReader>>;
+
+    fn delete(&self, hash: &str) -> io::Result<()>;
+
+    /// Hashes of all the stored blobs
+    fn list(&self) -> io::Result<Vec<String>>;
+}
+
+/// Content-addressed blob store, identical blobs being stored once
+#[derive(Clone)]
+pub struct BlobStore {
+    backend: Arc<dyn BlobBackend>,
+}
+
+impl BlobStore {
+    pub fn new(backend: impl BlobBackend) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    /// Stores a blob, returning its hash
+    pub async fn put(&self, data: Vec<u8>) -> io::Result<String> {
+        let hash = hash(&data);
+        let backend = self.backend.clone();
+        let key = hash.clone();
+        tokio::task::spawn_blocking(move || backend.put(&key, &data))
+            .await
+            .map_err(io::Error::other)??;
+
+        Ok(hash)
+    }
+
+    pub async fn size(&self, hash: &str) -> io::Result<Option<u64>> {
+        let backend = self.backend.clone();
+        let hash = hash.to_string();
+        tokio::task::spawn_blocking(move || backend.size(&hash))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Reads a range of bytes of a blob, which must lie within the blob
+    pub async fn read(&self, hash: &str, range: Range<u64>) -> io::Result<Option<Vec<u8>>> {
+        let backend = self.backend.clone();
+        let hash = hash.to_string();
+        tokio::task::spawn_blocking(move || backend.read(&hash, range))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Opens a range of bytes of a blob to stream it, which must lie within the blob
+    pub async fn open(&self, hash: &str, range: Range<u64>) -> io::Result<Option<BlobReader>> {
+        let backend = self.backend.clone();
+        let hash = hash.to_string();
+        tokio::task::spawn_blocking(move || backend.open(&hash, range))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Reads a whole blob
+    pub async fn get(&self, hash: &str) -> io::Result<Option<Vec<u8>>> {
+        match self.size(hash).await? {
+            Some(size) => self.read(hash, 0..size).await,
+            None => Ok(None),
+        }
+    }
+
+    pub async fn delete(&self, hash: &str) -> io::Result<()> {
+        let backend = self.backend.clone();
+        let hash = hash.to_string();
+        tokio::task::spawn_blocking(move || backend.delete(&hash))
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    pub async fn list(&self) -> io::Result<Vec<String>> {
+        let backend = self.backend.clone();
+        tokio::task::spawn_blocking(move || backend.list())
+            .await
+            .map_err(io::Error::other)?
+    }
+}
+
+/// Lowercase hex SHA-256 of some bytes
+pub fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Whether a string is a hash as returned by `hash`, safe to use as a file name
+pub fn is_valid_hash(hash: &str) -> bool {
+    hash.len() == 64
+        && hash
+            .bytes()
+            .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
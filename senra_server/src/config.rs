@@ -1,17 +1,28 @@
 use serde::Deserialize;
 use std::env;
+use std::net::IpAddr;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub server: ServerConfig,
     pub database: DatabaseConfig,
     pub auth: AuthConfig,
+    pub trash: TrashConfig,
+    pub analytics: AnalyticsConfig,
+    pub ranking: RankingConfig,
+    pub blob: BlobConfig,
+    pub avatar: AvatarConfig,
+    pub embed: EmbedConfig,
+    pub rate_limit: RateLimitConfig,
+    pub log: LogConfig,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerConfig {
     pub host: String,
     pub port: u16,
+    /// Addresses of the reverse proxies whose forwarded client addresses are believed
+    pub trusted_proxies: Vec<IpAddr>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -24,16 +35,146 @@ pub struct AuthConfig {
     pub jwt_secret: String,
 }
 
+#[derive(Debug, Deserialize, Clone)]
+pub struct TrashConfig {
+    /// Days deleted notebooks stay in the trash before being purged
+    pub retention_days: i64,
+    /// Seconds between two runs of the purge job
+    pub purge_interval: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnalyticsConfig {
+    /// Seconds during which further views of a notebook by the same viewer are not counted
+    pub view_window: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RankingConfig {
+    /// Seconds between two refreshes of the notebook rankings
+    pub refresh_interval: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct BlobConfig {
+    /// Directory of the filesystem blob store, blobs are kept in memory when unset
+    pub path: Option<String>,
+    /// Seconds between the sweeps deleting the blobs no longer referenced
+    pub sweep_interval: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AvatarConfig {
+    /// Largest accepted avatar upload in bytes
+    pub max_bytes: usize,
+    /// Largest accepted width or height of an uploaded avatar in pixels
+    pub max_dimension: u32,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct EmbedConfig {
+    /// Absolute URL the server is reached at, used in oEmbed responses
+    pub public_url: String,
+    /// URL of the JavaScript module of the wasm viewer build loaded by embed pages
+    pub viewer_url: String,
+    /// Directory of the wasm viewer build served at `/viewer` when set
+    pub viewer_dir: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    /// Limits of the `/auth` endpoints
+    pub auth: RateLimitGroup,
+    /// Limits of the requests creating, editing or deleting anything
+    pub write: RateLimitGroup,
+    pub lockout: LockoutConfig,
+}
+
+/// Limits of a group of routes, each client address and signed in user having its own bucket
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitGroup {
+    pub per_ip: Option<RateLimit>,
+    pub per_user: Option<RateLimit>,
+}
+
+/// Token bucket of `requests` tokens, refilled continuously over `period` seconds
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub period: u64,
+}
+
+impl RateLimit {
+    /// Parses `<requests>/<seconds>`, `off` disabling the limit
+    fn from_env(name: &str, default: Option<RateLimit>) -> Option<RateLimit> {
+        match env::var(name) {
+            Ok(value) if value == "off" => None,
+            Ok(value) => value
+                .split_once('/')
+                .and_then(|(requests, period)| {
+                    Some(RateLimit {
+                        requests: requests.trim().parse().ok()?,
+                        period: period.trim().parse().ok()?,
+                    })
+                })
+                .filter(|limit| limit.requests > 0 && limit.period > 0)
+                .or(default),
+            Err(_) => default,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LockoutConfig {
+    /// Failed logins in a row after which an account is locked
+    pub threshold: u32,
+    /// Seconds of the first lockout, doubled by every further failed login
+    pub base_duration: u64,
+    /// Longest lockout in seconds, failures older than this are forgotten
+    pub max_duration: u64,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LogConfig {
+    /// Format of the log lines written to stdout
+    pub format: LogFormat,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per line, fields and spans included, for log collectors
+    Json,
+}
+
 impl Default for Config {
     fn default() -> Self {
+        let server = ServerConfig {
+            host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
+            port: env::var("PORT")
+                .unwrap_or_else(|_| "3000".to_string())
+                .parse()
+                .unwrap_or(3000),
+            trusted_proxies: env::var("TRUSTED_PROXIES")
+                .map(|proxies| {
+                    proxies
+                        .split(',')
+                        .filter_map(|proxy| proxy.trim().parse().ok())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        };
+
+        let public_url = env::var("PUBLIC_URL")
+            .unwrap_or_else(|_| format!("http://{}:{}", server.host, server.port))
+            .trim_end_matches('/')
+            .to_string();
+
         Self {
-            server: ServerConfig {
-                host: env::var("HOST").unwrap_or_else(|_| "127.0.0.1".to_string()),
-                port: env::var("PORT")
-                    .unwrap_or_else(|_| "3000".to_string())
-                    .parse()
-                    .unwrap_or(3000),
-            },
+            server,
             database: DatabaseConfig {
                 url: env::var("DATABASE_URL")
                     .unwrap_or("sqlite:file:shaderlab?mode=memory&cache=shared".to_string()),
@@ -42,6 +183,99 @@ impl Default for Config {
                 jwt_secret: env::var("JWT_SECRET")
                     .unwrap_or("===SHADERLAB===SECRET===".to_string()),
             },
+            trash: TrashConfig {
+                retention_days: env::var("TRASH_RETENTION_DAYS")
+                    .ok()
+                    .and_then(|days| days.parse().ok())
+                    .unwrap_or(30),
+                purge_interval: env::var("TRASH_PURGE_INTERVAL")
+                    .ok()
+                    .and_then(|secs| secs.parse().ok())
+                    .unwrap_or(3600),
+            },
+            analytics: AnalyticsConfig {
+                view_window: env::var("VIEW_DEDUP_WINDOW")
+                    .ok()
+                    .and_then(|secs| secs.parse().ok())
+                    .unwrap_or(1800),
+            },
+            ranking: RankingConfig {
+                refresh_interval: env::var("RANKING_REFRESH_INTERVAL")
+                    .ok()
+                    .and_then(|secs| secs.parse().ok())
+                    .unwrap_or(300),
+            },
+            blob: BlobConfig {
+                path: env::var("BLOB_STORE_PATH").ok(),
+                sweep_interval: env::var("BLOB_SWEEP_INTERVAL")
+                    .ok()
+                    .and_then(|secs| secs.parse().ok())
+                    .unwrap_or(3600),
+            },
+            avatar: AvatarConfig {
+                max_bytes: env::var("AVATAR_MAX_BYTES")
+                    .ok()
+                    .and_then(|bytes| bytes.parse().ok())
+                    .unwrap_or(1024 * 1024),
+                max_dimension: env::var("AVATAR_MAX_DIMENSION")
+                    .ok()
+                    .and_then(|pixels| pixels.parse().ok())
+                    .unwrap_or(4096),
+            },
+            embed: EmbedConfig {
+                public_url,
+                viewer_url: env::var("EMBED_VIEWER_URL")
+                    .unwrap_or_else(|_| "/viewer/senra_app.js".to_string()),
+                viewer_dir: env::var("EMBED_VIEWER_DIR").ok(),
+            },
+            rate_limit: RateLimitConfig {
+                auth: RateLimitGroup {
+                    per_ip: RateLimit::from_env(
+                        "RATE_LIMIT_AUTH_IP",
+                        Some(RateLimit {
+                            requests: 10,
+                            period: 60,
+                        }),
+                    ),
+                    per_user: RateLimit::from_env("RATE_LIMIT_AUTH_USER", None),
+                },
+                write: RateLimitGroup {
+                    per_ip: RateLimit::from_env(
+                        "RATE_LIMIT_WRITE_IP",
+                        Some(RateLimit {
+                            requests: 300,
+                            period: 60,
+                        }),
+                    ),
+                    per_user: RateLimit::from_env(
+                        "RATE_LIMIT_WRITE_USER",
+                        Some(RateLimit {
+                            requests: 120,
+                            period: 60,
+                        }),
+                    ),
+                },
+                lockout: LockoutConfig {
+                    threshold: env::var("LOGIN_LOCKOUT_THRESHOLD")
+                        .ok()
+                        .and_then(|count| count.parse().ok())
+                        .unwrap_or(5),
+                    base_duration: env::var("LOGIN_LOCKOUT_DURATION")
+                        .ok()
+                        .and_then(|secs| secs.parse().ok())
+                        .unwrap_or(30),
+                    max_duration: env::var("LOGIN_LOCKOUT_MAX_DURATION")
+                        .ok()
+                        .and_then(|secs| secs.parse().ok())
+                        .unwrap_or(900),
+                },
+            },
+            log: LogConfig {
+                format: match env::var("LOG_FORMAT").as_deref() {
+                    Ok("json") => LogFormat::Json,
+                    _ => LogFormat::Text,
+                },
+            },
         }
     }
 }
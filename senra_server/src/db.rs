@@ -1,8 +1,11 @@
 use sqlx::SqlitePool;
+use sqlx::migrate::Migrator;
 
 use crate::config::Config;
 use crate::errors::{AppError, Result};
 
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
+
 #[derive(Clone)]
 pub struct Database {
     pool: SqlitePool,
@@ -22,10 +25,26 @@ impl Database {
     }
 
     pub async fn run_migrations(&self) -> Result<()> {
-        sqlx::migrate!("./migrations")
-            .run(self.pool())
-            .await
-            .unwrap();
+        MIGRATOR.run(self.pool()).await.unwrap();
+        Ok(())
+    }
+
+    /// Checks that a connection can be acquired and used
+    pub async fn ping(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(self.pool()).await?;
         Ok(())
     }
+
+    /// Number of migrations of the server not applied to the database yet
+    pub async fn pending_migrations(&self) -> Result<usize> {
+        let applied: Vec<i64> =
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success = 1")
+                .fetch_all(self.pool())
+                .await?;
+
+        Ok(MIGRATOR
+            .iter()
+            .filter(|migration| !applied.contains(&migration.version))
+            .count())
+    }
 }
@@ -19,6 +19,21 @@ pub enum AuthError {
 
     #[error("Token expired")]
     TokenExpired,
+
+    #[error("Invalid or expired two-factor challenge")]
+    InvalidChallenge,
+
+    #[error("Invalid two-factor code")]
+    InvalidTwoFactorCode,
+
+    #[error("Two-factor authentication is not enabled")]
+    TwoFactorNotEnabled,
+
+    #[error("Two-factor authentication is already enabled")]
+    TwoFactorAlreadyEnabled,
+
+    #[error("Two-factor authentication was not set up")]
+    TwoFactorNotSetUp,
 }
 
 impl ErrorResponse for AuthError {
@@ -29,6 +44,11 @@ impl ErrorResponse for AuthError {
             AuthError::InvalidPassword => StatusCode::BAD_REQUEST,
             AuthError::InvalidToken => StatusCode::UNAUTHORIZED,
             AuthError::TokenExpired => StatusCode::UNAUTHORIZED,
+            AuthError::InvalidChallenge => StatusCode::UNAUTHORIZED,
+            AuthError::InvalidTwoFactorCode => StatusCode::UNAUTHORIZED,
+            AuthError::TwoFactorNotEnabled => StatusCode::BAD_REQUEST,
+            AuthError::TwoFactorAlreadyEnabled => StatusCode::CONFLICT,
+            AuthError::TwoFactorNotSetUp => StatusCode::BAD_REQUEST,
         }
     }
 
@@ -0,0 +1,46 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use super::ErrorResponse;
+
+#[derive(Debug, Error)]
+pub enum CollectionError {
+    #[error("Collection not found")]
+    NotFound,
+
+    #[error("Permission denied")]
+    PermissionDenied,
+
+    #[error("No changes provided")]
+    NoChanges,
+
+    #[error("Invalid title")]
+    InvalidTitle,
+
+    #[error("Invalid visibility, expected public or private")]
+    InvalidVisibility,
+
+    #[error("Notebook {0} not found")]
+    NotebookNotFound(i64),
+
+    #[error("Notebook {0} is already in the collection")]
+    DuplicateNotebook(i64),
+}
+
+impl ErrorResponse for CollectionError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            CollectionError::NotFound => StatusCode::NOT_FOUND,
+            CollectionError::PermissionDenied => StatusCode::FORBIDDEN,
+            CollectionError::NoChanges => StatusCode::BAD_REQUEST,
+            CollectionError::InvalidTitle => StatusCode::BAD_REQUEST,
+            CollectionError::InvalidVisibility => StatusCode::BAD_REQUEST,
+            CollectionError::NotebookNotFound(_) => StatusCode::NOT_FOUND,
+            CollectionError::DuplicateNotebook(_) => StatusCode::CONFLICT,
+        }
+    }
+
+    fn error_message(&self) -> String {
+        self.to_string()
+    }
+}
@@ -0,0 +1,30 @@
+use axum::http::StatusCode;
+use thiserror::Error;
+
+use super::ErrorResponse;
+
+#[derive(Debug, Error)]
+pub enum EmbedError {
+    #[error("Notebook has no render cell to embed")]
+    NoRenderCell,
+
+    #[error("URL is not a notebook of this server")]
+    UnsupportedUrl,
+
+    #[error("Only the json format is supported")]
+    UnsupportedFormat,
+}
+
+impl ErrorResponse for EmbedError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            EmbedError::NoRenderCell => StatusCode::NOT_FOUND,
+            EmbedError::UnsupportedUrl => StatusCode::NOT_FOUND,
+            EmbedError::UnsupportedFormat => StatusCode::NOT_IMPLEMENTED,
+        }
+    }
+
+    fn error_message(&self) -> String {
+        self.to_string()
+    }
+}
@@ -1,23 +1,35 @@
 mod auth;
+mod collection;
+mod embed;
 mod notebook;
+mod rate_limit;
 mod shader;
 mod user;
 
 pub use auth::AuthError;
+pub use collection::CollectionError;
+pub use embed::EmbedError;
 pub use notebook::NotebookError;
+pub use rate_limit::RateLimitError;
 pub use shader::ShaderError;
 pub use user::UserError;
 
 use axum::Json;
-use axum::http::StatusCode;
+use axum::http::{HeaderValue, StatusCode, header};
 use axum::response::{IntoResponse, Response};
-use serde_json::json;
+use serde_json::{Value, json};
 use thiserror::Error;
 use time::OffsetDateTime;
 
+use crate::middleware::RequestId;
+
 pub trait ErrorResponse: std::fmt::Display {
     fn status_code(&self) -> StatusCode;
     fn error_message(&self) -> String;
+
+    fn error_details(&self) -> Option<Value> {
+        None
+    }
 }
 
 #[derive(Debug, Error)]
@@ -45,6 +57,18 @@ pub enum AppError {
 
     #[error("Shader error: {0}")]
     ShaderError(#[from] ShaderError),
+
+    #[error("Collection error: {0}")]
+    CollectionError(#[from] CollectionError),
+
+    #[error("Embed error: {0}")]
+    EmbedError(#[from] EmbedError),
+
+    #[error("Rate limit error: {0}")]
+    RateLimitError(#[from] RateLimitError),
+
+    #[error("Blob store error: {0}")]
+    BlobError(#[from] std::io::Error),
 }
 
 impl ErrorResponse for AppError {
@@ -58,6 +82,10 @@ impl ErrorResponse for AppError {
             AppError::InternalError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::NotebookError(e) => e.status_code(),
             AppError::ShaderError(e) => e.status_code(),
+            AppError::CollectionError(e) => e.status_code(),
+            AppError::EmbedError(e) => e.status_code(),
+            AppError::RateLimitError(e) => e.status_code(),
+            AppError::BlobError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
 
@@ -71,18 +99,42 @@ impl ErrorResponse for AppError {
             AppError::InternalError(msg) => msg.clone(),
             AppError::NotebookError(e) => e.error_message(),
             AppError::ShaderError(e) => e.error_message(),
+            AppError::CollectionError(e) => e.error_message(),
+            AppError::EmbedError(e) => e.error_message(),
+            AppError::RateLimitError(e) => e.error_message(),
+            AppError::BlobError(e) => e.to_string(),
+        }
+    }
+
+    fn error_details(&self) -> Option<Value> {
+        match self {
+            AppError::NotebookError(e) => e.error_details(),
+            AppError::RateLimitError(e) => e.error_details(),
+            _ => None,
         }
     }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let body = json!({
+        let mut body = json!({
             "error": self.error_message(),
             "timestamp": OffsetDateTime::now_utc().to_string(),
         });
+        if let Some(details) = self.error_details() {
+            body["details"] = details;
+        }
+        if let Some(request_id) = RequestId::current() {
+            body["request_id"] = request_id.into();
+        }
 
-        (self.status_code(), Json(body)).into_response()
+        let mut response = (self.status_code(), Json(body)).into_response();
+        if let AppError::RateLimitError(e) = &self {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(e.retry_after()));
+        }
+        response
     }
 }
 
@@ -1,4 +1,6 @@
 use axum::http::StatusCode;
+use senra_api::{FieldError, ShaderCompileError, ShadertoyError};
+use serde_json::{Value, json};
 use thiserror::Error;
 
 use super::ErrorResponse;
@@ -13,6 +15,30 @@ pub enum NotebookError {
 
     #[error("No changes provided")]
     NoChanges,
+
+    #[error("Invalid notebook content: {0}")]
+    InvalidContent(String),
+
+    #[error("Notebook content does not match the schema")]
+    SchemaViolation(Vec<FieldError>),
+
+    #[error("Invalid notebook archive: {0}")]
+    InvalidArchive(String),
+
+    #[error("{0}")]
+    ShadertoyImport(ShadertoyError),
+
+    #[error("Notebook content has invalid references")]
+    InvalidReferences(Vec<FieldError>),
+
+    #[error("{0}")]
+    InvalidExportTarget(ShaderCompileError),
+
+    #[error("Comment not found")]
+    CommentNotFound,
+
+    #[error("Invalid comment: {0}")]
+    InvalidComment(String),
 }
 
 impl ErrorResponse for NotebookError {
@@ -21,10 +47,30 @@ impl ErrorResponse for NotebookError {
             NotebookError::NotFound => StatusCode::NOT_FOUND,
             NotebookError::PermissionDenied => StatusCode::FORBIDDEN,
             NotebookError::NoChanges => StatusCode::BAD_REQUEST,
+            NotebookError::InvalidContent(_) => StatusCode::BAD_REQUEST,
+            NotebookError::SchemaViolation(_) => StatusCode::BAD_REQUEST,
+            NotebookError::InvalidArchive(_) => StatusCode::BAD_REQUEST,
+            NotebookError::ShadertoyImport(_) => StatusCode::BAD_REQUEST,
+            NotebookError::InvalidReferences(_) => StatusCode::BAD_REQUEST,
+            NotebookError::InvalidExportTarget(_) => StatusCode::BAD_REQUEST,
+            NotebookError::CommentNotFound => StatusCode::NOT_FOUND,
+            NotebookError::InvalidComment(_) => StatusCode::BAD_REQUEST,
         }
     }
 
     fn error_message(&self) -> String {
         self.to_string()
     }
+
+    fn error_details(&self) -> Option<Value> {
+        match self {
+            NotebookError::SchemaViolation(errors) | NotebookError::InvalidReferences(errors) => {
+                Some(json!(errors))
+            }
+            NotebookError::ShadertoyImport(ShadertoyError::Untranslatable(errors)) => {
+                Some(json!(errors))
+            }
+            _ => None,
+        }
+    }
 }
@@ -0,0 +1,39 @@
+use axum::http::StatusCode;
+use serde_json::{Value, json};
+use thiserror::Error;
+
+use super::ErrorResponse;
+
+#[derive(Debug, Error)]
+pub enum RateLimitError {
+    #[error("Too many requests, retry in {0} seconds")]
+    TooManyRequests(u64),
+
+    #[error("Too many failed logins, account locked for {0} seconds")]
+    AccountLocked(u64),
+}
+
+impl RateLimitError {
+    /// Seconds to wait before retrying
+    pub fn retry_after(&self) -> u64 {
+        match self {
+            RateLimitError::TooManyRequests(seconds) | RateLimitError::AccountLocked(seconds) => {
+                *seconds
+            }
+        }
+    }
+}
+
+impl ErrorResponse for RateLimitError {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::TOO_MANY_REQUESTS
+    }
+
+    fn error_message(&self) -> String {
+        self.to_string()
+    }
+
+    fn error_details(&self) -> Option<Value> {
+        Some(json!({ "retry_after": self.retry_after() }))
+    }
+}
@@ -1,4 +1,5 @@
 use axum::http::StatusCode;
+use senra_api::PackageError;
 use thiserror::Error;
 
 use super::ErrorResponse;
@@ -19,6 +20,15 @@ pub enum ShaderError {
 
     #[error("No changes provided")]
     NoChanges,
+
+    #[error("Package not found")]
+    PackageNotFound,
+
+    #[error("Package version {0} is already published")]
+    VersionExists(String),
+
+    #[error("{0}")]
+    InvalidPackage(PackageError),
 }
 
 impl ErrorResponse for ShaderError {
@@ -29,6 +39,9 @@ impl ErrorResponse for ShaderError {
             ShaderError::CompilationError(_) => StatusCode::BAD_REQUEST,
             ShaderError::InvalidData(_) => StatusCode::BAD_REQUEST,
             ShaderError::NoChanges => StatusCode::BAD_REQUEST,
+            ShaderError::PackageNotFound => StatusCode::NOT_FOUND,
+            ShaderError::VersionExists(_) => StatusCode::CONFLICT,
+            ShaderError::InvalidPackage(_) => StatusCode::BAD_REQUEST,
         }
     }
 
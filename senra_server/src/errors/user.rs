@@ -22,6 +22,15 @@ pub enum UserError {
 
     #[error("No changes provided")]
     NoChanges,
+
+    #[error("Users cannot follow themselves")]
+    SelfFollow,
+
+    #[error("Invalid avatar: {0}")]
+    InvalidAvatar(String),
+
+    #[error("Avatar larger than {0} bytes")]
+    AvatarTooLarge(usize),
 }
 
 impl ErrorResponse for UserError {
@@ -33,6 +42,9 @@ impl ErrorResponse for UserError {
             UserError::UserNotFound => StatusCode::NOT_FOUND,
             UserError::UserExists => StatusCode::CONFLICT,
             UserError::NoChanges => StatusCode::BAD_REQUEST,
+            UserError::SelfFollow => StatusCode::BAD_REQUEST,
+            UserError::InvalidAvatar(_) => StatusCode::BAD_REQUEST,
+            UserError::AvatarTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
         }
     }
 
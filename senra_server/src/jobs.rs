@@ -0,0 +1,74 @@
+use std::time::Duration;
+
+use tracing::{error, info};
+
+use crate::state::AppState;
+
+/// Spawns the background jobs of the server on the current runtime
+pub fn spawn_jobs(state: &AppState) {
+    tokio::spawn(purge_trash(state.clone()));
+    tokio::spawn(prune_view_log(state.clone()));
+    tokio::spawn(refresh_rankings(state.clone()));
+    tokio::spawn(sweep_blobs(state.clone()));
+}
+
+/// Periodically purges the notebooks kept in the trash longer than the retention period
+async fn purge_trash(state: AppState) {
+    let trash = &state.config.trash;
+    let mut interval = tokio::time::interval(Duration::from_secs(trash.purge_interval.max(1)));
+
+    loop {
+        interval.tick().await;
+        match state
+            .services
+            .notebook
+            .purge_expired(trash.retention_days)
+            .await
+        {
+            Ok(0) => {}
+            Ok(count) => info!("Purged {} notebooks from the trash", count),
+            Err(err) => error!("Failed to purge the trash: {}", err),
+        }
+    }
+}
+
+/// Periodically forgets the views older than the view deduplication window
+async fn prune_view_log(state: AppState) {
+    let view_window = state.config.analytics.view_window;
+    let mut interval = tokio::time::interval(Duration::from_secs(view_window.max(1)));
+
+    loop {
+        interval.tick().await;
+        if let Err(err) = state.services.analytics.prune_view_log().await {
+            error!("Failed to prune the view log: {}", err);
+        }
+    }
+}
+
+/// Periodically materializes the notebook rankings the feeds are listed from
+async fn refresh_rankings(state: AppState) {
+    let refresh_interval = state.config.ranking.refresh_interval;
+    let mut interval = tokio::time::interval(Duration::from_secs(refresh_interval.max(1)));
+
+    loop {
+        interval.tick().await;
+        if let Err(err) = state.services.notebook.refresh_rankings().await {
+            error!("Failed to refresh the notebook rankings: {}", err);
+        }
+    }
+}
+
+/// Periodically deletes the blobs left behind by purged notebooks and replaced resources
+async fn sweep_blobs(state: AppState) {
+    let sweep_interval = state.config.blob.sweep_interval;
+    let mut interval = tokio::time::interval(Duration::from_secs(sweep_interval.max(1)));
+
+    loop {
+        interval.tick().await;
+        match state.services.blob.sweep_orphans().await {
+            Ok(0) => {}
+            Ok(count) => info!("Deleted {} unreferenced blobs", count),
+            Err(err) => error!("Failed to sweep the blob store: {}", err),
+        }
+    }
+}
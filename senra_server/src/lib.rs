@@ -1,15 +1,22 @@
+mod avatar;
+mod blob;
 mod config;
 mod db;
 mod errors;
+mod jobs;
+mod metrics;
 mod middleware;
 mod models;
+mod rate_limit;
 mod routes;
 mod services;
 mod state;
 
-pub use config::Config;
+pub use config::{Config, LogFormat, RateLimit};
 pub use db::Database;
-pub use errors::Result;
+pub use errors::{AppError, Result};
+pub use jobs::spawn_jobs;
+pub use middleware::{Json, negotiate_encoding};
 pub use models::*;
 pub use routes::create_router;
 pub use state::AppState;
@@ -1,30 +1,81 @@
+use std::net::SocketAddr;
+
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 
-use senra_server::{AppState, Config, Database, Result, create_router};
+use senra_server::{
+    AppError, AppState, Config, Database, LogFormat, Result, create_router, spawn_jobs,
+};
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let config = Config::default();
+
+    let format = config.log.format;
     tracing_subscriber::registry()
         .with(
             tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
                 format!("{}=debug,tower_http=debug", env!("CARGO_CRATE_NAME")).into()
             }),
         )
-        .with(tracing_subscriber::fmt::layer())
+        .with((format == LogFormat::Text).then(tracing_subscriber::fmt::layer))
+        .with((format == LogFormat::Json).then(|| tracing_subscriber::fmt::layer().json()))
         .init();
 
-    let config = Config::default();
+    let db = Database::new(&config).await?;
+    let state = AppState::new(config, db);
+    state.db.run_migrations().await?;
+    state.services.blob.migrate_inline_blobs().await?;
+
+    if let Some(command) = std::env::args().nth(1) {
+        return run_command(&state, &command).await;
+    }
 
-    let addr = format!("{}:{}", config.server.host, config.server.port);
+    spawn_jobs(&state);
+
+    let addr = format!("{}:{}", state.config.server.host, state.config.server.port);
     let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
     tracing::debug!("Server listening on {}", listener.local_addr().unwrap());
 
-    let db = Database::new(&config).await?;
-    let state = AppState::new(config, db);
-    state.db.run_migrations().await?;
+    axum::serve(
+        listener,
+        create_router(state).into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
+
+    Ok(())
+}
 
-    axum::serve(listener, create_router(state)).await.unwrap();
+async fn run_command(state: &AppState, command: &str) -> Result<()> {
+    match command {
+        "migrate-content" => {
+            let (notebooks, versions) = state.services.notebook.migrate_stored_content().await?;
+            tracing::info!(
+                "Migrated content of {} notebooks and {} notebook versions",
+                notebooks,
+                versions
+            );
+        }
+        "purge-trash" => {
+            let count = state
+                .services
+                .notebook
+                .purge_expired(state.config.trash.retention_days)
+                .await?;
+            tracing::info!("Purged {} notebooks from the trash", count);
+        }
+        "refresh-rankings" => {
+            let count = state.services.notebook.refresh_rankings().await?;
+            tracing::info!("Ranked {} notebooks", count);
+        }
+        _ => {
+            return Err(AppError::InternalError(format!(
+                "Unknown command: {}",
+                command
+            )));
+        }
+    }
 
     Ok(())
 }
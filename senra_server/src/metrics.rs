@@ -0,0 +1,93 @@
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGauge, Opts, Registry, TextEncoder,
+};
+
+/// Prometheus metrics of the server, each server owning its own registry
+#[derive(Clone)]
+pub struct Metrics {
+    registry: Registry,
+    /// Requests served, labelled by method, matched route and status
+    pub http_requests: IntCounterVec,
+    /// Seconds spent serving requests, labelled by method and matched route
+    pub http_request_duration: HistogramVec,
+    /// Connections currently open in the database pool
+    pub db_connections: IntGauge,
+    /// Connections of the database pool currently idle
+    pub db_idle_connections: IntGauge,
+    /// Notebooks outside of the trash, refreshed on scrape
+    pub notebooks: IntGauge,
+    /// Registered users, refreshed on scrape
+    pub users: IntGauge,
+    /// Websocket sessions currently open
+    pub websocket_sessions: IntGauge,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("senra".to_string()), None)
+            .expect("Metrics prefix should be valid");
+
+        let http_requests = IntCounterVec::new(
+            Opts::new("http_requests_total", "HTTP requests served"),
+            &["method", "route", "status"],
+        )
+        .expect("Metric should be valid");
+        let http_request_duration = HistogramVec::new(
+            HistogramOpts::new(
+                "http_request_duration_seconds",
+                "Time spent serving HTTP requests",
+            ),
+            &["method", "route"],
+        )
+        .expect("Metric should be valid");
+        let db_connections = gauge("db_pool_connections", "Open database connections");
+        let db_idle_connections = gauge("db_pool_idle_connections", "Idle database connections");
+        let notebooks = gauge("notebooks", "Notebooks outside of the trash");
+        let users = gauge("users", "Registered users");
+        let websocket_sessions = gauge("websocket_sessions", "Open websocket sessions");
+
+        for collector in [
+            Box::new(http_requests.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(http_request_duration.clone()),
+            Box::new(db_connections.clone()),
+            Box::new(db_idle_connections.clone()),
+            Box::new(notebooks.clone()),
+            Box::new(users.clone()),
+            Box::new(websocket_sessions.clone()),
+        ] {
+            registry
+                .register(collector)
+                .expect("Metrics should be registered once");
+        }
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            db_connections,
+            db_idle_connections,
+            notebooks,
+            users,
+            websocket_sessions,
+        }
+    }
+
+    /// Metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut buffer = Vec::new();
+        TextEncoder::new()
+            .encode(&self.registry.gather(), &mut buffer)
+            .expect("Metrics should be encodable");
+        String::from_utf8(buffer).expect("Metrics should be valid UTF-8")
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn gauge(name: &str, help: &str) -> IntGauge {
+    IntGauge::new(name, help).expect("Metric should be valid")
+}
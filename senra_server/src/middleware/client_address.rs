@@ -0,0 +1,50 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::extract::ConnectInfo;
+use axum::http::{Extensions, HeaderMap};
+
+/// Address of the client of a request. It is the peer of the connection, unless the peer is a
+/// trusted reverse proxy, in which case it's the rightmost forwarded address not of a trusted
+/// proxy. Forwarded headers of other peers are ignored, as anyone can set them
+pub(super) fn client_address(
+    headers: &HeaderMap,
+    extensions: &Extensions,
+    trusted_proxies: &[IpAddr],
+) -> Option<IpAddr> {
+    let ConnectInfo(peer) = extensions.get::<ConnectInfo<SocketAddr>>()?;
+    let peer = peer.ip();
+    if !trusted_proxies.contains(&peer) {
+        return Some(peer);
+    }
+
+    Some(forwarded_address(headers, trusted_proxies).unwrap_or(peer))
+}
+
+/// Rightmost address forwarded by the trusted proxies that isn't one of them, each proxy
+/// appending the address it got the request from to `X-Forwarded-For`
+fn forwarded_address(headers: &HeaderMap, trusted_proxies: &[IpAddr]) -> Option<IpAddr> {
+    let forwarded_for: Vec<&str> = headers
+        .get_all("X-Forwarded-For")
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(str::trim)
+        .collect();
+
+    if forwarded_for.is_empty() {
+        return headers
+            .get("X-Real-IP")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok());
+    }
+
+    for address in forwarded_for.into_iter().rev() {
+        // Anything left of an address that doesn't parse can't be told apart from forgeries
+        let address: IpAddr = address.parse().ok()?;
+        if !trusted_proxies.contains(&address) {
+            return Some(address);
+        }
+    }
+
+    None
+}
@@ -0,0 +1,195 @@
+use std::sync::Arc;
+
+use axum::body::{Body, to_bytes};
+use axum::extract::{FromRequest, Request};
+use axum::http::{HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use senra_api::{ApiError, Encoding};
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+
+use crate::errors::{AppError, Result};
+
+/// Largest MessagePack request body accepted for transcoding
+const MAX_BODY_SIZE: usize = 16 * 1024 * 1024;
+
+/// JSON request body, and response body serialized in the encoding negotiated with the client
+///
+/// Responses carry the value itself, which [`negotiate_encoding`] serializes once the encoding
+/// is known, so byte payloads are native byte strings in MessagePack.
+pub struct Json<T>(pub T);
+
+impl<T, S> FromRequest<S> for Json<T>
+where
+    axum::Json<T>: FromRequest<S>,
+    S: Send + Sync,
+{
+    type Rejection = <axum::Json<T> as FromRequest<S>>::Rejection;
+
+    async fn from_request(
+        request: Request,
+        state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        let axum::Json(value) = axum::Json::<T>::from_request(request, state).await?;
+        Ok(Json(value))
+    }
+}
+
+impl<T> IntoResponse for Json<T>
+where
+    T: Serialize + Send + Sync + 'static,
+{
+    fn into_response(self) -> Response {
+        let value = Arc::new(self.0);
+        let mut response = Response::new(Body::empty());
+        response.headers_mut().insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static(Encoding::Json.mime_type()),
+        );
+        response
+            .extensions_mut()
+            .insert(EncodeBody(Arc::new(move |encoding| {
+                encoding.encode(&*value)
+            })));
+        response
+    }
+}
+
+/// Serializes the value of a [`Json`] response in an encoding
+#[derive(Clone)]
+struct EncodeBody(Arc<dyn Fn(Encoding) -> std::result::Result<Vec<u8>, ApiError> + Send + Sync>);
+
+/// Lets clients send and receive MessagePack instead of JSON per `Content-Type` and `Accept`.
+/// Handlers keep working with JSON, MessagePack byte strings arriving as base64 strings
+pub async fn negotiate_encoding(request: Request, next: Next) -> Response {
+    let accept = request
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .map(Encoding::from_accept)
+        .unwrap_or_default();
+    let content_type = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::from_content_type);
+
+    let request = match content_type {
+        Some(Encoding::MessagePack) => match decode_request(request).await {
+            Ok(request) => request,
+            Err(err) => return encode_response(err.into_response(), accept).await,
+        },
+        _ => request,
+    };
+
+    encode_response(next.run(request).await, accept).await
+}
+
+async fn decode_request(request: Request) -> Result<Request> {
+    let (mut parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|err| AppError::ValidationError(err.to_string()))?;
+
+    let value = rmpv::decode::read_value(&mut bytes.as_ref())
+        .map_err(|err| AppError::ValidationError(format!("Invalid MessagePack body: {}", err)))?;
+    let json = serde_json::to_vec(&to_json(value)?)
+        .map_err(|err| AppError::InternalError(err.to_string()))?;
+
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static(Encoding::Json.mime_type()),
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Ok(Request::from_parts(parts, Body::from(json)))
+}
+
+async fn encode_response(response: Response, encoding: Encoding) -> Response {
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(Encoding::from_content_type)
+        == Some(Encoding::Json);
+    if !is_json {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    parts
+        .headers
+        .insert(header::VARY, HeaderValue::from_static("accept"));
+
+    // Values of `Json` responses are serialized directly, other JSON bodies are transcoded
+    let encoded = match parts.extensions.remove::<EncodeBody>() {
+        Some(EncodeBody(encode)) => encode(encoding).map_err(|err| err.to_string()),
+        None if encoding == Encoding::Json => return Response::from_parts(parts, body),
+        None => match to_bytes(body, usize::MAX).await {
+            Ok(bytes) => serde_json::from_slice::<Value>(&bytes)
+                .map_err(|err| err.to_string())
+                .and_then(|value| encoding.encode(&value).map_err(|err| err.to_string())),
+            Err(err) => Err(err.to_string()),
+        },
+    };
+    match encoded {
+        Ok(bytes) => {
+            parts.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(encoding.mime_type()),
+            );
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(bytes))
+        }
+        Err(err) => AppError::InternalError(err).into_response(),
+    }
+}
+
+/// Converts a MessagePack value to JSON, byte strings becoming base64 strings
+fn to_json(value: rmpv::Value) -> Result<Value> {
+    let invalid =
+        |message: &str| AppError::ValidationError(format!("Invalid MessagePack body: {}", message));
+
+    Ok(match value {
+        rmpv::Value::Nil => Value::Null,
+        rmpv::Value::Boolean(value) => Value::Bool(value),
+        rmpv::Value::Integer(value) => match (value.as_i64(), value.as_u64()) {
+            (Some(value), _) => Value::from(value),
+            (None, Some(value)) => Value::from(value),
+            (None, None) => return Err(invalid("integer out of range")),
+        },
+        rmpv::Value::F32(value) => Number::from_f64(value as f64)
+            .map(Value::Number)
+            .ok_or_else(|| invalid("non-finite float"))?,
+        rmpv::Value::F64(value) => Number::from_f64(value)
+            .map(Value::Number)
+            .ok_or_else(|| invalid("non-finite float"))?,
+        rmpv::Value::String(value) => Value::String(
+            value
+                .into_str()
+                .ok_or_else(|| invalid("string is not UTF-8"))?,
+        ),
+        rmpv::Value::Binary(bytes) => Value::String(STANDARD.encode(bytes)),
+        rmpv::Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(to_json)
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        rmpv::Value::Map(entries) => Value::Object(
+            entries
+                .into_iter()
+                .map(|(key, value)| match key {
+                    rmpv::Value::String(key) => Ok((
+                        key.into_str().ok_or_else(|| invalid("key is not UTF-8"))?,
+                        to_json(value)?,
+                    )),
+                    _ => Err(invalid("map key is not a string")),
+                })
+                .collect::<Result<Map<_, _>>>()?,
+        ),
+        rmpv::Value::Ext(..) => return Err(invalid("extension types are not supported")),
+    })
+}
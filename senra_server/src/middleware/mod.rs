@@ -1,3 +1,14 @@
 mod auth;
+mod client_address;
+mod encoding;
+mod metrics;
+mod rate_limit;
+mod request_id;
+mod visitor;
 
 pub use auth::AuthUser;
+pub use encoding::{Json, negotiate_encoding};
+pub use metrics::track_requests;
+pub use rate_limit::rate_limit;
+pub use request_id::{RequestId, propagate_request_id};
+pub use visitor::Visitor;
@@ -0,0 +1,68 @@
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use senra_api::{RATE_LIMIT_LIMIT_HEADER, RATE_LIMIT_REMAINING_HEADER, RATE_LIMIT_RESET_HEADER};
+
+use super::client_address::client_address;
+use crate::config::RateLimit;
+use crate::errors::{AppError, RateLimitError};
+use crate::rate_limit::{ClientKey, Quota, RouteGroup};
+use crate::state::AppState;
+
+/// Limits the requests of the auth and write route groups per client address and per signed
+/// in user, answering with the state of the most depleted bucket in the rate limit headers
+pub async fn rate_limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(group) = RouteGroup::of(request.method(), request.uri().path()) else {
+        return next.run(request).await;
+    };
+    let limits = group.limits(&state.config.rate_limit);
+
+    let address = client_address(
+        request.headers(),
+        request.extensions(),
+        &state.config.server.trusted_proxies,
+    );
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    let user_id = match (token, limits.per_user) {
+        (Some(token), Some(_)) => state.services.auth.authorize(token).await.ok(),
+        _ => None,
+    };
+
+    // Tokens are only taken when every bucket has one, so rejected requests don't count
+    let buckets: Vec<(ClientKey, RateLimit)> = [
+        (address.map(ClientKey::Ip), limits.per_ip),
+        (user_id.map(ClientKey::User), limits.per_user),
+    ]
+    .into_iter()
+    .filter_map(|(key, limit)| Some((key?, limit?)))
+    .collect();
+    let quota = match state.rate_limiter.acquire(group, &buckets) {
+        Ok(quota) => quota,
+        Err(exhausted) => {
+            let mut response =
+                AppError::from(RateLimitError::TooManyRequests(exhausted.reset)).into_response();
+            set_headers(response.headers_mut(), exhausted);
+            return response;
+        }
+    };
+
+    let mut response = next.run(request).await;
+    if let Some(quota) = quota {
+        set_headers(response.headers_mut(), quota);
+    }
+    response
+}
+
+fn set_headers(headers: &mut HeaderMap, quota: Quota) {
+    headers.insert(RATE_LIMIT_LIMIT_HEADER, HeaderValue::from(quota.limit));
+    headers.insert(
+        RATE_LIMIT_REMAINING_HEADER,
+        HeaderValue::from(quota.remaining),
+    );
+    headers.insert(RATE_LIMIT_RESET_HEADER, HeaderValue::from(quota.reset));
+}
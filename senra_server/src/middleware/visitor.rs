@@ -0,0 +1,77 @@
+use std::convert::Infallible;
+
+use axum::extract::{FromRef, FromRequestParts};
+use axum::http::header;
+use axum::http::request::Parts;
+use sha2::{Digest, Sha256};
+
+use super::client_address::client_address;
+use crate::state::AppState;
+
+/// Anonymous identity of a request, used to deduplicate and attribute notebook views
+#[derive(Debug, Clone)]
+pub struct Visitor {
+    /// Salted hash of the client address and user agent, never stored in clear
+    pub fingerprint: String,
+    /// Host of the page linking to the viewed notebook, if any
+    pub referrer: Option<String>,
+}
+
+impl<S> FromRequestParts<S> for Visitor
+where
+    S: Send + Sync,
+    AppState: FromRef<S>,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let state = AppState::from_ref(state);
+
+        let address = client_address(
+            &parts.headers,
+            &parts.extensions,
+            &state.config.server.trusted_proxies,
+        )
+        .map(|address| address.to_string());
+        let user_agent = parts
+            .headers
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok());
+
+        let mut hasher = Sha256::new();
+        hasher.update(state.config.auth.jwt_secret.as_bytes());
+        hasher.update([0]);
+        hasher.update(address.unwrap_or_default().as_bytes());
+        hasher.update([0]);
+        hasher.update(user_agent.unwrap_or_default().as_bytes());
+        let fingerprint = hasher
+            .finalize()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+
+        let referrer = parts
+            .headers
+            .get(header::REFERER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(referrer_host);
+
+        Ok(Visitor {
+            fingerprint,
+            referrer,
+        })
+    }
+}
+
+/// Host of a referrer URL, without credentials, port, path or query
+fn referrer_host(referrer: &str) -> Option<String> {
+    let (_, rest) = referrer.split_once("://")?;
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+
+    (!host.is_empty()).then(|| host.to_lowercase())
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::Date;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NotebookDailyStats {
+    pub day: Date,
+    pub view_count: i64,
+    pub like_count: i64,
+    pub comment_count: i64,
+}
+
+/// Counted views of a notebook coming from a referring host, empty for direct views
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NotebookReferrer {
+    pub referrer: String,
+    pub view_count: i64,
+}
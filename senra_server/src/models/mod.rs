@@ -1,9 +1,17 @@
+mod analytics;
+mod collection;
 mod notebook;
+mod notification;
+mod package;
 mod resource;
 mod shader;
 mod user;
 
+pub use analytics::*;
+pub use collection::*;
 pub use notebook::*;
+pub use notification::*;
+pub use package::*;
 pub use resource::*;
 pub use shader::*;
 pub use user::*;
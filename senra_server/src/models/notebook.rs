@@ -13,11 +13,14 @@ pub struct Notebook {
     pub title: String,
     pub description: Option<String>,
     pub content: Value,
-    pub preview: Option<Vec<u8>>,
+    /// Hash of the preview image in the blob store
+    pub preview_hash: Option<String>,
     pub visibility: String,
     pub version: i32,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
+    /// Set while the notebook is in its owner's trash
+    pub deleted_at: Option<OffsetDateTime>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -63,6 +66,22 @@ pub struct NotebookComment {
     pub content: String,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
+    /// Comment this one replies to
+    pub parent_id: Option<i64>,
+    /// Set when the author edited the content
+    pub edited_at: Option<OffsetDateTime>,
+    pub anchor_cell_id: Option<String>,
+    pub anchor_start_line: Option<i64>,
+    pub anchor_end_line: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateNotebookComment {
+    pub content: String,
+    pub parent_id: Option<i64>,
+    pub anchor_cell_id: Option<String>,
+    pub anchor_start_line: Option<i64>,
+    pub anchor_end_line: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -77,6 +96,13 @@ pub struct CreateNotebook {
     pub visibility: String,
 }
 
+/// Ids of the shaders and resources of an imported archive, in creation order
+#[derive(Debug)]
+pub struct ArchiveIds {
+    pub shaders: Vec<i64>,
+    pub resources: Vec<i64>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateNotebook {
     pub title: Option<String>,
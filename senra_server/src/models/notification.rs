@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+/// Notification along with the actor username and notebook title
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Notification {
+    pub id: i64,
+    pub user_id: i64,
+    pub actor_id: i64,
+    pub kind: String,
+    pub notebook_id: i64,
+    pub comment_id: Option<i64>,
+    pub read_at: Option<OffsetDateTime>,
+    pub created_at: OffsetDateTime,
+    pub actor: String,
+    pub notebook_title: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateNotification {
+    pub user_id: i64,
+    pub actor_id: i64,
+    pub kind: String,
+    pub notebook_id: i64,
+    pub comment_id: Option<i64>,
+}
@@ -0,0 +1,52 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use time::OffsetDateTime;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ShaderPackage {
+    pub id: i64,
+    pub user_id: i64,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: OffsetDateTime,
+}
+
+/// Published version of a package, along with the package name
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ShaderPackageVersion {
+    pub id: i64,
+    pub package_id: i64,
+    pub name: String,
+    pub version: String,
+    pub major: i64,
+    pub minor: i64,
+    pub patch: i64,
+    pub shader_type: String,
+    pub code: String,
+    pub shader_id: Option<i64>,
+    pub created_at: OffsetDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PublishPackage {
+    pub shader_id: i64,
+    pub name: String,
+    pub version: String,
+    pub description: Option<String>,
+}
+
+/// Notebook resolving a package to one of its versions
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PackageNotebookDependent {
+    pub notebook_id: i64,
+    pub title: String,
+    pub version: String,
+}
+
+/// Package version importing a version of another package
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PackageVersionDependent {
+    pub name: String,
+    pub version: String,
+    pub dependency_version: String,
+}
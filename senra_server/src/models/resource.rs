@@ -9,7 +9,8 @@ pub struct Resource {
     pub notebook_id: i64,
     pub name: String,
     pub resource_type: String,
-    pub data: Vec<u8>,
+    /// Hash of the data in the blob store
+    pub data_hash: String,
     pub metadata: Option<Value>,
     pub created_at: OffsetDateTime,
 }
@@ -9,11 +9,19 @@ pub struct User {
     pub email: String,
     #[serde(skip_serializing)]
     pub password: Option<String>,
-    pub avatar: Vec<u8>,
+    /// Hash of the default size avatar in the blob store
+    pub avatar_hash: Option<String>,
     pub created_at: OffsetDateTime,
     pub updated_at: OffsetDateTime,
 }
 
+/// Resized variant of a user avatar
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserAvatar {
+    pub size: i64,
+    pub hash: String,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateUser {
     pub username: String,
@@ -34,3 +42,15 @@ pub struct EditUser {
     pub password: Option<String>,
     pub avatar: Option<Vec<u8>>,
 }
+
+/// TOTP second factor of a user, pending until `enabled_at` is set
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UserTwoFactor {
+    pub user_id: i64,
+    #[serde(skip_serializing)]
+    pub secret: String,
+    pub enabled_at: Option<OffsetDateTime>,
+    /// Time step of the last accepted code
+    pub last_step: Option<i64>,
+    pub created_at: OffsetDateTime,
+}
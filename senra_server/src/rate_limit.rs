@@ -0,0 +1,214 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::http::Method;
+
+use crate::config::{LockoutConfig, RateLimit, RateLimitConfig, RateLimitGroup};
+
+/// Number of tracked buckets or accounts past which the settled ones are forgotten
+const PRUNE_THRESHOLD: usize = 10_000;
+
+/// Routes taking credentials, limited as the auth group
+const CREDENTIAL_PATHS: [&str; 3] = ["/auth/login", "/auth/login/2fa", "/auth/register"];
+
+/// Routes sharing the same limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteGroup {
+    Auth,
+    Write,
+}
+
+impl RouteGroup {
+    /// Group of a request, `None` when it isn't limited
+    ///
+    /// Only the routes taking credentials are auth routes, so that refreshing sessions from a
+    /// shared address doesn't use up the logins of that address.
+    pub fn of(method: &Method, path: &str) -> Option<Self> {
+        if CREDENTIAL_PATHS.contains(&path.trim_end_matches('/')) {
+            Some(RouteGroup::Auth)
+        } else if [Method::POST, Method::PUT, Method::PATCH, Method::DELETE].contains(method) {
+            Some(RouteGroup::Write)
+        } else {
+            None
+        }
+    }
+
+    pub fn limits(self, config: &RateLimitConfig) -> &RateLimitGroup {
+        match self {
+            RouteGroup::Auth => &config.auth,
+            RouteGroup::Write => &config.write,
+        }
+    }
+}
+
+/// Client a bucket is kept for
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ClientKey {
+    Ip(IpAddr),
+    User(i64),
+}
+
+/// State of a bucket after a request took a token from it
+#[derive(Debug, Clone, Copy)]
+pub struct Quota {
+    pub limit: u32,
+    pub remaining: u32,
+    /// Seconds until the bucket is full again
+    pub reset: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+    capacity: f64,
+    /// Tokens added per second
+    rate: f64,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        self.tokens = (self.tokens + now.duration_since(self.updated).as_secs_f64() * self.rate)
+            .min(self.capacity);
+        self.updated = now;
+    }
+}
+
+/// In-memory token buckets per route group and client
+#[derive(Clone, Default)]
+pub struct RateLimiter {
+    buckets: Arc<Mutex<HashMap<(RouteGroup, ClientKey), Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Takes a token from every bucket of a client, only when all of them have one, returning
+    /// the state of the most depleted bucket
+    ///
+    /// When a bucket is empty nothing is taken, and the state of that bucket is returned as the
+    /// error, its `reset` being the seconds until a token is available.
+    pub fn acquire(
+        &self,
+        group: RouteGroup,
+        limits: &[(ClientKey, RateLimit)],
+    ) -> Result<Option<Quota>, Quota> {
+        let now = Instant::now();
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() > PRUNE_THRESHOLD {
+            buckets.retain(|_, bucket| {
+                bucket.refill(now);
+                bucket.tokens < bucket.capacity
+            });
+        }
+
+        for (key, limit) in limits {
+            let capacity = limit.requests as f64;
+            let bucket = buckets.entry((group, key.clone())).or_insert(Bucket {
+                tokens: capacity,
+                updated: now,
+                capacity,
+                rate: capacity / limit.period as f64,
+            });
+            bucket.refill(now);
+
+            if bucket.tokens < 1.0 {
+                return Err(Quota {
+                    limit: limit.requests,
+                    remaining: 0,
+                    reset: ((1.0 - bucket.tokens) / bucket.rate).ceil() as u64,
+                });
+            }
+        }
+
+        let mut quota: Option<Quota> = None;
+        for (key, limit) in limits {
+            let bucket = buckets
+                .get_mut(&(group, key.clone()))
+                .expect("Buckets are created before tokens are taken");
+            bucket.tokens -= 1.0;
+
+            let acquired = Quota {
+                limit: limit.requests,
+                remaining: bucket.tokens.floor() as u32,
+                reset: ((bucket.capacity - bucket.tokens) / bucket.rate).ceil() as u64,
+            };
+            if quota.is_none_or(|quota| acquired.remaining < quota.remaining) {
+                quota = Some(acquired);
+            }
+        }
+        Ok(quota)
+    }
+}
+
+struct Failures {
+    count: u32,
+    last: Instant,
+    locked_until: Option<Instant>,
+}
+
+/// Failed logins per account, locking accounts for longer and longer as failures go on
+#[derive(Clone)]
+pub struct LoginLockout {
+    config: LockoutConfig,
+    failures: Arc<Mutex<HashMap<String, Failures>>>,
+}
+
+impl LoginLockout {
+    pub fn new(config: &LockoutConfig) -> Self {
+        Self {
+            config: config.clone(),
+            failures: Arc::default(),
+        }
+    }
+
+    /// Seconds the account stays locked for, if it is
+    pub fn locked_for(&self, username: &str) -> Option<u64> {
+        let now = Instant::now();
+        let failures = self.failures.lock().unwrap();
+        let locked_until = failures.get(username)?.locked_until?;
+
+        (locked_until > now).then(|| (locked_until - now).as_secs_f64().ceil() as u64)
+    }
+
+    /// Records a failed login, returning the seconds the account is now locked for
+    pub fn record_failure(&self, username: &str) -> Option<u64> {
+        let now = Instant::now();
+        let max_duration = Duration::from_secs(self.config.max_duration);
+
+        let mut failures = self.failures.lock().unwrap();
+        if failures.len() > PRUNE_THRESHOLD {
+            failures.retain(|_, failures| now.duration_since(failures.last) < max_duration);
+        }
+
+        let entry = failures.entry(username.to_string()).or_insert(Failures {
+            count: 0,
+            last: now,
+            locked_until: None,
+        });
+        if now.duration_since(entry.last) >= max_duration {
+            entry.count = 0;
+        }
+        entry.count += 1;
+        entry.last = now;
+
+        let excess = entry.count.checked_sub(self.config.threshold.max(1))?;
+        let duration = self
+            .config
+            .base_duration
+            .saturating_mul(1 << excess.min(32) as u64)
+            .min(self.config.max_duration);
+        entry.locked_until = Some(now + Duration::from_secs(duration));
+
+        Some(duration)
+    }
+
+    /// Forgets the failures of an account once its owner logged in
+    pub fn reset(&self, username: &str) {
+        self.failures.lock().unwrap().remove(username);
+    }
+}
@@ -0,0 +1,74 @@
+use axum::Router;
+use axum::extract::{Path, Query, State};
+use axum::routing::get;
+use senra_api::{DailyStatsResponse, NotebookAnalyticsResponse, ReferrerResponse};
+use serde::Deserialize;
+
+use crate::errors::Result;
+use crate::middleware::{AuthUser, Json};
+use crate::state::AppState;
+
+/// Longest period the analytics can cover, in days
+const MAX_ANALYTICS_DAYS: i64 = 365;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+struct AnalyticsParams {
+    /// Number of days covered, ending today, 30 by default
+    days: Option<i64>,
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/notebooks/{id}/analytics", get(notebook_analytics))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/notebooks/{id}/analytics",
+    tag = "notebook",
+    params(
+        ("id" = i64, Path, description = "Notebook ID"),
+        AnalyticsParams
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved notebook analytics", body = NotebookAnalyticsResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Not the owner of the notebook"),
+        (status = 404, description = "Notebook not found")
+    )
+)]
+async fn notebook_analytics(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+    Query(params): Query<AnalyticsParams>,
+) -> Result<Json<NotebookAnalyticsResponse>> {
+    let days = params.days.unwrap_or(30).clamp(1, MAX_ANALYTICS_DAYS);
+
+    let (daily, referrers) = state
+        .services
+        .analytics
+        .notebook_analytics(auth_user.user_id, id, days)
+        .await?;
+
+    Ok(Json(NotebookAnalyticsResponse {
+        notebook_id: id,
+        daily: daily
+            .into_iter()
+            .map(|stats| DailyStatsResponse {
+                day: stats.day.to_string(),
+                view_count: stats.view_count,
+                like_count: stats.like_count,
+                comment_count: stats.comment_count,
+            })
+            .collect(),
+        referrers: referrers
+            .into_iter()
+            .map(|referrer| ReferrerResponse {
+                referrer: Some(referrer.referrer).filter(|host| !host.is_empty()),
+                view_count: referrer.view_count,
+            })
+            .collect(),
+    }))
+}
@@ -1,17 +1,26 @@
+use axum::Router;
 use axum::extract::State;
-use axum::routing::post;
-use axum::{Json, Router};
+use axum::routing::{get, post};
 use senra_api::*;
 
+use super::user::user_info_response;
 use crate::errors::Result;
+use crate::middleware::{AuthUser, Json};
 use crate::models::{CreateUser, LoginUser};
+use crate::services::LoginOutcome;
 use crate::state::AppState;
 
 pub fn router(state: AppState) -> Router {
     Router::new()
         .route("/auth/verify", post(verify_token))
         .route("/auth/login", post(login))
+        .route("/auth/login/2fa", post(login_two_factor))
         .route("/auth/register", post(register))
+        .route("/auth/2fa", get(get_two_factor))
+        .route("/auth/2fa/setup", post(setup_two_factor))
+        .route("/auth/2fa/enable", post(enable_two_factor))
+        .route("/auth/2fa/disable", post(disable_two_factor))
+        .route("/auth/2fa/recovery-codes", post(regenerate_recovery_codes))
         .with_state(state)
 }
 
@@ -41,16 +50,17 @@ async fn verify_token(
     tag = "auth",
     request_body = LoginRequest,
     responses(
-        (status = 200, description = "Login successful", body = AuthResponse),
-        (status = 401, description = "Invalid credentials")
+        (status = 200, description = "Login successful, or a challenge to answer with a second factor", body = LoginResponse),
+        (status = 401, description = "Invalid credentials"),
+        (status = 429, description = "Too many attempts, or account locked after failed logins", body = ErrorBody)
     ),
     tag = "auth"
 )]
 async fn login(
     State(state): State<AppState>,
     Json(payload): Json<LoginRequest>,
-) -> Result<Json<AuthResponse>> {
-    let (user, token) = state
+) -> Result<Json<LoginResponse>> {
+    let outcome = state
         .services
         .auth
         .login(LoginUser {
@@ -59,13 +69,46 @@ async fn login(
         })
         .await?;
 
+    let response = match outcome {
+        LoginOutcome::Authenticated(user, token) => LoginResponse::Authenticated(AuthResponse {
+            user: user_info_response(&state, user).await?,
+            token,
+        }),
+        LoginOutcome::Challenge {
+            challenge,
+            expires_in,
+        } => LoginResponse::Challenge(TwoFactorChallengeResponse {
+            challenge,
+            expires_in,
+        }),
+    };
+
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/login/2fa",
+    tag = "auth",
+    request_body = TwoFactorLoginRequest,
+    responses(
+        (status = 200, description = "Login successful", body = AuthResponse),
+        (status = 401, description = "Invalid or expired challenge, or invalid code"),
+        (status = 429, description = "Too many attempts, or account locked after failed logins", body = ErrorBody)
+    )
+)]
+async fn login_two_factor(
+    State(state): State<AppState>,
+    Json(payload): Json<TwoFactorLoginRequest>,
+) -> Result<Json<AuthResponse>> {
+    let (user, token) = state
+        .services
+        .auth
+        .login_two_factor(&payload.challenge, &payload.code)
+        .await?;
+
     Ok(Json(AuthResponse {
-        user: UserInfoResponse {
-            id: user.id,
-            username: user.username,
-            email: user.email,
-            avatar: user.avatar,
-        },
+        user: user_info_response(&state, user).await?,
         token,
     }))
 }
@@ -78,7 +121,8 @@ async fn login(
     responses(
         (status = 200, description = "Registration successful", body = AuthResponse),
         (status = 400, description = "Invalid request data"),
-        (status = 409, description = "Username or email already exists")
+        (status = 409, description = "Username or email already exists"),
+        (status = 429, description = "Too many attempts", body = ErrorBody)
     ),
     tag = "auth"
 )]
@@ -98,12 +142,132 @@ async fn register(
     let token = state.services.auth.generate_token(user.id).await?;
 
     Ok(Json(AuthResponse {
-        user: UserInfoResponse {
-            id: user.id,
-            username: user.username,
-            email: user.email,
-            avatar: user.avatar,
-        },
+        user: user_info_response(&state, user).await?,
         token,
     }))
 }
+
+#[utoipa::path(
+    get,
+    path = "/auth/2fa",
+    tag = "auth",
+    responses(
+        (status = 200, description = "Two-factor authentication status", body = TwoFactorStatusResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn get_two_factor(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<TwoFactorStatusResponse>> {
+    let (enabled, recovery_codes_left) =
+        state.services.two_factor.status(auth_user.user_id).await?;
+
+    Ok(Json(TwoFactorStatusResponse {
+        enabled,
+        recovery_codes_left,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/setup",
+    tag = "auth",
+    responses(
+        (status = 200, description = "New secret to confirm with a code", body = TwoFactorSetupResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 409, description = "Two-factor authentication is already enabled")
+    )
+)]
+async fn setup_two_factor(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<TwoFactorSetupResponse>> {
+    let user = state.services.user.get_user(auth_user.user_id).await?;
+    let setup = state.services.two_factor.setup(&user).await?;
+
+    Ok(Json(TwoFactorSetupResponse {
+        secret: setup.secret,
+        provisioning_uri: setup.provisioning_uri,
+        qr_code: setup.qr_code,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/enable",
+    tag = "auth",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodesResponse),
+        (status = 400, description = "Two-factor authentication was not set up"),
+        (status = 401, description = "Unauthorized, or invalid code"),
+        (status = 409, description = "Two-factor authentication is already enabled")
+    )
+)]
+async fn enable_two_factor(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>> {
+    let recovery_codes = state
+        .services
+        .two_factor
+        .enable(auth_user.user_id, &payload.code)
+        .await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/disable",
+    tag = "auth",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication disabled", body = TwoFactorStatusResponse),
+        (status = 400, description = "Two-factor authentication is not enabled"),
+        (status = 401, description = "Unauthorized, or invalid code")
+    )
+)]
+async fn disable_two_factor(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<TwoFactorStatusResponse>> {
+    state
+        .services
+        .two_factor
+        .disable(auth_user.user_id, &payload.code)
+        .await?;
+
+    Ok(Json(TwoFactorStatusResponse {
+        enabled: false,
+        recovery_codes_left: 0,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/2fa/recovery-codes",
+    tag = "auth",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "New recovery codes, replacing the previous ones", body = RecoveryCodesResponse),
+        (status = 400, description = "Two-factor authentication is not enabled"),
+        (status = 401, description = "Unauthorized, or invalid code")
+    )
+)]
+async fn regenerate_recovery_codes(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(payload): Json<TwoFactorCodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>> {
+    let recovery_codes = state
+        .services
+        .two_factor
+        .regenerate_recovery_codes(auth_user.user_id, &payload.code)
+        .await?;
+
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
@@ -0,0 +1,180 @@
+use std::ops::Range;
+
+use axum::Router;
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::Response;
+use axum::routing::get;
+use tokio_util::io::ReaderStream;
+
+use crate::blob;
+use crate::errors::{AppError, Result};
+use crate::middleware::AuthUser;
+use crate::state::AppState;
+
+/// Blobs never change once stored, so they can be cached for as long as clients want
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+/// Blobs only their owner can see must stay out of shared caches
+const PRIVATE_CACHE_CONTROL: &str = "private, max-age=31536000, immutable";
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/blobs/{hash}", get(get_blob))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/blobs/{hash}",
+    tag = "blob",
+    params(
+        ("hash" = String, Path, description = "Lowercase hex SHA-256 of the blob")
+    ),
+    responses(
+        (status = 200, description = "Whole blob", content_type = "application/octet-stream"),
+        (status = 206, description = "Requested range of the blob", content_type = "application/octet-stream"),
+        (status = 304, description = "Blob cached by the client is current"),
+        (status = 404, description = "Blob not found, or only referenced by notebooks the user can't see"),
+        (status = 416, description = "Requested range not satisfiable")
+    )
+)]
+async fn get_blob(
+    State(state): State<AppState>,
+    Path(hash): Path<String>,
+    auth_user: Option<AuthUser>,
+    headers: HeaderMap,
+) -> Result<Response> {
+    let not_found = || AppError::NotFound("Blob not found".to_string());
+    if !blob::is_valid_hash(&hash) {
+        return Err(not_found());
+    }
+
+    // Hashes of private or trashed notebook blobs may be known, they don't grant access
+    let viewer = auth_user.map_or(0, |auth_user| auth_user.user_id);
+    let blobs = &state.services.blob;
+    if !blobs.is_visible(viewer, &hash).await? {
+        return Err(not_found());
+    }
+    let public = viewer == 0 || blobs.is_visible(0, &hash).await?;
+
+    let store = blobs.store();
+    let size = store.size(&hash).await?.ok_or_else(not_found)?;
+    let etag = format!("\"{}\"", hash);
+
+    let response = Response::builder()
+        .header(header::ETAG, &etag)
+        .header(
+            header::CACHE_CONTROL,
+            if public {
+                CACHE_CONTROL
+            } else {
+                PRIVATE_CACHE_CONTROL
+            },
+        )
+        .header(header::ACCEPT_RANGES, "bytes");
+
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value
+                .split(',')
+                .any(|tag| tag.trim() == etag || tag.trim() == "*")
+        });
+    if cached {
+        return build(response.status(StatusCode::NOT_MODIFIED), Body::empty());
+    }
+
+    let content_type = state.services.blob.content_type(&hash, size).await?;
+    let response = response.header(header::CONTENT_TYPE, content_type);
+
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_range(value, size));
+
+    match range {
+        Some(Ok(range)) => {
+            let reader = store
+                .open(&hash, range.clone())
+                .await?
+                .ok_or_else(not_found)?;
+            build(
+                response
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(
+                        header::CONTENT_RANGE,
+                        format!("bytes {}-{}/{}", range.start, range.end - 1, size),
+                    )
+                    .header(header::CONTENT_LENGTH, range.end - range.start),
+                Body::from_stream(ReaderStream::new(reader)),
+            )
+        }
+        Some(Err(())) => build(
+            response
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", size)),
+            Body::empty(),
+        ),
+        None => {
+            let reader = store.open(&hash, 0..size).await?.ok_or_else(not_found)?;
+            build(
+                response
+                    .status(StatusCode::OK)
+                    .header(header::CONTENT_LENGTH, size),
+                Body::from_stream(ReaderStream::new(reader)),
+            )
+        }
+    }
+}
+
+fn build(response: axum::http::response::Builder, body: Body) -> Result<Response> {
+    response
+        .body(body)
+        .map_err(|err| AppError::InternalError(err.to_string()))
+}
+
+/// Parses a single `bytes` range of a blob of `size` bytes into the byte range to send.
+/// `None` when the header should be ignored, such as for multiple ranges, and `Err` when the
+/// range is not satisfiable
+fn parse_range(value: &str, size: u64) -> Option<std::result::Result<Range<u64>, ()>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    let range = match (start.is_empty(), end.is_empty()) {
+        // Last `end` bytes
+        (true, false) => {
+            let length: u64 = end.parse().ok()?;
+            if length == 0 {
+                return Some(Err(()));
+            }
+            size.saturating_sub(length)..size
+        }
+        (false, _) => {
+            let start: u64 = start.parse().ok()?;
+            let end = match end.is_empty() {
+                true => size,
+                false => end.parse::<u64>().ok()?.saturating_add(1).min(size),
+            };
+            if start >= size {
+                return Some(Err(()));
+            }
+            if end <= start {
+                // Backwards ranges are invalid and ignored
+                return None;
+            }
+            start..end
+        }
+        (true, true) => return None,
+    };
+
+    match range.start < size {
+        true => Some(Ok(range)),
+        false => Some(Err(())),
+    }
+}
@@ -0,0 +1,317 @@
+use axum::Router;
+use axum::extract::{Path, Query, State};
+use axum::routing::{delete, get, post};
+use senra_api::*;
+use serde::Deserialize;
+
+use super::notebook::notebook_previews;
+use crate::errors::Result;
+use crate::middleware::{AuthUser, Json};
+use crate::models::{Collection, CreateCollection, UpdateCollection};
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct PaginationParams {
+    page: Option<i64>,
+    per_page: Option<i64>,
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/collections", post(create_collection))
+        .route(
+            "/collections/{id}",
+            get(get_collection)
+                .patch(update_collection)
+                .delete(delete_collection),
+        )
+        .route("/collections/{id}/notebooks", post(add_notebook))
+        .route(
+            "/collections/{id}/notebooks/{notebook_id}",
+            delete(remove_notebook),
+        )
+        .route("/user/{id}/collections", get(list_user_collections))
+        .with_state(state)
+}
+
+fn collection_info(collection: Collection) -> CollectionInfo {
+    CollectionInfo {
+        id: collection.id,
+        title: collection.title,
+        description: collection.description,
+        visibility: collection.visibility,
+        notebook_count: collection.notebook_count,
+        created_at: collection.created_at.to_string(),
+        updated_at: collection.updated_at.to_string(),
+    }
+}
+
+/// Builds the response of a collection with its notebooks visible to `user_id`
+async fn collection_response(
+    state: &AppState,
+    collection: Collection,
+    user_id: Option<i64>,
+) -> Result<CollectionResponse> {
+    let notebooks = state
+        .services
+        .collection
+        .collection_notebooks(user_id.unwrap_or_default(), collection.id)
+        .await?;
+    let notebooks = notebook_previews(state, notebooks, user_id).await?;
+    let user = state.services.user.get_user(collection.user_id).await?;
+
+    Ok(CollectionResponse {
+        inner: collection_info(collection),
+        author: UserPreviewResponse {
+            id: user.id,
+            username: user.username,
+            avatar: user.avatar_hash.map(BlobRef::new),
+        },
+        notebooks,
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections",
+    tag = "collection",
+    request_body = CreateCollectionRequest,
+    responses(
+        (status = 200, description = "Successfully created collection", body = CollectionResponse),
+        (status = 400, description = "Invalid request data"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Notebook not found"),
+        (status = 409, description = "Notebook listed twice")
+    )
+)]
+async fn create_collection(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(payload): Json<CreateCollectionRequest>,
+) -> Result<Json<CollectionResponse>> {
+    let collection = state
+        .services
+        .collection
+        .create_collection(
+            auth_user.user_id,
+            CreateCollection {
+                title: payload.title,
+                description: payload.description,
+                visibility: payload.visibility,
+                notebook_ids: payload.notebook_ids,
+            },
+        )
+        .await?;
+
+    Ok(Json(
+        collection_response(&state, collection, Some(auth_user.user_id)).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/collections/{id}",
+    tag = "collection",
+    params(
+        ("id" = i64, Path, description = "Collection ID")
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved collection", body = CollectionResponse),
+        (status = 404, description = "Collection not found")
+    )
+)]
+async fn get_collection(
+    State(state): State<AppState>,
+    auth_user: Option<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<Json<CollectionResponse>> {
+    let user_id = auth_user.map(|user| user.user_id);
+
+    let collection = state
+        .services
+        .collection
+        .get_collection(user_id.unwrap_or_default(), id)
+        .await?;
+
+    Ok(Json(
+        collection_response(&state, collection, user_id).await?,
+    ))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/collections/{id}",
+    tag = "collection",
+    params(
+        ("id" = i64, Path, description = "Collection ID")
+    ),
+    request_body = EditCollectionRequest,
+    responses(
+        (status = 200, description = "Successfully updated collection", body = CollectionResponse),
+        (status = 400, description = "Invalid request data"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Collection owned by another user"),
+        (status = 404, description = "Collection or notebook not found"),
+        (status = 409, description = "Notebook listed twice")
+    )
+)]
+async fn update_collection(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+    Json(payload): Json<EditCollectionRequest>,
+) -> Result<Json<CollectionResponse>> {
+    let collection = state
+        .services
+        .collection
+        .update_collection(
+            auth_user.user_id,
+            id,
+            UpdateCollection {
+                title: payload.title,
+                description: payload.description,
+                visibility: payload.visibility,
+                notebook_ids: payload.notebook_ids,
+            },
+        )
+        .await?;
+
+    Ok(Json(
+        collection_response(&state, collection, Some(auth_user.user_id)).await?,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{id}",
+    tag = "collection",
+    params(
+        ("id" = i64, Path, description = "Collection ID")
+    ),
+    responses(
+        (status = 200, description = "Successfully deleted collection"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Collection owned by another user"),
+        (status = 404, description = "Collection not found")
+    )
+)]
+async fn delete_collection(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<()> {
+    state
+        .services
+        .collection
+        .delete_collection(auth_user.user_id, id)
+        .await
+}
+
+#[utoipa::path(
+    post,
+    path = "/collections/{id}/notebooks",
+    tag = "collection",
+    params(
+        ("id" = i64, Path, description = "Collection ID")
+    ),
+    request_body = AddCollectionNotebookRequest,
+    responses(
+        (status = 200, description = "Successfully added notebook", body = CollectionResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Collection owned by another user"),
+        (status = 404, description = "Collection or notebook not found"),
+        (status = 409, description = "Notebook already in the collection")
+    )
+)]
+async fn add_notebook(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+    Json(payload): Json<AddCollectionNotebookRequest>,
+) -> Result<Json<CollectionResponse>> {
+    let collection = state
+        .services
+        .collection
+        .add_notebook(
+            auth_user.user_id,
+            id,
+            payload.notebook_id,
+            payload.position.map(|position| position as usize),
+        )
+        .await?;
+
+    Ok(Json(
+        collection_response(&state, collection, Some(auth_user.user_id)).await?,
+    ))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/collections/{id}/notebooks/{notebook_id}",
+    tag = "collection",
+    params(
+        ("id" = i64, Path, description = "Collection ID"),
+        ("notebook_id" = i64, Path, description = "Notebook ID")
+    ),
+    responses(
+        (status = 200, description = "Successfully removed notebook", body = CollectionResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Collection owned by another user"),
+        (status = 404, description = "Collection or notebook not found")
+    )
+)]
+async fn remove_notebook(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((id, notebook_id)): Path<(i64, i64)>,
+) -> Result<Json<CollectionResponse>> {
+    let collection = state
+        .services
+        .collection
+        .remove_notebook(auth_user.user_id, id, notebook_id)
+        .await?;
+
+    Ok(Json(
+        collection_response(&state, collection, Some(auth_user.user_id)).await?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/user/{id}/collections",
+    tag = "collection",
+    params(
+        ("id" = i64, Path, description = "ID of the user owning the collections"),
+        PaginationParams
+    ),
+    responses(
+        (status = 200, description = "Successfully listed collections", body = CollectionListResponse),
+        (status = 404, description = "User not found")
+    )
+)]
+async fn list_user_collections(
+    State(state): State<AppState>,
+    auth_user: Option<AuthUser>,
+    Path(id): Path<i64>,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<CollectionListResponse>> {
+    let page = pagination.page.unwrap_or(1);
+    let per_page = pagination.per_page.unwrap_or(10);
+
+    let user = state.services.user.get_user(id).await?;
+    let (collections, total) = state
+        .services
+        .collection
+        .list_user_collections(
+            auth_user.map(|user| user.user_id).unwrap_or_default(),
+            user.id,
+            page,
+            per_page,
+        )
+        .await?;
+
+    Ok(Json(CollectionListResponse {
+        collections: collections.into_iter().map(collection_info).collect(),
+        total,
+    }))
+}
@@ -0,0 +1,259 @@
+use std::io::Cursor;
+
+use axum::Router;
+use axum::extract::{Path, Query, RawQuery, State};
+use axum::response::Html;
+use axum::routing::get;
+use image::io::Reader;
+use senra_api::*;
+use serde::Deserialize;
+use tower_http::services::ServeDir;
+
+use crate::errors::{EmbedError, Result};
+use crate::middleware::Json;
+use crate::models::Notebook;
+use crate::state::AppState;
+
+/// Size of the player when a render cell doesn't set one
+const DEFAULT_SIZE: (u32, u32) = (640, 360);
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct OEmbedParams {
+    /// URL of a notebook or of its embed page on this server
+    url: String,
+    maxwidth: Option<u32>,
+    maxheight: Option<u32>,
+    /// Only `json` is supported
+    format: Option<String>,
+}
+
+pub fn router(state: AppState) -> Router {
+    let router = Router::new()
+        .route("/embed/{id}", get(embed_notebook))
+        .route("/oembed", get(oembed));
+
+    let router = match &state.config.embed.viewer_dir {
+        Some(dir) => router.nest_service("/viewer", ServeDir::new(dir)),
+        None => router,
+    };
+
+    router.with_state(state)
+}
+
+/// Retrieves a public notebook along with the size of the render cell selected by `options`
+async fn embeddable(
+    state: &AppState,
+    id: i64,
+    options: &EmbedOptions,
+) -> Result<(Notebook, (u32, u32))> {
+    // Embeds are loaded by signed out visitors, so only public notebooks are embeddable
+    let notebook = state.services.notebook.get_notebook(0, id).await?;
+
+    let content: NotebookContent =
+        serde_json::from_value(notebook.content.clone()).map_err(|_| EmbedError::NoRenderCell)?;
+    let (_, config) = options
+        .render_cell(&content)
+        .ok_or(EmbedError::NoRenderCell)?;
+    let size = match (config.width, config.height) {
+        (0, _) | (_, 0) => DEFAULT_SIZE,
+        size => size,
+    };
+
+    Ok((notebook, size))
+}
+
+#[utoipa::path(
+    get,
+    path = "/embed/{id}",
+    tag = "embed",
+    params(
+        ("id" = i64, Path, description = "Notebook ID"),
+        ("cell" = Option<String>, Query, description = "Render cell to play, the first one by default"),
+        ("autoplay" = Option<bool>, Query, description = "Whether the shader plays once loaded, true by default"),
+        ("ui" = Option<bool>, Query, description = "Whether the title and controls are shown, true by default"),
+        ("t" = Option<f32>, Query, description = "Seconds the shader clock starts at")
+    ),
+    responses(
+        (status = 200, description = "Player page of the notebook", content_type = "text/html"),
+        (status = 404, description = "Notebook not found, not public or without render cell")
+    )
+)]
+async fn embed_notebook(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    RawQuery(query): RawQuery,
+) -> Result<Html<String>> {
+    let options = EmbedOptions::from_query(query.as_deref().unwrap_or_default());
+    let (notebook, _) = embeddable(&state, id, &options).await?;
+
+    let config = &state.config.embed;
+    let title = escape_html(&notebook.title);
+    let embed_url = format!("{}/embed/{}{}", config.public_url, id, options.to_query());
+    let oembed_url = format!(
+        "{}/oembed?url={}",
+        config.public_url,
+        encode_component(&embed_url)
+    );
+    let poster = match &notebook.preview_hash {
+        Some(hash) => format!(
+            r#"<img id="poster" src="{}" alt="">"#,
+            escape_html(&BlobRef::new(hash.clone()).url)
+        ),
+        None => String::new(),
+    };
+
+    Ok(Html(format!(
+        r#"<!doctype html>
+<html lang="en">
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1">
+    <title>{title} - ShaderLab</title>
+    <link rel="alternate" type="application/json+oembed" href="{oembed_url}" title="{title}">
+    <style>
+        html, body {{ margin: 0; width: 100%; height: 100%; overflow: hidden; background: #000; }}
+        canvas {{ display: block; width: 100%; height: 100%; }}
+        #poster {{ position: absolute; inset: 0; width: 100%; height: 100%; object-fit: cover; }}
+    </style>
+</head>
+<body>
+    {poster}
+    <script type="module">
+        import init from "{viewer_url}";
+        init().then(() => document.getElementById("poster")?.remove());
+    </script>
+</body>
+</html>
+"#,
+        oembed_url = escape_html(&oembed_url),
+        viewer_url = escape_html(&config.viewer_url),
+    )))
+}
+
+#[utoipa::path(
+    get,
+    path = "/oembed",
+    tag = "embed",
+    params(OEmbedParams),
+    responses(
+        (status = 200, description = "oEmbed description of the notebook", body = OEmbedResponse),
+        (status = 404, description = "Notebook not found, not public or without render cell"),
+        (status = 501, description = "Requested format not supported")
+    )
+)]
+async fn oembed(
+    State(state): State<AppState>,
+    Query(params): Query<OEmbedParams>,
+) -> Result<Json<OEmbedResponse>> {
+    if params
+        .format
+        .as_deref()
+        .is_some_and(|format| format != "json")
+    {
+        return Err(EmbedError::UnsupportedFormat.into());
+    }
+
+    let config = &state.config.embed;
+    let (id, options) =
+        parse_notebook_url(&config.public_url, &params.url).ok_or(EmbedError::UnsupportedUrl)?;
+    let (notebook, (width, height)) = embeddable(&state, id, &options).await?;
+    let (width, height) = fit(width, height, params.maxwidth, params.maxheight);
+
+    let user = state.services.user.get_user(notebook.user_id).await?;
+    let embed_url = format!("{}/embed/{}{}", config.public_url, id, options.to_query());
+    let html = format!(
+        r#"<iframe src="{}" width="{}" height="{}" title="{}" frameborder="0" allow="autoplay; fullscreen" allowfullscreen></iframe>"#,
+        escape_html(&embed_url),
+        width,
+        height,
+        escape_html(&notebook.title)
+    );
+
+    let thumbnail = match &notebook.preview_hash {
+        Some(hash) => state
+            .services
+            .blob
+            .get(hash)
+            .await?
+            .and_then(|data| {
+                Reader::new(Cursor::new(data))
+                    .with_guessed_format()
+                    .ok()?
+                    .into_dimensions()
+                    .ok()
+            })
+            .map(|size| (BlobRef::new(hash.clone()).url, size)),
+        None => None,
+    };
+
+    Ok(Json(OEmbedResponse {
+        kind: "rich".to_string(),
+        version: "1.0".to_string(),
+        title: notebook.title,
+        author_name: user.username,
+        author_url: format!("{}/user/{}", config.public_url, user.id),
+        provider_name: "ShaderLab".to_string(),
+        provider_url: config.public_url.clone(),
+        html,
+        width,
+        height,
+        thumbnail_url: thumbnail
+            .as_ref()
+            .map(|(url, _)| format!("{}{}", config.public_url, url)),
+        thumbnail_width: thumbnail.as_ref().map(|(_, (width, _))| *width),
+        thumbnail_height: thumbnail.as_ref().map(|(_, (_, height))| *height),
+    }))
+}
+
+/// Id and embed options of `/embed/{id}`, `/notebooks/{id}` and web app `/notebook/{id}`
+/// URLs of this server
+fn parse_notebook_url(public_url: &str, url: &str) -> Option<(i64, EmbedOptions)> {
+    let rest = url.strip_prefix(public_url)?;
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let id = ["/embed/", "/notebooks/", "/notebook/"]
+        .into_iter()
+        .find_map(|prefix| path.strip_prefix(prefix))?;
+
+    Some((
+        id.trim_end_matches('/').parse().ok()?,
+        EmbedOptions::from_query(query),
+    ))
+}
+
+/// Scales a size down to fit the maximum dimensions requested, keeping its aspect ratio
+fn fit(width: u32, height: u32, max_width: Option<u32>, max_height: Option<u32>) -> (u32, u32) {
+    let scale = [
+        max_width.map(|max| max as f64 / width as f64),
+        max_height.map(|max| max as f64 / height as f64),
+    ]
+    .into_iter()
+    .flatten()
+    .fold(1.0, f64::min);
+
+    (
+        (width as f64 * scale).round().max(1.0) as u32,
+        (height as f64 * scale).round().max(1.0) as u32,
+    )
+}
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn encode_component(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
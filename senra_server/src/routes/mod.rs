@@ -1,30 +1,70 @@
+mod analytics;
 mod auth;
+mod blob;
+mod collection;
+mod embed;
+mod monitoring;
 mod notebook;
+mod notification;
+mod package;
+mod schema;
 mod user;
 mod ws;
 
-use axum::Router;
 use axum::http::Method;
 use axum::response::{Html, Json};
 use axum::routing::get;
+use axum::{Router, middleware};
 use serde_json::json;
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 
+use crate::middleware::{
+    RequestId, negotiate_encoding, propagate_request_id, rate_limit, track_requests,
+};
 use crate::state::AppState;
 
 pub fn create_router(state: AppState) -> Router {
     Router::new()
+        .merge(analytics::router(state.clone()))
         .merge(auth::router(state.clone()))
+        .merge(blob::router(state.clone()))
+        .merge(collection::router(state.clone()))
+        .merge(embed::router(state.clone()))
+        .merge(monitoring::router(state.clone()))
         .merge(notebook::router(state.clone()))
+        .merge(notification::router(state.clone()))
+        .merge(package::router(state.clone()))
+        .merge(schema::router())
         .merge(user::router(state.clone()))
         .merge(ws::router(state.clone()))
         .merge(openapi())
         .route("/health", get(health_check))
-        .layer(TraceLayer::new_for_http())
+        .layer(middleware::from_fn_with_state(state.clone(), rate_limit))
+        .layer(middleware::from_fn(negotiate_encoding))
+        .layer(middleware::from_fn_with_state(
+            state.metrics.clone(),
+            track_requests,
+        ))
+        .layer(
+            TraceLayer::new_for_http().make_span_with(|request: &axum::extract::Request| {
+                let request_id = request
+                    .extensions()
+                    .get::<RequestId>()
+                    .map(|id| id.0.as_str())
+                    .unwrap_or_default();
+                tracing::info_span!(
+                    "request",
+                    method = %request.method(),
+                    uri = %request.uri(),
+                    request_id = %request_id,
+                )
+            }),
+        )
+        .layer(middleware::from_fn(propagate_request_id))
         .layer(
             CorsLayer::new()
-                .allow_methods([Method::GET, Method::POST, Method::PATCH])
+                .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
                 .allow_headers(Any)
                 .allow_origin(Any),
         )
@@ -38,45 +78,140 @@ fn openapi() -> Router {
         paths(
             auth::verify_token,
             auth::login,
+            auth::login_two_factor,
             auth::register,
+            auth::get_two_factor,
+            auth::setup_two_factor,
+            auth::enable_two_factor,
+            auth::disable_two_factor,
+            auth::regenerate_recovery_codes,
+            blob::get_blob,
             user::get_self,
             user::get_user,
             user::edit_user,
+            user::reset_avatar,
+            user::follow_user,
+            user::unfollow_user,
+            user::list_trash,
+            user::restore_notebook,
+            user::purge_notebook,
+            collection::create_collection,
+            collection::get_collection,
+            collection::update_collection,
+            collection::delete_collection,
+            collection::add_notebook,
+            collection::remove_notebook,
+            collection::list_user_collections,
+            embed::embed_notebook,
+            embed::oembed,
             notebook::list_notebooks,
+            notebook::list_following_notebooks,
             notebook::get_notebook,
             notebook::create_notebook,
             notebook::update_notebook,
             notebook::delete_notebook,
+            notebook::export_notebook,
+            notebook::export_shaders_notebook,
+            notebook::import_notebook,
+            notebook::import_shadertoy_notebook,
             notebook::like_notebook,
             notebook::unlike_notebook,
             notebook::list_versions,
+            notebook::get_library_module,
             notebook::list_comments,
             notebook::create_comment,
-            notebook::delete_comment
+            notebook::update_comment,
+            notebook::delete_comment,
+            analytics::notebook_analytics,
+            notification::list_notifications,
+            notification::mark_notifications_read,
+            package::publish_package,
+            package::get_package,
+            package::get_package_version,
+            package::get_package_used_by,
+            schema::notebook_schema
         ),
         components(
             schemas(
                 senra_api::AuthRequest,
                 senra_api::AuthResponse,
                 senra_api::LoginRequest,
+                senra_api::LoginResponse,
+                senra_api::TwoFactorChallengeResponse,
+                senra_api::TwoFactorLoginRequest,
+                senra_api::TwoFactorCodeRequest,
+                senra_api::TwoFactorSetupResponse,
+                senra_api::TwoFactorStatusResponse,
+                senra_api::RecoveryCodesResponse,
                 senra_api::RegisterRequest,
+                senra_api::BlobRef,
                 senra_api::UserResponse,
                 senra_api::UserInfoResponse,
                 senra_api::EditUserRequest,
+                senra_api::AvatarVariant,
+                senra_api::FollowResponse,
                 senra_api::NotebookListResponse,
+                senra_api::NotebookSort,
+                senra_api::NotebookInfo,
+                senra_api::TrashListResponse,
+                senra_api::TrashedNotebookResponse,
                 senra_api::NotebookResponse,
+                senra_api::ShadertoyImportResponse,
+                senra_api::ShaderResponse,
+                senra_api::ShaderExportResponse,
+                senra_api::ShaderExportFile,
+                senra_api::ShaderReflection,
+                senra_api::PipelineReflection,
+                senra_api::EntryPointReflection,
+                senra_api::BindGroupReflection,
+                senra_api::BindingReflection,
+                senra_api::BufferLayout,
+                senra_api::BufferMember,
+                senra_api::CompileTarget,
+                senra_api::CompiledShader,
                 senra_api::CreateNotebookRequest,
                 senra_api::EditNotebookRequest,
                 senra_api::NotebookVersionListResponse,
                 senra_api::NotebookCommentListResponse,
                 senra_api::CreateNotebookCommentRequest,
-                senra_api::NotebookCommentResponse
+                senra_api::UpdateNotebookCommentRequest,
+                senra_api::CommentAnchor,
+                senra_api::NotebookCommentResponse,
+                senra_api::NotebookAnalyticsResponse,
+                senra_api::DailyStatsResponse,
+                senra_api::ReferrerResponse,
+                senra_api::NotificationKind,
+                senra_api::NotificationResponse,
+                senra_api::NotificationListResponse,
+                senra_api::MarkNotificationsReadRequest,
+                senra_api::UnreadNotificationsResponse,
+                senra_api::CreateCollectionRequest,
+                senra_api::EditCollectionRequest,
+                senra_api::AddCollectionNotebookRequest,
+                senra_api::CollectionInfo,
+                senra_api::CollectionResponse,
+                senra_api::CollectionListResponse,
+                senra_api::OEmbedResponse,
+                senra_api::ErrorBody,
+                senra_api::PublishPackageRequest,
+                senra_api::PackageResponse,
+                senra_api::PackageVersionResponse,
+                senra_api::PackageUsedByResponse,
+                senra_api::PackageNotebookDependent,
+                senra_api::PackageVersionDependent,
+                senra_api::FieldError
             )
         ),
         tags(
             (name = "auth", description = "Authentication related endpoints"),
+            (name = "blob", description = "Content-addressed blob endpoints"),
             (name = "user", description = "User related endpoints"),
-            (name = "notebook", description = "Notebook related endpoints")
+            (name = "notebook", description = "Notebook related endpoints"),
+            (name = "collection", description = "Notebook collection endpoints"),
+            (name = "embed", description = "Embeddable player and oEmbed endpoints"),
+            (name = "notification", description = "Notification related endpoints"),
+            (name = "package", description = "Shader package related endpoints"),
+            (name = "schema", description = "Content format schema endpoints")
         )
     )]
     struct ApiDoc;
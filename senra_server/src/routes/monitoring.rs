@@ -0,0 +1,77 @@
+use axum::Router;
+use axum::extract::State;
+use axum::http::{StatusCode, header};
+use axum::response::{IntoResponse, Json};
+use axum::routing::get;
+use serde_json::{Value, json};
+use tracing::warn;
+
+use crate::errors::Result;
+use crate::state::AppState;
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/livez", get(livez))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+/// The process is up and serving requests
+async fn livez() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+/// The database is reachable and fully migrated, so the server can take traffic
+async fn readyz(State(state): State<AppState>) -> (StatusCode, Json<Value>) {
+    let database = match state.db.ping().await {
+        Ok(()) => "ok".to_string(),
+        Err(err) => {
+            warn!("Readiness check of the database failed: {}", err);
+            "unreachable".to_string()
+        }
+    };
+    let migrations = match state.db.pending_migrations().await {
+        Ok(0) => "ok".to_string(),
+        Ok(pending) => format!("{} pending", pending),
+        Err(err) => {
+            warn!("Readiness check of the migrations failed: {}", err);
+            "unknown".to_string()
+        }
+    };
+
+    let ready = database == "ok" && migrations == "ok";
+    let status = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        status,
+        Json(json!({
+            "status": if ready { "ok" } else { "unavailable" },
+            "checks": {
+                "database": database,
+                "migrations": migrations,
+            }
+        })),
+    )
+}
+
+/// Metrics in the Prometheus text format, the gauges read from the database refreshed first
+async fn metrics(State(state): State<AppState>) -> Result<impl IntoResponse> {
+    let metrics = &state.metrics;
+    let pool = state.db.pool();
+    metrics.db_connections.set(pool.size() as i64);
+    metrics.db_idle_connections.set(pool.num_idle() as i64);
+    metrics
+        .notebooks
+        .set(state.services.notebook.count_notebooks().await?);
+    metrics.users.set(state.services.user.count_users().await?);
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        metrics.render(),
+    ))
+}
@@ -1,12 +1,19 @@
-use axum::extract::{Path, Query, State};
-use axum::routing::{delete, get, post};
-use axum::{Json, Router};
+use axum::Router;
+use axum::body::Bytes;
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::http::header;
+use axum::response::IntoResponse;
+use axum::routing::{get, patch, post};
 use senra_api::*;
 use serde::Deserialize;
+use tracing::warn;
 
-use crate::errors::Result;
-use crate::middleware::AuthUser;
-use crate::models::{CreateNotebook, CreateResource, CreateShader, UpdateNotebook};
+use crate::errors::{AppError, NotebookError, Result};
+use crate::middleware::{AuthUser, Json, Visitor};
+use crate::models::{
+    ArchiveIds, CreateNotebook, CreateNotebookComment, CreateResource, CreateShader, Notebook,
+    NotebookComment, UpdateNotebook,
+};
 use crate::state::AppState;
 
 #[derive(Debug, Deserialize, utoipa::IntoParams)]
@@ -15,23 +22,56 @@ pub struct PaginationParams {
     per_page: Option<i64>,
 }
 
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct ListNotebooksParams {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    /// Feed to list, `hot` by default
+    sort: Option<NotebookSort>,
+}
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+pub struct ShaderExportParams {
+    /// Comma separated compile targets among `glsl_es`, `hlsl`, `msl`, `spirv` and `wgsl`,
+    /// all but `wgsl` by default
+    targets: Option<String>,
+}
+
+/// Largest accepted `.senra` archive upload, files inside being capped by `MAX_ARCHIVE_SIZE`
+const MAX_ARCHIVE_UPLOAD: usize = 16 * 1024 * 1024;
+
 pub fn router(state: AppState) -> Router {
     Router::new()
         .route("/notebooks", get(list_notebooks).post(create_notebook))
+        .route("/notebooks/following", get(list_following_notebooks))
         .route(
             "/notebooks/{id}",
             get(get_notebook)
                 .patch(update_notebook)
                 .delete(delete_notebook),
         )
+        .route(
+            "/notebooks/import",
+            post(import_notebook).layer(DefaultBodyLimit::max(MAX_ARCHIVE_UPLOAD)),
+        )
+        .route(
+            "/notebooks/import/shadertoy",
+            post(import_shadertoy_notebook),
+        )
+        .route("/notebooks/{id}/export", get(export_notebook))
+        .route(
+            "/notebooks/{id}/export/shaders",
+            get(export_shaders_notebook),
+        )
         .route("/notebooks/{id}/versions", get(list_versions))
+        .route("/notebooks/{id}/modules/{name}", get(get_library_module))
         .route(
             "/notebooks/{id}/comments",
             get(list_comments).post(create_comment),
         )
         .route(
             "/notebooks/{id}/comments/{comment_id}",
-            delete(delete_comment),
+            patch(update_comment).delete(delete_comment),
         )
         .route("/notebooks/{id}/like", post(like_notebook))
         .route("/notebooks/{id}/unlike", post(unlike_notebook))
@@ -42,7 +82,7 @@ pub fn router(state: AppState) -> Router {
     get,
     path = "/notebooks",
     tag = "notebook",
-    params(PaginationParams),
+    params(ListNotebooksParams),
     responses(
         (status = 200, description = "Successfully retrieved notebook list", body = NotebookListResponse),
         (status = 401, description = "Unauthorized")
@@ -51,19 +91,63 @@ pub fn router(state: AppState) -> Router {
 async fn list_notebooks(
     State(state): State<AppState>,
     auth_user: Option<AuthUser>,
+    Query(params): Query<ListNotebooksParams>,
+) -> Result<Json<NotebookListResponse>> {
+    let page = params.page.unwrap_or(1);
+    let per_page = params.per_page.unwrap_or(10);
+    let user_id = auth_user.map(|user| user.user_id);
+
+    let (notebook_data, total) = state
+        .services
+        .notebook
+        .list_notebooks(params.sort.unwrap_or_default(), page, per_page, user_id)
+        .await?;
+    let notebooks = notebook_previews(&state, notebook_data, user_id).await?;
+
+    Ok(Json(NotebookListResponse { notebooks, total }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/notebooks/following",
+    tag = "notebook",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Successfully retrieved notebooks of followed users", body = NotebookListResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn list_following_notebooks(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
     Query(pagination): Query<PaginationParams>,
 ) -> Result<Json<NotebookListResponse>> {
     let page = pagination.page.unwrap_or(1);
     let per_page = pagination.per_page.unwrap_or(10);
 
-    let notebook_service = state.services.notebook;
-    let (notebook_data, total) = notebook_service.list_notebooks(page, per_page).await?;
+    let (notebook_data, total) = state
+        .services
+        .notebook
+        .list_following_notebooks(auth_user.user_id, page, per_page)
+        .await?;
+    let notebooks = notebook_previews(&state, notebook_data, Some(auth_user.user_id)).await?;
+
+    Ok(Json(NotebookListResponse { notebooks, total }))
+}
 
-    let mut notebooks = Vec::new();
-    for notebook in notebook_data {
+/// Builds the previews of listed notebooks, `user_id` being the signed in user if any
+pub(super) async fn notebook_previews(
+    state: &AppState,
+    notebooks: Vec<Notebook>,
+    user_id: Option<i64>,
+) -> Result<Vec<NotebookPreviewResponse>> {
+    let notebook_service = &state.services.notebook;
+
+    let mut previews = Vec::new();
+    for notebook in notebooks {
         let stats = notebook_service.get_notebook_stats(notebook.id).await?;
         let tags = notebook_service.get_notebook_tags(notebook.id).await?;
-        let is_liked = match auth_user.as_ref().map(|user| user.user_id) {
+        let is_liked = match user_id {
             Some(user_id) => {
                 notebook_service
                     .is_notebook_liked(user_id, notebook.id)
@@ -73,7 +157,7 @@ async fn list_notebooks(
         };
         let user = state.services.user.get_user(notebook.user_id).await?;
 
-        notebooks.push(NotebookPreviewResponse {
+        previews.push(NotebookPreviewResponse {
             inner: NotebookInfo {
                 id: notebook.id,
                 title: notebook.title,
@@ -85,7 +169,7 @@ async fn list_notebooks(
             author: UserPreviewResponse {
                 id: user.id,
                 username: user.username,
-                avatar: Some(user.avatar),
+                avatar: user.avatar_hash.map(BlobRef::new),
             },
             stats: NotebookStats {
                 view_count: stats.view_count,
@@ -93,11 +177,11 @@ async fn list_notebooks(
                 comment_count: stats.comment_count,
                 is_liked,
             },
-            preview: notebook.preview,
+            preview: notebook.preview_hash.map(BlobRef::new),
         });
     }
 
-    Ok(Json(NotebookListResponse { notebooks, total }))
+    Ok(previews)
 }
 
 #[utoipa::path(
@@ -116,6 +200,7 @@ async fn list_notebooks(
 async fn get_notebook(
     State(state): State<AppState>,
     auth_user: Option<AuthUser>,
+    visitor: Visitor,
     Path(id): Path<i64>,
 ) -> Result<Json<NotebookResponse>> {
     let user_id = auth_user.as_ref().map(|user| user.user_id);
@@ -125,6 +210,22 @@ async fn get_notebook(
         .get_notebook(user_id.unwrap_or_default(), id)
         .await?;
 
+    // Authors viewing their own notebooks don't count
+    if user_id != Some(notebook.user_id) {
+        let viewer = match user_id {
+            Some(user_id) => format!("user:{}", user_id),
+            None => format!("anon:{}", visitor.fingerprint),
+        };
+        if let Err(err) = state
+            .services
+            .analytics
+            .record_view(id, &viewer, visitor.referrer.as_deref())
+            .await
+        {
+            warn!("Failed to record a view of notebook {}: {}", id, err);
+        }
+    }
+
     let stats = notebook_service.get_notebook_stats(id).await?;
     let tags = notebook_service.get_notebook_tags(id).await?;
     let is_liked = match user_id {
@@ -143,7 +244,7 @@ async fn get_notebook(
             notebook_id: r.notebook_id,
             name: r.name,
             resource_type: r.resource_type,
-            data: r.data,
+            data: BlobRef::new(r.data_hash),
             metadata: r.metadata,
             created_at: r.created_at.to_string(),
         })
@@ -175,7 +276,7 @@ async fn get_notebook(
         author: UserPreviewResponse {
             id: user.id,
             username: user.username,
-            avatar: Some(user.avatar),
+            avatar: user.avatar_hash.map(BlobRef::new),
         },
         stats: NotebookStats {
             view_count: stats.view_count,
@@ -264,7 +365,7 @@ async fn create_notebook(
             notebook_id: r.notebook_id,
             name: r.name,
             resource_type: r.resource_type,
-            data: r.data,
+            data: BlobRef::new(r.data_hash),
             metadata: r.metadata,
             created_at: r.created_at.to_string(),
         })
@@ -296,7 +397,7 @@ async fn create_notebook(
         author: UserPreviewResponse {
             id: user.id,
             username: user.username,
-            avatar: Some(user.avatar),
+            avatar: user.avatar_hash.map(BlobRef::new),
         },
         stats: NotebookStats {
             view_count: stats.view_count,
@@ -312,6 +413,286 @@ async fn create_notebook(
     }))
 }
 
+#[utoipa::path(
+    get,
+    path = "/notebooks/{id}/modules/{name}",
+    tag = "notebook",
+    params(
+        ("id" = i64, Path, description = "ID of the public notebook publishing the module"),
+        ("name" = String, Path, description = "Shader name")
+    ),
+    responses(
+        (status = 200, description = "Shader importable as `#import \"{id}/{name}\"`", body = ShaderResponse),
+        (status = 404, description = "Module not found")
+    )
+)]
+async fn get_library_module(
+    State(state): State<AppState>,
+    Path((id, name)): Path<(i64, String)>,
+) -> Result<Json<ShaderResponse>> {
+    let shader = state.services.shader.get_library_module(id, &name).await?;
+
+    Ok(Json(ShaderResponse {
+        id: shader.id,
+        notebook_id: shader.notebook_id,
+        name: shader.name,
+        shader_type: shader.shader_type,
+        code: shader.code,
+        version: shader.version,
+        created_at: shader.created_at.to_string(),
+        updated_at: shader.updated_at.to_string(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/notebooks/{id}/export",
+    tag = "notebook",
+    params(
+        ("id" = i64, Path, description = "Notebook ID")
+    ),
+    responses(
+        (status = 200, description = "Notebook as a .senra archive", body = Vec<u8>, content_type = "application/zip"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Notebook not found")
+    )
+)]
+async fn export_notebook(
+    State(state): State<AppState>,
+    auth_user: Option<AuthUser>,
+    Path(id): Path<i64>,
+) -> Result<impl IntoResponse> {
+    let user_id = auth_user.map(|user| user.user_id).unwrap_or_default();
+    let notebook_service = state.services.notebook;
+
+    let notebook = notebook_service.get_notebook(user_id, id).await?;
+    let tags = notebook_service.get_notebook_tags(id).await?;
+    let resources = state.services.resource.get_resources(id).await?;
+    let shaders = state.services.shader.get_shaders(id).await?;
+
+    let content = serde_json::from_value(notebook.content)
+        .map_err(|err| AppError::InternalError(err.to_string()))?;
+
+    let mut archive_resources = Vec::new();
+    for r in resources {
+        let data = state
+            .services
+            .blob
+            .get(&r.data_hash)
+            .await?
+            .ok_or_else(|| AppError::InternalError(format!("Missing data of resource {}", r.id)))?;
+        archive_resources.push(ArchiveResource {
+            id: r.id,
+            name: r.name,
+            resource_type: r.resource_type,
+            data,
+            metadata: r.metadata,
+        });
+    }
+
+    let archive = NotebookArchive {
+        title: notebook.title,
+        description: notebook.description,
+        tags: tags.into_iter().map(|tag| tag.tag).collect(),
+        visibility: notebook.visibility,
+        content,
+        shaders: shaders
+            .into_iter()
+            .map(|s| ArchiveShader {
+                id: s.id,
+                name: s.name,
+                shader_type: s.shader_type,
+                code: s.code,
+            })
+            .collect(),
+        resources: archive_resources,
+    };
+
+    let bytes = archive
+        .to_bytes()
+        .map_err(|err| AppError::InternalError(err.to_string()))?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", archive.file_name()),
+            ),
+        ],
+        bytes,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/notebooks/{id}/export/shaders",
+    tag = "notebook",
+    params(
+        ("id" = i64, Path, description = "Notebook ID"),
+        ShaderExportParams
+    ),
+    responses(
+        (status = 200, description = "Cross-compiled shaders with their pipeline reflection", body = ShaderExportResponse),
+        (status = 400, description = "Unknown compile target"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Notebook not found")
+    )
+)]
+async fn export_shaders_notebook(
+    State(state): State<AppState>,
+    auth_user: Option<AuthUser>,
+    Path(id): Path<i64>,
+    Query(params): Query<ShaderExportParams>,
+) -> Result<Json<ShaderExportResponse>> {
+    let targets = match params.targets.as_deref() {
+        Some(targets) => targets
+            .split(',')
+            .map(|target| target.trim().parse())
+            .collect::<std::result::Result<Vec<CompileTarget>, _>>()
+            .map_err(NotebookError::InvalidExportTarget)?,
+        None => CompileTarget::EXPORT.to_vec(),
+    };
+
+    let user_id = auth_user.map(|user| user.user_id).unwrap_or_default();
+    let notebook = state.services.notebook.get_notebook(user_id, id).await?;
+    let shaders = state.services.shader.get_shaders(id).await?;
+
+    let content = serde_json::from_value(notebook.content)
+        .map_err(|err| AppError::InternalError(err.to_string()))?;
+    // Shaders are exported with their imports expanded, as they are checked on save
+    let (preprocessed, mut errors) = state
+        .services
+        .notebook
+        .preprocessed_shaders(&content, &shaders)
+        .await?;
+    let shaders: Vec<ExportShader> = shaders
+        .iter()
+        .filter_map(|s| {
+            let (shader, language) = preprocessed.get(&s.id)?;
+            Some(ExportShader {
+                id: s.id,
+                name: &s.name,
+                source: ShaderSource::preprocessed(shader, *language),
+            })
+        })
+        .collect();
+
+    let mut export = export_shaders(&content, &shaders, &targets);
+    errors.append(&mut export.errors);
+    export.errors = errors;
+
+    Ok(Json(export))
+}
+
+#[utoipa::path(
+    post,
+    path = "/notebooks/import",
+    tag = "notebook",
+    request_body(content = Vec<u8>, description = "Notebook as a .senra archive", content_type = "application/zip"),
+    responses(
+        (status = 200, description = "Successfully imported notebook", body = NotebookResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 400, description = "Invalid archive")
+    )
+)]
+async fn import_notebook(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    visitor: Visitor,
+    body: Bytes,
+) -> Result<Json<NotebookResponse>> {
+    let archive = NotebookArchive::from_bytes(&body)
+        .map_err(|err| NotebookError::InvalidArchive(err.to_string()))?;
+
+    let notebook_id = create_from_archive(&state, auth_user.user_id, archive).await?;
+
+    get_notebook(State(state), Some(auth_user), visitor, Path(notebook_id)).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/notebooks/import/shadertoy",
+    tag = "notebook",
+    request_body(content = Object, description = "Shadertoy JSON export"),
+    responses(
+        (status = 200, description = "Successfully imported notebook", body = ShadertoyImportResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 400, description = "Invalid export or untranslatable shader code")
+    )
+)]
+async fn import_shadertoy_notebook(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    visitor: Visitor,
+    Json(payload): Json<serde_json::Value>,
+) -> Result<Json<ShadertoyImportResponse>> {
+    let import = import_shadertoy(&payload).map_err(NotebookError::ShadertoyImport)?;
+
+    let notebook_id = create_from_archive(&state, auth_user.user_id, import.archive).await?;
+    let Json(notebook) =
+        get_notebook(State(state), Some(auth_user), visitor, Path(notebook_id)).await?;
+
+    Ok(Json(ShadertoyImportResponse {
+        notebook,
+        warnings: import.warnings,
+    }))
+}
+
+/// Creates a notebook owned by the user from an archive, returning its id
+async fn create_from_archive(
+    state: &AppState,
+    user_id: i64,
+    archive: NotebookArchive,
+) -> Result<i64> {
+    let archive_ids = ArchiveIds {
+        shaders: archive.shaders.iter().map(|s| s.id).collect(),
+        resources: archive.resources.iter().map(|r| r.id).collect(),
+    };
+    let content = serde_json::to_value(&archive.content)
+        .map_err(|err| NotebookError::InvalidArchive(err.to_string()))?;
+
+    let notebook = state
+        .services
+        .notebook
+        .import_notebook(
+            user_id,
+            CreateNotebook {
+                title: archive.title,
+                description: archive.description,
+                content,
+                resources: archive
+                    .resources
+                    .into_iter()
+                    .map(|r| CreateResource {
+                        notebook_id: 0,
+                        name: r.name,
+                        resource_type: r.resource_type,
+                        data: r.data,
+                        metadata: r.metadata,
+                    })
+                    .collect(),
+                shaders: archive
+                    .shaders
+                    .into_iter()
+                    .map(|s| CreateShader {
+                        notebook_id: 0,
+                        name: s.name,
+                        shader_type: s.shader_type,
+                        code: s.code,
+                    })
+                    .collect(),
+                tags: archive.tags,
+                preview: None,
+                visibility: archive.visibility,
+            },
+            archive_ids,
+        )
+        .await?;
+
+    Ok(notebook.id)
+}
+
 #[utoipa::path(
     patch,
     path = "/notebooks/{id}",
@@ -365,7 +746,7 @@ async fn update_notebook(
             notebook_id: r.notebook_id,
             name: r.name,
             resource_type: r.resource_type,
-            data: r.data,
+            data: BlobRef::new(r.data_hash),
             metadata: r.metadata,
             created_at: r.created_at.to_string(),
         })
@@ -397,7 +778,7 @@ async fn update_notebook(
         author: UserPreviewResponse {
             id: user.id,
             username: user.username,
-            avatar: Some(user.avatar),
+            avatar: user.avatar_hash.map(BlobRef::new),
         },
         stats: NotebookStats {
             view_count: stats.view_count,
@@ -548,31 +929,23 @@ async fn list_versions(
 )]
 async fn list_comments(
     State(state): State<AppState>,
+    auth_user: Option<AuthUser>,
     Path(id): Path<i64>,
     Query(pagination): Query<PaginationParams>,
 ) -> Result<Json<NotebookCommentListResponse>> {
     let page = pagination.page.unwrap_or(1);
     let per_page = pagination.per_page.unwrap_or(10);
+    let user_id = auth_user.map(|user| user.user_id).unwrap_or_default();
 
     let (comment_data, total) = state
         .services
         .notebook
-        .list_comments(id, page, per_page)
+        .list_comments(user_id, id, page, per_page)
         .await?;
 
     let mut comments = Vec::new();
     for comment in comment_data {
-        let author = state.services.user.get_user(comment.user_id).await?;
-        comments.push(NotebookCommentResponse {
-            id: comment.id,
-            notebook_id: comment.notebook_id,
-            user_id: comment.user_id,
-            content: comment.content,
-            created_at: comment.created_at.to_string(),
-            updated_at: comment.updated_at.to_string(),
-            author: author.username,
-            author_avatar: Some(author.avatar),
-        });
+        comments.push(comment_response(&state, comment).await?);
     }
 
     Ok(Json(NotebookCommentListResponse { comments, total }))
@@ -598,24 +971,63 @@ async fn create_comment(
     Path(id): Path<i64>,
     Json(payload): Json<CreateNotebookCommentRequest>,
 ) -> Result<Json<NotebookCommentResponse>> {
+    let (anchor_cell_id, anchor_start_line, anchor_end_line) = match payload.anchor {
+        Some(anchor) => (
+            Some(anchor.cell_id),
+            anchor.start_line.map(i64::from),
+            anchor.end_line.map(i64::from),
+        ),
+        None => (None, None, None),
+    };
+
     let comment = state
         .services
         .notebook
-        .create_comment(auth_user.user_id, id, payload.content)
+        .create_comment(
+            auth_user.user_id,
+            id,
+            CreateNotebookComment {
+                content: payload.content,
+                parent_id: payload.parent_id,
+                anchor_cell_id,
+                anchor_start_line,
+                anchor_end_line,
+            },
+        )
         .await?;
 
-    let user = state.services.user.get_user(auth_user.user_id).await?;
+    Ok(Json(comment_response(&state, comment).await?))
+}
 
-    Ok(Json(NotebookCommentResponse {
-        id: comment.id,
-        notebook_id: comment.notebook_id,
-        user_id: comment.user_id,
-        content: comment.content,
-        created_at: comment.created_at.to_string(),
-        updated_at: comment.updated_at.to_string(),
-        author: user.username,
-        author_avatar: Some(user.avatar),
-    }))
+#[utoipa::path(
+    patch,
+    path = "/notebooks/{id}/comments/{comment_id}",
+    tag = "notebook",
+    params(
+        ("id" = i64, Path, description = "Notebook ID"),
+        ("comment_id" = i64, Path, description = "Comment ID")
+    ),
+    request_body = UpdateNotebookCommentRequest,
+    responses(
+        (status = 200, description = "Successfully edited comment", body = NotebookCommentResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Comment written by another user"),
+        (status = 404, description = "Comment not found")
+    )
+)]
+async fn update_comment(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path((id, comment_id)): Path<(i64, i64)>,
+    Json(payload): Json<UpdateNotebookCommentRequest>,
+) -> Result<Json<NotebookCommentResponse>> {
+    let comment = state
+        .services
+        .notebook
+        .update_comment(auth_user.user_id, id, comment_id, payload.content)
+        .await?;
+
+    Ok(Json(comment_response(&state, comment).await?))
 }
 
 #[utoipa::path(
@@ -643,3 +1055,28 @@ async fn delete_comment(
         .delete_comment(auth_user.user_id, id, comment_id)
         .await
 }
+
+async fn comment_response(
+    state: &AppState,
+    comment: NotebookComment,
+) -> Result<NotebookCommentResponse> {
+    let author = state.services.user.get_user(comment.user_id).await?;
+
+    Ok(NotebookCommentResponse {
+        id: comment.id,
+        notebook_id: comment.notebook_id,
+        user_id: comment.user_id,
+        content: comment.content,
+        created_at: comment.created_at.to_string(),
+        updated_at: comment.updated_at.to_string(),
+        author: author.username,
+        author_avatar: author.avatar_hash.map(BlobRef::new),
+        parent_id: comment.parent_id,
+        edited_at: comment.edited_at.map(|edited_at| edited_at.to_string()),
+        anchor: comment.anchor_cell_id.map(|cell_id| CommentAnchor {
+            cell_id,
+            start_line: comment.anchor_start_line.map(|line| line as u32),
+            end_line: comment.anchor_end_line.map(|line| line as u32),
+        }),
+    })
+}
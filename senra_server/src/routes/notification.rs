@@ -0,0 +1,106 @@
+use axum::Router;
+use axum::extract::{Query, State};
+use axum::routing::{get, post};
+use senra_api::*;
+use serde::Deserialize;
+
+use crate::errors::{AppError, Result};
+use crate::middleware::{AuthUser, Json};
+use crate::models::Notification;
+use crate::state::AppState;
+
+#[derive(Debug, Deserialize, utoipa::IntoParams)]
+struct NotificationParams {
+    page: Option<i64>,
+    per_page: Option<i64>,
+    /// Only list unread notifications
+    unread: Option<bool>,
+}
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/notifications", get(list_notifications))
+        .route("/notifications/read", post(mark_notifications_read))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    get,
+    path = "/notifications",
+    tag = "notification",
+    params(NotificationParams),
+    responses(
+        (status = 200, description = "Successfully retrieved notifications", body = NotificationListResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn list_notifications(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(params): Query<NotificationParams>,
+) -> Result<Json<NotificationListResponse>> {
+    let page = params.page.unwrap_or(1);
+    let per_page = params.per_page.unwrap_or(20);
+
+    let (notifications, total, unread) = state
+        .services
+        .notification
+        .list_notifications(
+            auth_user.user_id,
+            params.unread.unwrap_or(false),
+            page,
+            per_page,
+        )
+        .await?;
+
+    Ok(Json(NotificationListResponse {
+        notifications: notifications
+            .into_iter()
+            .map(notification_response)
+            .collect::<Result<_>>()?,
+        total,
+        unread,
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/notifications/read",
+    tag = "notification",
+    request_body = MarkNotificationsReadRequest,
+    responses(
+        (status = 200, description = "Successfully marked notifications as read", body = UnreadNotificationsResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn mark_notifications_read(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(payload): Json<MarkNotificationsReadRequest>,
+) -> Result<Json<UnreadNotificationsResponse>> {
+    let unread = state
+        .services
+        .notification
+        .mark_read(auth_user.user_id, payload.ids)
+        .await?;
+
+    Ok(Json(UnreadNotificationsResponse { unread }))
+}
+
+pub(super) fn notification_response(notification: Notification) -> Result<NotificationResponse> {
+    let kind = NotificationKind::from_key(&notification.kind).ok_or_else(|| {
+        AppError::InternalError(format!("Unknown notification kind `{}`", notification.kind))
+    })?;
+
+    Ok(NotificationResponse {
+        id: notification.id,
+        kind,
+        actor_id: notification.actor_id,
+        actor: notification.actor,
+        notebook_id: notification.notebook_id,
+        notebook_title: notification.notebook_title,
+        comment_id: notification.comment_id,
+        read: notification.read_at.is_some(),
+        created_at: notification.created_at.to_string(),
+    })
+}
@@ -0,0 +1,163 @@
+use axum::Router;
+use axum::extract::{Path, State};
+use axum::routing::{get, post};
+use senra_api::*;
+
+use crate::errors::Result;
+use crate::middleware::{AuthUser, Json};
+use crate::models::{PublishPackage, ShaderPackageVersion};
+use crate::state::AppState;
+
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/packages", post(publish_package))
+        .route("/packages/{name}", get(get_package))
+        .route(
+            "/packages/{name}/versions/{version}",
+            get(get_package_version),
+        )
+        .route("/packages/{name}/used-by", get(get_package_used_by))
+        .with_state(state)
+}
+
+#[utoipa::path(
+    post,
+    path = "/packages",
+    tag = "package",
+    request_body = PublishPackageRequest,
+    responses(
+        (status = 200, description = "Successfully published package version", body = PackageVersionResponse),
+        (status = 400, description = "Invalid name, version or shader code"),
+        (status = 401, description = "Unauthorized"),
+        (status = 403, description = "Package owned by another user"),
+        (status = 404, description = "Shader not found"),
+        (status = 409, description = "Version already published")
+    )
+)]
+async fn publish_package(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Json(payload): Json<PublishPackageRequest>,
+) -> Result<Json<PackageVersionResponse>> {
+    let package_version = state
+        .services
+        .shader
+        .publish_package(
+            auth_user.user_id,
+            PublishPackage {
+                shader_id: payload.shader_id,
+                name: payload.name,
+                version: payload.version,
+                description: payload.description,
+            },
+        )
+        .await?;
+
+    Ok(Json(package_version_response(package_version)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/packages/{name}",
+    tag = "package",
+    params(
+        ("name" = String, Path, description = "Package name")
+    ),
+    responses(
+        (status = 200, description = "Successfully retrieved package", body = PackageResponse),
+        (status = 404, description = "Package not found")
+    )
+)]
+async fn get_package(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<PackageResponse>> {
+    let (package, versions) = state.services.shader.get_package(&name).await?;
+
+    Ok(Json(PackageResponse {
+        id: package.id,
+        user_id: package.user_id,
+        name: package.name,
+        description: package.description,
+        versions: versions.into_iter().map(|v| v.version).collect(),
+        created_at: package.created_at.to_string(),
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/packages/{name}/versions/{version}",
+    tag = "package",
+    params(
+        ("name" = String, Path, description = "Package name"),
+        ("version" = String, Path, description = "Version, `MAJOR.MINOR.PATCH`")
+    ),
+    responses(
+        (status = 200, description = "Package version importable as `#import \"{name}@{version}\"`", body = PackageVersionResponse),
+        (status = 404, description = "Package version not found")
+    )
+)]
+async fn get_package_version(
+    State(state): State<AppState>,
+    Path((name, version)): Path<(String, String)>,
+) -> Result<Json<PackageVersionResponse>> {
+    let package_version = state
+        .services
+        .shader
+        .get_package_version(&name, &version)
+        .await?;
+
+    Ok(Json(package_version_response(package_version)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/packages/{name}/used-by",
+    tag = "package",
+    params(
+        ("name" = String, Path, description = "Package name")
+    ),
+    responses(
+        (status = 200, description = "Public notebooks and package versions depending on the package", body = PackageUsedByResponse),
+        (status = 404, description = "Package not found")
+    )
+)]
+async fn get_package_used_by(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<PackageUsedByResponse>> {
+    let (notebooks, packages) = state.services.shader.package_used_by(&name).await?;
+
+    Ok(Json(PackageUsedByResponse {
+        notebooks: notebooks
+            .into_iter()
+            .map(|n| PackageNotebookDependent {
+                notebook_id: n.notebook_id,
+                title: n.title,
+                version: n.version,
+            })
+            .collect(),
+        packages: packages
+            .into_iter()
+            .map(|p| PackageVersionDependent {
+                name: p.name,
+                version: p.version,
+                dependency_version: p.dependency_version,
+            })
+            .collect(),
+    }))
+}
+
+fn package_version_response(package_version: ShaderPackageVersion) -> PackageVersionResponse {
+    PackageVersionResponse {
+        dependencies: package_imports(&package_version.code)
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+        name: package_version.name,
+        version: package_version.version,
+        shader_type: package_version.shader_type,
+        code: package_version.code,
+        created_at: package_version.created_at.to_string(),
+    }
+}
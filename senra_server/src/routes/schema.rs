@@ -0,0 +1,19 @@
+use axum::routing::get;
+use axum::{Json, Router};
+use serde_json::Value;
+
+pub fn router() -> Router {
+    Router::new().route("/schema/notebook.json", get(notebook_schema))
+}
+
+#[utoipa::path(
+    get,
+    path = "/schema/notebook.json",
+    tag = "schema",
+    responses(
+        (status = 200, description = "JSON Schema of the notebook content format", body = Object)
+    )
+)]
+async fn notebook_schema() -> Json<Value> {
+    Json(senra_api::notebook_content_schema())
+}
@@ -1,12 +1,12 @@
-use axum::extract::{Path, Query, State};
-use axum::routing::get;
-use axum::{Json, Router};
+use axum::Router;
+use axum::extract::{DefaultBodyLimit, Path, Query, State};
+use axum::routing::{delete, get, post};
 use senra_api::*;
 use serde::Deserialize;
 
 use crate::errors::Result;
-use crate::middleware::AuthUser;
-use crate::models::EditUser;
+use crate::middleware::{AuthUser, Json};
+use crate::models::{EditUser, User};
 use crate::state::AppState;
 
 #[derive(Debug, Deserialize, utoipa::IntoParams)]
@@ -17,8 +17,21 @@ pub struct PaginationParams {
 
 pub fn router(state: AppState) -> Router {
     Router::new()
-        .route("/user", get(get_self).patch(edit_user))
+        .route(
+            "/user",
+            get(get_self)
+                .patch(edit_user)
+                // Avatars are sent as base64, four characters per three bytes
+                .layer(DefaultBodyLimit::max(
+                    state.config.avatar.max_bytes.div_ceil(3) * 4 + 64 * 1024,
+                )),
+        )
+        .route("/user/avatar", delete(reset_avatar))
         .route("/user/{id}", get(get_user))
+        .route("/user/{id}/follow", post(follow_user).delete(unfollow_user))
+        .route("/user/trash", get(list_trash))
+        .route("/user/trash/{id}", delete(purge_notebook))
+        .route("/user/trash/{id}/restore", post(restore_notebook))
         .with_state(state)
 }
 
@@ -43,7 +56,7 @@ async fn get_self(
 
     let user = state.services.user.get_user(auth_user.user_id).await?;
 
-    let notebook_service = state.services.notebook;
+    let notebook_service = &state.services.notebook;
     let (notebook_data, total) = notebook_service
         .list_notebooks_by_user(user.id, page, per_page)
         .await?;
@@ -68,7 +81,7 @@ async fn get_self(
             author: UserPreviewResponse {
                 id: user.id,
                 username: user.username.clone(),
-                avatar: Some(user.avatar.clone()),
+                avatar: user.avatar_hash.clone().map(BlobRef::new),
             },
             stats: NotebookStats {
                 view_count: stats.view_count,
@@ -76,16 +89,22 @@ async fn get_self(
                 comment_count: stats.comment_count,
                 is_liked,
             },
-            preview: notebook.preview,
+            preview: notebook.preview_hash.map(BlobRef::new),
         });
     }
 
+    let (follower_count, following_count) = state.services.user.follow_counts(user.id).await?;
+
     Ok(Json(UserResponse {
         id: user.id,
         username: user.username,
-        avatar: Some(user.avatar),
+        avatar: user.avatar_hash.map(BlobRef::new),
+        avatar_variants: avatar_variants(&state, user.id).await?,
         created_at: user.created_at.to_string(),
         notebooks: NotebookListResponse { notebooks, total },
+        follower_count,
+        following_count,
+        is_following: false,
     }))
 }
 
@@ -112,7 +131,7 @@ async fn get_user(
 
     let user = state.services.user.get_user(id).await?;
 
-    let notebook_service = state.services.notebook;
+    let notebook_service = &state.services.notebook;
     let (notebook_data, total) = notebook_service
         .list_notebooks_by_user(id, page, per_page)
         .await?;
@@ -142,7 +161,7 @@ async fn get_user(
             author: UserPreviewResponse {
                 id: user.id,
                 username: user.username.clone(),
-                avatar: Some(user.avatar.clone()),
+                avatar: user.avatar_hash.clone().map(BlobRef::new),
             },
             stats: NotebookStats {
                 view_count: stats.view_count,
@@ -150,16 +169,26 @@ async fn get_user(
                 comment_count: stats.comment_count,
                 is_liked,
             },
-            preview: notebook.preview,
+            preview: notebook.preview_hash.map(BlobRef::new),
         });
     }
 
+    let (follower_count, following_count) = state.services.user.follow_counts(user.id).await?;
+    let is_following = match auth_user.as_ref().map(|auth| auth.user_id) {
+        Some(user_id) => state.services.user.is_following(user_id, user.id).await?,
+        None => false,
+    };
+
     Ok(Json(UserResponse {
         id: user.id,
         username: user.username,
-        avatar: Some(user.avatar),
+        avatar: user.avatar_hash.map(BlobRef::new),
+        avatar_variants: avatar_variants(&state, user.id).await?,
         created_at: user.created_at.to_string(),
         notebooks: NotebookListResponse { notebooks, total },
+        follower_count,
+        following_count,
+        is_following,
     }))
 }
 
@@ -171,7 +200,8 @@ async fn get_user(
     responses(
         (status = 200, description = "Successfully updated user information", body = UserInfoResponse),
         (status = 401, description = "Unauthorized"),
-        (status = 400, description = "Invalid request data")
+        (status = 400, description = "Invalid request data or avatar"),
+        (status = 413, description = "Avatar too large")
     )
 )]
 async fn edit_user(
@@ -193,10 +223,227 @@ async fn edit_user(
         )
         .await?;
 
-    Ok(Json(UserInfoResponse {
+    Ok(Json(user_info_response(&state, user).await?))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user/avatar",
+    tag = "user",
+    responses(
+        (status = 200, description = "Successfully reset the avatar to the default one", body = UserInfoResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn reset_avatar(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+) -> Result<Json<UserInfoResponse>> {
+    let user = state.services.user.reset_avatar(auth_user.user_id).await?;
+
+    Ok(Json(user_info_response(&state, user).await?))
+}
+
+pub(super) async fn user_info_response(state: &AppState, user: User) -> Result<UserInfoResponse> {
+    let (follower_count, following_count) = state.services.user.follow_counts(user.id).await?;
+    let avatar_variants = avatar_variants(state, user.id).await?;
+
+    Ok(UserInfoResponse {
         id: user.id,
         username: user.username,
         email: user.email,
-        avatar: user.avatar,
+        avatar: user.avatar_hash.map(BlobRef::new),
+        avatar_variants,
+        follower_count,
+        following_count,
+    })
+}
+
+async fn avatar_variants(state: &AppState, user_id: i64) -> Result<Vec<AvatarVariant>> {
+    let variants = state.services.user.avatar_variants(user_id).await?;
+
+    Ok(variants
+        .into_iter()
+        .map(|variant| AvatarVariant {
+            size: variant.size as u32,
+            blob: BlobRef::new(variant.hash),
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/{id}/follow",
+    tag = "user",
+    params(
+        ("id" = i64, Path, description = "ID of the user to follow")
+    ),
+    responses(
+        (status = 200, description = "Successfully followed user", body = FollowResponse),
+        (status = 400, description = "Users cannot follow themselves"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User not found")
+    )
+)]
+async fn follow_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Json<FollowResponse>> {
+    state
+        .services
+        .user
+        .follow_user(auth_user.user_id, id)
+        .await?;
+    follow_response(&state, auth_user.user_id, id).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user/{id}/follow",
+    tag = "user",
+    params(
+        ("id" = i64, Path, description = "ID of the user to unfollow")
+    ),
+    responses(
+        (status = 200, description = "Successfully unfollowed user", body = FollowResponse),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "User not found")
+    )
+)]
+async fn unfollow_user(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Json<FollowResponse>> {
+    state
+        .services
+        .user
+        .unfollow_user(auth_user.user_id, id)
+        .await?;
+    follow_response(&state, auth_user.user_id, id).await
+}
+
+async fn follow_response(
+    state: &AppState,
+    follower_id: i64,
+    followee_id: i64,
+) -> Result<Json<FollowResponse>> {
+    let is_following = state
+        .services
+        .user
+        .is_following(follower_id, followee_id)
+        .await?;
+    let (follower_count, _) = state.services.user.follow_counts(followee_id).await?;
+
+    Ok(Json(FollowResponse {
+        user_id: followee_id,
+        is_following,
+        follower_count,
     }))
 }
+
+#[utoipa::path(
+    get,
+    path = "/user/trash",
+    tag = "user",
+    params(PaginationParams),
+    responses(
+        (status = 200, description = "Successfully retrieved deleted notebooks", body = TrashListResponse),
+        (status = 401, description = "Unauthorized")
+    )
+)]
+async fn list_trash(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Query(pagination): Query<PaginationParams>,
+) -> Result<Json<TrashListResponse>> {
+    let page = pagination.page.unwrap_or(1);
+    let per_page = pagination.per_page.unwrap_or(10);
+
+    let notebook_service = &state.services.notebook;
+    let (notebook_data, total) = notebook_service
+        .list_trash(auth_user.user_id, page, per_page)
+        .await?;
+    let retention = time::Duration::days(state.config.trash.retention_days);
+
+    let mut notebooks = Vec::new();
+    for notebook in notebook_data {
+        let tags = notebook_service.get_notebook_tags(notebook.id).await?;
+        let deleted_at = notebook.deleted_at.unwrap_or(notebook.updated_at);
+
+        notebooks.push(TrashedNotebookResponse {
+            inner: NotebookInfo {
+                id: notebook.id,
+                title: notebook.title,
+                description: notebook.description,
+                tags: tags.into_iter().map(|tag| tag.tag).collect(),
+                created_at: notebook.created_at.to_string(),
+                updated_at: notebook.updated_at.to_string(),
+            },
+            deleted_at: deleted_at.to_string(),
+            purge_at: (deleted_at + retention).to_string(),
+        });
+    }
+
+    Ok(Json(TrashListResponse { notebooks, total }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/user/trash/{id}/restore",
+    tag = "user",
+    params(
+        ("id" = i64, Path, description = "ID of the deleted notebook")
+    ),
+    responses(
+        (status = 200, description = "Successfully restored notebook", body = NotebookInfo),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Notebook not in the trash")
+    )
+)]
+async fn restore_notebook(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<Json<NotebookInfo>> {
+    let notebook_service = &state.services.notebook;
+    let notebook = notebook_service
+        .restore_notebook(auth_user.user_id, id)
+        .await?;
+    let tags = notebook_service.get_notebook_tags(notebook.id).await?;
+
+    Ok(Json(NotebookInfo {
+        id: notebook.id,
+        title: notebook.title,
+        description: notebook.description,
+        tags: tags.into_iter().map(|tag| tag.tag).collect(),
+        created_at: notebook.created_at.to_string(),
+        updated_at: notebook.updated_at.to_string(),
+    }))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/user/trash/{id}",
+    tag = "user",
+    params(
+        ("id" = i64, Path, description = "ID of the deleted notebook")
+    ),
+    responses(
+        (status = 200, description = "Successfully purged notebook"),
+        (status = 401, description = "Unauthorized"),
+        (status = 404, description = "Notebook not in the trash")
+    )
+)]
+async fn purge_notebook(
+    State(state): State<AppState>,
+    auth_user: AuthUser,
+    Path(id): Path<i64>,
+) -> Result<()> {
+    state
+        .services
+        .notebook
+        .purge_notebook(auth_user.user_id, id)
+        .await
+}
@@ -1,10 +1,14 @@
 use axum::Router;
+use axum::extract::ws::{Message, WebSocket};
 use axum::extract::{Query, State, WebSocketUpgrade};
 use axum::response::IntoResponse;
 use axum::routing::get;
+use senra_api::Response;
 use serde::Deserialize;
-use tracing::{debug, info};
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{debug, info, warn};
 
+use super::notification::notification_response;
 use crate::errors::Result;
 use crate::state::AppState;
 
@@ -30,12 +34,46 @@ async fn ws_handler(
     Ok(ws.on_upgrade(move |socket| handle_socket(socket, state, user_id)))
 }
 
-async fn handle_socket(mut socket: axum::extract::ws::WebSocket, state: AppState, user_id: i64) {
-    while let Some(Ok(msg)) = socket.recv().await {
-        if let axum::extract::ws::Message::Text(text) = msg {
-            debug!("Received WebSocket message: {}", text);
+/// Pushes the notifications of the user as `Response::Notification` until the socket closes
+async fn handle_socket(mut socket: WebSocket, state: AppState, user_id: i64) {
+    let mut notifications = state.services.notification.subscribe();
+    state.metrics.websocket_sessions.inc();
+
+    loop {
+        tokio::select! {
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    debug!("Received WebSocket message: {}", text);
+                }
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => break,
+            },
+            notification = notifications.recv() => match notification {
+                Ok(notification) if notification.user_id == user_id => {
+                    let message = notification_response(notification)
+                        .map(Response::Notification)
+                        .map_err(|err| err.to_string())
+                        .and_then(|response| {
+                            serde_json::to_string(&response).map_err(|err| err.to_string())
+                        });
+                    match message {
+                        Ok(message) => {
+                            if socket.send(Message::Text(message.into())).await.is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => warn!("Failed to push notification: {}", err),
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("WebSocket of user {} missed {} notifications", user_id, skipped);
+                }
+                Err(RecvError::Closed) => break,
+            },
         }
     }
 
+    state.metrics.websocket_sessions.dec();
     info!("WebSocket connection closed for user {}", user_id);
 }
@@ -0,0 +1,205 @@
+use sqlx::{Executor, Sqlite, SqlitePool};
+use time::{Duration, OffsetDateTime};
+
+use crate::errors::{NotebookError, Result};
+use crate::models::*;
+
+/// Activity counted per notebook and day in `notebook_daily_stats`
+#[derive(Debug, Clone, Copy)]
+pub(super) enum DailyActivity {
+    View,
+    Like,
+    Comment,
+}
+
+impl DailyActivity {
+    fn column(self) -> &'static str {
+        match self {
+            DailyActivity::View => "view_count",
+            DailyActivity::Like => "like_count",
+            DailyActivity::Comment => "comment_count",
+        }
+    }
+}
+
+/// Adds one to today's count of an activity on a notebook
+pub(super) async fn record_daily_activity<'e, E>(
+    executor: E,
+    notebook_id: i64,
+    activity: DailyActivity,
+) -> Result<()>
+where
+    E: Executor<'e, Database = Sqlite>,
+{
+    let column = activity.column();
+    let query = format!(
+        r#"
+        INSERT INTO notebook_daily_stats (notebook_id, day, {column})
+        VALUES ($1, date('now'), 1)
+        ON CONFLICT(notebook_id, day) DO UPDATE SET {column} = {column} + 1
+        "#
+    );
+    sqlx::query(&query)
+        .bind(notebook_id)
+        .execute(executor)
+        .await?;
+
+    Ok(())
+}
+
+#[derive(Clone)]
+pub struct AnalyticsService {
+    pool: SqlitePool,
+    /// Seconds during which further views by the same viewer are not counted
+    view_window: u64,
+}
+
+impl AnalyticsService {
+    pub fn new(pool: &SqlitePool, view_window: u64) -> Self {
+        Self {
+            pool: pool.clone(),
+            view_window,
+        }
+    }
+
+    /// Counts a view of a notebook unless the same viewer was counted within the view window,
+    /// returning whether the view was counted
+    pub async fn record_view(
+        &self,
+        notebook_id: i64,
+        viewer: &str,
+        referrer: Option<&str>,
+    ) -> Result<bool> {
+        let mut tx = self.pool.begin().await?;
+
+        // Only inserts, or refreshes a log entry older than the window
+        let result = sqlx::query(
+            r#"
+            INSERT INTO notebook_view_log (notebook_id, viewer)
+            VALUES ($1, $2)
+            ON CONFLICT(notebook_id, viewer) DO UPDATE SET viewed_at = CURRENT_TIMESTAMP
+            WHERE datetime(notebook_view_log.viewed_at) <= datetime('now', '-' || $3 || ' seconds')
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(viewer)
+        .bind(self.view_window as i64)
+        .execute(&mut *tx)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        sqlx::query(
+            r#"
+            UPDATE notebook_stats
+            SET view_count = view_count + 1
+            WHERE notebook_id = $1
+            "#,
+        )
+        .bind(notebook_id)
+        .execute(&mut *tx)
+        .await?;
+
+        record_daily_activity(&mut *tx, notebook_id, DailyActivity::View).await?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO notebook_daily_referrers (notebook_id, day, referrer, view_count)
+            VALUES ($1, date('now'), $2, 1)
+            ON CONFLICT(notebook_id, day, referrer) DO UPDATE SET view_count = view_count + 1
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(referrer.unwrap_or_default())
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+        Ok(true)
+    }
+
+    /// Forgets the views that can no longer prevent another view from being counted
+    pub async fn prune_view_log(&self) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM notebook_view_log
+            WHERE datetime(viewed_at) <= datetime('now', '-' || $1 || ' seconds')
+            "#,
+        )
+        .bind(self.view_window as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Daily stats of the last `days` days of a notebook, oldest first and including the days
+    /// without activity, along with its referrers over the same period
+    pub async fn notebook_analytics(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        days: i64,
+    ) -> Result<(Vec<NotebookDailyStats>, Vec<NotebookReferrer>)> {
+        let owner_id: i64 = sqlx::query_scalar(
+            "SELECT user_id FROM notebooks WHERE id = $1 AND deleted_at IS NULL",
+        )
+        .bind(notebook_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(NotebookError::NotFound)?;
+
+        if owner_id != user_id {
+            Err(NotebookError::PermissionDenied)?;
+        }
+
+        let today = OffsetDateTime::now_utc().date();
+        let first_day = today - Duration::days(days - 1);
+
+        let recorded: Vec<NotebookDailyStats> = sqlx::query_as(
+            r#"
+            SELECT day, view_count, like_count, comment_count
+            FROM notebook_daily_stats
+            WHERE notebook_id = $1 AND day >= $2
+            ORDER BY day
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(first_day)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut recorded = recorded.into_iter().peekable();
+        let daily_stats = (0..days)
+            .map(|offset| {
+                let day = first_day + Duration::days(offset);
+                recorded
+                    .next_if(|stats| stats.day == day)
+                    .unwrap_or(NotebookDailyStats {
+                        day,
+                        view_count: 0,
+                        like_count: 0,
+                        comment_count: 0,
+                    })
+            })
+            .collect();
+
+        let referrers = sqlx::query_as(
+            r#"
+            SELECT referrer, SUM(view_count) AS view_count
+            FROM notebook_daily_referrers
+            WHERE notebook_id = $1 AND day >= $2
+            GROUP BY referrer
+            ORDER BY view_count DESC, referrer
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(first_day)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((daily_stats, referrers))
+    }
+}
@@ -6,34 +6,61 @@ use serde::{Deserialize, Serialize};
 use sqlx::SqlitePool;
 use time::OffsetDateTime;
 
-use crate::errors::{AppError, AuthError, Result};
+use crate::errors::{AppError, AuthError, RateLimitError, Result};
 use crate::models::{LoginUser, User};
+use crate::rate_limit::LoginLockout;
+use crate::services::TwoFactorService;
 
 const TOKEN_EXPIRATION: i64 = 3600 * 24; // 24 hours
 const REFRESH_THRESHOLD: i64 = 3600; // 1 hour
+const CHALLENGE_EXPIRATION: i64 = 300; // 5 minutes
+const CHALLENGE_SCOPE: &str = "2fa";
 
 #[derive(Debug, Serialize, Deserialize)]
 struct Claims {
     sub: i64,
     exp: i64,
     iat: i64,
+    /// Set on tokens only good for a step of the login, never on session tokens
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scope: Option<String>,
+}
+
+/// Result of checking the password of a user
+pub enum LoginOutcome {
+    Authenticated(User, String),
+    /// The user has a second factor, to be answered along with a challenge token before it
+    /// expires
+    Challenge {
+        challenge: String,
+        expires_in: u64,
+    },
 }
 
 #[derive(Clone)]
 pub struct AuthService {
     pool: SqlitePool,
     jwt_secret: Arc<str>,
+    lockout: LoginLockout,
+    two_factor: TwoFactorService,
 }
 
 impl AuthService {
-    pub fn new(pool: &SqlitePool, jwt_secret: &str) -> Self {
+    pub fn new(
+        pool: &SqlitePool,
+        jwt_secret: &str,
+        lockout: LoginLockout,
+        two_factor: &TwoFactorService,
+    ) -> Self {
         Self {
             pool: pool.clone(),
             jwt_secret: Arc::from(jwt_secret),
+            lockout,
+            two_factor: two_factor.clone(),
         }
     }
 
-    pub async fn login(&self, login_user: LoginUser) -> Result<(User, String)> {
+    pub async fn login(&self, login_user: LoginUser) -> Result<LoginOutcome> {
         if login_user.username.is_empty() {
             return Err(AuthError::InvalidUsername.into());
         }
@@ -42,25 +69,86 @@ impl AuthService {
             return Err(AuthError::InvalidPassword.into());
         }
 
+        // Locked accounts are refused even with the right password, so guessing goes no faster
+        if let Some(seconds) = self.lockout.locked_for(&login_user.username) {
+            return Err(RateLimitError::AccountLocked(seconds).into());
+        }
+
         let user: Option<User> = sqlx::query_as(
             r#"
-            SELECT id, username, email, password, avatar, created_at, updated_at
+            SELECT id, username, email, password, avatar_hash, created_at, updated_at
             FROM users
             WHERE username = $1
             "#,
         )
-        .bind(login_user.username)
+        .bind(&login_user.username)
         .fetch_optional(&self.pool)
         .await?;
 
-        let user = user.ok_or(AuthError::InvalidCredentials)?;
-        let password_hash = user.password.clone().ok_or(AuthError::InvalidCredentials)?;
+        let verified = match user.as_ref().and_then(|user| user.password.as_ref()) {
+            Some(password_hash) => verify(&login_user.password, password_hash)
+                .map_err(|_| AppError::InternalError("Failed to verify password".to_string()))?,
+            None => false,
+        };
+        // Unknown usernames count too, so lockouts don't tell which accounts exist
+        let Some(user) = user.filter(|_| verified) else {
+            return Err(match self.lockout.record_failure(&login_user.username) {
+                Some(seconds) => RateLimitError::AccountLocked(seconds).into(),
+                None => AuthError::InvalidCredentials.into(),
+            });
+        };
 
-        if !verify(login_user.password, &password_hash)
-            .map_err(|_| AppError::InternalError("Failed to verify password".to_string()))?
-        {
-            return Err(AuthError::InvalidCredentials.into());
+        // Failures are kept until the second step succeeds, so its codes can't be guessed by
+        // logging in again between attempts
+        if self.two_factor.is_enabled(user.id).await? {
+            return Ok(LoginOutcome::Challenge {
+                challenge: self.generate_challenge(user.id)?,
+                expires_in: CHALLENGE_EXPIRATION as u64,
+            });
         }
+        self.lockout.reset(&login_user.username);
+
+        let token = self.generate_token(user.id).await?;
+
+        Ok(LoginOutcome::Authenticated(user, token))
+    }
+
+    /// Second step of the login of a user with two-factor authentication, answering the
+    /// challenge returned by `login` with a TOTP or recovery code
+    pub async fn login_two_factor(&self, challenge: &str, code: &str) -> Result<(User, String)> {
+        let claims = self
+            .decode_claims(challenge)
+            .ok()
+            .filter(|claims| claims.scope.as_deref() == Some(CHALLENGE_SCOPE))
+            .ok_or(AuthError::InvalidChallenge)?;
+
+        let user: User = sqlx::query_as(
+            r#"
+            SELECT id, username, email, password, avatar_hash, created_at, updated_at
+            FROM users
+            WHERE id = $1
+            "#,
+        )
+        .bind(claims.sub)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(AuthError::InvalidChallenge)?;
+
+        if let Some(seconds) = self.lockout.locked_for(&user.username) {
+            return Err(RateLimitError::AccountLocked(seconds).into());
+        }
+
+        match self.two_factor.verify(user.id, code).await {
+            Ok(()) => {}
+            Err(AppError::AuthError(AuthError::InvalidTwoFactorCode)) => {
+                return Err(match self.lockout.record_failure(&user.username) {
+                    Some(seconds) => RateLimitError::AccountLocked(seconds).into(),
+                    None => AuthError::InvalidTwoFactorCode.into(),
+                });
+            }
+            Err(e) => return Err(e),
+        }
+        self.lockout.reset(&user.username);
 
         let token = self.generate_token(user.id).await?;
 
@@ -68,32 +156,23 @@ impl AuthService {
     }
 
     pub async fn authorize(&self, token: &str) -> Result<i64> {
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.jwt_secret.as_bytes()),
-            &Validation::default(),
-        )
-        .map_err(|_| AuthError::InvalidToken)?;
+        let claims = self.decode_session(token)?;
 
-        Ok(token_data.claims.sub)
+        Ok(claims.sub)
     }
 
     pub async fn refresh_token(&self, token: &str) -> Result<Option<String>> {
-        let token_data = decode::<Claims>(
-            token,
-            &DecodingKey::from_secret(self.jwt_secret.as_bytes()),
-            &Validation::default(),
-        )
-        .map_err(|_| AuthError::InvalidToken)?;
+        let claims = self.decode_session(token)?;
 
         let now = OffsetDateTime::now_utc().unix_timestamp();
-        let expires_in = token_data.claims.exp - now;
+        let expires_in = claims.exp - now;
 
         if expires_in < REFRESH_THRESHOLD {
             let claims = Claims {
-                sub: token_data.claims.sub,
+                sub: claims.sub,
                 exp: now + TOKEN_EXPIRATION,
                 iat: now,
+                scope: None,
             };
             let token = encode(
                 &Header::default(),
@@ -115,6 +194,7 @@ impl AuthService {
             sub: user_id,
             exp: now + TOKEN_EXPIRATION,
             iat: now,
+            scope: None,
         };
         let token = encode(
             &Header::default(),
@@ -125,4 +205,44 @@ impl AuthService {
 
         Ok(token)
     }
+
+    fn generate_challenge(&self, user_id: i64) -> Result<String> {
+        let now = OffsetDateTime::now_utc().unix_timestamp();
+
+        let claims = Claims {
+            sub: user_id,
+            exp: now + CHALLENGE_EXPIRATION,
+            iat: now,
+            scope: Some(CHALLENGE_SCOPE.to_string()),
+        };
+        let token = encode(
+            &Header::default(),
+            &claims,
+            &EncodingKey::from_secret(self.jwt_secret.as_bytes()),
+        )
+        .map_err(|_| AppError::InternalError("Failed to generate token".to_string()))?;
+
+        Ok(token)
+    }
+
+    fn decode_claims(&self, token: &str) -> Result<Claims> {
+        let token_data = decode::<Claims>(
+            token,
+            &DecodingKey::from_secret(self.jwt_secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|_| AuthError::InvalidToken)?;
+
+        Ok(token_data.claims)
+    }
+
+    /// Claims of a session token, refusing the scoped tokens of a login step
+    fn decode_session(&self, token: &str) -> Result<Claims> {
+        let claims = self.decode_claims(token)?;
+        if claims.scope.is_some() {
+            return Err(AuthError::InvalidToken.into());
+        }
+
+        Ok(claims)
+    }
 }
@@ -0,0 +1,170 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use sqlx::SqlitePool;
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::blob::{self, BlobStore};
+use crate::errors::Result;
+
+/// Bytes read at the start of a blob to guess its content type
+const SNIFF_LENGTH: u64 = 32;
+
+#[derive(Clone)]
+pub struct BlobService {
+    pool: SqlitePool,
+    store: BlobStore,
+    /// Blobs found unreferenced by the last sweep, deleted if still so by the next one. Locked
+    /// for the whole sweep so a blob stored meanwhile is never deleted under its new row
+    orphans: Arc<Mutex<HashSet<String>>>,
+}
+
+impl BlobService {
+    pub fn new(pool: &SqlitePool, store: BlobStore) -> Self {
+        Self {
+            pool: pool.clone(),
+            store,
+            orphans: Arc::default(),
+        }
+    }
+
+    pub fn store(&self) -> &BlobStore {
+        &self.store
+    }
+
+    /// Stores a blob, returning its hash. Identical blobs are stored once, so storing one the
+    /// last sweep found unreferenced keeps the next sweep from deleting it
+    pub async fn put(&self, data: Vec<u8>) -> Result<String> {
+        self.orphans.lock().await.remove(&blob::hash(&data));
+        Ok(self.store.put(data).await?)
+    }
+
+    pub async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>> {
+        if !blob::is_valid_hash(hash) {
+            return Ok(None);
+        }
+        Ok(self.store.get(hash).await?)
+    }
+
+    /// Whether a blob is referenced by anything a user can see, `viewer` being 0 when signed
+    /// out. Avatars are seen by everyone, previews and resources only along with their notebook
+    pub async fn is_visible(&self, viewer: i64, hash: &str) -> Result<bool> {
+        let visible = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM users WHERE avatar_hash = $1
+                UNION ALL
+                SELECT 1 FROM user_avatars WHERE hash = $1
+                UNION ALL
+                SELECT 1 FROM notebooks
+                WHERE preview_hash = $1 AND deleted_at IS NULL
+                    AND (visibility = 'public' OR user_id = $2)
+                UNION ALL
+                SELECT 1 FROM resources r
+                JOIN notebooks n ON n.id = r.notebook_id
+                WHERE r.data_hash = $1 AND n.deleted_at IS NULL
+                    AND (n.visibility = 'public' OR n.user_id = $2)
+            )
+            "#,
+        )
+        .bind(hash)
+        .bind(viewer)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(visible)
+    }
+
+    /// Guesses the content type of a stored blob from its first bytes
+    pub async fn content_type(&self, hash: &str, size: u64) -> Result<&'static str> {
+        let head = self
+            .store
+            .read(hash, 0..size.min(SNIFF_LENGTH))
+            .await?
+            .unwrap_or_default();
+
+        Ok(image::guess_format(&head)
+            .map(|format| format.to_mime_type())
+            .unwrap_or("application/octet-stream"))
+    }
+
+    /// Deletes the stored blobs no row references anymore, such as those of purged notebooks or
+    /// replaced resources and avatars, returning how many were deleted. Blobs are stored before
+    /// the rows referencing them, so only blobs already unreferenced at the previous sweep go
+    pub async fn sweep_orphans(&self) -> Result<u64> {
+        let mut orphans = self.orphans.lock().await;
+        let referenced: HashSet<String> = sqlx::query_scalar(
+            r#"
+            SELECT avatar_hash FROM users WHERE avatar_hash IS NOT NULL
+            UNION
+            SELECT hash FROM user_avatars
+            UNION
+            SELECT preview_hash FROM notebooks WHERE preview_hash IS NOT NULL
+            UNION
+            SELECT data_hash FROM resources WHERE data_hash IS NOT NULL
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .collect();
+
+        let previous = std::mem::take(&mut *orphans);
+        let mut deleted = 0;
+        for hash in self.store.list().await? {
+            if referenced.contains(&hash) {
+                continue;
+            }
+            if previous.contains(&hash) {
+                self.store.delete(&hash).await?;
+                deleted += 1;
+            } else {
+                orphans.insert(hash);
+            }
+        }
+
+        Ok(deleted)
+    }
+
+    /// Moves the blobs still stored inline in the database into the blob store, returning how
+    /// many were moved
+    pub async fn migrate_inline_blobs(&self) -> Result<u64> {
+        let mut moved = 0;
+
+        for (table, inline, hash) in [
+            ("resources", "data", "data_hash"),
+            ("notebooks", "preview", "preview_hash"),
+            ("users", "avatar", "avatar_hash"),
+        ] {
+            let rows: Vec<(i64, Vec<u8>)> = sqlx::query_as(&format!(
+                "SELECT id, {inline} FROM {table} WHERE {hash} IS NULL AND {inline} IS NOT NULL"
+            ))
+            .fetch_all(&self.pool)
+            .await?;
+
+            for (id, data) in rows {
+                let blob_hash = self.put(data).await?;
+                sqlx::query(&format!(
+                    "UPDATE {table} SET {hash} = $1, {inline} = $2 WHERE id = $3"
+                ))
+                .bind(blob_hash)
+                // `resources.data` is not nullable
+                .bind(if table == "resources" {
+                    Some(Vec::new())
+                } else {
+                    None
+                })
+                .bind(id)
+                .execute(&self.pool)
+                .await?;
+                moved += 1;
+            }
+        }
+
+        if moved > 0 {
+            info!("Moved {} inline blobs to the blob store", moved);
+        }
+        Ok(moved)
+    }
+}
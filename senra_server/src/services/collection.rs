@@ -0,0 +1,362 @@
+use std::collections::HashSet;
+
+use sqlx::{Sqlite, SqlitePool, Transaction};
+
+use crate::errors::{CollectionError, Result};
+use crate::models::{Collection, CreateCollection, Notebook, UpdateCollection};
+
+/// Columns of a collection along with the number of its notebooks visible to the user bound
+/// to `$2`
+const COLLECTION_COLUMNS: &str = r#"
+    c.id, c.user_id, c.title, c.description, c.visibility, c.created_at, c.updated_at,
+    (
+        SELECT COUNT(*) FROM collection_notebooks cn
+        JOIN notebooks n ON n.id = cn.notebook_id
+        WHERE cn.collection_id = c.id AND n.deleted_at IS NULL
+            AND (n.visibility = 'public' OR n.user_id = $2)
+    ) AS notebook_count
+"#;
+
+#[derive(Clone)]
+pub struct CollectionService {
+    pool: SqlitePool,
+}
+
+impl CollectionService {
+    pub fn new(pool: &SqlitePool) -> Self {
+        Self { pool: pool.clone() }
+    }
+
+    /// Retrieves a collection visible to a user, `viewer` being 0 when signed out
+    pub async fn get_collection(&self, viewer: i64, id: i64) -> Result<Collection> {
+        let collection = sqlx::query_as(&format!(
+            r#"
+            SELECT {COLLECTION_COLUMNS} FROM collections c
+            WHERE c.id = $1 AND (c.visibility = 'public' OR c.user_id = $2)
+            "#
+        ))
+        .bind(id)
+        .bind(viewer)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(collection.ok_or(CollectionError::NotFound)?)
+    }
+
+    /// Lists the collections of a user visible to `viewer`, most recently updated first
+    pub async fn list_user_collections(
+        &self,
+        viewer: i64,
+        user_id: i64,
+        page: i64,
+        per_page: i64,
+    ) -> Result<(Vec<Collection>, i64)> {
+        let offset = (page - 1) * per_page;
+
+        let collections = sqlx::query_as(&format!(
+            r#"
+            SELECT {COLLECTION_COLUMNS} FROM collections c
+            WHERE c.user_id = $1 AND (c.visibility = 'public' OR c.user_id = $2)
+            ORDER BY c.updated_at DESC, c.id DESC
+            LIMIT $3 OFFSET $4
+            "#
+        ))
+        .bind(user_id)
+        .bind(viewer)
+        .bind(per_page)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM collections
+            WHERE user_id = $1 AND (visibility = 'public' OR user_id = $2)
+            "#,
+        )
+        .bind(user_id)
+        .bind(viewer)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((collections, total))
+    }
+
+    /// Notebooks of a collection visible to `viewer`, in the collection order
+    pub async fn collection_notebooks(&self, viewer: i64, id: i64) -> Result<Vec<Notebook>> {
+        let notebooks = sqlx::query_as(
+            r#"
+            SELECT n.* FROM collection_notebooks cn
+            JOIN notebooks n ON n.id = cn.notebook_id
+            WHERE cn.collection_id = $1 AND n.deleted_at IS NULL
+                AND (n.visibility = 'public' OR n.user_id = $2)
+            ORDER BY cn.position
+            "#,
+        )
+        .bind(id)
+        .bind(viewer)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(notebooks)
+    }
+
+    pub async fn create_collection(
+        &self,
+        user_id: i64,
+        create_collection: CreateCollection,
+    ) -> Result<Collection> {
+        check_title(&create_collection.title)?;
+        check_visibility(&create_collection.visibility)?;
+
+        let mut tx = self.pool.begin().await?;
+
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO collections (user_id, title, description, visibility)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(create_collection.title.trim())
+        .bind(create_collection.description)
+        .bind(create_collection.visibility)
+        .fetch_one(&mut *tx)
+        .await?;
+
+        set_entries(&mut tx, user_id, id, &create_collection.notebook_ids).await?;
+        tx.commit().await?;
+
+        self.get_collection(user_id, id).await
+    }
+
+    pub async fn update_collection(
+        &self,
+        user_id: i64,
+        id: i64,
+        update_collection: UpdateCollection,
+    ) -> Result<Collection> {
+        self.owned_collection(user_id, id).await?;
+
+        let mut tx = self.pool.begin().await?;
+        let mut has_changes = false;
+
+        if let Some(title) = &update_collection.title {
+            check_title(title)?;
+            sqlx::query("UPDATE collections SET title = $1 WHERE id = $2")
+                .bind(title.trim())
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            has_changes = true;
+        }
+
+        if let Some(description) = &update_collection.description {
+            sqlx::query("UPDATE collections SET description = $1 WHERE id = $2")
+                .bind(description)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            has_changes = true;
+        }
+
+        if let Some(visibility) = &update_collection.visibility {
+            check_visibility(visibility)?;
+            sqlx::query("UPDATE collections SET visibility = $1 WHERE id = $2")
+                .bind(visibility)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            has_changes = true;
+        }
+
+        if let Some(notebook_ids) = &update_collection.notebook_ids {
+            set_entries(&mut tx, user_id, id, notebook_ids).await?;
+            has_changes = true;
+        }
+
+        if !has_changes {
+            return Err(CollectionError::NoChanges.into());
+        }
+
+        touch(&mut tx, id).await?;
+        tx.commit().await?;
+
+        self.get_collection(user_id, id).await
+    }
+
+    pub async fn delete_collection(&self, user_id: i64, id: i64) -> Result<()> {
+        self.owned_collection(user_id, id).await?;
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM collection_notebooks WHERE collection_id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query("DELETE FROM collections WHERE id = $1")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    /// Adds a notebook visible to the owner at `position` of the collection, at the end when
+    /// unset or past the end
+    pub async fn add_notebook(
+        &self,
+        user_id: i64,
+        id: i64,
+        notebook_id: i64,
+        position: Option<usize>,
+    ) -> Result<Collection> {
+        self.owned_collection(user_id, id).await?;
+
+        let mut tx = self.pool.begin().await?;
+        let mut entries = entries(&mut tx, id).await?;
+        if entries.contains(&notebook_id) {
+            return Err(CollectionError::DuplicateNotebook(notebook_id).into());
+        }
+        let position = position.unwrap_or(entries.len()).min(entries.len());
+        entries.insert(position, notebook_id);
+
+        set_entries(&mut tx, user_id, id, &entries).await?;
+        touch(&mut tx, id).await?;
+        tx.commit().await?;
+
+        self.get_collection(user_id, id).await
+    }
+
+    pub async fn remove_notebook(
+        &self,
+        user_id: i64,
+        id: i64,
+        notebook_id: i64,
+    ) -> Result<Collection> {
+        self.owned_collection(user_id, id).await?;
+
+        let mut tx = self.pool.begin().await?;
+        let mut entries = entries(&mut tx, id).await?;
+        let len = entries.len();
+        entries.retain(|entry| *entry != notebook_id);
+        if entries.len() == len {
+            return Err(CollectionError::NotebookNotFound(notebook_id).into());
+        }
+
+        set_entries(&mut tx, user_id, id, &entries).await?;
+        touch(&mut tx, id).await?;
+        tx.commit().await?;
+
+        self.get_collection(user_id, id).await
+    }
+
+    /// Checks that a collection exists and belongs to a user
+    async fn owned_collection(&self, user_id: i64, id: i64) -> Result<Collection> {
+        let collection = self.get_collection(user_id, id).await?;
+        if collection.user_id != user_id {
+            return Err(CollectionError::PermissionDenied.into());
+        }
+        Ok(collection)
+    }
+}
+
+fn check_title(title: &str) -> Result<()> {
+    if title.trim().is_empty() {
+        return Err(CollectionError::InvalidTitle.into());
+    }
+    Ok(())
+}
+
+fn check_visibility(visibility: &str) -> Result<()> {
+    if !matches!(visibility, "public" | "private") {
+        return Err(CollectionError::InvalidVisibility.into());
+    }
+    Ok(())
+}
+
+async fn touch(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<()> {
+    sqlx::query("UPDATE collections SET updated_at = CURRENT_TIMESTAMP WHERE id = $1")
+        .bind(id)
+        .execute(&mut **tx)
+        .await?;
+    Ok(())
+}
+
+/// Ids of all the notebooks of a collection in order, including those hidden from its owner
+async fn entries(tx: &mut Transaction<'_, Sqlite>, id: i64) -> Result<Vec<i64>> {
+    let entries = sqlx::query_scalar(
+        r#"
+        SELECT notebook_id FROM collection_notebooks
+        WHERE collection_id = $1
+        ORDER BY position
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&mut **tx)
+    .await?;
+
+    Ok(entries)
+}
+
+/// Replaces the notebooks of a collection, notebooks not already in it having to be visible
+/// to `user_id`
+async fn set_entries(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    id: i64,
+    notebook_ids: &[i64],
+) -> Result<()> {
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = notebook_ids.iter().find(|id| !seen.insert(**id)) {
+        return Err(CollectionError::DuplicateNotebook(*duplicate).into());
+    }
+
+    let current: HashSet<i64> = entries(tx, id).await?.into_iter().collect();
+    for notebook_id in notebook_ids.iter().filter(|id| !current.contains(id)) {
+        let visible: bool = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM notebooks
+                WHERE id = $1 AND deleted_at IS NULL AND (visibility = 'public' OR user_id = $2)
+            )
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(user_id)
+        .fetch_one(&mut **tx)
+        .await?;
+        if !visible {
+            return Err(CollectionError::NotebookNotFound(*notebook_id).into());
+        }
+    }
+
+    sqlx::query(
+        r#"
+        DELETE FROM collection_notebooks
+        WHERE collection_id = $1 AND notebook_id NOT IN (SELECT value FROM json_each($2))
+        "#,
+    )
+    .bind(id)
+    .bind(serde_json::to_string(notebook_ids).unwrap_or_default())
+    .execute(&mut **tx)
+    .await?;
+
+    for (position, notebook_id) in notebook_ids.iter().enumerate() {
+        sqlx::query(
+            r#"
+            INSERT INTO collection_notebooks (collection_id, notebook_id, position)
+            VALUES ($1, $2, $3)
+            ON CONFLICT(collection_id, notebook_id) DO UPDATE SET position = excluded.position
+            "#,
+        )
+        .bind(id)
+        .bind(notebook_id)
+        .bind(position as i64)
+        .execute(&mut **tx)
+        .await?;
+    }
+
+    Ok(())
+}
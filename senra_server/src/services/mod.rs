@@ -1,11 +1,21 @@
+mod analytics;
 mod auth;
+mod blob;
+mod collection;
 mod notebook;
+mod notification;
 mod resource;
 mod shader;
+mod two_factor;
 mod user;
 
-pub use auth::AuthService;
+pub use analytics::AnalyticsService;
+pub use auth::{AuthService, LoginOutcome};
+pub use blob::BlobService;
+pub use collection::CollectionService;
 pub use notebook::NotebookService;
+pub use notification::NotificationService;
 pub use resource::ResourceService;
 pub use shader::ShaderService;
+pub use two_factor::TwoFactorService;
 pub use user::UserService;
@@ -1,16 +1,116 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use jsonschema::Validator;
+use senra_api::{
+    CellPayload, FieldError, NotebookContent, NotebookSort, NotificationKind, PackageLock,
+    PreprocessedShader, ShaderLanguage, ShaderSource, migrate_content, notebook_content_schema,
+};
+use serde_json::Value;
 use sqlx::{QueryBuilder, SqlitePool};
-
+use tracing::warn;
+
+use super::analytics::{DailyActivity, record_daily_activity};
+use super::blob::BlobService;
+use super::notification::NotificationService;
+use super::shader::{
+    check_shader_type, library_modules, notebook_packages, record_notebook_packages, resolve_module,
+};
 use crate::errors::{NotebookError, Result};
 use crate::models::*;
 
+/// Top ranks of the hot feed reordered for the signed in user, bounding the rows scored per
+/// request
+const PERSONALIZED_WINDOW: i64 = 200;
+
 #[derive(Clone)]
 pub struct NotebookService {
     pool: SqlitePool,
+    content_validator: Arc<Validator>,
+    notifications: NotificationService,
+    blobs: BlobService,
 }
 
 impl NotebookService {
-    pub fn new(pool: &SqlitePool) -> Self {
-        Self { pool: pool.clone() }
+    pub fn new(
+        pool: &SqlitePool,
+        notifications: &NotificationService,
+        blobs: &BlobService,
+    ) -> Self {
+        let content_validator = jsonschema::validator_for(&notebook_content_schema())
+            .expect("Notebook content schema should be valid");
+
+        Self {
+            pool: pool.clone(),
+            content_validator: Arc::new(content_validator),
+            notifications: notifications.clone(),
+            blobs: blobs.clone(),
+        }
+    }
+
+    /// Upgrades incoming content to the current schema version and validates it against the schema
+    fn prepare_content(&self, content: &mut Value) -> Result<()> {
+        migrate_content(content).map_err(|err| NotebookError::InvalidContent(err.to_string()))?;
+
+        let errors: Vec<FieldError> = self
+            .content_validator
+            .iter_errors(content)
+            .map(|err| FieldError {
+                path: err.instance_path.to_string(),
+                message: err.to_string(),
+            })
+            .collect();
+
+        if !errors.is_empty() {
+            Err(NotebookError::SchemaViolation(errors))?;
+        }
+
+        Ok(())
+    }
+
+    /// Checks the render cell cross-references of prepared content against the notebook shaders
+    ///
+    /// Shaders are preprocessed first, see [`preprocess_shaders`].
+    fn check_references(
+        content: &Value,
+        shaders: &[Shader],
+        library: &HashMap<String, String>,
+        packages: &PackageLock,
+    ) -> Result<()> {
+        let content: NotebookContent = serde_json::from_value(content.clone())
+            .map_err(|err| NotebookError::InvalidContent(err.to_string()))?;
+
+        let (preprocessed, errors) = preprocess_shaders(&content, shaders, library, packages);
+        if !errors.is_empty() {
+            Err(NotebookError::InvalidReferences(errors))?;
+        }
+
+        content
+            .validate(|id| {
+                let (shader, language) = preprocessed.get(&id)?;
+                Some(ShaderSource::preprocessed(shader, *language))
+            })
+            .map_err(NotebookError::InvalidReferences)?;
+
+        Ok(())
+    }
+
+    /// Preprocesses the shaders of a notebook the way they are checked when it is saved, with
+    /// the library modules and package versions they currently import
+    ///
+    /// Shaders whose imports can't be expanded are reported in the returned errors.
+    pub async fn preprocessed_shaders(
+        &self,
+        content: &NotebookContent,
+        shaders: &[Shader],
+    ) -> Result<(PreprocessedShaders, Vec<FieldError>)> {
+        let mut conn = self.pool.acquire().await?;
+        let mut sources: Vec<&str> = shaders.iter().map(|shader| shader.code.as_str()).collect();
+        let library = library_modules(&mut conn, &sources).await?;
+        sources.extend(library.values().map(String::as_str));
+        let (packages, _) = notebook_packages(&mut conn, &sources).await?;
+
+        Ok(preprocess_shaders(content, shaders, &library, &packages))
     }
 
     /// Retrieves all tags associated with a notebook
@@ -62,6 +162,8 @@ impl NotebookService {
 
     /// Like a notebook
     pub async fn like_notebook(&self, user_id: i64, notebook_id: i64) -> Result<()> {
+        let owner_id = self.visible_notebook_owner(user_id, notebook_id).await?;
+
         let mut tx = self.pool.begin().await?;
 
         // Check if user has already liked this notebook
@@ -94,12 +196,31 @@ impl NotebookService {
         .execute(&mut *tx)
         .await?;
 
+        record_daily_activity(&mut *tx, notebook_id, DailyActivity::Like).await?;
+
         tx.commit().await?;
+
+        let notification = CreateNotification {
+            user_id: owner_id,
+            actor_id: user_id,
+            kind: NotificationKind::Like.as_str().to_string(),
+            notebook_id,
+            comment_id: None,
+        };
+        if let Err(err) = self.notifications.notify(notification).await {
+            warn!(
+                "Failed to notify the like of notebook {}: {}",
+                notebook_id, err
+            );
+        }
+
         Ok(())
     }
 
     /// Unlike a notebook
     pub async fn unlike_notebook(&self, user_id: i64, notebook_id: i64) -> Result<()> {
+        self.visible_notebook_owner(user_id, notebook_id).await?;
+
         let mut tx = self.pool.begin().await?;
 
         // Remove like record
@@ -132,38 +253,42 @@ impl NotebookService {
         Ok(())
     }
 
-    /// Lists notebooks for a user with pagination
-    pub async fn list_notebooks(&self, page: i64, per_page: i64) -> Result<(Vec<Notebook>, i64)> {
+    /// Owner of a notebook the user can see, the same as `get_notebook` does
+    async fn visible_notebook_owner(&self, user_id: i64, notebook_id: i64) -> Result<i64> {
+        let owner_id = sqlx::query_scalar(
+            r#"
+            SELECT user_id FROM notebooks
+            WHERE id = $1 AND (user_id = $2 OR visibility = 'public')
+                AND deleted_at IS NULL
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(NotebookError::NotFound)?;
+
+        Ok(owner_id)
+    }
+
+    /// Lists public notebooks of a feed, in the order materialized by the last ranking refresh.
+    /// For a signed in user the hot feed is boosted by how much the notebook tags match the
+    /// tags of the notebooks the user liked
+    pub async fn list_notebooks(
+        &self,
+        sort: NotebookSort,
+        page: i64,
+        per_page: i64,
+        user_id: Option<i64>,
+    ) -> Result<(Vec<Notebook>, i64)> {
         let offset = (page - 1) * per_page;
 
-        // Get recommended notebooks using Bilibili-like recommendation algorithm
-        let notebooks: Vec<Notebook> = sqlx::query_as(
+        if sort == NotebookSort::New {
+            let notebooks: Vec<Notebook> = sqlx::query_as(
                 r#"
-                WITH notebook_scores AS (
-                    SELECT 
-                        n.*,
-                        -- Base popularity score (weights: views 0.4, likes 0.3, comments 0.3)
-                        (s.view_count * 0.4 + s.like_count * 0.3 + s.comment_count * 0.3) as base_score,
-                        -- Time decay factor (higher weight for content within 24 hours)
-                        CASE 
-                            WHEN datetime(n.updated_at) > datetime('now', '-24 hours') THEN 1.5
-                            WHEN datetime(n.updated_at) > datetime('now', '-7 days') THEN 1.2
-                            ELSE 1.0
-                        END as time_factor,
-                        -- Content quality factor (based on engagement rate)
-                        CASE 
-                            WHEN s.view_count > 0 THEN 
-                                (s.like_count + s.comment_count) * 1.0 / s.view_count
-                            ELSE 0
-                        END as quality_factor
-                    FROM notebooks n
-                    JOIN notebook_stats s ON n.id = s.notebook_id
-                    WHERE n.visibility = 'public'
-                )
-                SELECT * FROM notebook_scores
-                ORDER BY 
-                    (base_score * time_factor * (1 + quality_factor)) DESC,
-                    updated_at DESC
+                SELECT * FROM notebooks
+                WHERE visibility = 'public' AND deleted_at IS NULL
+                ORDER BY created_at DESC, id DESC
                 LIMIT $1 OFFSET $2
                 "#,
             )
@@ -172,13 +297,206 @@ impl NotebookService {
             .fetch_all(&self.pool)
             .await?;
 
-        // Get total count
-        let total = sqlx::query_scalar(
-            r#"
+            let total = sqlx::query_scalar(
+                r#"
                 SELECT COUNT(*) FROM notebooks
-                WHERE visibility = 'public'
+                WHERE visibility = 'public' AND deleted_at IS NULL
+                "#,
+            )
+            .fetch_one(&self.pool)
+            .await?;
+
+            return Ok((notebooks, total));
+        }
+
+        // The top of the hot feed is reordered by the tags the signed in user liked, the rest
+        // of the feed being listed by rank
+        let mut window: Vec<Notebook> = Vec::new();
+        let mut after_rank = 0;
+        if let (Some(user_id), NotebookSort::Hot) = (user_id, sort) {
+            window = sqlx::query_as(
+                r#"
+                WITH liked_tags AS (
+                    -- Share of the user's likes that went to notebooks with each tag
+                    SELECT
+                        t.tag,
+                        COUNT(*) * 1.0 / (SELECT COUNT(*) FROM notebook_likes WHERE user_id = $3)
+                            as weight
+                    FROM notebook_likes l
+                    JOIN notebook_tags t ON l.notebook_id = t.notebook_id
+                    WHERE l.user_id = $3
+                    GROUP BY t.tag
+                )
+                SELECT n.* FROM notebook_rankings r
+                JOIN notebooks n ON n.id = r.notebook_id
+                WHERE r.feed = $1 AND r.rank <= $2
+                    AND n.visibility = 'public' AND n.deleted_at IS NULL
+                ORDER BY
+                    r.score * (1 + COALESCE((
+                        SELECT SUM(lt.weight) FROM notebook_tags t
+                        JOIN liked_tags lt ON t.tag = lt.tag
+                        WHERE t.notebook_id = n.id
+                    ), 0)) DESC,
+                    r.rank
                 "#,
+            )
+            .bind(sort.as_str())
+            .bind(PERSONALIZED_WINDOW)
+            .bind(user_id)
+            .fetch_all(&self.pool)
+            .await?;
+            after_rank = PERSONALIZED_WINDOW;
+        }
+
+        let skipped = window.len().min(offset as usize) as i64;
+        let mut notebooks: Vec<Notebook> = window
+            .into_iter()
+            .skip(offset as usize)
+            .take(per_page as usize)
+            .collect();
+        let remaining = per_page - notebooks.len() as i64;
+        if remaining > 0 {
+            let ranked: Vec<Notebook> = sqlx::query_as(
+                r#"
+                SELECT n.* FROM notebook_rankings r
+                JOIN notebooks n ON n.id = r.notebook_id
+                WHERE r.feed = $1 AND r.rank > $2
+                    AND n.visibility = 'public' AND n.deleted_at IS NULL
+                ORDER BY r.rank
+                LIMIT $3 OFFSET $4
+                "#,
+            )
+            .bind(sort.as_str())
+            .bind(after_rank)
+            .bind(remaining)
+            .bind(offset - skipped)
+            .fetch_all(&self.pool)
+            .await?;
+            notebooks.extend(ranked);
+        }
+
+        let total = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM notebook_rankings r
+            JOIN notebooks n ON n.id = r.notebook_id
+            WHERE r.feed = $1 AND n.visibility = 'public' AND n.deleted_at IS NULL
+            "#,
         )
+        .bind(sort.as_str())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((notebooks, total))
+    }
+
+    /// Recomputes the scores and ranks of the public notebooks in every ranked feed, replacing
+    /// the previous rankings at once. Returns the number of rankings written across the feeds
+    pub async fn refresh_rankings(&self) -> Result<u64> {
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("DELETE FROM notebook_rankings")
+            .execute(&mut *tx)
+            .await?;
+
+        // Engagement weights: views 0.4, likes 0.3, comments 0.3
+        let hot = r#"
+            SELECT
+                n.id AS notebook_id,
+                (s.view_count * 0.4 + s.like_count * 0.3 + s.comment_count * 0.3)
+                -- Time decay factor (higher weight for content updated within 24 hours)
+                * CASE
+                    WHEN datetime(n.updated_at) > datetime('now', '-24 hours') THEN 1.5
+                    WHEN datetime(n.updated_at) > datetime('now', '-7 days') THEN 1.2
+                    ELSE 1.0
+                END
+                -- Content quality factor (based on engagement rate)
+                * (1 + CASE
+                    WHEN s.view_count > 0 THEN
+                        (s.like_count + s.comment_count) * 1.0 / s.view_count
+                    ELSE 0
+                END) AS score
+            FROM notebooks n
+            JOIN notebook_stats s ON n.id = s.notebook_id
+            WHERE n.visibility = 'public' AND n.deleted_at IS NULL
+        "#;
+        let top_week = r#"
+            SELECT
+                n.id AS notebook_id,
+                COALESCE(SUM(d.view_count * 0.4 + d.like_count * 0.3 + d.comment_count * 0.3), 0)
+                    AS score
+            FROM notebooks n
+            LEFT JOIN notebook_daily_stats d
+                ON d.notebook_id = n.id AND d.day > date('now', '-7 days')
+            WHERE n.visibility = 'public' AND n.deleted_at IS NULL
+            GROUP BY n.id
+        "#;
+        let top_all_time = r#"
+            SELECT
+                n.id AS notebook_id,
+                s.view_count * 0.4 + s.like_count * 0.3 + s.comment_count * 0.3 AS score
+            FROM notebooks n
+            JOIN notebook_stats s ON n.id = s.notebook_id
+            WHERE n.visibility = 'public' AND n.deleted_at IS NULL
+        "#;
+
+        let mut ranked = 0;
+        for (sort, scores) in NotebookSort::RANKED
+            .into_iter()
+            .zip([hot, top_week, top_all_time])
+        {
+            // Ties go to the newest notebook so that pages never overlap
+            let query = format!(
+                r#"
+                INSERT INTO notebook_rankings (feed, notebook_id, score, rank)
+                SELECT
+                    $1, notebook_id, score,
+                    ROW_NUMBER() OVER (ORDER BY score DESC, notebook_id DESC)
+                FROM ({scores})
+                "#
+            );
+            ranked += sqlx::query(&query)
+                .bind(sort.as_str())
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+        }
+
+        tx.commit().await?;
+        Ok(ranked)
+    }
+
+    /// Lists the public notebooks of the users a user follows, most recently updated first
+    pub async fn list_following_notebooks(
+        &self,
+        user_id: i64,
+        page: i64,
+        per_page: i64,
+    ) -> Result<(Vec<Notebook>, i64)> {
+        let offset = (page - 1) * per_page;
+
+        let notebooks: Vec<Notebook> = sqlx::query_as(
+            r#"
+            SELECT n.* FROM notebooks n
+            JOIN user_follows f ON n.user_id = f.followee_id
+            WHERE f.follower_id = $1 AND n.visibility = 'public' AND n.deleted_at IS NULL
+            ORDER BY n.updated_at DESC, n.id DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(per_page)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM notebooks n
+            JOIN user_follows f ON n.user_id = f.followee_id
+            WHERE f.follower_id = $1 AND n.visibility = 'public' AND n.deleted_at IS NULL
+            "#,
+        )
+        .bind(user_id)
         .fetch_one(&self.pool)
         .await?;
 
@@ -197,7 +515,7 @@ impl NotebookService {
         let notebooks: Vec<Notebook> = sqlx::query_as(
             r#"
             SELECT * FROM notebooks
-            WHERE user_id = $1
+            WHERE user_id = $1 AND deleted_at IS NULL
             ORDER BY updated_at DESC
             LIMIT $2 OFFSET $3
             "#,
@@ -208,20 +526,32 @@ impl NotebookService {
         .fetch_all(&self.pool)
         .await?;
 
-        let total = sqlx::query_scalar("SELECT COUNT(*) FROM notebooks WHERE user_id = $1")
-            .bind(user_id)
-            .fetch_one(&self.pool)
-            .await?;
+        let total = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notebooks WHERE user_id = $1 AND deleted_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
 
         Ok((notebooks, total))
     }
 
+    /// Number of notebooks outside of the trash
+    pub async fn count_notebooks(&self) -> Result<i64> {
+        Ok(
+            sqlx::query_scalar("SELECT COUNT(*) FROM notebooks WHERE deleted_at IS NULL")
+                .fetch_one(&self.pool)
+                .await?,
+        )
+    }
+
     /// Retrieves a specific notebook by ID
     pub async fn get_notebook(&self, user_id: i64, id: i64) -> Result<Notebook> {
-        let notebook: Notebook = sqlx::query_as(
+        let mut notebook: Notebook = sqlx::query_as(
             r#"
             SELECT n.* FROM notebooks n
             WHERE n.id = $1 AND (n.user_id = $2 OR n.visibility = 'public')
+                AND n.deleted_at IS NULL
             "#,
         )
         .bind(id)
@@ -230,17 +560,7 @@ impl NotebookService {
         .await?
         .ok_or(NotebookError::NotFound)?;
 
-        // Increment view count
-        sqlx::query(
-            r#"
-            UPDATE notebook_stats
-            SET view_count = view_count + 1
-            WHERE notebook_id = $1
-            "#,
-        )
-        .bind(id)
-        .execute(&self.pool)
-        .await?;
+        upgrade_stored_content(&mut notebook.content);
 
         Ok(notebook)
     }
@@ -256,12 +576,51 @@ impl NotebookService {
         user_id: i64,
         create_notebook: CreateNotebook,
     ) -> Result<Notebook> {
+        self.insert_notebook(user_id, create_notebook, None).await
+    }
+
+    /// Creates a notebook from an archive
+    /// The content references shaders and resources by their ids in the archive, given in
+    /// the same order as `create_notebook.shaders` and `create_notebook.resources`, and is
+    /// rewritten to use the ids assigned on creation
+    pub async fn import_notebook(
+        &self,
+        user_id: i64,
+        create_notebook: CreateNotebook,
+        archive_ids: ArchiveIds,
+    ) -> Result<Notebook> {
+        self.insert_notebook(user_id, create_notebook, Some(archive_ids))
+            .await
+    }
+
+    async fn insert_notebook(
+        &self,
+        user_id: i64,
+        mut create_notebook: CreateNotebook,
+        archive_ids: Option<ArchiveIds>,
+    ) -> Result<Notebook> {
+        self.prepare_content(&mut create_notebook.content)?;
+        for shader in &create_notebook.shaders {
+            check_shader_type(&shader.shader_type)?;
+        }
+
+        // Blobs are stored first, a failed insert only leaves unreferenced blobs behind
+        let preview_hash = match create_notebook.preview {
+            Some(preview) => Some(self.blobs.put(preview).await?),
+            None => None,
+        };
+        let mut resource_hashes = Vec::new();
+        for resource in &mut create_notebook.resources {
+            let data = std::mem::take(&mut resource.data);
+            resource_hashes.push(self.blobs.put(data).await?);
+        }
+
         let mut tx = self.pool.begin().await?;
 
         // Create notebook record
-        let notebook: Notebook = sqlx::query_as(
+        let mut notebook: Notebook = sqlx::query_as(
             r#"
-            INSERT INTO notebooks (user_id, title, description, content, preview, visibility)
+            INSERT INTO notebooks (user_id, title, description, content, preview_hash, visibility)
             VALUES ($1, $2, $3, $4, $5, $6)
             RETURNING *
             "#,
@@ -270,29 +629,34 @@ impl NotebookService {
         .bind(create_notebook.title)
         .bind(create_notebook.description)
         .bind(create_notebook.content)
-        .bind(create_notebook.preview)
+        .bind(preview_hash)
         .bind(create_notebook.visibility)
         .fetch_one(&mut *tx)
         .await?;
 
         // Create resources
-        for resource in create_notebook.resources {
-            sqlx::query(
+        let mut resource_ids = Vec::new();
+        for (resource, data_hash) in create_notebook.resources.into_iter().zip(resource_hashes) {
+            let resource_id: i64 = sqlx::query_scalar(
                 r#"
-                INSERT INTO resources (notebook_id, name, resource_type, data, metadata)
-                VALUES ($1, $2, $3, $4, $5)
+                INSERT INTO resources (notebook_id, name, resource_type, data, data_hash, metadata)
+                VALUES ($1, $2, $3, X'', $4, $5)
+                RETURNING id
                 "#,
             )
             .bind(notebook.id)
             .bind(resource.name)
             .bind(resource.resource_type)
-            .bind(resource.data)
+            .bind(data_hash)
             .bind(resource.metadata)
-            .execute(&mut *tx)
+            .fetch_one(&mut *tx)
             .await?;
+
+            resource_ids.push(resource_id);
         }
 
         // Create shaders
+        let mut shaders = Vec::new();
         for shader in create_notebook.shaders {
             let shader: Shader = sqlx::query_as(
                 r#"
@@ -316,11 +680,51 @@ impl NotebookService {
                 "#,
             )
             .bind(shader.id)
-            .bind(shader.code)
+            .bind(&shader.code)
             .execute(&mut *tx)
             .await?;
+
+            shaders.push(shader);
         }
 
+        // Point the content at the ids assigned to the archived shaders and resources
+        if let Some(archive_ids) = archive_ids {
+            let mut content: NotebookContent = serde_json::from_value(notebook.content)
+                .map_err(|err| NotebookError::InvalidContent(err.to_string()))?;
+            content.remap_ids(
+                &archive_ids
+                    .shaders
+                    .into_iter()
+                    .zip(shaders.iter().map(|shader| shader.id))
+                    .collect(),
+                &archive_ids
+                    .resources
+                    .into_iter()
+                    .zip(resource_ids)
+                    .collect(),
+            );
+            notebook.content = serde_json::to_value(content)
+                .map_err(|err| NotebookError::InvalidContent(err.to_string()))?;
+
+            sqlx::query(
+                r#"
+                UPDATE notebooks SET content = $1
+                WHERE id = $2
+                "#,
+            )
+            .bind(&notebook.content)
+            .bind(notebook.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let mut sources: Vec<&str> = shaders.iter().map(|shader| shader.code.as_str()).collect();
+        let library = library_modules(&mut tx, &sources).await?;
+        sources.extend(library.values().map(String::as_str));
+        let (packages, package_versions) = notebook_packages(&mut tx, &sources).await?;
+        Self::check_references(&notebook.content, &shaders, &library, &packages)?;
+        record_notebook_packages(&mut tx, notebook.id, &packages, &package_versions).await?;
+
         // Create initial version
         sqlx::query(
             r#"
@@ -373,10 +777,28 @@ impl NotebookService {
         &self,
         user_id: i64,
         id: i64,
-        update_notebook: UpdateNotebook,
+        mut update_notebook: UpdateNotebook,
     ) -> Result<Notebook> {
+        if let Some(content) = &mut update_notebook.content {
+            self.prepare_content(content)?;
+        }
+
         let mut tx = self.pool.begin().await?;
 
+        // Checked first, as validating the content reports errors quoting the notebook shaders
+        // and storing the preview writes into the blob store
+        sqlx::query_scalar::<_, i64>(
+            r#"
+            SELECT id FROM notebooks
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(NotebookError::NotFound)?;
+
         let mut query_builder = QueryBuilder::new("UPDATE notebooks SET ");
         let mut has_changes = false;
 
@@ -399,6 +821,23 @@ impl NotebookService {
                 query_builder.push(", ");
             }
 
+            let shaders: Vec<Shader> = sqlx::query_as(
+                r#"
+                SELECT * FROM shaders
+                WHERE notebook_id = $1
+                "#,
+            )
+            .bind(id)
+            .fetch_all(&mut *tx)
+            .await?;
+            let mut sources: Vec<&str> =
+                shaders.iter().map(|shader| shader.code.as_str()).collect();
+            let library = library_modules(&mut tx, &sources).await?;
+            sources.extend(library.values().map(String::as_str));
+            let (packages, package_versions) = notebook_packages(&mut tx, &sources).await?;
+            Self::check_references(content, &shaders, &library, &packages)?;
+            record_notebook_packages(&mut tx, id, &packages, &package_versions).await?;
+
             // Get current version and increment
             let current_version: i64 = sqlx::query_scalar(
                 r#"
@@ -434,11 +873,14 @@ impl NotebookService {
             has_changes = true;
         }
 
-        if let Some(preview) = &update_notebook.preview {
+        if let Some(preview) = update_notebook.preview {
+            let preview_hash = self.blobs.put(preview).await?;
             if has_changes {
                 query_builder.push(", ");
             }
-            query_builder.push("preview = ").push_bind(preview);
+            query_builder
+                .push("preview_hash = ")
+                .push_bind(preview_hash);
             has_changes = true;
         }
 
@@ -455,10 +897,11 @@ impl NotebookService {
                 .push(", updated_at = CURRENT_TIMESTAMP WHERE id = ")
                 .push_bind(id)
                 .push(" AND user_id = ")
-                .push_bind(user_id);
+                .push_bind(user_id)
+                .push(" AND deleted_at IS NULL");
 
             query_builder
-                .push(" RETURNING id, user_id, title, description, content, preview, visibility, version, created_at, updated_at");
+                .push(" RETURNING id, user_id, title, description, content, preview_hash, visibility, version, created_at, updated_at, deleted_at");
 
             let notebook = query_builder
                 .build_query_as::<Notebook>()
@@ -499,18 +942,89 @@ impl NotebookService {
         }
     }
 
-    /// Deletes a notebook and all its related data
+    /// Moves a notebook to its owner's trash, hiding it until it is restored or purged
+    pub async fn delete_notebook(&self, user_id: i64, id: i64) -> Result<()> {
+        let result = sqlx::query(
+            r#"
+            UPDATE notebooks SET deleted_at = CURRENT_TIMESTAMP
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            Err(NotebookError::NotFound)?;
+        }
+
+        Ok(())
+    }
+
+    /// Lists the notebooks in a user's trash, most recently deleted first
+    pub async fn list_trash(
+        &self,
+        user_id: i64,
+        page: i64,
+        per_page: i64,
+    ) -> Result<(Vec<Notebook>, i64)> {
+        let offset = (page - 1) * per_page;
+
+        let notebooks = sqlx::query_as(
+            r#"
+            SELECT * FROM notebooks
+            WHERE user_id = $1 AND deleted_at IS NOT NULL
+            ORDER BY deleted_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(per_page)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notebooks WHERE user_id = $1 AND deleted_at IS NOT NULL",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((notebooks, total))
+    }
+
+    /// Restores a notebook from its owner's trash
+    pub async fn restore_notebook(&self, user_id: i64, id: i64) -> Result<Notebook> {
+        let notebook = sqlx::query_as(
+            r#"
+            UPDATE notebooks SET deleted_at = NULL
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL
+            RETURNING *
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(notebook.ok_or(NotebookError::NotFound)?)
+    }
+
+    /// Permanently deletes a notebook from its owner's trash along with all its related data
     /// This includes:
     /// - Tags
     /// - Versions
     /// - Comments
     /// - Statistics
     /// - Likes
-    pub async fn delete_notebook(&self, user_id: i64, id: i64) -> Result<()> {
+    /// - Shaders and resources
+    pub async fn purge_notebook(&self, user_id: i64, id: i64) -> Result<()> {
         let result = sqlx::query(
             r#"
             DELETE FROM notebooks
-            WHERE id = $1 AND user_id = $2
+            WHERE id = $1 AND user_id = $2 AND deleted_at IS NOT NULL
             "#,
         )
         .bind(id)
@@ -525,6 +1039,23 @@ impl NotebookService {
         Ok(())
     }
 
+    /// Permanently deletes the notebooks which have been in the trash for more than
+    /// `retention_days`, returning how many were purged
+    pub async fn purge_expired(&self, retention_days: i64) -> Result<u64> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM notebooks
+            WHERE deleted_at IS NOT NULL
+                AND datetime(deleted_at) <= datetime('now', '-' || $1 || ' days')
+            "#,
+        )
+        .bind(retention_days)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
     /// Lists versions of a notebook with pagination
     pub async fn list_versions(
         &self,
@@ -537,7 +1068,7 @@ impl NotebookService {
             r#"
             SELECT EXISTS(
                 SELECT 1 FROM notebooks
-                WHERE id = $1
+                WHERE id = $1 AND deleted_at IS NULL
             )
             "#,
         )
@@ -551,7 +1082,7 @@ impl NotebookService {
 
         let offset = (page - 1) * per_page;
 
-        let versions: Vec<NotebookVersion> = sqlx::query_as(
+        let mut versions: Vec<NotebookVersion> = sqlx::query_as(
             r#"
             SELECT * FROM notebook_versions
             WHERE notebook_id = $1
@@ -565,6 +1096,10 @@ impl NotebookService {
         .fetch_all(&self.pool)
         .await?;
 
+        for version in &mut versions {
+            upgrade_stored_content(&mut version.content);
+        }
+
         let total = sqlx::query_scalar(
             r#"
             SELECT COUNT(*) FROM notebook_versions
@@ -578,21 +1113,91 @@ impl NotebookService {
         Ok((versions, total))
     }
 
-    /// Lists comments for a notebook with pagination
+    /// Rewrites stored notebook and version contents to the current schema version
+    /// Returns the number of notebook and version rows that were changed
+    pub async fn migrate_stored_content(&self) -> Result<(u64, u64)> {
+        let mut tx = self.pool.begin().await?;
+
+        let notebooks: Vec<(i64, Value)> = sqlx::query_as("SELECT id, content FROM notebooks")
+            .fetch_all(&mut *tx)
+            .await?;
+
+        let mut notebook_count = 0;
+        for (id, mut content) in notebooks {
+            match migrate_content(&mut content) {
+                Ok(false) => {}
+                Ok(true) => {
+                    sqlx::query("UPDATE notebooks SET content = $1 WHERE id = $2")
+                        .bind(content)
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                    notebook_count += 1;
+                }
+                Err(err) => warn!("Skipping content migration of notebook {}: {}", id, err),
+            }
+        }
+
+        let versions: Vec<(i64, Value)> =
+            sqlx::query_as("SELECT id, content FROM notebook_versions")
+                .fetch_all(&mut *tx)
+                .await?;
+
+        let mut version_count = 0;
+        for (id, mut content) in versions {
+            match migrate_content(&mut content) {
+                Ok(false) => {}
+                Ok(true) => {
+                    sqlx::query("UPDATE notebook_versions SET content = $1 WHERE id = $2")
+                        .bind(content)
+                        .bind(id)
+                        .execute(&mut *tx)
+                        .await?;
+                    version_count += 1;
+                }
+                Err(err) => warn!("Skipping content migration of version {}: {}", id, err),
+            }
+        }
+
+        tx.commit().await?;
+
+        Ok((notebook_count, version_count))
+    }
+
+    /// Lists a page of top-level comments for a notebook the user can see, followed by all
+    /// their replies
     pub async fn list_comments(
         &self,
+        user_id: i64,
         notebook_id: i64,
         page: i64,
         per_page: i64,
     ) -> Result<(Vec<NotebookComment>, i64)> {
+        self.visible_notebook_owner(user_id, notebook_id).await?;
+
         let offset = (page - 1) * per_page;
 
         let comments: Vec<NotebookComment> = sqlx::query_as(
             r#"
-            SELECT * FROM notebook_comments
-            WHERE notebook_id = $1
-            ORDER BY created_at DESC
-            LIMIT $2 OFFSET $3
+            WITH RECURSIVE roots AS (
+                SELECT * FROM notebook_comments
+                WHERE notebook_id = $1 AND parent_id IS NULL
+                ORDER BY created_at DESC, id DESC
+                LIMIT $2 OFFSET $3
+            ),
+            thread AS (
+                SELECT * FROM roots
+                UNION ALL
+                SELECT c.* FROM notebook_comments c
+                JOIN thread t ON c.parent_id = t.id
+            )
+            SELECT * FROM thread
+            ORDER BY
+                parent_id IS NOT NULL,
+                CASE WHEN parent_id IS NULL THEN created_at END DESC,
+                CASE WHEN parent_id IS NULL THEN id END DESC,
+                created_at ASC,
+                id ASC
             "#,
         )
         .bind(notebook_id)
@@ -604,7 +1209,7 @@ impl NotebookService {
         let total = sqlx::query_scalar(
             r#"
             SELECT COUNT(*) FROM notebook_comments
-            WHERE notebook_id = $1
+            WHERE notebook_id = $1 AND parent_id IS NULL
             "#,
         )
         .bind(notebook_id)
@@ -614,36 +1219,138 @@ impl NotebookService {
         Ok((comments, total))
     }
 
-    /// Creates a new comment for a notebook
+    /// Creates a new comment for a notebook, or a reply to one of its comments
     pub async fn create_comment(
         &self,
         user_id: i64,
         notebook_id: i64,
-        content: String,
+        create_comment: CreateNotebookComment,
     ) -> Result<NotebookComment> {
+        check_comment_content(&create_comment.content)?;
+
+        let content: Value = sqlx::query_scalar(
+            r#"
+            SELECT content FROM notebooks
+            WHERE id = $1 AND (user_id = $2 OR visibility = 'public') AND deleted_at IS NULL
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(NotebookError::NotFound)?;
+
+        if let Some(parent_id) = create_comment.parent_id {
+            let parent_exists: bool = sqlx::query_scalar(
+                r#"
+                SELECT EXISTS(
+                    SELECT 1 FROM notebook_comments
+                    WHERE id = $1 AND notebook_id = $2
+                )
+                "#,
+            )
+            .bind(parent_id)
+            .bind(notebook_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+            if !parent_exists {
+                Err(NotebookError::InvalidComment(
+                    "The replied comment does not belong to this notebook".to_string(),
+                ))?;
+            }
+        }
+
+        if let Some(cell_id) = &create_comment.anchor_cell_id {
+            check_comment_anchor(
+                content,
+                cell_id,
+                create_comment.anchor_start_line,
+                create_comment.anchor_end_line,
+            )?;
+        }
+
         let comment: NotebookComment = sqlx::query_as(
             r#"
-            INSERT INTO notebook_comments (notebook_id, user_id, content)
-            VALUES ($1, $2, $3)
+            INSERT INTO notebook_comments (
+                notebook_id, user_id, content, parent_id,
+                anchor_cell_id, anchor_start_line, anchor_end_line
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
             RETURNING *
             "#,
         )
         .bind(notebook_id)
         .bind(user_id)
+        .bind(create_comment.content)
+        .bind(create_comment.parent_id)
+        .bind(create_comment.anchor_cell_id)
+        .bind(create_comment.anchor_start_line)
+        .bind(create_comment.anchor_end_line)
+        .fetch_one(&self.pool)
+        .await?;
+
+        record_daily_activity(&self.pool, notebook_id, DailyActivity::Comment).await?;
+
+        if let Err(err) = self.notifications.notify_comment(&comment).await {
+            warn!("Failed to notify comment {}: {}", comment.id, err);
+        }
+
+        Ok(comment)
+    }
+
+    /// Edits the content of a comment on a notebook the user can see, marking it as edited
+    pub async fn update_comment(
+        &self,
+        user_id: i64,
+        notebook_id: i64,
+        comment_id: i64,
+        content: String,
+    ) -> Result<NotebookComment> {
+        self.visible_notebook_owner(user_id, notebook_id).await?;
+        check_comment_content(&content)?;
+
+        let author_id: i64 = sqlx::query_scalar(
+            r#"
+            SELECT user_id FROM notebook_comments
+            WHERE id = $1 AND notebook_id = $2
+            "#,
+        )
+        .bind(comment_id)
+        .bind(notebook_id)
+        .fetch_optional(&self.pool)
+        .await?
+        .ok_or(NotebookError::CommentNotFound)?;
+
+        if author_id != user_id {
+            Err(NotebookError::PermissionDenied)?;
+        }
+
+        let comment: NotebookComment = sqlx::query_as(
+            r#"
+            UPDATE notebook_comments
+            SET content = $1, updated_at = CURRENT_TIMESTAMP, edited_at = CURRENT_TIMESTAMP
+            WHERE id = $2
+            RETURNING *
+            "#,
+        )
         .bind(content)
+        .bind(comment_id)
         .fetch_one(&self.pool)
         .await?;
 
         Ok(comment)
     }
 
-    /// Deletes a comment from a notebook
+    /// Deletes a comment from a notebook the user can see along with its replies
     pub async fn delete_comment(
         &self,
         user_id: i64,
         notebook_id: i64,
         comment_id: i64,
     ) -> Result<()> {
+        self.visible_notebook_owner(user_id, notebook_id).await?;
+
         let result = sqlx::query(
             r#"
             DELETE FROM notebook_comments
@@ -657,9 +1364,102 @@ impl NotebookService {
         .await?;
 
         if result.rows_affected() == 0 {
-            Err(NotebookError::NotFound)?;
+            Err(NotebookError::CommentNotFound)?;
         }
 
         Ok(())
     }
 }
+
+fn check_comment_content(content: &str) -> Result<()> {
+    if content.trim().is_empty() {
+        Err(NotebookError::InvalidComment(
+            "Comment content is empty".to_string(),
+        ))?;
+    }
+    Ok(())
+}
+
+/// Checks that an anchor points at a cell of the notebook content and within its lines
+fn check_comment_anchor(
+    mut content: Value,
+    cell_id: &str,
+    start_line: Option<i64>,
+    end_line: Option<i64>,
+) -> Result<()> {
+    let invalid = |message: &str| NotebookError::InvalidComment(message.to_string());
+
+    upgrade_stored_content(&mut content);
+    let content: NotebookContent = serde_json::from_value(content)
+        .map_err(|_| invalid("The notebook content cannot be anchored to"))?;
+    let cell = content
+        .cells
+        .iter()
+        .find(|cell| cell.id == cell_id)
+        .ok_or_else(|| invalid("The anchored cell does not exist"))?;
+
+    let (start_line, end_line) = match (start_line, end_line) {
+        (None, None) => return Ok(()),
+        (Some(start_line), Some(end_line)) => (start_line, end_line),
+        _ => Err(invalid(
+            "An anchor line range needs both a start and an end line",
+        ))?,
+    };
+    let source = match &cell.payload {
+        CellPayload::Markdown(source) | CellPayload::Code(source) => source,
+        CellPayload::Render(_) => Err(invalid("Render cells have no lines to anchor to"))?,
+    };
+    let line_count = source.lines().count().max(1) as i64;
+    if start_line < 1 || start_line > end_line || end_line > line_count {
+        Err(invalid(&format!(
+            "Lines {}-{} are outside the anchored cell, which has {} lines",
+            start_line, end_line, line_count
+        )))?;
+    }
+
+    Ok(())
+}
+
+/// Upgrades stored content on read, leaving documents that cannot be migrated untouched
+fn upgrade_stored_content(content: &mut Value) {
+    let mut upgraded = content.clone();
+    match migrate_content(&mut upgraded) {
+        Ok(true) => *content = upgraded,
+        Ok(false) => {}
+        Err(err) => warn!("Failed to migrate stored notebook content: {}", err),
+    }
+}
+
+/// Preprocessed shaders of a notebook and their language, keyed by shader id
+pub type PreprocessedShaders = HashMap<i64, (PreprocessedShader, ShaderLanguage)>;
+
+/// Expands the imports of the notebook `shaders`, from the notebook shaders and code cells, the
+/// `library` modules published by other notebooks and the resolved `packages`, and prepends
+/// the prelude the viewer compiles shaders with
+///
+/// Shaders whose imports can't be expanded are left out and reported in the returned errors.
+fn preprocess_shaders(
+    content: &NotebookContent,
+    shaders: &[Shader],
+    library: &HashMap<String, String>,
+    packages: &PackageLock,
+) -> (PreprocessedShaders, Vec<FieldError>) {
+    let mut preprocessed = HashMap::new();
+    let mut errors = Vec::new();
+    let resolve = resolve_module(content, shaders, library);
+    for shader in shaders {
+        // Shaders stored before their language was recorded are WGSL
+        let language = shader.shader_type.parse().unwrap_or_default();
+        match packages.preprocess(&shader.name, &shader.code, &resolve) {
+            Ok(code) => {
+                preprocessed.insert(shader.id, (code.with_prelude(language), language));
+            }
+            Err(err) => errors.push(FieldError {
+                path: format!("/shaders/{}", shader.id),
+                message: err.to_string(),
+            }),
+        }
+    }
+
+    (preprocessed, errors)
+}
@@ -0,0 +1,233 @@
+use senra_api::NotificationKind;
+use sqlx::{QueryBuilder, SqlitePool};
+use tokio::sync::broadcast;
+
+use crate::errors::Result;
+use crate::models::*;
+
+/// Notifications buffered for slow WebSocket connections before they start missing some
+const CHANNEL_CAPACITY: usize = 256;
+
+#[derive(Clone)]
+pub struct NotificationService {
+    pool: SqlitePool,
+    sender: broadcast::Sender<Notification>,
+}
+
+impl NotificationService {
+    pub fn new(pool: &SqlitePool) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+
+        Self {
+            pool: pool.clone(),
+            sender,
+        }
+    }
+
+    /// Receives every notification created from now on, for all the users
+    pub fn subscribe(&self) -> broadcast::Receiver<Notification> {
+        self.sender.subscribe()
+    }
+
+    /// Notifies a user of the activity of another, ignoring their own activity
+    pub async fn notify(&self, create_notification: CreateNotification) -> Result<()> {
+        if create_notification.user_id == create_notification.actor_id {
+            return Ok(());
+        }
+
+        let id: i64 = sqlx::query_scalar(
+            r#"
+            INSERT INTO notifications (user_id, actor_id, kind, notebook_id, comment_id)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id
+            "#,
+        )
+        .bind(create_notification.user_id)
+        .bind(create_notification.actor_id)
+        .bind(create_notification.kind)
+        .bind(create_notification.notebook_id)
+        .bind(create_notification.comment_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let notification: Notification = sqlx::query_as(
+            r#"
+            SELECT n.*, u.username AS actor, nb.title AS notebook_title
+            FROM notifications n
+            JOIN users u ON n.actor_id = u.id
+            JOIN notebooks nb ON n.notebook_id = nb.id
+            WHERE n.id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        // Nobody is connected when sending fails
+        let _ = self.sender.send(notification);
+        Ok(())
+    }
+
+    /// Notifies the notebook owner, the author of the replied comment and the mentioned users
+    /// of a new comment, each of them once
+    pub async fn notify_comment(&self, comment: &NotebookComment) -> Result<()> {
+        let owner_id: i64 = sqlx::query_scalar("SELECT user_id FROM notebooks WHERE id = $1")
+            .bind(comment.notebook_id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        let mut recipients = vec![(owner_id, NotificationKind::Comment)];
+
+        if let Some(parent_id) = comment.parent_id {
+            let parent_author_id: i64 =
+                sqlx::query_scalar("SELECT user_id FROM notebook_comments WHERE id = $1")
+                    .bind(parent_id)
+                    .fetch_one(&self.pool)
+                    .await?;
+            recipients.push((parent_author_id, NotificationKind::Reply));
+        }
+
+        for username in mentioned_usernames(&comment.content) {
+            // Mentioned users are only notified of notebooks they can see
+            let user_id: Option<i64> = sqlx::query_scalar(
+                r#"
+                SELECT u.id FROM users u
+                JOIN notebooks nb ON nb.id = $2
+                WHERE u.username = $1 AND (nb.visibility = 'public' OR nb.user_id = u.id)
+                "#,
+            )
+            .bind(username)
+            .bind(comment.notebook_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+            if let Some(user_id) = user_id {
+                recipients.push((user_id, NotificationKind::Mention));
+            }
+        }
+
+        let mut notified = Vec::new();
+        for (user_id, kind) in recipients {
+            if notified.contains(&user_id) {
+                continue;
+            }
+            notified.push(user_id);
+
+            self.notify(CreateNotification {
+                user_id,
+                actor_id: comment.user_id,
+                kind: kind.as_str().to_string(),
+                notebook_id: comment.notebook_id,
+                comment_id: Some(comment.id),
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Lists the notifications of a user, newest first, with the total and unread counts
+    pub async fn list_notifications(
+        &self,
+        user_id: i64,
+        unread_only: bool,
+        page: i64,
+        per_page: i64,
+    ) -> Result<(Vec<Notification>, i64, i64)> {
+        let offset = (page - 1) * per_page;
+
+        let notifications: Vec<Notification> = sqlx::query_as(
+            r#"
+            SELECT n.*, u.username AS actor, nb.title AS notebook_title
+            FROM notifications n
+            JOIN users u ON n.actor_id = u.id
+            JOIN notebooks nb ON n.notebook_id = nb.id
+            WHERE n.user_id = $1 AND ($2 = 0 OR n.read_at IS NULL)
+            ORDER BY n.created_at DESC, n.id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+        )
+        .bind(user_id)
+        .bind(unread_only)
+        .bind(per_page)
+        .bind(offset)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let total = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM notifications
+            WHERE user_id = $1 AND ($2 = 0 OR read_at IS NULL)
+            "#,
+        )
+        .bind(user_id)
+        .bind(unread_only)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let unread = self.unread_count(user_id).await?;
+
+        Ok((notifications, total, unread))
+    }
+
+    pub async fn unread_count(&self, user_id: i64) -> Result<i64> {
+        let unread = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(unread)
+    }
+
+    /// Marks the given notifications of a user as read, or all of them, returning the unread
+    /// count left
+    pub async fn mark_read(&self, user_id: i64, ids: Option<Vec<i64>>) -> Result<i64> {
+        let mut query_builder = QueryBuilder::new(
+            "UPDATE notifications SET read_at = CURRENT_TIMESTAMP WHERE read_at IS NULL AND user_id = ",
+        );
+        query_builder.push_bind(user_id);
+
+        if let Some(ids) = ids {
+            if ids.is_empty() {
+                return self.unread_count(user_id).await;
+            }
+
+            query_builder.push(" AND id IN (");
+            let mut separated = query_builder.separated(", ");
+            for id in ids {
+                separated.push_bind(id);
+            }
+            separated.push_unseparated(")");
+        }
+
+        query_builder.build().execute(&self.pool).await?;
+
+        self.unread_count(user_id).await
+    }
+}
+
+/// Usernames mentioned with `@username`, ignoring `@` within words such as email addresses
+fn mentioned_usernames(content: &str) -> Vec<&str> {
+    let is_username_char = |c: char| c.is_alphanumeric() || c == '_' || c == '-';
+
+    let mut usernames = Vec::new();
+    for (index, _) in content.match_indices('@') {
+        let preceded_by_word = content[..index]
+            .chars()
+            .next_back()
+            .is_some_and(is_username_char);
+        if preceded_by_word {
+            continue;
+        }
+
+        let rest = &content[index + 1..];
+        let end = rest.find(|c| !is_username_char(c)).unwrap_or(rest.len());
+        let username = &rest[..end];
+        if !username.is_empty() && !usernames.contains(&username) {
+            usernames.push(username);
+        }
+    }
+    usernames
+}
@@ -1,16 +1,21 @@
 use sqlx::{QueryBuilder, SqlitePool};
 
+use super::blob::BlobService;
 use crate::errors::{NotebookError, Result};
 use crate::models::{CreateResource, Resource, UpdateResource};
 
 #[derive(Clone)]
 pub struct ResourceService {
     pool: SqlitePool,
+    blobs: BlobService,
 }
 
 impl ResourceService {
-    pub fn new(pool: &SqlitePool) -> Self {
-        Self { pool: pool.clone() }
+    pub fn new(pool: &SqlitePool, blobs: &BlobService) -> Self {
+        Self {
+            pool: pool.clone(),
+            blobs: blobs.clone(),
+        }
     }
 
     pub async fn create_resource(
@@ -23,7 +28,7 @@ impl ResourceService {
             r#"
             SELECT EXISTS(
                 SELECT 1 FROM notebooks
-                WHERE id = $1 AND user_id = $2
+                WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
             )
             "#,
         )
@@ -36,17 +41,19 @@ impl ResourceService {
             return Err(NotebookError::NotFound.into());
         }
 
+        let data_hash = self.blobs.put(create_resource.data).await?;
+
         let resource: Resource = sqlx::query_as(
             r#"
-            INSERT INTO resources (notebook_id, name, resource_type, data, metadata)
-            VALUES ($1, $2, $3, $4, $5)
+            INSERT INTO resources (notebook_id, name, resource_type, data, data_hash, metadata)
+            VALUES ($1, $2, $3, X'', $4, $5)
             RETURNING *
             "#,
         )
         .bind(create_resource.notebook_id)
         .bind(create_resource.name)
         .bind(create_resource.resource_type)
-        .bind(create_resource.data)
+        .bind(data_hash)
         .bind(create_resource.metadata)
         .fetch_one(&self.pool)
         .await?;
@@ -59,7 +66,7 @@ impl ResourceService {
             r#"
             SELECT r.* FROM resources r
             JOIN notebooks n ON r.notebook_id = n.id
-            WHERE r.id = $1 AND n.user_id = $2
+            WHERE r.id = $1 AND n.user_id = $2 AND n.deleted_at IS NULL
             "#,
         )
         .bind(id)
@@ -97,11 +104,12 @@ impl ResourceService {
             has_changes = true;
         }
 
-        if let Some(data) = &update_resource.data {
+        if let Some(data) = update_resource.data {
+            let data_hash = self.blobs.put(data).await?;
             if has_changes {
                 query_builder.push(", ");
             }
-            query_builder.push("data = ").push_bind(data);
+            query_builder.push("data_hash = ").push_bind(data_hash);
             has_changes = true;
         }
 
@@ -120,7 +128,7 @@ impl ResourceService {
         query_builder.push(
             r#"
             WHERE id = $1 AND notebook_id IN (
-                SELECT id FROM notebooks WHERE user_id = $2
+                SELECT id FROM notebooks WHERE user_id = $2 AND deleted_at IS NULL
             )
             RETURNING *
             "#,
@@ -141,7 +149,7 @@ impl ResourceService {
             r#"
             DELETE FROM resources
             WHERE id = $1 AND notebook_id IN (
-                SELECT id FROM notebooks WHERE user_id = $2
+                SELECT id FROM notebooks WHERE user_id = $2 AND deleted_at IS NULL
             )
             "#,
         )
@@ -1,7 +1,17 @@
-use sqlx::{QueryBuilder, SqlitePool};
+use std::collections::{HashMap, HashSet};
+
+use senra_api::{
+    CellPayload, CompileTarget, FieldError, LibraryImport, NotebookContent, PackageError,
+    PackageImport, PackageLock, PackageSource, ShaderCompileError, ShaderLanguage, ShaderSource,
+    ShaderStage, Version, check_package_name, package_imports, qualify_imports, shader_imports,
+};
+use sqlx::{QueryBuilder, SqliteConnection, SqlitePool};
 
 use crate::errors::{NotebookError, Result, ShaderError};
-use crate::models::{CreateShader, Shader, ShaderVersion, UpdateShader};
+use crate::models::{
+    CreateShader, PackageNotebookDependent, PackageVersionDependent, PublishPackage, Shader,
+    ShaderPackage, ShaderPackageVersion, ShaderVersion, UpdateShader,
+};
 
 #[derive(Clone)]
 pub struct ShaderService {
@@ -14,6 +24,8 @@ impl ShaderService {
     }
 
     pub async fn create_shader(&self, user_id: i64, create_shader: CreateShader) -> Result<Shader> {
+        check_shader_type(&create_shader.shader_type)?;
+
         let mut tx = self.pool.begin().await?;
 
         // Verify notebook ownership
@@ -21,7 +33,7 @@ impl ShaderService {
             r#"
             SELECT EXISTS(
                 SELECT 1 FROM notebooks
-                WHERE id = $1 AND user_id = $2
+                WHERE id = $1 AND user_id = $2 AND deleted_at IS NULL
             )
             "#,
         )
@@ -77,12 +89,20 @@ impl ShaderService {
         Ok(shaders)
     }
 
+    /// Retrieves a shader published as a library module by a public notebook
+    pub async fn get_library_module(&self, notebook_id: i64, name: &str) -> Result<Shader> {
+        let mut conn = self.pool.acquire().await?;
+        let shader = find_library_module(&mut conn, notebook_id, name).await?;
+
+        Ok(shader.ok_or(ShaderError::NotFound)?)
+    }
+
     pub async fn get_shader(&self, user_id: i64, id: i64) -> Result<Shader> {
         let shader: Option<Shader> = sqlx::query_as(
             r#"
             SELECT s.* FROM shaders s
             JOIN notebooks n ON s.notebook_id = n.id
-            WHERE s.id = $1 AND n.user_id = $2
+            WHERE s.id = $1 AND n.user_id = $2 AND n.deleted_at IS NULL
             "#,
         )
         .bind(id)
@@ -110,6 +130,7 @@ impl ShaderService {
         }
 
         if let Some(shader_type) = &update_shader.shader_type {
+            check_shader_type(shader_type)?;
             if has_changes {
                 query_builder.push(", ");
             }
@@ -162,7 +183,7 @@ impl ShaderService {
         query_builder.push(
             r#"
             WHERE id = $1 AND notebook_id IN (
-                SELECT id FROM notebooks WHERE user_id = $2
+                SELECT id FROM notebooks WHERE user_id = $2 AND deleted_at IS NULL
             )
             RETURNING *
             "#,
@@ -183,7 +204,7 @@ impl ShaderService {
             r#"
             DELETE FROM shaders
             WHERE id = $1 AND notebook_id IN (
-                SELECT id FROM notebooks WHERE user_id = $2
+                SELECT id FROM notebooks WHERE user_id = $2 AND deleted_at IS NULL
             )
             "#,
         )
@@ -250,4 +271,431 @@ impl ShaderService {
 
         Ok((versions, total))
     }
+
+    /// Publishes the current code of a WGSL shader as a new version of a package
+    ///
+    /// The first publication creates the package, later versions may only be published by its
+    /// owner. Published versions are immutable, publishing a version twice fails. Packages may
+    /// only import other packages, so that they compile the same in every notebook.
+    pub async fn publish_package(
+        &self,
+        user_id: i64,
+        publish: PublishPackage,
+    ) -> Result<ShaderPackageVersion> {
+        check_package_name(&publish.name).map_err(ShaderError::InvalidPackage)?;
+        let version: Version = publish
+            .version
+            .parse()
+            .map_err(ShaderError::InvalidPackage)?;
+
+        let shader = self.get_shader(user_id, publish.shader_id).await?;
+        if check_shader_type(&shader.shader_type)? != ShaderLanguage::Wgsl {
+            Err(ShaderError::InvalidData(
+                "Only WGSL shaders can be published as packages".to_string(),
+            ))?;
+        }
+        if let Some(import) = shader_imports(&shader.code)
+            .into_iter()
+            .find(|import| PackageImport::parse(import).is_none())
+        {
+            Err(ShaderError::InvalidData(format!(
+                "Packages can only import other packages, found `{}`",
+                import
+            )))?;
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let dependencies = package_versions(&mut tx, &[&shader.code]).await?;
+        let lock = resolve_packages(&[&shader.code], &dependencies)
+            .map_err(ShaderError::InvalidPackage)?;
+        let preprocessed = lock
+            .preprocess(&publish.name, &shader.code, |_| None)
+            .map_err(|err| ShaderError::CompilationError(err.to_string()))?;
+        ShaderSource::preprocessed(&preprocessed, ShaderLanguage::Wgsl)
+            .compile(&ShaderStage::Fragment, CompileTarget::Wgsl)
+            .map_err(|err| ShaderError::CompilationError(err.to_string()))?;
+
+        let package: Option<ShaderPackage> = sqlx::query_as(
+            r#"
+            SELECT * FROM shader_packages
+            WHERE name = $1
+            "#,
+        )
+        .bind(&publish.name)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        let package = match package {
+            Some(package) if package.user_id != user_id => Err(ShaderError::PermissionDenied)?,
+            Some(package) => package,
+            None => {
+                sqlx::query_as(
+                    r#"
+                    INSERT INTO shader_packages (user_id, name, description)
+                    VALUES ($1, $2, $3)
+                    RETURNING *
+                    "#,
+                )
+                .bind(user_id)
+                .bind(&publish.name)
+                .bind(&publish.description)
+                .fetch_one(&mut *tx)
+                .await?
+            }
+        };
+
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO shader_package_versions
+                (package_id, version, major, minor, patch, shader_type, code, shader_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (package_id, version) DO NOTHING
+            "#,
+        )
+        .bind(package.id)
+        .bind(version.to_string())
+        .bind(version.major as i64)
+        .bind(version.minor as i64)
+        .bind(version.patch as i64)
+        .bind(&shader.shader_type)
+        .bind(&shader.code)
+        .bind(shader.id)
+        .execute(&mut *tx)
+        .await?;
+
+        if inserted.rows_affected() == 0 {
+            Err(ShaderError::VersionExists(format!(
+                "{}@{}",
+                publish.name, version
+            )))?;
+        }
+        let package_version_id = inserted.last_insert_rowid();
+
+        // Record the pinned imports in the used-by graph
+        for import in package_imports(&shader.code) {
+            let Some(dependency) = dependencies.iter().find(|dependency| {
+                dependency.name == import.name && dependency.version == import.version.to_string()
+            }) else {
+                continue;
+            };
+            sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO shader_package_dependencies
+                    (package_version_id, dependency_version_id)
+                VALUES ($1, $2)
+                "#,
+            )
+            .bind(package_version_id)
+            .bind(dependency.id)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        let package_version = find_package_version(&mut tx, &publish.name, &version.to_string())
+            .await?
+            .ok_or(ShaderError::PackageNotFound)?;
+
+        tx.commit().await?;
+
+        Ok(package_version)
+    }
+
+    /// Retrieves a package along with its versions, from the oldest to the latest
+    pub async fn get_package(
+        &self,
+        name: &str,
+    ) -> Result<(ShaderPackage, Vec<ShaderPackageVersion>)> {
+        let package: Option<ShaderPackage> = sqlx::query_as(
+            r#"
+            SELECT * FROM shader_packages
+            WHERE name = $1
+            "#,
+        )
+        .bind(name)
+        .fetch_optional(&self.pool)
+        .await?;
+        let package = package.ok_or(ShaderError::PackageNotFound)?;
+
+        let versions = sqlx::query_as(
+            r#"
+            SELECT v.*, p.name FROM shader_package_versions v
+            JOIN shader_packages p ON v.package_id = p.id
+            WHERE v.package_id = $1
+            ORDER BY v.major, v.minor, v.patch
+            "#,
+        )
+        .bind(package.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((package, versions))
+    }
+
+    pub async fn get_package_version(
+        &self,
+        name: &str,
+        version: &str,
+    ) -> Result<ShaderPackageVersion> {
+        let mut conn = self.pool.acquire().await?;
+        let package_version = find_package_version(&mut conn, name, version).await?;
+
+        Ok(package_version.ok_or(ShaderError::PackageNotFound)?)
+    }
+
+    /// Lists the public notebooks resolving a package and the package versions importing it
+    pub async fn package_used_by(
+        &self,
+        name: &str,
+    ) -> Result<(Vec<PackageNotebookDependent>, Vec<PackageVersionDependent>)> {
+        let (package, _) = self.get_package(name).await?;
+
+        let notebooks = sqlx::query_as(
+            r#"
+            SELECT n.id AS notebook_id, n.title, v.version
+            FROM notebook_package_dependencies d
+            JOIN shader_package_versions v ON d.package_version_id = v.id
+            JOIN notebooks n ON d.notebook_id = n.id
+            WHERE v.package_id = $1 AND n.visibility = 'public' AND n.deleted_at IS NULL
+            ORDER BY n.id
+            "#,
+        )
+        .bind(package.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let packages = sqlx::query_as(
+            r#"
+            SELECT p.name, v.version, dv.version AS dependency_version
+            FROM shader_package_dependencies d
+            JOIN shader_package_versions v ON d.package_version_id = v.id
+            JOIN shader_packages p ON v.package_id = p.id
+            JOIN shader_package_versions dv ON d.dependency_version_id = dv.id
+            WHERE dv.package_id = $1
+            ORDER BY p.name, v.major, v.minor, v.patch
+            "#,
+        )
+        .bind(package.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok((notebooks, packages))
+    }
+}
+
+/// Checks that a `shader_type` names a supported shader language
+pub(crate) fn check_shader_type(shader_type: &str) -> Result<ShaderLanguage> {
+    Ok(shader_type
+        .parse()
+        .map_err(|err: ShaderCompileError| ShaderError::InvalidData(err.to_string()))?)
+}
+
+/// Fetches the library modules imported by `sources` and their own imports, keyed by import
+/// name, with their relative imports qualified
+///
+/// Missing modules are skipped, preprocessing reports them.
+pub(crate) async fn library_modules(
+    conn: &mut SqliteConnection,
+    sources: &[&str],
+) -> Result<HashMap<String, String>> {
+    let mut modules = HashMap::new();
+    let mut pending: Vec<String> = sources
+        .iter()
+        .flat_map(|code| shader_imports(code))
+        .map(str::to_string)
+        .collect();
+
+    while let Some(import) = pending.pop() {
+        let Some(library_import) = LibraryImport::parse(&import) else {
+            continue;
+        };
+        if modules.contains_key(&import) {
+            continue;
+        }
+        let Some(shader) =
+            find_library_module(conn, library_import.notebook_id, library_import.name).await?
+        else {
+            continue;
+        };
+
+        let code = qualify_imports(&shader.code, shader.notebook_id);
+        pending.extend(shader_imports(&code).into_iter().map(str::to_string));
+        modules.insert(import, code);
+    }
+
+    Ok(modules)
+}
+
+/// Resolves the modules a notebook shader may import: the notebook shaders by name, its code
+/// cells by id and the `library` modules of other notebooks
+pub(crate) fn resolve_module<'a>(
+    content: &'a NotebookContent,
+    shaders: &'a [Shader],
+    library: &'a HashMap<String, String>,
+) -> impl Fn(&str) -> Option<&'a str> {
+    move |name| {
+        if let Some(code) = library.get(name) {
+            return Some(code);
+        }
+        if let Some(shader) = shaders.iter().find(|shader| shader.name == name) {
+            return Some(&shader.code);
+        }
+        content.cells.iter().find_map(|cell| match &cell.payload {
+            CellPayload::Code(code) if cell.id == name => Some(code.as_str()),
+            _ => None,
+        })
+    }
+}
+
+async fn find_library_module(
+    conn: &mut SqliteConnection,
+    notebook_id: i64,
+    name: &str,
+) -> Result<Option<Shader>> {
+    let shader = sqlx::query_as(
+        r#"
+        SELECT s.* FROM shaders s
+        JOIN notebooks n ON s.notebook_id = n.id
+        WHERE n.id = $1 AND s.name = $2 AND n.visibility = 'public'
+            AND n.deleted_at IS NULL
+        "#,
+    )
+    .bind(notebook_id)
+    .bind(name)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(shader)
+}
+
+/// Fetches the package versions imported by `sources` and by the fetched versions themselves
+///
+/// Missing versions are skipped, resolution reports them.
+pub(crate) async fn package_versions(
+    conn: &mut SqliteConnection,
+    sources: &[&str],
+) -> Result<Vec<ShaderPackageVersion>> {
+    let mut versions: Vec<ShaderPackageVersion> = Vec::new();
+    let mut missing = HashSet::new();
+    let mut pending: Vec<(String, String)> = sources
+        .iter()
+        .flat_map(|code| package_imports(code))
+        .map(|import| (import.name.to_string(), import.version.to_string()))
+        .collect();
+
+    while let Some((name, version)) = pending.pop() {
+        let fetched = versions
+            .iter()
+            .any(|fetched| fetched.name == name && fetched.version == version);
+        if fetched || missing.contains(&(name.clone(), version.clone())) {
+            continue;
+        }
+        let Some(package_version) = find_package_version(conn, &name, &version).await? else {
+            missing.insert((name, version));
+            continue;
+        };
+
+        pending.extend(
+            package_imports(&package_version.code)
+                .into_iter()
+                .map(|import| (import.name.to_string(), import.version.to_string())),
+        );
+        versions.push(package_version);
+    }
+
+    Ok(versions)
+}
+
+/// Resolves the package imports of `sources` against fetched package versions
+pub(crate) fn resolve_packages(
+    sources: &[&str],
+    versions: &[ShaderPackageVersion],
+) -> std::result::Result<PackageLock, PackageError> {
+    let available: Vec<PackageSource> = versions
+        .iter()
+        .filter_map(|package_version| {
+            Some(PackageSource {
+                name: &package_version.name,
+                version: package_version.version.parse().ok()?,
+                code: &package_version.code,
+            })
+        })
+        .collect();
+
+    PackageLock::resolve(sources, &available)
+}
+
+/// Fetches and resolves the packages imported by the `sources` of a notebook
+pub(crate) async fn notebook_packages(
+    conn: &mut SqliteConnection,
+    sources: &[&str],
+) -> Result<(PackageLock, Vec<ShaderPackageVersion>)> {
+    let versions = package_versions(conn, sources).await?;
+    let lock = resolve_packages(sources, &versions).map_err(|err| {
+        NotebookError::InvalidReferences(vec![FieldError {
+            path: "/shaders".to_string(),
+            message: err.to_string(),
+        }])
+    })?;
+
+    Ok((lock, versions))
+}
+
+/// Records the package versions a notebook resolved to in the used-by graph
+pub(crate) async fn record_notebook_packages(
+    conn: &mut SqliteConnection,
+    notebook_id: i64,
+    lock: &PackageLock,
+    versions: &[ShaderPackageVersion],
+) -> Result<()> {
+    sqlx::query(
+        r#"
+        DELETE FROM notebook_package_dependencies
+        WHERE notebook_id = $1
+        "#,
+    )
+    .bind(notebook_id)
+    .execute(&mut *conn)
+    .await?;
+
+    for import in lock.imports() {
+        let Some(package_version) = versions.iter().find(|package_version| {
+            package_version.name == import.name
+                && package_version.version == import.version.to_string()
+        }) else {
+            continue;
+        };
+        sqlx::query(
+            r#"
+            INSERT INTO notebook_package_dependencies (notebook_id, package_version_id)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(notebook_id)
+        .bind(package_version.id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
+async fn find_package_version(
+    conn: &mut SqliteConnection,
+    name: &str,
+    version: &str,
+) -> Result<Option<ShaderPackageVersion>> {
+    let package_version = sqlx::query_as(
+        r#"
+        SELECT v.*, p.name FROM shader_package_versions v
+        JOIN shader_packages p ON v.package_id = p.id
+        WHERE p.name = $1 AND v.version = $2
+        "#,
+    )
+    .bind(name)
+    .bind(version)
+    .fetch_optional(conn)
+    .await?;
+
+    Ok(package_version)
 }
@@ -0,0 +1,298 @@
+use std::sync::Arc;
+
+use hmac::{Hmac, Mac};
+use qrcode::QrCode;
+use qrcode::render::svg;
+use rand::Rng;
+use sha2::Sha256;
+use sqlx::SqlitePool;
+use time::OffsetDateTime;
+use totp_rs::{Algorithm, Secret, TOTP};
+
+use crate::errors::{AppError, AuthError, Result};
+use crate::models::{User, UserTwoFactor};
+
+/// Issuer shown next to the account in authenticator apps
+const ISSUER: &str = "ShaderLab";
+const DIGITS: usize = 6;
+const STEP: u64 = 30;
+/// Steps a code is still accepted before or after its own, to allow for clock drift
+const SKEW: u64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+/// Characters of recovery codes, leaving out the ones easily mistaken for another
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+/// Secret and provisioning data of a second factor being set up
+pub struct TwoFactorSetup {
+    pub secret: String,
+    pub provisioning_uri: String,
+    pub qr_code: String,
+}
+
+#[derive(Clone)]
+pub struct TwoFactorService {
+    pool: SqlitePool,
+    /// Key of the recovery code hashes, so a leaked database alone can't be brute-forced
+    secret: Arc<str>,
+}
+
+impl TwoFactorService {
+    pub fn new(pool: &SqlitePool, secret: &str) -> Self {
+        Self {
+            pool: pool.clone(),
+            secret: Arc::from(secret),
+        }
+    }
+
+    pub async fn is_enabled(&self, user_id: i64) -> Result<bool> {
+        Ok(self
+            .two_factor(user_id)
+            .await?
+            .is_some_and(|two_factor| two_factor.enabled_at.is_some()))
+    }
+
+    /// Whether the second factor of a user is enabled, and how many recovery codes are unused
+    pub async fn status(&self, user_id: i64) -> Result<(bool, u32)> {
+        if !self.is_enabled(user_id).await? {
+            return Ok((false, 0));
+        }
+
+        let left: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM user_recovery_codes WHERE user_id = $1 AND used_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok((true, left as u32))
+    }
+
+    /// Generates a new secret for a user, replacing any previous pending one. The second factor
+    /// stays disabled until a code of the secret is confirmed with `enable`
+    pub async fn setup(&self, user: &User) -> Result<TwoFactorSetup> {
+        if self.is_enabled(user.id).await? {
+            return Err(AuthError::TwoFactorAlreadyEnabled.into());
+        }
+
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        let totp = totp(&secret, &user.username)?;
+        let provisioning_uri = totp.get_url();
+        let qr_code = QrCode::new(provisioning_uri.as_bytes())
+            .map_err(|_| AppError::InternalError("Failed to render QR code".to_string()))?
+            .render::<svg::Color>()
+            .min_dimensions(200, 200)
+            .build();
+
+        sqlx::query(
+            r#"
+            INSERT INTO user_two_factor (user_id, secret)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE
+            SET secret = excluded.secret, last_step = NULL, created_at = CURRENT_TIMESTAMP
+            "#,
+        )
+        .bind(user.id)
+        .bind(&secret)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(TwoFactorSetup {
+            secret,
+            provisioning_uri,
+            qr_code,
+        })
+    }
+
+    /// Enables the pending second factor of a user once a code of it is confirmed, returning
+    /// the recovery codes
+    pub async fn enable(&self, user_id: i64, code: &str) -> Result<Vec<String>> {
+        let two_factor = self
+            .two_factor(user_id)
+            .await?
+            .ok_or(AuthError::TwoFactorNotSetUp)?;
+        if two_factor.enabled_at.is_some() {
+            return Err(AuthError::TwoFactorAlreadyEnabled.into());
+        }
+        self.verify_totp(&two_factor, code).await?;
+
+        sqlx::query("UPDATE user_two_factor SET enabled_at = $2 WHERE user_id = $1")
+            .bind(user_id)
+            .bind(OffsetDateTime::now_utc())
+            .execute(&self.pool)
+            .await?;
+
+        self.replace_recovery_codes(user_id).await
+    }
+
+    /// Disables the second factor of a user, confirmed by a code or a recovery code
+    pub async fn disable(&self, user_id: i64, code: &str) -> Result<()> {
+        self.verify(user_id, code).await?;
+
+        sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+        sqlx::query("DELETE FROM user_two_factor WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the recovery codes of a user, confirmed by a code or a recovery code
+    pub async fn regenerate_recovery_codes(&self, user_id: i64, code: &str) -> Result<Vec<String>> {
+        self.verify(user_id, code).await?;
+
+        self.replace_recovery_codes(user_id).await
+    }
+
+    /// Checks a code of the enabled second factor of a user, either a TOTP code which can't be
+    /// used twice or a recovery code which is used up
+    pub async fn verify(&self, user_id: i64, code: &str) -> Result<()> {
+        let two_factor = self
+            .two_factor(user_id)
+            .await?
+            .filter(|two_factor| two_factor.enabled_at.is_some())
+            .ok_or(AuthError::TwoFactorNotEnabled)?;
+
+        let code = code.trim();
+        if code.len() == DIGITS && code.bytes().all(|byte| byte.is_ascii_digit()) {
+            return self.verify_totp(&two_factor, code).await;
+        }
+
+        let used = sqlx::query(
+            r#"
+            UPDATE user_recovery_codes SET used_at = CURRENT_TIMESTAMP
+            WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+            "#,
+        )
+        .bind(user_id)
+        .bind(self.hash_recovery_code(code))
+        .execute(&self.pool)
+        .await?;
+
+        if used.rows_affected() == 0 {
+            return Err(AuthError::InvalidTwoFactorCode.into());
+        }
+
+        Ok(())
+    }
+
+    async fn two_factor(&self, user_id: i64) -> Result<Option<UserTwoFactor>> {
+        let two_factor = sqlx::query_as(
+            r#"
+            SELECT user_id, secret, enabled_at, last_step, created_at
+            FROM user_two_factor
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(two_factor)
+    }
+
+    async fn verify_totp(&self, two_factor: &UserTwoFactor, code: &str) -> Result<()> {
+        let totp = totp(&two_factor.secret, "")?;
+        let current = OffsetDateTime::now_utc().unix_timestamp() as u64 / STEP;
+
+        let step = (current.saturating_sub(SKEW)..=current + SKEW)
+            .find(|step| constant_time_eq(&totp.generate(step * STEP), code))
+            .ok_or(AuthError::InvalidTwoFactorCode)?;
+
+        // Only a later step than the last accepted one is taken, so a code can't be replayed
+        let accepted = sqlx::query(
+            r#"
+            UPDATE user_two_factor SET last_step = $2
+            WHERE user_id = $1 AND (last_step IS NULL OR last_step < $2)
+            "#,
+        )
+        .bind(two_factor.user_id)
+        .bind(step as i64)
+        .execute(&self.pool)
+        .await?;
+
+        if accepted.rows_affected() == 0 {
+            return Err(AuthError::InvalidTwoFactorCode.into());
+        }
+
+        Ok(())
+    }
+
+    async fn replace_recovery_codes(&self, user_id: i64) -> Result<Vec<String>> {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+            .map(|_| generate_recovery_code())
+            .collect();
+
+        let mut tx = self.pool.begin().await?;
+        sqlx::query("DELETE FROM user_recovery_codes WHERE user_id = $1")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        for code in &codes {
+            sqlx::query("INSERT INTO user_recovery_codes (user_id, code_hash) VALUES ($1, $2)")
+                .bind(user_id)
+                .bind(self.hash_recovery_code(code))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+
+        Ok(codes)
+    }
+
+    /// HMAC-SHA256 of a recovery code, ignoring case, spaces and dashes
+    fn hash_recovery_code(&self, code: &str) -> String {
+        let normalized: String = code
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect();
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(self.secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(normalized.as_bytes());
+        mac.finalize()
+            .into_bytes()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect()
+    }
+}
+
+fn totp(secret: &str, account_name: &str) -> Result<TOTP> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|_| AppError::InternalError("Invalid two-factor secret".to_string()))?;
+
+    // Unchecked as usernames may contain characters the otpauth label can't, they get escaped
+    Ok(TOTP::new_unchecked(
+        Algorithm::SHA1,
+        DIGITS,
+        SKEW as u8,
+        STEP,
+        secret,
+        Some(ISSUER.to_string()),
+        account_name.to_string(),
+    ))
+}
+
+/// Recovery code such as `k7hw2-qxm9d`
+fn generate_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let mut code: String = (0..10)
+        .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect();
+    code.insert(5, '-');
+    code
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
@@ -1,21 +1,26 @@
-use std::hash::{DefaultHasher, Hash, Hasher};
-use std::io::Cursor;
-
 use bcrypt::{DEFAULT_COST, hash};
-use image::{ImageBuffer, ImageFormat, Rgba};
-use sqlx::{QueryBuilder, SqlitePool};
+use sqlx::{QueryBuilder, Sqlite, SqlitePool, Transaction};
 
+use super::blob::BlobService;
+use crate::avatar::{self, AvatarVariant};
+use crate::config::AvatarConfig;
 use crate::errors::{AppError, Result, UserError};
-use crate::models::{CreateUser, EditUser, User};
+use crate::models::{CreateUser, EditUser, User, UserAvatar};
 
 #[derive(Clone)]
 pub struct UserService {
     pool: SqlitePool,
+    blobs: BlobService,
+    avatar: AvatarConfig,
 }
 
 impl UserService {
-    pub fn new(pool: &SqlitePool) -> Self {
-        Self { pool: pool.clone() }
+    pub fn new(pool: &SqlitePool, blobs: &BlobService, avatar: &AvatarConfig) -> Self {
+        Self {
+            pool: pool.clone(),
+            blobs: blobs.clone(),
+            avatar: avatar.clone(),
+        }
     }
 
     pub async fn get_user(&self, user_id: i64) -> Result<User> {
@@ -32,6 +37,79 @@ impl UserService {
         Ok(user.ok_or(UserError::UserNotFound)?)
     }
 
+    /// Number of registered users
+    pub async fn count_users(&self) -> Result<i64> {
+        Ok(sqlx::query_scalar("SELECT COUNT(*) FROM users")
+            .fetch_one(&self.pool)
+            .await?)
+    }
+
+    /// Follows another user, following them again being a no-op
+    pub async fn follow_user(&self, follower_id: i64, followee_id: i64) -> Result<()> {
+        if follower_id == followee_id {
+            return Err(UserError::SelfFollow.into());
+        }
+        self.get_user(followee_id).await?;
+
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO user_follows (follower_id, followee_id)
+            VALUES ($1, $2)
+            "#,
+        )
+        .bind(follower_id)
+        .bind(followee_id)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn unfollow_user(&self, follower_id: i64, followee_id: i64) -> Result<()> {
+        self.get_user(followee_id).await?;
+
+        sqlx::query("DELETE FROM user_follows WHERE follower_id = $1 AND followee_id = $2")
+            .bind(follower_id)
+            .bind(followee_id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn is_following(&self, follower_id: i64, followee_id: i64) -> Result<bool> {
+        let following = sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM user_follows
+                WHERE follower_id = $1 AND followee_id = $2
+            )
+            "#,
+        )
+        .bind(follower_id)
+        .bind(followee_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(following)
+    }
+
+    /// Returns the number of followers of a user and of users they follow
+    pub async fn follow_counts(&self, user_id: i64) -> Result<(i64, i64)> {
+        let counts = sqlx::query_as(
+            r#"
+            SELECT
+                (SELECT COUNT(*) FROM user_follows WHERE followee_id = $1),
+                (SELECT COUNT(*) FROM user_follows WHERE follower_id = $1)
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(counts)
+    }
+
     pub async fn create_user(&self, create_user: CreateUser) -> Result<User> {
         if create_user.username.is_empty() {
             return Err(UserError::InvalidUsername.into());
@@ -58,57 +136,28 @@ impl UserService {
         let password_hash = hash(create_user.password, DEFAULT_COST)
             .map_err(|_| AppError::InternalError("Failed to hash password".to_string()))?;
 
-        let mut hasher = DefaultHasher::new();
-        create_user.username.hash(&mut hasher);
-        let seed = hasher.finish();
-
-        let mut bytes = Vec::new();
-        let mut img = ImageBuffer::new(64, 64);
-
-        let color = Rgba([
-            ((seed >> 16) & 0xFF) as u8,
-            ((seed >> 8) & 0xFF) as u8,
-            (seed & 0xFF) as u8,
-            255,
-        ]);
-
-        let grid_size = 5;
-        let cell_size = 12;
-        let padding = (64 - grid_size * cell_size) / 2;
-
-        for y in 0..grid_size {
-            for x in 0..(grid_size / 2 + 1) {
-                let pattern = (seed >> (y * 3 + x)) & 0x7;
-                if pattern % 2 == 0 {
-                    for px in padding + x * cell_size..padding + (x + 1) * cell_size {
-                        for py in padding + y * cell_size..padding + (y + 1) * cell_size {
-                            *img.get_pixel_mut(px as u32, py as u32) = color;
-                            if x < grid_size / 2 {
-                                *img.get_pixel_mut((64 - px - 1) as u32, py as u32) = color;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::WebP)
-            .ok();
+        let variants = self
+            .put_variants(avatar::identicon(&create_user.username)?)
+            .await?;
+        let mut tx = self.pool.begin().await?;
 
         let user: User = sqlx::query_as(
             r#"
-            INSERT INTO users (username, email, password, avatar)
+            INSERT INTO users (username, email, password, avatar_hash)
             VALUES ($1, $2, $3, $4)
-            RETURNING id, username, email, password, avatar, created_at, updated_at
+            RETURNING id, username, email, password, avatar_hash, created_at, updated_at
             "#,
         )
         .bind(create_user.username)
         .bind(create_user.email)
         .bind(password_hash)
-        .bind(bytes)
-        .fetch_one(&self.pool)
+        .bind(default_variant(&variants))
+        .fetch_one(&mut *tx)
         .await?;
 
+        replace_variants(&mut tx, user.id, &variants).await?;
+        tx.commit().await?;
+
         Ok(user)
     }
 
@@ -149,13 +198,25 @@ impl UserService {
             has_changes = true;
         }
 
-        if let Some(avatar) = &edit_user.avatar {
-            if has_changes {
-                query_builder.push(", ");
+        let variants = match edit_user.avatar {
+            Some(avatar) => {
+                let config = self.avatar.clone();
+                let variants =
+                    tokio::task::spawn_blocking(move || avatar::process(&avatar, &config))
+                        .await
+                        .map_err(|err| AppError::InternalError(err.to_string()))??;
+                let variants = self.put_variants(variants).await?;
+                if has_changes {
+                    query_builder.push(", ");
+                }
+                query_builder
+                    .push("avatar_hash = ")
+                    .push_bind(default_variant(&variants));
+                has_changes = true;
+                Some(variants)
             }
-            query_builder.push("avatar = ").push_bind(avatar);
-            has_changes = true;
-        }
+            None => None,
+        };
 
         if !has_changes {
             return Err(UserError::NoChanges.into());
@@ -165,13 +226,100 @@ impl UserService {
             .push(", updated_at = datetime('now') WHERE id = ")
             .push_bind(user_id);
         query_builder
-            .push(" RETURNING id, username, email, password, avatar, created_at, updated_at");
+            .push(" RETURNING id, username, email, password, avatar_hash, created_at, updated_at");
 
+        let mut tx = self.pool.begin().await?;
         let user = query_builder
             .build_query_as::<User>()
-            .fetch_one(&self.pool)
+            .fetch_one(&mut *tx)
             .await?;
+        if let Some(variants) = variants {
+            replace_variants(&mut tx, user_id, &variants).await?;
+        }
+        tx.commit().await?;
 
         Ok(user)
     }
+
+    /// Replaces the avatar of a user with their default identicon
+    pub async fn reset_avatar(&self, user_id: i64) -> Result<User> {
+        let user = self.get_user(user_id).await?;
+        let variants = self
+            .put_variants(avatar::identicon(&user.username)?)
+            .await?;
+
+        let mut tx = self.pool.begin().await?;
+        let user: User = sqlx::query_as(
+            r#"
+            UPDATE users SET avatar_hash = $1, updated_at = datetime('now')
+            WHERE id = $2
+            RETURNING id, username, email, password, avatar_hash, created_at, updated_at
+            "#,
+        )
+        .bind(default_variant(&variants))
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        replace_variants(&mut tx, user_id, &variants).await?;
+        tx.commit().await?;
+
+        Ok(user)
+    }
+
+    /// Resized variants of the avatar of a user, smallest first
+    pub async fn avatar_variants(&self, user_id: i64) -> Result<Vec<UserAvatar>> {
+        let variants = sqlx::query_as(
+            r#"
+            SELECT size, hash FROM user_avatars
+            WHERE user_id = $1
+            ORDER BY size
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(variants)
+    }
+
+    /// Stores encoded avatar variants in the blob store
+    async fn put_variants(&self, variants: Vec<AvatarVariant>) -> Result<Vec<UserAvatar>> {
+        let mut stored = Vec::with_capacity(variants.len());
+        for variant in variants {
+            stored.push(UserAvatar {
+                size: variant.size as i64,
+                hash: self.blobs.put(variant.data).await?,
+            });
+        }
+        Ok(stored)
+    }
+}
+
+fn default_variant(variants: &[UserAvatar]) -> Option<String> {
+    variants
+        .iter()
+        .find(|variant| variant.size == avatar::DEFAULT_SIZE as i64)
+        .map(|variant| variant.hash.clone())
+}
+
+async fn replace_variants(
+    tx: &mut Transaction<'_, Sqlite>,
+    user_id: i64,
+    variants: &[UserAvatar],
+) -> Result<()> {
+    sqlx::query("DELETE FROM user_avatars WHERE user_id = $1")
+        .bind(user_id)
+        .execute(&mut **tx)
+        .await?;
+
+    for variant in variants {
+        sqlx::query("INSERT INTO user_avatars (user_id, size, hash) VALUES ($1, $2, $3)")
+            .bind(user_id)
+            .bind(variant.size)
+            .bind(&variant.hash)
+            .execute(&mut **tx)
+            .await?;
+    }
+
+    Ok(())
 }
@@ -1,22 +1,32 @@
 use std::sync::Arc;
 
+use crate::blob::{BlobStore, FsBackend, MemoryBackend};
 use crate::config::Config;
 use crate::db::Database;
+use crate::metrics::Metrics;
+use crate::rate_limit::{LoginLockout, RateLimiter};
 use crate::services::*;
 
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<Config>,
     pub db: Arc<Database>,
+    pub metrics: Metrics,
+    pub rate_limiter: RateLimiter,
     pub services: Services,
 }
 
 #[derive(Clone)]
 pub struct Services {
+    pub analytics: AnalyticsService,
     pub auth: AuthService,
+    pub blob: BlobService,
+    pub collection: CollectionService,
     pub notebook: NotebookService,
+    pub notification: NotificationService,
     pub resource: ResourceService,
     pub shader: ShaderService,
+    pub two_factor: TwoFactorService,
     pub user: UserService,
 }
 
@@ -25,17 +35,38 @@ impl AppState {
         let config = Arc::new(config);
         let db = Arc::new(db);
 
+        let store = match &config.blob.path {
+            Some(path) => BlobStore::new(
+                FsBackend::new(path).expect("Blob store directory should be writable"),
+            ),
+            None => BlobStore::new(MemoryBackend::new()),
+        };
+        let blob = BlobService::new(db.pool(), store);
+        let notification = NotificationService::new(db.pool());
+        let two_factor = TwoFactorService::new(db.pool(), &config.auth.jwt_secret);
         let services = Services {
-            auth: AuthService::new(db.pool(), &config.auth.jwt_secret),
-            notebook: NotebookService::new(db.pool()),
-            resource: ResourceService::new(db.pool()),
+            analytics: AnalyticsService::new(db.pool(), config.analytics.view_window),
+            auth: AuthService::new(
+                db.pool(),
+                &config.auth.jwt_secret,
+                LoginLockout::new(&config.rate_limit.lockout),
+                &two_factor,
+            ),
+            blob: blob.clone(),
+            collection: CollectionService::new(db.pool()),
+            notebook: NotebookService::new(db.pool(), &notification, &blob),
+            notification,
+            resource: ResourceService::new(db.pool(), &blob),
             shader: ShaderService::new(db.pool()),
-            user: UserService::new(db.pool()),
+            two_factor,
+            user: UserService::new(db.pool(), &blob, &config.avatar),
         };
 
         Self {
             config,
             db,
+            metrics: Metrics::new(),
+            rate_limiter: RateLimiter::new(),
             services,
         }
     }
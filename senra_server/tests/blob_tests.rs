@@ -0,0 +1,476 @@
+mod server;
+
+use axum::body::{Body, Bytes};
+use axum::http::{self, HeaderMap, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use senra_server::{AppState, Config, Database, create_router};
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    request: Request<Body>,
+) -> (StatusCode, HeaderMap, Bytes) {
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, headers, body)
+}
+
+fn get(uri: &str, headers: &[(http::HeaderName, &str)]) -> Request<Body> {
+    headers
+        .iter()
+        .fold(Request::builder().uri(uri), |builder, (name, value)| {
+            builder.header(name, *value)
+        })
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_blob_download() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let (status, _, body) = send(
+        &mut app,
+        Request::builder()
+            .uri("/user")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let hash = body["avatar"]["hash"].as_str().unwrap().to_string();
+    let url = body["avatar"]["url"].as_str().unwrap().to_string();
+    assert_eq!(url, format!("/blobs/{}", hash));
+
+    // Whole blob, cacheable forever
+    let (status, headers, avatar) = send(&mut app, get(&url, &[])).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[http::header::CONTENT_TYPE], "image/webp");
+    assert_eq!(
+        headers[http::header::CACHE_CONTROL],
+        "public, max-age=31536000, immutable"
+    );
+    assert_eq!(headers[http::header::ETAG], format!("\"{}\"", hash));
+    assert_eq!(headers[http::header::ACCEPT_RANGES], "bytes");
+    let size = avatar.len();
+    assert!(size > 8);
+
+    // Ranges
+    let (status, headers, body) =
+        send(&mut app, get(&url, &[(http::header::RANGE, "bytes=0-3")])).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        headers[http::header::CONTENT_RANGE],
+        format!("bytes 0-3/{}", size)
+    );
+    assert_eq!(body, avatar.slice(0..4));
+
+    let (status, headers, body) =
+        send(&mut app, get(&url, &[(http::header::RANGE, "bytes=-2")])).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        headers[http::header::CONTENT_RANGE],
+        format!("bytes {}-{}/{}", size - 2, size - 1, size)
+    );
+    assert_eq!(body, avatar.slice(size - 2..));
+
+    let range = format!("bytes={}-", size - 3);
+    let (status, _, body) = send(&mut app, get(&url, &[(http::header::RANGE, &range)])).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body, avatar.slice(size - 3..));
+
+    let range = format!("bytes={}-", size);
+    let (status, headers, _) = send(&mut app, get(&url, &[(http::header::RANGE, &range)])).await;
+    assert_eq!(status, StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        headers[http::header::CONTENT_RANGE],
+        format!("bytes */{}", size)
+    );
+
+    // Multiple ranges are not supported, the whole blob is sent instead
+    let (status, _, body) = send(
+        &mut app,
+        get(&url, &[(http::header::RANGE, "bytes=0-1,4-5")]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, avatar);
+
+    // Conditional requests
+    let etag = format!("\"{}\"", hash);
+    let (status, _, body) =
+        send(&mut app, get(&url, &[(http::header::IF_NONE_MATCH, &etag)])).await;
+    assert_eq!(status, StatusCode::NOT_MODIFIED);
+    assert!(body.is_empty());
+
+    // Unknown and malformed hashes
+    let (status, _, _) = send(&mut app, get(&format!("/blobs/{}", "0".repeat(64)), &[])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = send(&mut app, get("/blobs/..%2F..%2Fetc", &[])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_identical_blobs_are_shared() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let resource = |name: &str| {
+        json!({
+            "notebook_id": 0,
+            "name": name,
+            "resource_type": "texture",
+            "data": [1, 2, 3, 4],
+            "metadata": { "width": 2, "height": 2 }
+        })
+    };
+    let (status, _, body) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/notebooks")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_vec(&json!({
+                    "title": "Shared Blobs",
+                    "description": null,
+                    "content": { "cells": [] },
+                    "resources": [resource("first.png"), resource("second.png")],
+                    "shaders": [],
+                    "tags": [],
+                    "visibility": "public"
+                }))
+                .unwrap(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+
+    let resources = body["resources"].as_array().unwrap();
+    assert_eq!(resources.len(), 2);
+    assert_eq!(resources[0]["data"], resources[1]["data"]);
+
+    let url = resources[0]["data"]["url"].as_str().unwrap();
+    let (status, headers, data) = send(&mut app, get(url, &[])).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers[http::header::CONTENT_TYPE],
+        "application/octet-stream"
+    );
+    assert_eq!(data.as_ref(), [1, 2, 3, 4]);
+}
+
+#[tokio::test]
+async fn test_blob_visibility() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("blob_owner", "blob_owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let owner_token = format!("Bearer {}", server.create_token(owner.id).await.unwrap());
+    let other = server
+        .create_user("blob_other", "blob_other@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = format!("Bearer {}", server.create_token(other.id).await.unwrap());
+
+    let (status, _, body) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/notebooks")
+            .header(http::header::AUTHORIZATION, &owner_token)
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_vec(&json!({
+                    "title": "Private Blobs",
+                    "description": null,
+                    "content": { "cells": [] },
+                    "resources": [{
+                        "notebook_id": 0,
+                        "name": "secret.bin",
+                        "resource_type": "texture",
+                        "data": [9, 8, 7, 6, 5],
+                        "metadata": null
+                    }],
+                    "shaders": [],
+                    "tags": [],
+                    "visibility": "private"
+                }))
+                .unwrap(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let id = body["id"].as_i64().unwrap();
+    let url = body["resources"][0]["data"]["url"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    // Knowing the hash isn't enough to read the blob of a private notebook
+    let (status, _, _) = send(&mut app, get(&url, &[])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _, _) = send(
+        &mut app,
+        get(&url, &[(http::header::AUTHORIZATION, &other_token)]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Its owner reads it, kept out of shared caches
+    let (status, headers, data) = send(
+        &mut app,
+        get(&url, &[(http::header::AUTHORIZATION, &owner_token)]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(data.as_ref(), [9, 8, 7, 6, 5]);
+    assert_eq!(
+        headers[http::header::CACHE_CONTROL],
+        "private, max-age=31536000, immutable"
+    );
+
+    // Nor are conditional requests answered for blobs that can't be seen
+    let etag = format!("\"{}\"", url.trim_start_matches("/blobs/"));
+    let (status, _, _) = send(&mut app, get(&url, &[(http::header::IF_NONE_MATCH, &etag)])).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Blobs of trashed notebooks are hidden from everyone
+    let (status, _, _) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::DELETE)
+            .uri(format!("/notebooks/{}", id))
+            .header(http::header::AUTHORIZATION, &owner_token)
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = send(
+        &mut app,
+        get(&url, &[(http::header::AUTHORIZATION, &owner_token)]),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_blob_streamed_from_disk() {
+    let path = std::env::temp_dir().join(format!("senra_blobs_{}", std::process::id()));
+    let mut config = Config::default();
+    config.blob.path = Some(path.to_string_lossy().into_owned());
+    let db = Database::new(&config).await.unwrap();
+    db.run_migrations().await.unwrap();
+    let mut app = create_router(AppState::new(config, db)).into_service();
+
+    let (status, _, body) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/auth/register")
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_vec(&json!({
+                    "username": "disk_user",
+                    "email": "disk_user@test.com",
+                    "password": "test_password"
+                }))
+                .unwrap(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let url = body["user"]["avatar"]["url"].as_str().unwrap().to_string();
+    let hash = body["user"]["avatar"]["hash"].as_str().unwrap();
+    let stored = std::fs::read(path.join(&hash[..2]).join(hash)).unwrap();
+
+    let (status, headers, data) = send(&mut app, get(&url, &[])).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        headers[http::header::CONTENT_LENGTH],
+        stored.len().to_string()
+    );
+    assert_eq!(data.as_ref(), stored);
+
+    let (status, headers, data) =
+        send(&mut app, get(&url, &[(http::header::RANGE, "bytes=2-5")])).await;
+    assert_eq!(status, StatusCode::PARTIAL_CONTENT);
+    assert_eq!(headers[http::header::CONTENT_LENGTH], "4");
+    assert_eq!(data.as_ref(), &stored[2..6]);
+
+    std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[tokio::test]
+async fn test_unreferenced_blobs_are_swept() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("blob_sweeper", "blob_sweeper@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = format!("Bearer {}", server.create_token(user.id).await.unwrap());
+
+    let mut create_notebook = async |title: &str, data: &[u8]| {
+        let (status, _, body) = send(
+            &mut app,
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, &token)
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": title,
+                        "description": null,
+                        "content": { "cells": [] },
+                        "resources": [{
+                            "notebook_id": 0,
+                            "name": "data.bin",
+                            "resource_type": "texture",
+                            "data": data,
+                            "metadata": null
+                        }],
+                        "shaders": [],
+                        "tags": [],
+                        "visibility": "public"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let url = body["resources"][0]["data"]["url"].as_str().unwrap();
+        (
+            body["id"].as_i64().unwrap(),
+            url.trim_start_matches("/blobs/").to_string(),
+        )
+    };
+    let (purged_id, purged_hash) = create_notebook("Purged", &[1, 3, 5, 7]).await;
+    let (_, kept_hash) = create_notebook("Kept", &[2, 4, 6, 8]).await;
+
+    for request in [
+        Request::builder()
+            .method(http::Method::DELETE)
+            .uri(format!("/notebooks/{}", purged_id)),
+        Request::builder()
+            .method(http::Method::DELETE)
+            .uri(format!("/user/trash/{}", purged_id)),
+    ] {
+        let (status, _, _) = send(
+            &mut app,
+            request
+                .header(http::header::AUTHORIZATION, &token)
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    // Blobs are only deleted once unreferenced at two sweeps in a row
+    let blob = &server.get_state().services.blob;
+    assert_eq!(blob.sweep_orphans().await.unwrap(), 0);
+    assert!(blob.store().get(&purged_hash).await.unwrap().is_some());
+
+    assert_eq!(blob.sweep_orphans().await.unwrap(), 1);
+    assert!(blob.store().get(&purged_hash).await.unwrap().is_none());
+    assert!(blob.store().get(&kept_hash).await.unwrap().is_some());
+}
+
+#[tokio::test]
+async fn test_preview_of_others_is_not_stored() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("preview_owner", "preview_owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let other = server
+        .create_user("preview_other", "preview_other@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = format!("Bearer {}", server.create_token(other.id).await.unwrap());
+    let notebook = server
+        .create_notebook(owner.id, server::NotebookOptions::new())
+        .await
+        .unwrap();
+
+    let blob = &server.get_state().services.blob;
+    let stored = blob.store().list().await.unwrap().len();
+
+    let (status, _, _) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::PATCH)
+            .uri(format!("/notebooks/{}", notebook.id))
+            .header(http::header::AUTHORIZATION, &other_token)
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_vec(&json!({ "preview": "AQIDBA==" })).unwrap(),
+            ))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    assert_eq!(blob.store().list().await.unwrap().len(), stored);
+}
+
+#[tokio::test]
+async fn test_stored_orphans_are_not_swept() {
+    let server = MockServer::new().await;
+    let blob = &server.get_state().services.blob;
+
+    let hash = blob.put(vec![9, 8, 7, 6]).await.unwrap();
+    assert_eq!(blob.sweep_orphans().await.unwrap(), 0);
+
+    // Storing it again before its row is written takes it off the orphans of the last sweep
+    assert_eq!(blob.put(vec![9, 8, 7, 6]).await.unwrap(), hash);
+    assert_eq!(blob.sweep_orphans().await.unwrap(), 0);
+    assert!(blob.store().get(&hash).await.unwrap().is_some());
+
+    assert_eq!(blob.sweep_orphans().await.unwrap(), 1);
+    assert!(blob.store().get(&hash).await.unwrap().is_none());
+}
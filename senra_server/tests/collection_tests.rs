@@ -0,0 +1,364 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::{MockServer, NotebookOptions};
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(http::header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    };
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn notebook_ids(collection: &Value) -> Vec<i64> {
+    collection["notebooks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|notebook| notebook["id"].as_i64().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_collection_crud_and_ordering() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+    let other_user = server
+        .create_user("other_user", "other_user@test.com", "test_password")
+        .await
+        .unwrap();
+
+    let mut notebooks = Vec::new();
+    for title in ["First", "Second", "Third"] {
+        let notebook = server
+            .create_notebook(user.id, NotebookOptions::new().with_title(title))
+            .await
+            .unwrap();
+        notebooks.push(notebook.id);
+    }
+    // Public notebooks of other users can be collected too
+    let other_notebook = server
+        .create_notebook(other_user.id, NotebookOptions::new())
+        .await
+        .unwrap();
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/collections",
+        Some(&token),
+        Some(json!({
+            "title": "Raymarching",
+            "description": "From spheres to fractals",
+            "visibility": "public",
+            "notebook_ids": [notebooks[1], notebooks[0]]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["title"], "Raymarching");
+    assert_eq!(body["notebook_count"], 2);
+    assert_eq!(body["author"]["id"], user.id);
+    assert_eq!(notebook_ids(&body), vec![notebooks[1], notebooks[0]]);
+    let uri = format!("/collections/{}", body["id"]);
+
+    // Appended by default, inserted at a position otherwise
+    let add = format!("{}/notebooks", uri);
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        &add,
+        Some(&token),
+        Some(json!({ "notebook_id": other_notebook.id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        notebook_ids(&body),
+        vec![notebooks[1], notebooks[0], other_notebook.id]
+    );
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        &add,
+        Some(&token),
+        Some(json!({ "notebook_id": notebooks[2], "position": 1 })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        notebook_ids(&body),
+        vec![notebooks[1], notebooks[2], notebooks[0], other_notebook.id]
+    );
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        &add,
+        Some(&token),
+        Some(json!({ "notebook_id": notebooks[2] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::DELETE,
+        &format!("{}/notebooks/{}", uri, notebooks[1]),
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        notebook_ids(&body),
+        vec![notebooks[2], notebooks[0], other_notebook.id]
+    );
+
+    // Editing the notebooks replaces and reorders them
+    let (status, body) = send(
+        &mut app,
+        http::Method::PATCH,
+        &uri,
+        Some(&token),
+        Some(json!({
+            "title": "Raymarching 101",
+            "notebook_ids": [notebooks[0], notebooks[2]]
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["title"], "Raymarching 101");
+    assert_eq!(body["description"], "From spheres to fractals");
+    assert_eq!(notebook_ids(&body), vec![notebooks[0], notebooks[2]]);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        &uri,
+        Some(&token),
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        &uri,
+        Some(&token),
+        Some(json!({ "notebook_ids": [notebooks[0], notebooks[0]] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    // Trashed notebooks are hidden from the collection
+    send(
+        &mut app,
+        http::Method::DELETE,
+        &format!("/notebooks/{}", notebooks[0]),
+        Some(&token),
+        None,
+    )
+    .await;
+    let (status, body) = send(&mut app, http::Method::GET, &uri, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["notebook_count"], 1);
+    assert_eq!(notebook_ids(&body), vec![notebooks[2]]);
+
+    let (status, _) = send(&mut app, http::Method::DELETE, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&mut app, http::Method::GET, &uri, Some(&token), None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_collection_permissions() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+    let other_user = server
+        .create_user("other_user", "other_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = server.create_token(other_user.id).await.unwrap();
+
+    let private_notebook = server
+        .create_notebook(
+            other_user.id,
+            NotebookOptions::new().with_visibility("private"),
+        )
+        .await
+        .unwrap();
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/collections",
+        Some(&token),
+        Some(json!({ "title": "Public", "description": null, "visibility": "public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let public_uri = format!("/collections/{}", body["id"]);
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/collections",
+        Some(&token),
+        Some(json!({ "title": "Drafts", "description": null, "visibility": "private" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let private_uri = format!("/collections/{}", body["id"]);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/collections",
+        Some(&token),
+        Some(json!({ "title": " ", "description": null, "visibility": "public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/collections",
+        Some(&token),
+        Some(json!({ "title": "Hidden", "description": null, "visibility": "unlisted" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // Private notebooks of other users can't be collected
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        &format!("{}/notebooks", public_uri),
+        Some(&token),
+        Some(json!({ "notebook_id": private_notebook.id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Private collections are hidden from others
+    let (status, _) = send(
+        &mut app,
+        http::Method::GET,
+        &private_uri,
+        Some(&other_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&mut app, http::Method::GET, &private_uri, None, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(
+        &mut app,
+        http::Method::GET,
+        &private_uri,
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Only the owner edits a collection
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        &public_uri,
+        Some(&other_token),
+        Some(json!({ "title": "Mine now" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &mut app,
+        http::Method::DELETE,
+        &public_uri,
+        Some(&other_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    let (status, _) = send(
+        &mut app,
+        http::Method::DELETE,
+        &private_uri,
+        Some(&other_token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let collections = format!("/user/{}/collections", user.id);
+    let (status, body) = send(
+        &mut app,
+        http::Method::GET,
+        &collections,
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 2);
+
+    let (status, body) = send(&mut app, http::Method::GET, &collections, None, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["collections"][0]["title"], "Public");
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::GET,
+        "/user/999999/collections",
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
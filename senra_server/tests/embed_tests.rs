@@ -0,0 +1,395 @@
+mod server;
+
+use std::io::Cursor;
+
+use axum::body::{Body, Bytes};
+use axum::http::{self, HeaderMap, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use http_body_util::BodyExt;
+use image::{ImageFormat, RgbaImage};
+use senra_server::Config;
+use serde_json::{Value, json};
+use server::{MockServer, NotebookOptions};
+use tower::{Service, ServiceExt};
+
+const FRAGMENT_SHADER: &str = r#"
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Bytes) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header(http::header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    };
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, headers, body)
+}
+
+fn render_cell(id: &str, shader_id: i64, width: u32, height: u32) -> Value {
+    json!({
+        "id": id,
+        "cell_type": "render",
+        "content": {
+            "width": width,
+            "height": height,
+            "shader_ids": [shader_id],
+            "resource_ids": [],
+            "pipeline": {
+                "shader_bindings": [
+                    { "shader_index": 0, "shader_stage": "fragment", "entry_point": "fs_main" }
+                ],
+                "vertex_attributes": [],
+                "resource_bindings": []
+            },
+            "camera": {
+                "position": [0.0, 0.0, 3.0],
+                "target": [0.0, 0.0, 0.0],
+                "up": [0.0, 1.0, 0.0],
+                "fov": 45.0,
+                "near": 0.1,
+                "far": 100.0
+            },
+            "performance": {}
+        }
+    })
+}
+
+fn preview_png() -> Vec<u8> {
+    let mut png = Vec::new();
+    RgbaImage::new(32, 18)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    png
+}
+
+/// Creates a notebook with two render cells, returning its id
+async fn create_render_notebook(
+    app: &mut RouterIntoService<Body>,
+    token: &str,
+    visibility: &str,
+) -> i64 {
+    let (status, _, body) = send(
+        app,
+        http::Method::POST,
+        "/notebooks",
+        Some(token),
+        Some(json!({
+            "title": "Plasma <3",
+            "description": null,
+            "content": { "cells": [] },
+            "resources": [],
+            "shaders": [{
+                "notebook_id": 0,
+                "name": "fragment",
+                "shader_type": "wgsl",
+                "code": FRAGMENT_SHADER
+            }],
+            "tags": [],
+            "preview": STANDARD.encode(preview_png()),
+            "visibility": visibility
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+
+    let (status, _, _) = send(
+        app,
+        http::Method::PATCH,
+        &format!("/notebooks/{}", id),
+        Some(token),
+        Some(json!({
+            "content": {
+                "cells": [
+                    { "id": "intro", "cell_type": "markdown", "content": "# Plasma" },
+                    render_cell("wide", shader_id, 1280, 720),
+                    render_cell("square", shader_id, 400, 400)
+                ]
+            }
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    id
+}
+
+#[tokio::test]
+async fn test_embed_page() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let id = create_render_notebook(&mut app, &token, "public").await;
+    let (status, headers, body) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("/embed/{}?cell=square&autoplay=0&t=2.5", id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        headers[http::header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html")
+    );
+    let page = String::from_utf8(body.to_vec()).unwrap();
+    assert!(page.contains("<title>Plasma &lt;3 - ShaderLab</title>"));
+    assert!(page.contains(r#"import init from "/viewer/senra_app.js""#));
+    assert!(page.contains("application/json+oembed"));
+    assert!(page.contains("cell%3Dsquare%26autoplay%3D0%26t%3D2.5"));
+    assert!(page.contains(r#"<img id="poster" src="/blobs/"#));
+
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("/embed/{}?cell=intro", id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Notebooks without render cells can't be embedded
+    let notebook = server
+        .create_notebook(user.id, NotebookOptions::new())
+        .await
+        .unwrap();
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("/embed/{}", notebook.id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_oembed() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+    let public_url = Config::default().embed.public_url;
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let id = create_render_notebook(&mut app, &token, "public").await;
+    let oembed = |url: String, extra: &str| {
+        format!(
+            "/oembed?url={}{}",
+            url.replace(':', "%3A")
+                .replace('/', "%2F")
+                .replace('?', "%3F")
+                .replace('=', "%3D")
+                .replace('&', "%26"),
+            extra
+        )
+    };
+
+    let (status, _, body) = send(
+        &mut app,
+        http::Method::GET,
+        &oembed(format!("{}/notebooks/{}", public_url, id), ""),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["type"], "rich");
+    assert_eq!(body["version"], "1.0");
+    assert_eq!(body["title"], "Plasma <3");
+    assert_eq!(body["author_name"], "test_user");
+    assert_eq!(body["width"], 1280);
+    assert_eq!(body["height"], 720);
+    assert!(
+        body["html"]
+            .as_str()
+            .unwrap()
+            .contains(&format!(r#"src="{}/embed/{}""#, public_url, id))
+    );
+    assert!(body["html"].as_str().unwrap().contains("Plasma &lt;3"));
+    assert!(
+        body["thumbnail_url"]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("{}/blobs/", public_url))
+    );
+    assert_eq!(body["thumbnail_width"], 32);
+    assert_eq!(body["thumbnail_height"], 18);
+
+    // The notebook page of the web app describes the same notebook
+    let (status, _, page) = send(
+        &mut app,
+        http::Method::GET,
+        &oembed(format!("{}/notebook/{}/", public_url, id), ""),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let page: Value = serde_json::from_slice(&page).unwrap();
+    assert_eq!(page["title"], body["title"]);
+    assert_eq!(page["html"], body["html"]);
+
+    // Embed options are kept and sizes fit the requested bounds
+    let (status, _, body) = send(
+        &mut app,
+        http::Method::GET,
+        &oembed(
+            format!("{}/embed/{}?cell=square&ui=0", public_url, id),
+            "&maxwidth=200&format=json",
+        ),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["width"], 200);
+    assert_eq!(body["height"], 200);
+    assert!(
+        body["html"]
+            .as_str()
+            .unwrap()
+            .contains(&format!("/embed/{}?cell=square&amp;ui=0", id))
+    );
+
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::GET,
+        &oembed(format!("{}/notebooks/{}", public_url, id), "&format=xml"),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_IMPLEMENTED);
+
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::GET,
+        &oembed(format!("https://example.com/notebooks/{}", id), ""),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_embed_respects_visibility() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+    let public_url = Config::default().embed.public_url;
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let id = create_render_notebook(&mut app, &token, "private").await;
+
+    // Embeds are loaded signed out, even the owner gets nothing for private notebooks
+    for token in [None, Some(token.as_str())] {
+        let (status, _, _) = send(
+            &mut app,
+            http::Method::GET,
+            &format!("/embed/{}", id),
+            token,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, _, _) = send(
+            &mut app,
+            http::Method::GET,
+            &format!("/oembed?url={}/notebooks/{}", public_url, id),
+            token,
+            None,
+        )
+        .await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    // Embeddable once public, until trashed
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        &format!("/notebooks/{}", id),
+        Some(&token),
+        Some(json!({ "visibility": "public" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("/embed/{}", id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    send(
+        &mut app,
+        http::Method::DELETE,
+        &format!("/notebooks/{}", id),
+        Some(&token),
+        None,
+    )
+    .await;
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("/embed/{}", id),
+        None,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
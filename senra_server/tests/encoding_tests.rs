@@ -0,0 +1,242 @@
+mod server;
+
+use axum::body::{Body, Bytes};
+use axum::http::{self, HeaderMap, Request, StatusCode};
+use axum::routing::{RouterIntoService, get};
+use axum::{Router, middleware};
+use http_body_util::BodyExt;
+use senra_api::{
+    BlobRef, CreateNotebookRequest, CreateResourceRequest, Encoding, NotebookResponse, UserResponse,
+};
+use senra_server::{Json, negotiate_encoding};
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    request: Request<Body>,
+) -> (StatusCode, HeaderMap, Bytes) {
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, headers, body)
+}
+
+fn create_request(data: Vec<u8>) -> CreateNotebookRequest {
+    CreateNotebookRequest {
+        title: "Encoded Notebook".to_string(),
+        description: None,
+        content: json!({ "cells": [] }),
+        resources: vec![CreateResourceRequest {
+            notebook_id: 0,
+            name: "noise.png".to_string(),
+            resource_type: "texture".to_string(),
+            data,
+            metadata: None,
+        }],
+        shaders: Vec::new(),
+        tags: Vec::new(),
+        preview: None,
+        visibility: "public".to_string(),
+    }
+}
+
+/// Field of a MessagePack map
+fn field<'a>(value: &'a rmpv::Value, key: &str) -> &'a rmpv::Value {
+    value
+        .as_map()
+        .unwrap()
+        .iter()
+        .find(|(name, _)| name.as_str() == Some(key))
+        .map(|(_, value)| value)
+        .unwrap()
+}
+
+async fn download(app: &mut RouterIntoService<Body>, url: &str) -> Bytes {
+    let request = Request::builder().uri(url).body(Body::empty()).unwrap();
+    let (status, _, body) = send(app, request).await;
+    assert_eq!(status, StatusCode::OK);
+    body
+}
+
+#[tokio::test]
+async fn test_message_pack_round_trip() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let data: Vec<u8> = (0..=255).rev().collect();
+    let body = Encoding::MessagePack
+        .encode(&create_request(data.clone()))
+        .unwrap();
+    let (status, headers, body) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/notebooks")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::CONTENT_TYPE, "application/msgpack")
+            .header(http::header::ACCEPT, "application/msgpack")
+            .body(Body::from(body))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[http::header::CONTENT_TYPE], "application/msgpack");
+    assert_eq!(headers[http::header::VARY], "accept");
+
+    let notebook: NotebookResponse = Encoding::MessagePack.decode(&body).unwrap();
+    assert_eq!(notebook.inner.title, "Encoded Notebook");
+    let stored = download(&mut app, &notebook.resources[0].data.url).await;
+    assert_eq!(stored.as_ref(), data.as_slice());
+
+    // Errors follow the negotiated encoding too
+    let (status, headers, body) = send(
+        &mut app,
+        Request::builder()
+            .uri("/user/999999")
+            .header(http::header::ACCEPT, "application/msgpack")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(headers[http::header::CONTENT_TYPE], "application/msgpack");
+    let error: Value = Encoding::MessagePack.decode(&body).unwrap();
+    assert!(error["error"].is_string());
+
+    let (status, headers, body) = send(
+        &mut app,
+        Request::builder()
+            .uri("/user")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::ACCEPT, "application/msgpack")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[http::header::CONTENT_TYPE], "application/msgpack");
+    let profile: UserResponse = Encoding::MessagePack.decode(&body).unwrap();
+    assert_eq!(profile.notebooks.total, 1);
+
+    // Truncated bodies are rejected before reaching handlers
+    let (status, _, _) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/notebooks")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::CONTENT_TYPE, "application/msgpack")
+            .body(Body::from(vec![0x81, 0xa5, b't']))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_json_base64_round_trip() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let data: Vec<u8> = (0..=255).collect();
+    let body = Encoding::Json
+        .encode(&create_request(data.clone()))
+        .unwrap();
+    let request: Value = serde_json::from_slice(&body).unwrap();
+    assert!(request["resources"][0]["data"].is_string());
+
+    let (status, headers, body) = send(
+        &mut app,
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/notebooks")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(body))
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[http::header::CONTENT_TYPE], "application/json");
+
+    let notebook: NotebookResponse = Encoding::Json.decode(&body).unwrap();
+    let stored = download(&mut app, &notebook.resources[0].data.url).await;
+    assert_eq!(stored.as_ref(), data.as_slice());
+}
+
+#[tokio::test]
+async fn test_message_pack_response_bytes_are_binary() {
+    let data: Vec<u8> = (0..=255).collect();
+    let blob = {
+        let data = data.clone();
+        move || {
+            let data = data.clone();
+            async move {
+                Json(senra_api::Response::Blob {
+                    blob: BlobRef::new("0".repeat(64)),
+                    data,
+                })
+            }
+        }
+    };
+    let mut app = Router::new()
+        .route("/blob", get(blob))
+        .layer(middleware::from_fn(negotiate_encoding))
+        .into_service();
+
+    let (status, headers, body) = send(
+        &mut app,
+        Request::builder()
+            .uri("/blob")
+            .header(http::header::ACCEPT, "application/msgpack")
+            .body(Body::empty())
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[http::header::CONTENT_TYPE], "application/msgpack");
+
+    let value = rmpv::decode::read_value(&mut body.as_ref()).unwrap();
+    let payload = field(&value, "payload");
+    assert_eq!(field(payload, "data"), &rmpv::Value::Binary(data.clone()));
+    match Encoding::MessagePack.decode(&body).unwrap() {
+        senra_api::Response::Blob { data: decoded, .. } => assert_eq!(decoded, data),
+        response => panic!("unexpected response {:?}", response),
+    }
+
+    // JSON clients get the same bytes as base64
+    let (status, headers, body) = send(
+        &mut app,
+        Request::builder().uri("/blob").body(Body::empty()).unwrap(),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(headers[http::header::CONTENT_TYPE], "application/json");
+    let value: Value = serde_json::from_slice(&body).unwrap();
+    assert!(value["payload"]["data"].is_string());
+    match Encoding::Json.decode(&body).unwrap() {
+        senra_api::Response::Blob { data: decoded, .. } => assert_eq!(decoded, data),
+        response => panic!("unexpected response {:?}", response),
+    }
+}
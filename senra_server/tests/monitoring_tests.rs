@@ -0,0 +1,128 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use senra_server::{AppState, Config, Database, create_router};
+use serde_json::Value;
+use server::{MockServer, NotebookOptions};
+use tower::{Service, ServiceExt};
+
+async fn get(app: &mut RouterIntoService<Body>, uri: &str) -> (StatusCode, String) {
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap();
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, String::from_utf8(body.to_vec()).unwrap())
+}
+
+/// Value of the sample of a metric with exactly the given labels
+fn sample(metrics: &str, metric: &str) -> Option<f64> {
+    metrics
+        .lines()
+        .find_map(|line| line.strip_prefix(metric)?.strip_prefix(' ')?.parse().ok())
+}
+
+#[tokio::test]
+async fn test_metrics() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let notebook = server
+        .create_notebook(user.id, NotebookOptions::new())
+        .await
+        .unwrap();
+
+    for _ in 0..2 {
+        let (status, _) = get(&mut app, &format!("/notebooks/{}", notebook.id)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+    let (status, _) = get(&mut app, "/notebooks/999999").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = get(&mut app, "/no/such/route").await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, metrics) = get(&mut app, "/metrics").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Requests are labelled by route pattern, not by path
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"senra_http_requests_total{method="GET",route="/notebooks/{id}",status="200"}"#
+        ),
+        Some(2.0)
+    );
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"senra_http_requests_total{method="GET",route="/notebooks/{id}",status="404"}"#
+        ),
+        Some(1.0)
+    );
+    assert!(!metrics.contains(r#"route="/notebooks/999999""#));
+    assert!(!metrics.contains("/no/such/route"));
+    assert_eq!(
+        sample(
+            &metrics,
+            r#"senra_http_request_duration_seconds_count{method="GET",route="/notebooks/{id}"}"#
+        ),
+        Some(3.0)
+    );
+
+    assert_eq!(sample(&metrics, "senra_notebooks"), Some(1.0));
+    assert_eq!(sample(&metrics, "senra_users"), Some(1.0));
+    assert_eq!(sample(&metrics, "senra_websocket_sessions"), Some(0.0));
+    assert!(sample(&metrics, "senra_db_pool_connections").unwrap() >= 1.0);
+    assert!(sample(&metrics, "senra_db_pool_idle_connections").is_some());
+}
+
+#[tokio::test]
+async fn test_liveness_and_readiness() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let (status, body) = get(&mut app, "/livez").await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        serde_json::from_str::<Value>(&body).unwrap()["status"],
+        "ok"
+    );
+
+    let (status, body) = get(&mut app, "/readyz").await;
+    assert_eq!(status, StatusCode::OK);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["checks"]["database"], "ok");
+    assert_eq!(body["checks"]["migrations"], "ok");
+
+    // A server whose database was never migrated is alive but not ready
+    let mut config = Config::default();
+    config.database.url = "sqlite:file:unmigrated?mode=memory&cache=shared".to_string();
+    let db = Database::new(&config).await.unwrap();
+    let mut app = create_router(AppState::new(config, db)).into_service();
+
+    let (status, _) = get(&mut app, "/livez").await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = get(&mut app, "/readyz").await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    let body: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(body["status"], "unavailable");
+    assert_eq!(body["checks"]["database"], "ok");
+    assert_eq!(body["checks"]["migrations"], "unknown");
+}
@@ -0,0 +1,236 @@
+mod server;
+
+use std::net::SocketAddr;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::{MockServer, NotebookOptions};
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: Option<&str>,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+    if let Some(token) = token {
+        request = request.header(http::header::AUTHORIZATION, format!("Bearer {}", token));
+    }
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let body = body.map_or(Body::empty(), |body| {
+        Body::from(serde_json::to_vec(&body).unwrap())
+    });
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request.body(body).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+/// Views a notebook anonymously over a connection from `peer`
+async fn view_from(
+    app: &mut RouterIntoService<Body>,
+    uri: &str,
+    peer: &str,
+    headers: &[(&str, &str)],
+) -> Value {
+    let peer: SocketAddr = format!("{}:40000", peer).parse().unwrap();
+    let mut request = Request::builder()
+        .method(http::Method::GET)
+        .uri(uri)
+        .extension(ConnectInfo(peer));
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_views_are_deduplicated() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("owner", "owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let owner_token = server.create_token(owner.id).await.unwrap();
+    let reader = server
+        .create_user("reader", "reader@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader_token = server.create_token(reader.id).await.unwrap();
+
+    let notebook = server
+        .create_notebook(owner.id, NotebookOptions::new())
+        .await
+        .unwrap();
+    let uri = format!("/notebooks/{}", notebook.id);
+
+    // The author's own views are not counted
+    let (status, body) = send(
+        &mut app,
+        http::Method::GET,
+        &uri,
+        Some(&owner_token),
+        &[],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["stats"]["view_count"], 0);
+
+    // Refreshes of a signed in reader are counted once
+    for _ in 0..3 {
+        send(
+            &mut app,
+            http::Method::GET,
+            &uri,
+            Some(&reader_token),
+            &[("Referer", "https://Forum.example.com:8080/thread?id=1")],
+            None,
+        )
+        .await;
+    }
+
+    // Anonymous viewers are told apart by their fingerprint
+    for peer in ["203.0.113.1", "203.0.113.1", "203.0.113.2"] {
+        view_from(&mut app, &uri, peer, &[("User-Agent", "test")]).await;
+    }
+    let body = view_from(&mut app, &uri, "203.0.113.1", &[("User-Agent", "test")]).await;
+    assert_eq!(body["stats"]["view_count"], 3);
+
+    // Rotating forwarded headers doesn't make a new viewer, as the peer is no trusted proxy
+    for attempt in 0..3 {
+        let forwarded_for = format!("192.0.2.{}", attempt);
+        view_from(
+            &mut app,
+            &uri,
+            "203.0.113.1",
+            &[
+                ("User-Agent", "test"),
+                ("X-Forwarded-For", &forwarded_for),
+                ("X-Real-IP", &forwarded_for),
+            ],
+        )
+        .await;
+    }
+    let body = view_from(&mut app, &uri, "203.0.113.1", &[("User-Agent", "test")]).await;
+    assert_eq!(body["stats"]["view_count"], 3);
+}
+
+#[tokio::test]
+async fn test_notebook_analytics() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("owner", "owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let owner_token = server.create_token(owner.id).await.unwrap();
+    let reader = server
+        .create_user("reader", "reader@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader_token = server.create_token(reader.id).await.unwrap();
+
+    let notebook = server
+        .create_notebook(owner.id, NotebookOptions::new())
+        .await
+        .unwrap();
+    let uri = format!("/notebooks/{}", notebook.id);
+
+    send(
+        &mut app,
+        http::Method::GET,
+        &uri,
+        Some(&reader_token),
+        &[("Referer", "https://Forum.example.com:8080/thread?id=1")],
+        None,
+    )
+    .await;
+    send(&mut app, http::Method::GET, &uri, None, &[], None).await;
+    send(
+        &mut app,
+        http::Method::POST,
+        &format!("{}/like", uri),
+        Some(&reader_token),
+        &[],
+        None,
+    )
+    .await;
+    send(
+        &mut app,
+        http::Method::POST,
+        &format!("{}/comments", uri),
+        Some(&reader_token),
+        &[],
+        Some(json!({ "content": "Nice" })),
+    )
+    .await;
+
+    let analytics = format!("{}/analytics?days=7", uri);
+    let (status, _) = send(
+        &mut app,
+        http::Method::GET,
+        &analytics,
+        Some(&reader_token),
+        &[],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::GET,
+        &analytics,
+        Some(&owner_token),
+        &[],
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let daily = body["daily"].as_array().unwrap();
+    assert_eq!(daily.len(), 7);
+    assert!(daily[..6].iter().all(|day| day["view_count"] == 0));
+    let today = &daily[6];
+    assert_eq!(today["view_count"], 2);
+    assert_eq!(today["like_count"], 1);
+    assert_eq!(today["comment_count"], 1);
+
+    assert_eq!(
+        body["referrers"],
+        json!([
+            { "referrer": null, "view_count": 1 },
+            { "referrer": "forum.example.com", "view_count": 1 },
+        ])
+    );
+}
@@ -0,0 +1,581 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use http_body_util::BodyExt;
+use senra_api::{CellPayload, NotebookArchive};
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+const FRAGMENT_SHADER: &str = r#"
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+fn render_content(shader_id: i64, resource_id: i64) -> Value {
+    json!({
+        "cells": [{
+            "id": "render",
+            "cell_type": "render",
+            "content": {
+                "width": 320,
+                "height": 240,
+                "shader_ids": [shader_id],
+                "resource_ids": [resource_id],
+                "pipeline": {
+                    "shader_bindings": [
+                        { "shader_index": 0, "shader_stage": "fragment", "entry_point": "fs_main" }
+                    ],
+                    "vertex_attributes": [],
+                    "resource_bindings": [
+                        { "resource_index": 0, "group": 0, "binding": 0, "binding_type": "texture" }
+                    ]
+                },
+                "camera": {
+                    "position": [0.0, 0.0, 3.0],
+                    "target": [0.0, 0.0, 0.0],
+                    "up": [0.0, 1.0, 0.0],
+                    "fov": 45.0,
+                    "near": 0.1,
+                    "far": 100.0
+                },
+                "performance": {}
+            }
+        }]
+    })
+}
+
+#[tokio::test]
+async fn test_notebook_archive_workflow() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+    let other = server
+        .create_user("other_user", "other_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = server.create_token(other.id).await.unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Archived Notebook",
+                        "description": "Exported and imported",
+                        "content": { "cells": [] },
+                        "resources": [{
+                            "notebook_id": 0,
+                            "name": "noise.png",
+                            "resource_type": "texture",
+                            "data": [1, 2, 3, 4],
+                            "metadata": { "width": 2, "height": 2 }
+                        }],
+                        "shaders": [{
+                            "notebook_id": 0,
+                            "name": "fragment",
+                            "shader_type": "wgsl",
+                            "code": FRAGMENT_SHADER
+                        }],
+                        "tags": ["archive"],
+                        "visibility": "private"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let notebook_id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+    let resource_id = body["resources"][0]["id"].as_i64().unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, resource_id)
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Private notebooks can only be exported by their owner
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!("/notebooks/{}/export", notebook_id))
+                .header(
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", other_token),
+                )
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!("/notebooks/{}/export", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[http::header::CONTENT_TYPE],
+        "application/zip"
+    );
+    assert_eq!(
+        response.headers()[http::header::CONTENT_DISPOSITION],
+        "attachment; filename=\"Archived_Notebook.senra\""
+    );
+    let archive_bytes = response.into_body().collect().await.unwrap().to_bytes();
+
+    let archive = NotebookArchive::from_bytes(&archive_bytes).unwrap();
+    assert_eq!(archive.title, "Archived Notebook");
+    assert_eq!(archive.tags, ["archive"]);
+    assert_eq!(archive.shaders[0].id, shader_id);
+    assert_eq!(archive.shaders[0].code, FRAGMENT_SHADER);
+    assert_eq!(archive.resources[0].data, [1, 2, 3, 4]);
+    assert_eq!(
+        archive.resources[0].metadata,
+        Some(json!({ "width": 2, "height": 2 }))
+    );
+
+    // Importing creates a copy whose content points at the new shaders and resources
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks/import")
+                .header(
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", other_token),
+                )
+                .header(http::header::CONTENT_TYPE, "application/zip")
+                .body(Body::from(archive_bytes))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_ne!(body["id"].as_i64().unwrap(), notebook_id);
+    assert_eq!(body["author"]["id"], other.id);
+    assert_eq!(body["title"], "Archived Notebook");
+    assert_eq!(body["visibility"], "private");
+
+    let imported_shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+    let imported_resource_id = body["resources"][0]["id"].as_i64().unwrap();
+    assert_ne!(imported_shader_id, shader_id);
+    assert_ne!(imported_resource_id, resource_id);
+
+    let content =
+        serde_json::from_value::<senra_api::NotebookContent>(body["content"].clone()).unwrap();
+    let CellPayload::Render(config) = &content.cells[0].payload else {
+        panic!("expected a render payload");
+    };
+    assert_eq!(config.shader_ids, [imported_shader_id]);
+    assert_eq!(config.resource_ids, [imported_resource_id]);
+
+    // Anything that is not an archive is rejected
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks/import")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, "application/zip")
+                .body(Body::from("not an archive"))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_shadertoy_import() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let export = |code: &str| {
+        json!({
+            "Shader": {
+                "info": { "id": "Xs3Xz8", "name": "Gradient", "username": "someone", "tags": ["2d"] },
+                "renderpass": [{
+                    "inputs": [],
+                    "outputs": [],
+                    "code": code,
+                    "name": "Image",
+                    "type": "image"
+                }]
+            }
+        })
+    };
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks/import/shadertoy")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&export(
+                        "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    fragColor = vec4(fragCoord / iResolution.xy, 0.5, 1.0);\n}",
+                    ))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["notebook"]["title"], "Gradient");
+    assert_eq!(body["notebook"]["tags"], json!(["2d"]));
+    assert_eq!(body["notebook"]["shaders"].as_array().unwrap().len(), 2);
+    assert_eq!(body["warnings"], json!([]));
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks/import/shadertoy")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&export(
+                        "void mainImage(out vec4 fragColor, in vec2 fragCoord) {\n    fragColor = missing(fragCoord);\n}",
+                    ))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["details"][0]["path"], "/renderpass/0/code");
+}
+
+#[tokio::test]
+async fn test_notebook_shader_export() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Exported Shaders",
+                        "content": { "cells": [] },
+                        "resources": [{
+                            "notebook_id": 0,
+                            "name": "noise.png",
+                            "resource_type": "texture",
+                            "data": [1, 2, 3, 4],
+                            "metadata": { "width": 2, "height": 2 }
+                        }],
+                        "shaders": [{
+                            "notebook_id": 0,
+                            "name": "fragment",
+                            "shader_type": "wgsl",
+                            "code": FRAGMENT_SHADER
+                        }],
+                        "tags": [],
+                        "visibility": "public"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let notebook_id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+    let resource_id = body["resources"][0]["id"].as_i64().unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, resource_id)
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!(
+                    "/notebooks/{}/export/shaders?targets=msl,spirv",
+                    notebook_id
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+
+    let paths: Vec<&str> = body["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| file["path"].as_str().unwrap())
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            format!("msl/{}-fragment.metal", shader_id),
+            format!("spirv/{}-fragment.spv", shader_id),
+        ]
+    );
+    assert_eq!(body["files"][1]["output"]["kind"], "spirv");
+    assert_eq!(body["errors"], json!([]));
+
+    let binding = &body["reflection"]["pipelines"][0]["bind_groups"][0]["bindings"][0];
+    assert_eq!(binding["binding_type"], "texture");
+    assert_eq!(binding["resource_id"], resource_id);
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!(
+                    "/notebooks/{}/export/shaders?targets=dxil",
+                    notebook_id
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_archive_upload_limit() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    // Archives past the framework default are read, up to the import limit
+    for (size, status) in [
+        (3 * 1024 * 1024, StatusCode::BAD_REQUEST),
+        (17 * 1024 * 1024, StatusCode::PAYLOAD_TOO_LARGE),
+    ] {
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/notebooks/import")
+                    .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                    .header(http::header::CONTENT_TYPE, "application/zip")
+                    .body(Body::from(vec![0; size]))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), status);
+    }
+}
+
+#[tokio::test]
+async fn test_shader_export_expands_imports() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Imported Shaders",
+                        "content": { "cells": [] },
+                        "resources": [],
+                        "shaders": [
+                            {
+                                "notebook_id": 0,
+                                "name": "color",
+                                "shader_type": "wgsl",
+                                "code": "fn color() -> vec4<f32> {\n    return vec4<f32>(fract(uniforms.time));\n}"
+                            },
+                            {
+                                "notebook_id": 0,
+                                "name": "fragment",
+                                "shader_type": "wgsl",
+                                "code": "#import \"color\"\n@fragment\nfn fs_main() -> @location(0) vec4<f32> {\n    return color();\n}"
+                            }
+                        ],
+                        "tags": [],
+                        "visibility": "public"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let notebook_id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][1]["id"].as_i64().unwrap();
+
+    // Imports are expanded and the uniforms of the prelude declared before cross-compiling
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!(
+                    "/notebooks/{}/export/shaders?targets=msl",
+                    notebook_id
+                ))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["errors"], json!([]));
+    assert!(
+        body["files"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|file| file["path"] == format!("msl/{}-fragment.metal", shader_id))
+    );
+}
@@ -2,11 +2,39 @@ mod server;
 
 use axum::body::Body;
 use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
 use http_body_util::BodyExt;
 use serde_json::{Value, json};
 use server::{MockServer, NotebookOptions};
 use tower::{Service, ServiceExt};
 
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+    let body = body.map_or(Body::empty(), |body| {
+        Body::from(serde_json::to_vec(&body).unwrap())
+    });
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request.body(body).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
 #[tokio::test]
 async fn test_notebook_comment_workflow() {
     let mut server = MockServer::new().await;
@@ -22,173 +50,313 @@ async fn test_notebook_comment_workflow() {
         .create_notebook(user.id, NotebookOptions::new())
         .await
         .unwrap();
+    let comments = format!("/notebooks/{}/comments", notebook.id);
 
     // Test creating a comment
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
-        .await
-        .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::POST)
-                .uri(format!("/notebooks/{}/comments", notebook.id))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-                .body(Body::from(
-                    serde_json::to_vec(&json!({
-                        "content": "This is a test comment"
-                    }))
-                    .unwrap(),
-                ))
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
-    let body = response.into_body().collect().await.unwrap().to_bytes();
-    let body: Value = serde_json::from_slice(&body).unwrap();
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &token,
+        Some(json!({ "content": "This is a test comment" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
     let comment_id = body["id"].as_i64().unwrap();
 
     // Test creating another comment
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
-        .await
-        .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::POST)
-                .uri(format!("/notebooks/{}/comments", notebook.id))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-                .body(Body::from(
-                    serde_json::to_vec(&json!({
-                        "content": "This is another test comment"
-                    }))
-                    .unwrap(),
-                ))
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &token,
+        Some(json!({ "content": "This is another test comment" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
 
     // Test getting comment list
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
-        .await
-        .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::GET)
-                .uri(format!("/notebooks/{}/comments", notebook.id))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
-    let body = response.into_body().collect().await.unwrap().to_bytes();
-    let body: Value = serde_json::from_slice(&body).unwrap();
+    let (status, body) = send(&mut app, http::Method::GET, &comments, &token, None).await;
+    assert_eq!(status, StatusCode::OK);
     assert_eq!(body["comments"].as_array().unwrap().len(), 2);
     assert_eq!(body["total"], 2);
 
     // Test pagination
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
-        .await
-        .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::GET)
-                .uri(format!(
-                    "/notebooks/{}/comments?page=1&per_page=1",
-                    notebook.id
-                ))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
-    let body = response.into_body().collect().await.unwrap().to_bytes();
-    let body: Value = serde_json::from_slice(&body).unwrap();
+    let (status, body) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("{}?page=1&per_page=1", comments),
+        &token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
     assert_eq!(body["comments"].as_array().unwrap().len(), 1);
     assert_eq!(body["total"], 2);
 
     // Test deleting a comment
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
-        .await
-        .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::DELETE)
-                .uri(format!(
-                    "/notebooks/{}/comments/{}",
-                    notebook.id, comment_id
-                ))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
-
-    assert_eq!(response.status(), StatusCode::OK);
+    let (status, _) = send(
+        &mut app,
+        http::Method::DELETE,
+        &format!("{}/{}", comments, comment_id),
+        &token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
 
     // Test deleting a non-existent comment
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+    let (status, _) = send(
+        &mut app,
+        http::Method::DELETE,
+        &format!("{}/999", comments),
+        &token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Test deleting a comment with invalid notebook ID
+    let (status, _) = send(
+        &mut app,
+        http::Method::DELETE,
+        &format!("/notebooks/999/comments/{}", comment_id),
+        &token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Verify comment has been deleted
+    let (status, body) = send(&mut app, http::Method::GET, &comments, &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["comments"].as_array().unwrap().len(), 1);
+    assert_eq!(body["total"], 1);
+}
+
+#[tokio::test]
+async fn test_threaded_comments() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
         .await
-        .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::DELETE)
-                .uri(format!("/notebooks/{}/comments/999", notebook.id))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .body(Body::empty())
-                .unwrap(),
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+    let other_user = server
+        .create_user("other_user", "other_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = server.create_token(other_user.id).await.unwrap();
+    let notebook = server
+        .create_notebook(
+            user.id,
+            NotebookOptions::new().with_content(json!({
+                "cells": [
+                    { "id": "intro", "cell_type": "markdown", "content": "# Title\n\nSome\ntext" }
+                ]
+            })),
         )
         .await
         .unwrap();
+    let comments = format!("/notebooks/{}/comments", notebook.id);
 
-    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let (status, root) = send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &token,
+        Some(json!({
+            "content": "Typo on **line 3**",
+            "anchor": { "cell_id": "intro", "start_line": 3, "end_line": 4 }
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(root["anchor"]["cell_id"], "intro");
+    assert_eq!(root["edited_at"], Value::Null);
+    let root_id = root["id"].as_i64().unwrap();
 
-    // Test deleting a comment with invalid notebook ID
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
-        .await
+    let (status, reply) = send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &other_token,
+        Some(json!({ "content": "Fixed", "parent_id": root_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(reply["parent_id"], root_id);
+    let reply_id = reply["id"].as_i64().unwrap();
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &token,
+        Some(json!({ "content": "Thanks", "parent_id": reply_id })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &token,
+        Some(json!({ "content": "Another thread" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Invalid replies and anchors
+    for body in [
+        json!({ "content": "  " }),
+        json!({ "content": "Reply", "parent_id": 999 }),
+        json!({ "content": "Anchor", "anchor": { "cell_id": "missing" } }),
+        json!({ "content": "Anchor", "anchor": { "cell_id": "intro", "start_line": 2 } }),
+        json!({ "content": "Anchor", "anchor": { "cell_id": "intro", "start_line": 4, "end_line": 5 } }),
+    ] {
+        let (status, _) = send(&mut app, http::Method::POST, &comments, &token, Some(body)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    // Pages hold top-level comments along with their replies
+    let (status, body) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("{}?page=2&per_page=1", comments),
+        &token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 2);
+    let contents: Vec<&str> = body["comments"]
+        .as_array()
         .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::DELETE)
-                .uri(format!("/notebooks/999/comments/{}", comment_id))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .body(Body::empty())
-                .unwrap(),
-        )
-        .await
-        .unwrap();
+        .iter()
+        .map(|comment| comment["content"].as_str().unwrap())
+        .collect();
+    assert_eq!(contents, ["Typo on **line 3**", "Fixed", "Thanks"]);
 
-    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    // Only the author edits a comment
+    let uri = format!("{}/{}", comments, reply_id);
+    let edit = json!({ "content": "Fixed in the last version" });
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        &uri,
+        &token,
+        Some(edit.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
 
-    // Verify comment has been deleted
-    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+    let (status, body) = send(
+        &mut app,
+        http::Method::PATCH,
+        &uri,
+        &other_token,
+        Some(edit),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["content"], "Fixed in the last version");
+    assert!(body["edited_at"].is_string());
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        &format!("{}/999", comments),
+        &token,
+        Some(json!({ "content": "Edit" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    // Deleting a comment removes its replies
+    let uri = format!("{}/{}", comments, root_id);
+    let (status, _) = send(&mut app, http::Method::DELETE, &uri, &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = send(&mut app, http::Method::GET, &comments, &token, None).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["comments"].as_array().unwrap().len(), 1);
+
+    let remaining: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM notebook_comments WHERE id = $1 OR parent_id = $1",
+    )
+    .bind(reply_id)
+    .fetch_one(server.get_db().pool())
+    .await
+    .unwrap();
+    assert_eq!(remaining, 0);
+}
+
+#[tokio::test]
+async fn test_comments_of_hidden_notebooks() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("owner", "owner@test.com", "test_password")
         .await
-        .unwrap()
-        .call(
-            Request::builder()
-                .method(http::Method::GET)
-                .uri(format!("/notebooks/{}/comments", notebook.id))
-                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
-                .body(Body::empty())
-                .unwrap(),
-        )
+        .unwrap();
+    let owner_token = server.create_token(owner.id).await.unwrap();
+    let commenter = server
+        .create_user("commenter", "commenter@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(commenter.id).await.unwrap();
+    let notebook = server
+        .create_notebook(owner.id, NotebookOptions::new())
         .await
         .unwrap();
+    let uri = format!("/notebooks/{}", notebook.id);
 
-    assert_eq!(response.status(), StatusCode::OK);
-    let body = response.into_body().collect().await.unwrap().to_bytes();
-    let body: Value = serde_json::from_slice(&body).unwrap();
-    assert_eq!(body["comments"].as_array().unwrap().len(), 1);
-    assert_eq!(body["total"], 1);
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        &format!("{}/comments", uri),
+        &token,
+        Some(json!({ "content": "Nice shader" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let comment = format!("{}/comments/{}", uri, body["id"]);
+
+    // Comments of a trashed notebook can't be edited or deleted, even by their authors
+    let (status, _) = send(&mut app, http::Method::DELETE, &uri, &owner_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        &comment,
+        &token,
+        Some(json!({ "content": "Edited" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = send(&mut app, http::Method::DELETE, &comment, &token, None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let restore = format!("/user/trash/{}/restore", notebook.id);
+    let (status, _) = send(&mut app, http::Method::POST, &restore, &owner_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::PATCH,
+        &comment,
+        &token,
+        Some(json!({ "content": "Edited" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["content"], "Edited");
+    let (status, _) = send(&mut app, http::Method::DELETE, &comment, &token, None).await;
+    assert_eq!(status, StatusCode::OK);
 }
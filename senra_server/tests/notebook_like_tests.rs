@@ -7,6 +7,35 @@ use serde_json::{Value, json};
 use server::MockServer;
 use tower::{Service, ServiceExt};
 
+async fn send(
+    app: &mut axum::routing::RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token));
+    let request = match body {
+        Some(body) => request
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_vec(&body).unwrap())),
+        None => request.body(Body::empty()),
+    };
+
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request.unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
 #[tokio::test]
 async fn test_notebook_like_workflow() {
     let mut server = MockServer::new().await;
@@ -163,3 +192,93 @@ async fn test_notebook_like_workflow() {
 
     assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
 }
+
+#[tokio::test]
+async fn test_like_requires_visible_notebook() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("like_owner", "like_owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let owner_token = server.create_token(owner.id).await.unwrap();
+    let other = server
+        .create_user("like_other", "like_other@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = server.create_token(other.id).await.unwrap();
+
+    let mut notebook_ids = Vec::new();
+    for visibility in ["private", "public"] {
+        let (status, body) = send(
+            &mut app,
+            http::Method::POST,
+            "/notebooks",
+            &owner_token,
+            Some(json!({
+                "title": "Hidden Notebook",
+                "description": null,
+                "content": { "cells": [] },
+                "resources": [],
+                "shaders": [],
+                "tags": [],
+                "visibility": visibility
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        notebook_ids.push(body["id"].as_i64().unwrap());
+    }
+    let (private_id, trashed_id) = (notebook_ids[0], notebook_ids[1]);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::DELETE,
+        &format!("/notebooks/{}", trashed_id),
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Neither a private notebook of someone else nor a trashed one can be liked or unliked
+    for id in [private_id, trashed_id] {
+        for action in ["like", "unlike"] {
+            let (status, _) = send(
+                &mut app,
+                http::Method::POST,
+                &format!("/notebooks/{}/{}", id, action),
+                &other_token,
+                None,
+            )
+            .await;
+            assert_eq!(status, StatusCode::NOT_FOUND);
+        }
+    }
+
+    let likes: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM notebook_likes WHERE user_id = $1")
+        .bind(other.id)
+        .fetch_one(server.get_db().pool())
+        .await
+        .unwrap();
+    assert_eq!(likes, 0);
+    let notifications: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM notifications WHERE user_id = $1")
+            .bind(owner.id)
+            .fetch_one(server.get_db().pool())
+            .await
+            .unwrap();
+    assert_eq!(notifications, 0);
+
+    // The owner still likes their own private notebook
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        &format!("/notebooks/{}/like", private_id),
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
@@ -0,0 +1,219 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use http_body_util::BodyExt;
+use senra_api::CURRENT_CONTENT_VERSION;
+use serde_json::{Value, json};
+use server::{MockServer, NotebookOptions};
+use tower::{Service, ServiceExt};
+
+fn legacy_content() -> Value {
+    json!({
+        "cells": [
+            { "id": 1, "cell_type": "markdown", "content": "# Legacy" },
+            {
+                "id": 2,
+                "cell_type": "render",
+                "content": json!({
+                    "width": 320,
+                    "height": 240,
+                    "shader_ids": [],
+                    "resource_ids": [],
+                    "pipeline": {
+                        "shader_bindings": [],
+                        "vertex_attributes": [],
+                        "resource_bindings": []
+                    },
+                    "camera": {
+                        "position": [0.0, 0.0, 3.0],
+                        "target": [0.0, 0.0, 0.0],
+                        "up": [0.0, 1.0, 0.0],
+                        "fov": 45.0,
+                        "near": 0.1,
+                        "far": 100.0
+                    },
+                    "performance": {}
+                })
+                .to_string()
+            }
+        ]
+    })
+}
+
+#[tokio::test]
+async fn test_notebook_content_migration() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+    let notebook = server
+        .create_notebook(user.id, NotebookOptions::new())
+        .await
+        .unwrap();
+
+    // Content is upgraded on write
+    assert_eq!(notebook.content["version"], CURRENT_CONTENT_VERSION);
+
+    // Simulate documents stored before versioning existed
+    let pool = server.get_db().pool();
+    sqlx::query("UPDATE notebooks SET content = $1 WHERE id = $2")
+        .bind(legacy_content())
+        .bind(notebook.id)
+        .execute(pool)
+        .await
+        .unwrap();
+    sqlx::query("UPDATE notebook_versions SET content = $1 WHERE notebook_id = $2")
+        .bind(legacy_content())
+        .bind(notebook.id)
+        .execute(pool)
+        .await
+        .unwrap();
+
+    // Content is upgraded on read
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!("/notebooks/{}", notebook.id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["content"]["version"], CURRENT_CONTENT_VERSION);
+    assert_eq!(body["content"]["cells"][0]["id"], "1");
+    assert_eq!(body["content"]["cells"][1]["content"]["width"], 320);
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!("/notebooks/{}/versions", notebook.id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["versions"][0]["content"]["version"],
+        CURRENT_CONTENT_VERSION
+    );
+
+    // Bulk migration rewrites the stored rows
+    let (notebooks, versions) = server.migrate_stored_content().await.unwrap();
+    assert_eq!((notebooks, versions), (1, 1));
+
+    let stored: Value = sqlx::query_scalar("SELECT content FROM notebooks WHERE id = $1")
+        .bind(notebook.id)
+        .fetch_one(server.get_db().pool())
+        .await
+        .unwrap();
+    assert_eq!(stored["version"], CURRENT_CONTENT_VERSION);
+    assert!(stored["cells"][1]["content"].is_object());
+
+    let (notebooks, versions) = server.migrate_stored_content().await.unwrap();
+    assert_eq!((notebooks, versions), (0, 0));
+
+    // Documents from an unknown version are rejected on write
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook.id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": { "version": "99.0", "cells": [] }
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_legacy_shader_types_migration() {
+    let mut server = MockServer::new().await;
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let notebook = server
+        .create_notebook(user.id, NotebookOptions::new())
+        .await
+        .unwrap();
+
+    // Simulate shaders stored when `shader_type` held their stage
+    let pool = server.get_db().pool();
+    let shader_id: i64 = sqlx::query_scalar(
+        "INSERT INTO shaders (notebook_id, name, shader_type, code) VALUES ($1, 'main', 'fragment', '') RETURNING id",
+    )
+    .bind(notebook.id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    let package_id: i64 = sqlx::query_scalar(
+        "INSERT INTO shader_packages (user_id, name) VALUES ($1, 'legacy') RETURNING id",
+    )
+    .bind(user.id)
+    .fetch_one(pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO shader_package_versions (package_id, version, major, minor, patch, shader_type, code) VALUES ($1, '1.0.0', 1, 0, 0, 'vertex', '')",
+    )
+    .bind(package_id)
+    .execute(pool)
+    .await
+    .unwrap();
+
+    sqlx::raw_sql(include_str!("../migrations/12_shader_languages.sql"))
+        .execute(pool)
+        .await
+        .unwrap();
+
+    let shader_type: String = sqlx::query_scalar("SELECT shader_type FROM shaders WHERE id = $1")
+        .bind(shader_id)
+        .fetch_one(pool)
+        .await
+        .unwrap();
+    assert_eq!(shader_type, "wgsl");
+    let shader_type: String =
+        sqlx::query_scalar("SELECT shader_type FROM shader_package_versions WHERE package_id = $1")
+            .bind(package_id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+    assert_eq!(shader_type, "wgsl");
+
+    // Published versions are immutable again
+    let update = sqlx::query("UPDATE shader_package_versions SET shader_type = 'glsl'")
+        .execute(pool)
+        .await;
+    assert!(update.is_err());
+}
@@ -0,0 +1,179 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use senra_api::NotebookSort;
+use serde_json::Value;
+use server::{MockServer, NotebookOptions};
+use time::OffsetDateTime;
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: &str,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn titles(body: &Value) -> Vec<String> {
+    body["notebooks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|notebook| notebook["title"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_ranked_feeds() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let author = server
+        .create_user("author", "author@test.com", "test_password")
+        .await
+        .unwrap();
+    let author_token = server.create_token(author.id).await.unwrap();
+    let reader = server
+        .create_user("reader", "reader@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader_token = server.create_token(reader.id).await.unwrap();
+
+    server
+        .create_notebook_with_stats(
+            author.id,
+            NotebookOptions::new().with_title("Classic"),
+            OffsetDateTime::now_utc() - time::Duration::days(30),
+            1000,
+            100,
+            10,
+        )
+        .await
+        .unwrap();
+    let rising = server
+        .create_notebook(author.id, NotebookOptions::new().with_title("Rising"))
+        .await
+        .unwrap();
+    server
+        .create_notebook(author.id, NotebookOptions::new().with_title("Latest"))
+        .await
+        .unwrap();
+
+    let like = format!("/notebooks/{}/like", rising.id);
+    send(&mut app, http::Method::POST, &like, &reader_token).await;
+
+    // Feeds are empty until the rankings are materialized
+    let (status, body) = send(&mut app, http::Method::GET, "/notebooks", &author_token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 0);
+
+    // Every public notebook is ranked once per ranked feed
+    let ranked = server.refresh_rankings().await.unwrap();
+    assert_eq!(ranked, 3 * NotebookSort::RANKED.len() as u64);
+
+    let (_, body) = send(&mut app, http::Method::GET, "/notebooks", &author_token).await;
+    assert_eq!(titles(&body), vec!["Classic", "Rising", "Latest"]);
+    assert_eq!(body["total"], 3);
+
+    // Only the like of this week counts for the weekly feed
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notebooks?sort=top-week",
+        &author_token,
+    )
+    .await;
+    assert_eq!(titles(&body), vec!["Rising", "Latest", "Classic"]);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notebooks?sort=top-all-time",
+        &author_token,
+    )
+    .await;
+    assert_eq!(titles(&body), vec!["Classic", "Rising", "Latest"]);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notebooks?sort=new",
+        &author_token,
+    )
+    .await;
+    assert_eq!(titles(&body), vec!["Latest", "Rising", "Classic"]);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::GET,
+        "/notebooks?sort=random",
+        &author_token,
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_stable_paging_with_ties() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let author = server
+        .create_user("author", "author@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(author.id).await.unwrap();
+
+    let updated_at = OffsetDateTime::now_utc();
+    for index in 0..5 {
+        server
+            .create_notebook_with_stats(
+                author.id,
+                NotebookOptions::new().with_title(&format!("Notebook {}", index)),
+                updated_at,
+                10,
+                1,
+                1,
+            )
+            .await
+            .unwrap();
+    }
+    server.refresh_rankings().await.unwrap();
+
+    for sort in ["hot", "top-week", "top-all-time", "new"] {
+        let mut listed = Vec::new();
+        for page in 1..=3 {
+            let uri = format!("/notebooks?sort={}&page={}&per_page=2", sort, page);
+            let (_, body) = send(&mut app, http::Method::GET, &uri, &token).await;
+            assert_eq!(body["total"], 5);
+            listed.extend(titles(&body));
+        }
+
+        // Equally scored notebooks are listed newest first, each exactly once
+        let expected: Vec<String> = (0..5)
+            .rev()
+            .map(|index| format!("Notebook {}", index))
+            .collect();
+        assert_eq!(listed, expected, "{}", sort);
+    }
+}
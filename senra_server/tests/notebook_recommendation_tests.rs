@@ -62,6 +62,7 @@ async fn test_notebook_recommendation_algorithm() {
             .await
             .unwrap(),
     ];
+    server.refresh_rankings().await.unwrap();
 
     let response = ServiceExt::<Request<Body>>::ready(&mut app)
         .await
@@ -0,0 +1,148 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+#[tokio::test]
+async fn test_notebook_schema_validation() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    // Schema is published
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri("/schema/notebook.json")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["title"], "NotebookContent");
+    assert!(body["$defs"]["RenderConfig"].is_object());
+
+    // Violations are reported with field paths
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Invalid Notebook",
+                        "description": null,
+                        "content": {
+                            "version": "1.1",
+                            "cells": [
+                                { "id": "a", "cell_type": "markdown", "content": 42 },
+                                { "id": "b", "cell_type": "python", "content": "print()" },
+                                {
+                                    "id": "c",
+                                    "cell_type": "render",
+                                    "content": {
+                                        "width": 320,
+                                        "height": 240,
+                                        "shader_ids": [],
+                                        "resource_ids": [],
+                                        "pipeline": {
+                                            "shader_bindings": [],
+                                            "vertex_attributes": [{
+                                                "name": "position",
+                                                "format": "vec3",
+                                                "offset": 0,
+                                                "stride": 12
+                                            }],
+                                            "resource_bindings": []
+                                        },
+                                        "camera": {
+                                            "position": [0.0, 0.0, 3.0],
+                                            "target": [0.0, 0.0, 0.0],
+                                            "up": [0.0, 1.0, 0.0],
+                                            "fov": 45.0,
+                                            "near": 0.1,
+                                            "far": 100.0
+                                        },
+                                        "performance": {}
+                                    }
+                                }
+                            ]
+                        },
+                        "resources": [],
+                        "shaders": [],
+                        "tags": [],
+                        "visibility": "public"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let paths: Vec<&str> = body["details"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|detail| detail["path"].as_str().unwrap())
+        .collect();
+    assert!(paths.contains(&"/cells/0/content"));
+    assert!(paths.contains(&"/cells/1/cell_type"));
+    assert!(paths.contains(&"/cells/2/content/pipeline/vertex_attributes/0/format"));
+
+    // Valid content is accepted
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Valid Notebook",
+                        "description": null,
+                        "content": {
+                            "cells": [
+                                { "id": "a", "cell_type": "markdown", "content": "# Hello" }
+                            ]
+                        },
+                        "resources": [],
+                        "shaders": [],
+                        "tags": [],
+                        "visibility": "public"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
@@ -0,0 +1,151 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use serde_json::Value;
+use server::{MockServer, NotebookOptions};
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: &str,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .body(Body::empty())
+        .unwrap();
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+#[tokio::test]
+async fn test_trash_restore_and_purge() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+    let other_user = server
+        .create_user("other_user", "other_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = server.create_token(other_user.id).await.unwrap();
+
+    let notebook = server
+        .create_notebook(user.id, NotebookOptions::new())
+        .await
+        .unwrap();
+    let uri = format!("/notebooks/{}", notebook.id);
+
+    let (status, _) = send(&mut app, http::Method::DELETE, &uri, &token).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&mut app, http::Method::GET, &uri, &token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let comments = format!("{}/comments", uri);
+    let (status, _) = send(&mut app, http::Method::GET, &comments, &token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, body) = send(&mut app, http::Method::GET, "/notebooks", &token).await;
+    assert_eq!(body["total"], 0);
+
+    let (status, body) = send(&mut app, http::Method::GET, "/user/trash", &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["notebooks"][0]["id"], notebook.id);
+    assert!(body["notebooks"][0]["purge_at"].is_string());
+
+    // The trash is private to the owner
+    let (_, body) = send(&mut app, http::Method::GET, "/user/trash", &other_token).await;
+    assert_eq!(body["total"], 0);
+
+    let restore = format!("/user/trash/{}/restore", notebook.id);
+    let (status, _) = send(&mut app, http::Method::POST, &restore, &other_token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (status, body) = send(&mut app, http::Method::POST, &restore, &token).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["id"], notebook.id);
+
+    let (status, _) = send(&mut app, http::Method::GET, &uri, &token).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = send(&mut app, http::Method::GET, &comments, &token).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Only trashed notebooks are purged
+    let purge = format!("/user/trash/{}", notebook.id);
+    let (status, _) = send(&mut app, http::Method::DELETE, &purge, &token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    send(&mut app, http::Method::DELETE, &uri, &token).await;
+    let (status, _) = send(&mut app, http::Method::DELETE, &purge, &token).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (status, _) = send(&mut app, http::Method::POST, &restore, &token).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_purge_expired_notebooks() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let mut notebooks = Vec::new();
+    for title in ["Expired", "Recent", "Kept"] {
+        let notebook = server
+            .create_notebook(user.id, NotebookOptions::new().with_title(title))
+            .await
+            .unwrap();
+        notebooks.push(notebook.id);
+    }
+    for id in &notebooks[..2] {
+        let (status, _) = send(
+            &mut app,
+            http::Method::DELETE,
+            &format!("/notebooks/{}", id),
+            &token,
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    sqlx::query("UPDATE notebooks SET deleted_at = datetime('now', '-31 days') WHERE id = $1")
+        .bind(notebooks[0])
+        .execute(server.get_db().pool())
+        .await
+        .unwrap();
+
+    assert_eq!(server.purge_expired(30).await.unwrap(), 1);
+
+    let (_, body) = send(&mut app, http::Method::GET, "/user/trash", &token).await;
+    assert_eq!(body["total"], 1);
+    assert_eq!(body["notebooks"][0]["id"], notebooks[1]);
+
+    server.refresh_rankings().await.unwrap();
+    let (_, body) = send(&mut app, http::Method::GET, "/notebooks", &token).await;
+    assert_eq!(body["total"], 1);
+}
@@ -0,0 +1,642 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+const FRAGMENT_SHADER: &str = r#"
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+fn render_content(shader_id: i64, entry_point: &str) -> Value {
+    json!({
+        "cells": [{
+            "id": "render",
+            "cell_type": "render",
+            "content": {
+                "width": 320,
+                "height": 240,
+                "shader_ids": [shader_id],
+                "resource_ids": [],
+                "pipeline": {
+                    "shader_bindings": [
+                        { "shader_index": 0, "shader_stage": "fragment", "entry_point": entry_point }
+                    ],
+                    "vertex_attributes": [],
+                    "resource_bindings": []
+                },
+                "camera": {
+                    "position": [0.0, 0.0, 3.0],
+                    "target": [0.0, 0.0, 0.0],
+                    "up": [0.0, 1.0, 0.0],
+                    "fov": 45.0,
+                    "near": 0.1,
+                    "far": 100.0
+                },
+                "performance": {}
+            }
+        }]
+    })
+}
+
+#[tokio::test]
+async fn test_notebook_reference_validation() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    // References to shaders outside the notebook are rejected on create
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Render Notebook",
+                        "description": null,
+                        "content": render_content(42, "fs_main"),
+                        "resources": [],
+                        "shaders": [],
+                        "tags": [],
+                        "visibility": "public"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["details"][0]["path"], "/cells/0/content/shader_ids/0");
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Render Notebook",
+                        "description": null,
+                        "content": { "cells": [] },
+                        "resources": [],
+                        "shaders": [{
+                            "notebook_id": 0,
+                            "name": "fragment",
+                            "shader_type": "wgsl",
+                            "code": FRAGMENT_SHADER
+                        }],
+                        "tags": [],
+                        "visibility": "public"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let notebook_id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+
+    // Entry points must be declared in the referenced shader
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, "main")
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["details"][0]["path"],
+        "/cells/0/content/pipeline/shader_bindings/0/entry_point"
+    );
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, "fs_main")
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+const GLSL_FRAGMENT_SHADER: &str = r#"#version 450
+layout(location = 0) out vec4 color;
+
+void main() {
+    color = vec4(1.0, 0.0, 0.0, 1.0);
+}
+"#;
+
+#[tokio::test]
+async fn test_glsl_shader_validation() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let create_notebook = |shader_type: &str| {
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/notebooks")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_vec(&json!({
+                    "title": "GLSL Notebook",
+                    "description": null,
+                    "content": { "cells": [] },
+                    "resources": [],
+                    "shaders": [{
+                        "notebook_id": 0,
+                        "name": "fragment",
+                        "shader_type": shader_type,
+                        "code": GLSL_FRAGMENT_SHADER
+                    }],
+                    "tags": [],
+                    "visibility": "public"
+                }))
+                .unwrap(),
+            ))
+            .unwrap()
+    };
+
+    // Shader types name a supported language
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(create_notebook("hlsl"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(create_notebook("glsl"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let notebook_id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+    assert_eq!(body["shaders"][0]["shader_type"], "glsl");
+
+    // GLSL shaders are compiled as GLSL and entered through `main`
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, "fs_main")
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(
+        body["details"][0]["path"],
+        "/cells/0/content/pipeline/shader_bindings/0/entry_point"
+    );
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, "main")
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+const NOISE_MODULE: &str = r#"
+fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}
+"#;
+
+const PALETTE_MODULE: &str = r#"#import "noise"
+fn palette(p: vec2<f32>) -> vec3<f32> {
+    return vec3<f32>(hash(p), 0.5, 1.0);
+}
+"#;
+
+#[tokio::test]
+async fn test_shader_import_validation() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let create_notebook = |shaders: Value, visibility: &str| {
+        Request::builder()
+            .method(http::Method::POST)
+            .uri("/notebooks")
+            .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(
+                serde_json::to_vec(&json!({
+                    "title": "Import Notebook",
+                    "description": null,
+                    "content": { "cells": [] },
+                    "resources": [],
+                    "shaders": shaders,
+                    "tags": [],
+                    "visibility": visibility
+                }))
+                .unwrap(),
+            ))
+            .unwrap()
+    };
+    let shader = |name: &str, code: &str| json!({ "notebook_id": 0, "name": name, "shader_type": "wgsl", "code": code });
+
+    // Shaders of public notebooks are published as library modules
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(create_notebook(
+            json!([
+                shader("noise", NOISE_MODULE),
+                shader("palette", PALETTE_MODULE)
+            ]),
+            "public",
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let library_id = body["id"].as_i64().unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!("/notebooks/{}/modules/palette", library_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["code"], PALETTE_MODULE);
+
+    // Imports of library modules resolve against their own notebook
+    let main_shader = format!(
+        "#import \"{}/palette\"\n#import \"tint\"\n@fragment\nfn fs_main() -> @location(0) vec4<f32> {{\n    return vec4<f32>(palette(vec2<f32>(0.0)) * tint(), 1.0);\n}}\n",
+        library_id
+    );
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(create_notebook(
+            json!([
+                shader("main", &main_shader),
+                shader("tint", "fn tint() -> f32 { return 0.5; }")
+            ]),
+            "private",
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let notebook_id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, "fs_main")
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // Shaders of private notebooks are not published
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri(format!("/notebooks/{}/modules/tint", notebook_id))
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Import cycles are rejected
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(create_notebook(
+            json!([
+                shader("a", "#import \"b\"\nfn a() {}"),
+                shader("b", "#import \"a\"\nfn b() {}")
+            ]),
+            "public",
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body["details"][0]["message"], "Import cycle a -> b -> a");
+}
+
+#[tokio::test]
+async fn test_update_requires_ownership_before_validation() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("owner", "owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let owner_token = server.create_token(owner.id).await.unwrap();
+    let other = server
+        .create_user("other", "other@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = server.create_token(other.id).await.unwrap();
+
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/notebooks")
+                .header(
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", owner_token),
+                )
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "title": "Private Notebook",
+                        "description": null,
+                        "content": { "cells": [] },
+                        "resources": [],
+                        "shaders": [{
+                            "notebook_id": 0,
+                            "name": "fragment",
+                            "shader_type": "wgsl",
+                            "code": FRAGMENT_SHADER
+                        }],
+                        "tags": [],
+                        "visibility": "private"
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    let notebook_id = body["id"].as_i64().unwrap();
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+
+    // Validation errors quoting the shaders of the notebook don't reach other users
+    let response = ServiceExt::<Request<Body>>::ready(&mut app)
+        .await
+        .unwrap()
+        .call(
+            Request::builder()
+                .method(http::Method::PATCH)
+                .uri(format!("/notebooks/{}", notebook_id))
+                .header(
+                    http::header::AUTHORIZATION,
+                    format!("Bearer {}", other_token),
+                )
+                .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body(Body::from(
+                    serde_json::to_vec(&json!({
+                        "content": render_content(shader_id, "main")
+                    }))
+                    .unwrap(),
+                ))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    let body: Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(body.get("details"), None);
+}
+
+const ANIMATED_SHADER: &str = r#"
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return vec4<f32>(fract(uniforms.time), 0.0, 0.0, 1.0);
+}
+"#;
+
+const UNIFORMS_SHADER: &str = r#"
+@group(0) @binding(0) var<uniform> uniforms: vec4<f32>;
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return uniforms;
+}
+"#;
+
+#[tokio::test]
+async fn test_shader_prelude_validation() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    // Shaders are checked with the prelude declaring the uniforms of the viewer
+    for (code, status) in [
+        (ANIMATED_SHADER, StatusCode::OK),
+        (UNIFORMS_SHADER, StatusCode::BAD_REQUEST),
+    ] {
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri("/notebooks")
+                    .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "title": "Animated Notebook",
+                            "description": null,
+                            "content": { "cells": [] },
+                            "resources": [],
+                            "shaders": [{
+                                "notebook_id": 0,
+                                "name": "fragment",
+                                "shader_type": "wgsl",
+                                "code": code
+                            }],
+                            "tags": [],
+                            "visibility": "public"
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let notebook_id = body["id"].as_i64().unwrap();
+        let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+
+        let response = ServiceExt::<Request<Body>>::ready(&mut app)
+            .await
+            .unwrap()
+            .call(
+                Request::builder()
+                    .method(http::Method::PATCH)
+                    .uri(format!("/notebooks/{}", notebook_id))
+                    .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(
+                        serde_json::to_vec(&json!({
+                            "content": render_content(shader_id, "fs_main")
+                        }))
+                        .unwrap(),
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), status);
+    }
+}
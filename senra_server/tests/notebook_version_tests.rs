@@ -59,7 +59,8 @@ async fn test_notebook_version_workflow() {
                     serde_json::to_vec(&json!({
                         "content": {
                             "cells": [{
-                                "type": "code",
+                                "id": "hello",
+                                "cell_type": "code",
                                 "content": "print('Hello World')"
                             }]
                         }
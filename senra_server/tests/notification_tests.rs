@@ -0,0 +1,252 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::{MockServer, NotebookOptions};
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+    let body = body.map_or(Body::empty(), |body| {
+        Body::from(serde_json::to_vec(&body).unwrap())
+    });
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request.body(body).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn kinds(body: &Value) -> Vec<&str> {
+    body["notifications"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|notification| notification["kind"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_notifications_for_likes_comments_and_mentions() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("owner", "owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let owner_token = server.create_token(owner.id).await.unwrap();
+    let reader = server
+        .create_user("reader", "reader@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader_token = server.create_token(reader.id).await.unwrap();
+    let friend = server
+        .create_user("friend", "friend@test.com", "test_password")
+        .await
+        .unwrap();
+    let friend_token = server.create_token(friend.id).await.unwrap();
+
+    let notebook = server
+        .create_notebook(owner.id, NotebookOptions::new())
+        .await
+        .unwrap();
+    let mut notifications = server.subscribe_notifications();
+
+    // Users are not notified of their own activity
+    let like = format!("/notebooks/{}/like", notebook.id);
+    send(&mut app, http::Method::POST, &like, &owner_token, None).await;
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications",
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(body["total"], 0);
+
+    let (status, _) = send(&mut app, http::Method::POST, &like, &reader_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let pushed = notifications.recv().await.unwrap();
+    assert_eq!(pushed.user_id, owner.id);
+    assert_eq!(pushed.kind, "like");
+    assert_eq!(pushed.actor, "reader");
+
+    let comments = format!("/notebooks/{}/comments", notebook.id);
+    let (_, comment) = send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &reader_token,
+        Some(json!({ "content": "Nice one, have a look @friend! (reader@test.com)" })),
+    )
+    .await;
+
+    // Replying to the owner's notebook notifies the comment author once, not as a mention
+    send(
+        &mut app,
+        http::Method::POST,
+        &comments,
+        &owner_token,
+        Some(json!({ "content": "Thanks @reader", "parent_id": comment["id"] })),
+    )
+    .await;
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications",
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(kinds(&body), ["comment", "like"]);
+    assert_eq!(body["unread"], 2);
+    assert_eq!(body["notifications"][0]["notebook_title"], "Test Notebook");
+    assert_eq!(body["notifications"][0]["comment_id"], comment["id"]);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications",
+        &reader_token,
+        None,
+    )
+    .await;
+    assert_eq!(kinds(&body), ["reply"]);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications",
+        &friend_token,
+        None,
+    )
+    .await;
+    assert_eq!(kinds(&body), ["mention"]);
+    assert_eq!(body["notifications"][0]["actor"], "reader");
+
+    // Marking specific notifications, then all of them, as read
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications",
+        &owner_token,
+        None,
+    )
+    .await;
+    let like_id = body["notifications"][1]["id"].clone();
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/notifications/read",
+        &owner_token,
+        Some(json!({ "ids": [like_id] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["unread"], 1);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications?unread=true",
+        &owner_token,
+        None,
+    )
+    .await;
+    assert_eq!(kinds(&body), ["comment"]);
+    assert_eq!(body["notifications"][0]["read"], false);
+
+    // Other users' notifications are left untouched
+    send(
+        &mut app,
+        http::Method::POST,
+        "/notifications/read",
+        &reader_token,
+        Some(json!({ "ids": [like_id] })),
+    )
+    .await;
+    let (_, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/notifications/read",
+        &owner_token,
+        Some(json!({})),
+    )
+    .await;
+    assert_eq!(body["unread"], 0);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications",
+        &reader_token,
+        None,
+    )
+    .await;
+    assert_eq!(body["unread"], 1);
+}
+
+#[tokio::test]
+async fn test_mentions_respect_notebook_visibility() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let owner = server
+        .create_user("owner", "owner@test.com", "test_password")
+        .await
+        .unwrap();
+    let owner_token = server.create_token(owner.id).await.unwrap();
+    let friend = server
+        .create_user("friend", "friend@test.com", "test_password")
+        .await
+        .unwrap();
+    let friend_token = server.create_token(friend.id).await.unwrap();
+
+    let notebook = server
+        .create_notebook(owner.id, NotebookOptions::new().with_visibility("private"))
+        .await
+        .unwrap();
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        &format!("/notebooks/{}/comments", notebook.id),
+        &owner_token,
+        Some(json!({ "content": "@friend @nobody" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notifications",
+        &friend_token,
+        None,
+    )
+    .await;
+    assert_eq!(body["total"], 0);
+}
@@ -0,0 +1,428 @@
+mod server;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use axum::body::Body;
+use axum::extract::ConnectInfo;
+use axum::http::{self, HeaderMap, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use senra_api::{RATE_LIMIT_LIMIT_HEADER, RATE_LIMIT_REMAINING_HEADER};
+use senra_server::{AppState, Config, Database, create_router};
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    send_from(app, None, method, uri, headers, body).await
+}
+
+/// Sends a request as if it came over a connection from `peer`
+async fn send_from(
+    app: &mut RouterIntoService<Body>,
+    peer: Option<&str>,
+    method: http::Method,
+    uri: &str,
+    headers: &[(&str, &str)],
+    body: Option<Value>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    for (name, value) in headers {
+        request = request.header(*name, *value);
+    }
+    if let Some(peer) = peer {
+        let peer: SocketAddr = format!("{}:40000", peer).parse().unwrap();
+        request = request.extension(ConnectInfo(peer));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    };
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (
+        status,
+        headers,
+        serde_json::from_slice(&body).unwrap_or(Value::Null),
+    )
+}
+
+async fn login(
+    app: &mut RouterIntoService<Body>,
+    username: &str,
+    password: &str,
+    headers: &[(&str, &str)],
+) -> (StatusCode, HeaderMap, Value) {
+    login_from(app, None, username, password, headers).await
+}
+
+async fn login_from(
+    app: &mut RouterIntoService<Body>,
+    peer: Option<&str>,
+    username: &str,
+    password: &str,
+    headers: &[(&str, &str)],
+) -> (StatusCode, HeaderMap, Value) {
+    send_from(
+        app,
+        peer,
+        http::Method::POST,
+        "/auth/login",
+        headers,
+        Some(json!({ "username": username, "password": password })),
+    )
+    .await
+}
+
+/// Server with limits tweaked by `configure`, sharing the database of the mock server
+async fn server_with(configure: impl FnOnce(&mut Config)) -> RouterIntoService<Body> {
+    let mut config = Config::default();
+    configure(&mut config);
+
+    let db = Database::new(&config).await.unwrap();
+    db.run_migrations().await.unwrap();
+    create_router(AppState::new(config, db)).into_service()
+}
+
+fn header<'a>(headers: &'a HeaderMap, name: &str) -> &'a str {
+    headers[name].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_auth_rate_limit_per_ip() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+    let client = Some("203.0.113.7");
+
+    // Distinct usernames so no account gets locked out on the way
+    for attempt in 0..10 {
+        let (status, headers, _) = login_from(
+            &mut app,
+            client,
+            &format!("ghost_{}", attempt),
+            "guess",
+            &[],
+        )
+        .await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert_eq!(header(&headers, RATE_LIMIT_LIMIT_HEADER), "10");
+        assert_eq!(
+            header(&headers, RATE_LIMIT_REMAINING_HEADER),
+            (9 - attempt).to_string()
+        );
+    }
+
+    let (status, headers, body) = login_from(&mut app, client, "ghost_10", "guess", &[]).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&headers, RATE_LIMIT_REMAINING_HEADER), "0");
+    let retry_after: u64 = header(&headers, "Retry-After").parse().unwrap();
+    assert!((1..=6).contains(&retry_after));
+    assert_eq!(body["details"]["retry_after"], retry_after);
+
+    // Other clients keep their own buckets
+    let (status, _, _) = login_from(&mut app, Some("198.51.100.1"), "ghost_10", "guess", &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Session refreshes are writes, not logins
+    let (status, headers, _) = send_from(
+        &mut app,
+        client,
+        http::Method::POST,
+        "/auth/verify",
+        &[],
+        Some(json!({ "token": "expired" })),
+    )
+    .await;
+    assert_ne!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&headers, RATE_LIMIT_LIMIT_HEADER), "300");
+
+    // Reads are never limited
+    let (status, headers, _) =
+        send_from(&mut app, client, http::Method::GET, "/livez", &[], None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(!headers.contains_key(RATE_LIMIT_LIMIT_HEADER));
+}
+
+#[tokio::test]
+async fn test_write_rate_limit_per_user() {
+    let mut app = server_with(|config| {
+        config.rate_limit.write.per_ip = None;
+        config.rate_limit.write.per_user = Some(senra_server::RateLimit {
+            requests: 3,
+            period: 60,
+        });
+    })
+    .await;
+
+    let mut tokens = Vec::new();
+    for name in ["writer", "other_writer"] {
+        let (status, _, body) = send(
+            &mut app,
+            http::Method::POST,
+            "/auth/register",
+            &[],
+            Some(json!({
+                "username": format!("rate_{}", name),
+                "email": format!("rate_{}@test.com", name),
+                "password": "test_password"
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        tokens.push(format!("Bearer {}", body["token"].as_str().unwrap()));
+    }
+
+    let notebook = json!({
+        "title": "Throttled",
+        "description": null,
+        "content": { "cells": [] },
+        "resources": [],
+        "shaders": [],
+        "tags": [],
+        "preview": null,
+        "visibility": "public"
+    });
+    for remaining in ["2", "1", "0"] {
+        let (status, headers, _) = send(
+            &mut app,
+            http::Method::POST,
+            "/notebooks",
+            &[("Authorization", &tokens[0])],
+            Some(notebook.clone()),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(header(&headers, RATE_LIMIT_REMAINING_HEADER), remaining);
+    }
+
+    let (status, headers, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/notebooks",
+        &[("Authorization", &tokens[0])],
+        Some(notebook.clone()),
+    )
+    .await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&headers, "Retry-After"), "20");
+
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/notebooks",
+        &[("Authorization", &tokens[1])],
+        Some(notebook),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_rejected_requests_keep_the_ip_budget() {
+    let mut app = server_with(|config| {
+        config.rate_limit.write.per_ip = Some(senra_server::RateLimit {
+            requests: 3,
+            period: 60,
+        });
+        config.rate_limit.write.per_user = Some(senra_server::RateLimit {
+            requests: 1,
+            period: 60,
+        });
+    })
+    .await;
+    let client = Some("203.0.113.9");
+
+    let mut tokens = Vec::new();
+    for name in ["greedy", "patient"] {
+        let (status, _, body) = send_from(
+            &mut app,
+            client,
+            http::Method::POST,
+            "/auth/register",
+            &[],
+            Some(json!({
+                "username": format!("budget_{}", name),
+                "email": format!("budget_{}@test.com", name),
+                "password": "test_password"
+            })),
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        tokens.push(format!("Bearer {}", body["token"].as_str().unwrap()));
+    }
+
+    let notebook = json!({
+        "title": "Budget",
+        "description": null,
+        "content": { "cells": [] },
+        "resources": [],
+        "shaders": [],
+        "tags": [],
+        "preview": null,
+        "visibility": "public"
+    });
+    let mut create = async |token: &str| {
+        send_from(
+            &mut app,
+            client,
+            http::Method::POST,
+            "/notebooks",
+            &[("Authorization", token)],
+            Some(notebook.clone()),
+        )
+        .await
+    };
+
+    let (status, _, _) = create(&tokens[0]).await;
+    assert_eq!(status, StatusCode::OK);
+    for _ in 0..3 {
+        let (status, headers, _) = create(&tokens[0]).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(header(&headers, RATE_LIMIT_LIMIT_HEADER), "1");
+    }
+
+    // Requests refused for the user took nothing from the address
+    let (status, headers, _) = create(&tokens[1]).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(header(&headers, RATE_LIMIT_REMAINING_HEADER), "0");
+}
+
+#[tokio::test]
+async fn test_login_lockout() {
+    let mut app = server_with(|config| {
+        config.rate_limit.auth.per_ip = None;
+        config.rate_limit.lockout.threshold = 3;
+        config.rate_limit.lockout.base_duration = 1;
+        config.rate_limit.lockout.max_duration = 60;
+    })
+    .await;
+
+    let (status, _, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/register",
+        &[],
+        Some(json!({
+            "username": "lockout_user",
+            "email": "lockout_user@test.com",
+            "password": "test_password"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+
+    for _ in 0..2 {
+        let (status, _, _) = login(&mut app, "lockout_user", "wrong_password", &[]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, headers, _) = login(&mut app, "lockout_user", "wrong_password", &[]).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&headers, "Retry-After"), "1");
+
+    // Even the right password is refused while locked
+    let (status, _, _) = login(&mut app, "lockout_user", "test_password", &[]).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    // Every further failure doubles the lockout
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let (status, headers, _) = login(&mut app, "lockout_user", "wrong_password", &[]).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(header(&headers, "Retry-After"), "2");
+
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    let (status, _, _) = login(&mut app, "lockout_user", "test_password", &[]).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A successful login forgets the failures
+    let (status, _, _) = login(&mut app, "lockout_user", "wrong_password", &[]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Unknown accounts get locked alike
+    for _ in 0..2 {
+        let (status, _, _) = login(&mut app, "nobody_here", "guess", &[]).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+    let (status, _, _) = login(&mut app, "nobody_here", "guess", &[]).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn test_rate_limit_forwarded_address() {
+    let mut app = server_with(|config| {
+        config.server.trusted_proxies = vec!["10.0.0.1".parse().unwrap()];
+        config.rate_limit.auth.per_ip = Some(senra_server::RateLimit {
+            requests: 3,
+            period: 60,
+        });
+    })
+    .await;
+
+    // Forwarded addresses of untrusted peers are ignored, so rotating them doesn't help
+    for attempt in 0..4 {
+        let forwarded_for = format!("192.0.2.{}", attempt);
+        let (status, _, _) = login_from(
+            &mut app,
+            Some("203.0.113.9"),
+            &format!("rotating_{}", attempt),
+            "guess",
+            &[
+                ("X-Forwarded-For", &forwarded_for),
+                ("X-Real-IP", &forwarded_for),
+            ],
+        )
+        .await;
+        let expected = match attempt {
+            0..3 => StatusCode::UNAUTHORIZED,
+            _ => StatusCode::TOO_MANY_REQUESTS,
+        };
+        assert_eq!(status, expected);
+    }
+
+    // Behind a trusted proxy, the rightmost address it didn't add itself is the client, so
+    // addresses prepended by the client don't help either
+    for attempt in 0..4 {
+        let forwarded_for = format!("192.0.2.{}, 198.51.100.20, 10.0.0.1", attempt);
+        let (status, _, _) = login_from(
+            &mut app,
+            Some("10.0.0.1"),
+            &format!("proxied_{}", attempt),
+            "guess",
+            &[("X-Forwarded-For", &forwarded_for)],
+        )
+        .await;
+        let expected = match attempt {
+            0..3 => StatusCode::UNAUTHORIZED,
+            _ => StatusCode::TOO_MANY_REQUESTS,
+        };
+        assert_eq!(status, expected);
+    }
+
+    // While other clients of the proxy keep their own buckets
+    let (status, _, _) = login_from(
+        &mut app,
+        Some("10.0.0.1"),
+        "proxied_other",
+        "guess",
+        &[("X-Forwarded-For", "198.51.100.21")],
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,81 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, HeaderMap, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use senra_api::{ErrorBody, REQUEST_ID_HEADER};
+use serde_json::Value;
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+async fn get(
+    app: &mut RouterIntoService<Body>,
+    uri: &str,
+    request_id: Option<&str>,
+) -> (StatusCode, HeaderMap, Value) {
+    let mut request = Request::builder().method(http::Method::GET).uri(uri);
+    if let Some(request_id) = request_id {
+        request = request.header(REQUEST_ID_HEADER, request_id);
+    }
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let headers = response.headers().clone();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (
+        status,
+        headers,
+        serde_json::from_slice(&body).unwrap_or(Value::Null),
+    )
+}
+
+fn request_id(headers: &HeaderMap) -> &str {
+    headers[REQUEST_ID_HEADER].to_str().unwrap()
+}
+
+#[tokio::test]
+async fn test_request_id_generated() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let (status, headers, _) = get(&mut app, "/livez", None).await;
+    assert_eq!(status, StatusCode::OK);
+    let first = request_id(&headers).to_string();
+    assert_eq!(first.len(), 36);
+
+    let (_, headers, _) = get(&mut app, "/livez", None).await;
+    assert_ne!(request_id(&headers), first);
+
+    // Error bodies carry the id of the response
+    let (status, headers, body) = get(&mut app, "/notebooks/999999", None).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let body: ErrorBody = serde_json::from_value(body).unwrap();
+    assert_eq!(body.request_id.as_deref(), Some(request_id(&headers)));
+}
+
+#[tokio::test]
+async fn test_request_id_propagated() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let (_, headers, _) = get(&mut app, "/livez", Some("client-42.retry:1")).await;
+    assert_eq!(request_id(&headers), "client-42.retry:1");
+
+    let (status, headers, body) = get(&mut app, "/notebooks/999999", Some("trace_abc")).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    assert_eq!(request_id(&headers), "trace_abc");
+    assert_eq!(body["request_id"], "trace_abc");
+
+    // Ids that could forge log lines or bloat them are replaced
+    for invalid in ["with spaces", "quote\"d", &"a".repeat(129)] {
+        let (_, headers, _) = get(&mut app, "/livez", Some(invalid)).await;
+        assert_ne!(request_id(&headers), invalid);
+        assert_eq!(request_id(&headers).len(), 36);
+    }
+}
@@ -96,6 +96,22 @@ impl MockServer {
         Ok(notebook)
     }
 
+    pub async fn migrate_stored_content(&self) -> Result<(u64, u64)> {
+        self.state.services.notebook.migrate_stored_content().await
+    }
+
+    pub async fn purge_expired(&self, retention_days: i64) -> Result<u64> {
+        self.state
+            .services
+            .notebook
+            .purge_expired(retention_days)
+            .await
+    }
+
+    pub async fn refresh_rankings(&self) -> Result<u64> {
+        self.state.services.notebook.refresh_rankings().await
+    }
+
     async fn update_notebook_stats(
         &self,
         notebook_id: i64,
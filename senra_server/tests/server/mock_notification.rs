@@ -0,0 +1,10 @@
+use senra_server::Notification;
+use tokio::sync::broadcast;
+
+use crate::server::MockServer;
+
+impl MockServer {
+    pub fn subscribe_notifications(&self) -> broadcast::Receiver<Notification> {
+        self.state.services.notification.subscribe()
+    }
+}
@@ -1,6 +1,7 @@
 #![allow(dead_code, unused_imports)]
 
 mod mock_notebook;
+mod mock_notification;
 mod mock_user;
 
 use axum::Router;
@@ -35,4 +36,8 @@ impl MockServer {
     pub fn get_db(&self) -> &Database {
         &self.state.db
     }
+
+    pub fn get_state(&self) -> &AppState {
+        &self.state
+    }
 }
@@ -0,0 +1,287 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+const NOISE_1_0: &str = r#"fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(p.x) * 43758.5453);
+}"#;
+
+const NOISE_1_1: &str = r#"fn hash(p: vec2<f32>) -> f32 {
+    return fract(sin(dot(p, vec2<f32>(12.9898, 78.233))) * 43758.5453);
+}"#;
+
+const SDF_1_0: &str = r#"#import "noise@1.0.0"
+fn sd_circle(p: vec2<f32>, r: f32) -> f32 {
+    return length(p) - r + hash(p) * 0.01;
+}"#;
+
+const MAIN_SHADER: &str = r#"#import "noise@1.1.0"
+#import "sdf@1.0.0"
+@fragment
+fn fs_main(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    return vec4<f32>(sd_circle(position.xy, 0.5) + hash(position.xy));
+}"#;
+
+async fn send(app: &mut RouterIntoService<Body>, request: Request<Body>) -> (StatusCode, Value) {
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn post(uri: &str, token: &str, body: Value) -> Request<Body> {
+    Request::builder()
+        .method(http::Method::POST)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(Body::from(serde_json::to_vec(&body).unwrap()))
+        .unwrap()
+}
+
+fn get(uri: &str) -> Request<Body> {
+    Request::builder()
+        .method(http::Method::GET)
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap()
+}
+
+fn notebook(shaders: &[(&str, &str)]) -> Value {
+    let shaders: Vec<Value> = shaders
+        .iter()
+        .map(|(name, code)| {
+            json!({ "notebook_id": 0, "name": name, "shader_type": "wgsl", "code": code })
+        })
+        .collect();
+
+    json!({
+        "title": "Package Notebook",
+        "description": null,
+        "content": { "cells": [] },
+        "resources": [],
+        "shaders": shaders,
+        "tags": [],
+        "visibility": "public"
+    })
+}
+
+/// Creates a notebook holding `code` and publishes the shader as `name@version`
+async fn publish_shader(
+    app: &mut RouterIntoService<Body>,
+    token: &str,
+    code: &str,
+    name: &str,
+    version: &str,
+) -> (StatusCode, Value) {
+    let (status, body) = send(app, post("/notebooks", token, notebook(&[(name, code)]))).await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+
+    send(
+        app,
+        post(
+            "/packages",
+            token,
+            json!({ "shader_id": shader_id, "name": name, "version": version, "description": null }),
+        ),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn test_publish_package_versions() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+    let other_user = server
+        .create_user("other_user", "other_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let other_token = server.create_token(other_user.id).await.unwrap();
+
+    let (status, body) = publish_shader(&mut app, &token, NOISE_1_0, "noise", "1.0.0").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["version"], "1.0.0");
+
+    let (status, body) = publish_shader(&mut app, &token, SDF_1_0, "sdf", "1.0.0").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["dependencies"], json!(["noise@1.0.0"]));
+
+    // Versions are immutable
+    let (status, _) = publish_shader(&mut app, &token, NOISE_1_1, "noise", "1.0.0").await;
+    assert_eq!(status, StatusCode::CONFLICT);
+
+    let update = sqlx::query("UPDATE shader_package_versions SET code = 'fn hash() {}'")
+        .execute(server.get_db().pool())
+        .await;
+    assert!(update.is_err());
+
+    // Only the owner publishes new versions of a package
+    let (status, _) = publish_shader(&mut app, &other_token, NOISE_1_1, "noise", "1.1.0").await;
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    let (status, _) = publish_shader(&mut app, &token, NOISE_1_1, "noise", "1.1.0").await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Packages are self-contained, valid and semantically versioned
+    for (code, version) in [("fn broken() { let x = ; }", "1.2.0"), (NOISE_1_1, "1.2")] {
+        let (status, _) = publish_shader(&mut app, &token, code, "noise", version).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    let (_, body) = send(
+        &mut app,
+        post(
+            "/notebooks",
+            &token,
+            notebook(&[
+                ("tinted", "#import \"tint\"\nfn f() {}"),
+                ("tint", "fn g() {}"),
+            ]),
+        ),
+    )
+    .await;
+    let shader_id = body["shaders"][0]["id"].as_i64().unwrap();
+    let (status, body) = send(
+        &mut app,
+        post(
+            "/packages",
+            &token,
+            json!({ "shader_id": shader_id, "name": "tinted", "version": "1.0.0", "description": null }),
+        ),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(
+        body["error"],
+        "Invalid shader data: Packages can only import other packages, found `tint`"
+    );
+
+    let (status, body) = send(&mut app, get("/packages/noise")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["versions"], json!(["1.0.0", "1.1.0"]));
+
+    let (status, body) = send(&mut app, get("/packages/noise/versions/1.0.0")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["code"], NOISE_1_0);
+}
+
+#[tokio::test]
+async fn test_notebook_package_dependencies() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    for (code, name, version) in [
+        (NOISE_1_0, "noise", "1.0.0"),
+        (NOISE_1_1, "noise", "1.1.0"),
+        (SDF_1_0, "sdf", "1.0.0"),
+    ] {
+        let (status, body) = publish_shader(&mut app, &token, code, name, version).await;
+        assert_eq!(status, StatusCode::OK, "{}", body);
+    }
+
+    // `sdf` pins noise 1.0.0 and the notebook 1.1.0, both resolve to 1.1.0
+    let (status, body) = send(
+        &mut app,
+        post("/notebooks", &token, notebook(&[("main", MAIN_SHADER)])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let notebook_id = body["id"].as_i64().unwrap();
+
+    let (status, body) = send(&mut app, get("/packages/noise/used-by")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["notebooks"],
+        json!([
+            // The notebook `sdf` was published from
+            { "notebook_id": notebook_id - 1, "title": "Package Notebook", "version": "1.0.0" },
+            { "notebook_id": notebook_id, "title": "Package Notebook", "version": "1.1.0" }
+        ])
+    );
+    assert_eq!(
+        body["packages"],
+        json!([{ "name": "sdf", "version": "1.0.0", "dependency_version": "1.0.0" }])
+    );
+
+    // Imports of unpublished or incompatible versions fail validation
+    for import in ["noise@1.2.0", "noise@2.0.0"] {
+        let code = format!("#import \"{}\"\n{}", import, MAIN_SHADER);
+        let (status, _) = send(
+            &mut app,
+            post("/notebooks", &token, notebook(&[("main", &code)])),
+        )
+        .await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
+
+#[tokio::test]
+async fn test_publisher_of_dependencies_is_kept() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let publisher = server
+        .create_user("publisher", "publisher@test.com", "test_password")
+        .await
+        .unwrap();
+    let publisher_token = server.create_token(publisher.id).await.unwrap();
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let (status, body) =
+        publish_shader(&mut app, &publisher_token, NOISE_1_0, "noise", "1.0.0").await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let code = "#import \"noise@1.0.0\"\nfn f() -> f32 {\n    return hash(vec2<f32>(0.0));\n}";
+    let (status, body) = send(
+        &mut app,
+        post("/notebooks", &token, notebook(&[("main", code)])),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let notebook_id = body["id"].as_i64().unwrap();
+
+    let delete = sqlx::query("DELETE FROM users WHERE id = $1")
+        .bind(publisher.id)
+        .execute(server.get_db().pool())
+        .await;
+    assert!(delete.is_err());
+
+    // The notebook of the other user still resolves its imports
+    let (status, body) = send(&mut app, get("/packages/noise/used-by")).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        body["notebooks"],
+        json!([{ "notebook_id": notebook_id, "title": "Package Notebook", "version": "1.0.0" }])
+    );
+    let (status, _) = send(&mut app, get("/packages/noise/versions/1.0.0")).await;
+    assert_eq!(status, StatusCode::OK);
+}
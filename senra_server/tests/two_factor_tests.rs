@@ -0,0 +1,370 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::MockServer;
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+use totp_rs::{Algorithm, Secret, TOTP};
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: Option<&str>,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let mut request = Request::builder().method(method).uri(uri);
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let request = match body {
+        Some(body) => request
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_vec(&body).unwrap()))
+            .unwrap(),
+        None => request.body(Body::empty()).unwrap(),
+    };
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn register(app: &mut RouterIntoService<Body>, username: &str) -> String {
+    let (status, body) = send(
+        app,
+        http::Method::POST,
+        "/auth/register",
+        None,
+        Some(json!({
+            "username": username,
+            "email": format!("{}@test.com", username),
+            "password": "test_password"
+        })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    body["token"].as_str().unwrap().to_string()
+}
+
+async fn login(app: &mut RouterIntoService<Body>, username: &str) -> (StatusCode, Value) {
+    send(
+        app,
+        http::Method::POST,
+        "/auth/login",
+        None,
+        Some(json!({ "username": username, "password": "test_password" })),
+    )
+    .await
+}
+
+async fn login_two_factor(
+    app: &mut RouterIntoService<Body>,
+    challenge: &str,
+    code: &str,
+) -> (StatusCode, Value) {
+    send(
+        app,
+        http::Method::POST,
+        "/auth/login/2fa",
+        None,
+        Some(json!({ "challenge": challenge, "code": code })),
+    )
+    .await
+}
+
+/// Code of a secret at a time step, counted from the current one
+fn code(secret: &str, offset: u64) -> String {
+    let totp = TOTP::new_unchecked(
+        Algorithm::SHA1,
+        6,
+        1,
+        30,
+        Secret::Encoded(secret.to_string()).to_bytes().unwrap(),
+        None,
+        String::new(),
+    );
+    let step = OffsetDateTime::now_utc().unix_timestamp() as u64 / 30 + offset;
+    totp.generate(step * 30)
+}
+
+/// Sets up and enables two-factor authentication, returning the secret and recovery codes
+async fn enable(app: &mut RouterIntoService<Body>, token: &str) -> (String, Vec<String>) {
+    let (status, setup) = send(
+        app,
+        http::Method::POST,
+        "/auth/2fa/setup",
+        Some(token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let secret = setup["secret"].as_str().unwrap().to_string();
+
+    let (status, body) = send(
+        app,
+        http::Method::POST,
+        "/auth/2fa/enable",
+        Some(token),
+        Some(json!({ "code": code(&secret, 0) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let recovery_codes = body["recovery_codes"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|code| code.as_str().unwrap().to_string())
+        .collect();
+
+    (secret, recovery_codes)
+}
+
+#[tokio::test]
+async fn test_two_factor_setup() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+    let token = register(&mut app, "tfa_setup").await;
+
+    let (status, body) = send(&mut app, http::Method::GET, "/auth/2fa", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "enabled": false, "recovery_codes_left": 0 }));
+
+    // Enabling needs a secret set up first
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/enable",
+        Some(&token),
+        Some(json!({ "code": "123456" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, setup) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/setup",
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let secret = setup["secret"].as_str().unwrap();
+    let uri = setup["provisioning_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/ShaderLab:tfa_setup?"));
+    assert!(uri.contains(&format!("secret={}", secret)));
+    assert!(setup["qr_code"].as_str().unwrap().contains("<svg"));
+
+    // Still disabled until a code is confirmed, so logins don't ask for one yet
+    let (status, body) = login(&mut app, "tfa_setup").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["token"].is_string());
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/enable",
+        Some(&token),
+        Some(json!({ "code": "000000" })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/enable",
+        Some(&token),
+        Some(json!({ "code": code(secret, 0) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["recovery_codes"].as_array().unwrap().len(), 10);
+
+    let (status, body) = send(&mut app, http::Method::GET, "/auth/2fa", Some(&token), None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body, json!({ "enabled": true, "recovery_codes_left": 10 }));
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/setup",
+        Some(&token),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::CONFLICT);
+}
+
+#[tokio::test]
+async fn test_two_factor_login() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+    let token = register(&mut app, "tfa_login").await;
+    let (secret, _) = enable(&mut app, &token).await;
+
+    let (status, body) = login(&mut app, "tfa_login").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.get("token").is_none());
+    assert_eq!(body["expires_in"], 300);
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    // The challenge is no session token
+    let (status, _) = send(&mut app, http::Method::GET, "/user", Some(&challenge), None).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/verify",
+        None,
+        Some(json!({ "token": challenge })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, _) = login_two_factor(&mut app, &challenge, "000000").await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Nor is a session token a challenge
+    let (status, _) = login_two_factor(&mut app, &token, &code(&secret, 1)).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let next_code = code(&secret, 1);
+    let (status, body) = login_two_factor(&mut app, &challenge, &next_code).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["user"]["username"], "tfa_login");
+    let session = body["token"].as_str().unwrap();
+
+    let (status, _) = send(&mut app, http::Method::GET, "/user", Some(session), None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // A code is only good once
+    let (status, _) = login_two_factor(&mut app, &challenge, &next_code).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_two_factor_recovery_codes() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+    let token = register(&mut app, "tfa_recovery").await;
+    let (_, recovery_codes) = enable(&mut app, &token).await;
+    assert!(
+        recovery_codes
+            .iter()
+            .all(|code| code.len() == 11 && code.as_bytes()[5] == b'-')
+    );
+
+    // Only keyed hashes are stored, neither the codes nor their plain digests
+    let hashes: Vec<String> = sqlx::query_scalar(
+        r#"
+        SELECT code_hash FROM user_recovery_codes
+        WHERE user_id = (SELECT id FROM users WHERE username = 'tfa_recovery')
+        "#,
+    )
+    .fetch_all(server.get_db().pool())
+    .await
+    .unwrap();
+    assert_eq!(hashes.len(), 10);
+    for code in &recovery_codes {
+        let normalized = code.replace('-', "");
+        let digest: String = Sha256::digest(normalized.as_bytes())
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert!(!hashes.contains(code) && !hashes.contains(&normalized));
+        assert!(!hashes.contains(&digest));
+    }
+
+    let (_, body) = login(&mut app, "tfa_recovery").await;
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    // Recovery codes are taken regardless of case and dashes, but only once
+    let typed = recovery_codes[0].to_uppercase().replace('-', " ");
+    let (status, _) = login_two_factor(&mut app, &challenge, &typed).await;
+    assert_eq!(status, StatusCode::OK);
+    let (status, _) = login_two_factor(&mut app, &challenge, &recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (_, body) = send(&mut app, http::Method::GET, "/auth/2fa", Some(&token), None).await;
+    assert_eq!(body["recovery_codes_left"], 9);
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/recovery-codes",
+        Some(&token),
+        Some(json!({ "code": recovery_codes[1] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    let regenerated = body["recovery_codes"].as_array().unwrap();
+    assert_eq!(regenerated.len(), 10);
+
+    // The previous codes are gone
+    let (status, _) = login_two_factor(&mut app, &challenge, &recovery_codes[2]).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/disable",
+        Some(&token),
+        Some(json!({ "code": regenerated[0] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["enabled"], false);
+
+    let (status, body) = login(&mut app, "tfa_recovery").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body["token"].is_string());
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/2fa/disable",
+        Some(&token),
+        Some(json!({ "code": regenerated[1] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_two_factor_lockout() {
+    let server = MockServer::new().await;
+    let mut app = server.into_service();
+    let token = register(&mut app, "tfa_lockout").await;
+    let (_, recovery_codes) = enable(&mut app, &token).await;
+
+    let (_, body) = login(&mut app, "tfa_lockout").await;
+    let challenge = body["challenge"].as_str().unwrap().to_string();
+
+    // Wrong codes count as failed logins, and logging in again doesn't forget them
+    for attempt in 0..4 {
+        let (status, _) = login_two_factor(&mut app, &challenge, "000000").await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        if attempt == 1 {
+            let (status, _) = login(&mut app, "tfa_lockout").await;
+            assert_eq!(status, StatusCode::OK);
+        }
+    }
+    let (status, _) = login_two_factor(&mut app, &challenge, "000000").await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+
+    let (status, _) = login_two_factor(&mut app, &challenge, &recovery_codes[0]).await;
+    assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+}
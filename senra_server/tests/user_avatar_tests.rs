@@ -0,0 +1,226 @@
+mod server;
+
+use std::io::Cursor;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use http_body_util::BodyExt;
+use image::{ImageFormat, Rgba, RgbaImage};
+use serde_json::{Value, json};
+use server::MockServer;
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(match body {
+            Some(body) => Body::from(serde_json::to_vec(&body).unwrap()),
+            None => Body::empty(),
+        })
+        .unwrap();
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+async fn download(app: &mut RouterIntoService<Body>, url: &str) -> Vec<u8> {
+    let request = Request::builder().uri(url).body(Body::empty()).unwrap();
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request)
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    response
+        .into_body()
+        .collect()
+        .await
+        .unwrap()
+        .to_bytes()
+        .to_vec()
+}
+
+fn variant_sizes(user: &Value) -> Vec<u64> {
+    user["avatar_variants"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|variant| variant["size"].as_u64().unwrap())
+        .collect()
+}
+
+fn variant_url(user: &Value, size: u64) -> String {
+    user["avatar_variants"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|variant| variant["size"] == size)
+        .map(|variant| variant["blob"]["url"].as_str().unwrap().to_string())
+        .unwrap()
+}
+
+/// 300x200 PNG, red on the left half and blue on the right
+fn wide_png() -> Vec<u8> {
+    let image = RgbaImage::from_fn(300, 200, |x, _| match x < 150 {
+        true => Rgba([255, 0, 0, 255]),
+        false => Rgba([0, 0, 255, 255]),
+    });
+    let mut bytes = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+#[tokio::test]
+async fn test_avatar_upload() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    // New users get an identicon in every size
+    let (status, body) = send(&mut app, http::Method::GET, "/user", &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(variant_sizes(&body), vec![64, 128, 256]);
+    let default_avatar = body["avatar"].clone();
+    assert_eq!(
+        default_avatar["url"].as_str().unwrap(),
+        variant_url(&body, 128)
+    );
+
+    let (status, body) = send(
+        &mut app,
+        http::Method::PATCH,
+        "/user",
+        &token,
+        Some(json!({ "avatar": wide_png() })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(variant_sizes(&body), vec![64, 128, 256]);
+    assert_ne!(body["avatar"], default_avatar);
+    assert_eq!(
+        body["avatar"]["url"].as_str().unwrap(),
+        variant_url(&body, 128)
+    );
+
+    // Variants are square WebP images cropped to the center
+    for size in [64, 128, 256] {
+        let data = download(&mut app, &variant_url(&body, size)).await;
+        assert_eq!(image::guess_format(&data).unwrap(), ImageFormat::WebP);
+
+        let image = image::load_from_memory(&data).unwrap().to_rgba8();
+        assert_eq!(image.dimensions(), (size as u32, size as u32));
+        assert_eq!(image.get_pixel(1, size as u32 / 2), &Rgba([255, 0, 0, 255]));
+        assert_eq!(
+            image.get_pixel(size as u32 - 2, size as u32 / 2),
+            &Rgba([0, 0, 255, 255])
+        );
+    }
+
+    // Resetting brings the identicon back
+    let (status, body) = send(&mut app, http::Method::DELETE, "/user/avatar", &token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["avatar"], default_avatar);
+    assert_eq!(variant_sizes(&body), vec![64, 128, 256]);
+}
+
+#[tokio::test]
+async fn test_avatar_validation() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let user = server
+        .create_user("test_user", "test_user@test.com", "test_password")
+        .await
+        .unwrap();
+    let token = server.create_token(user.id).await.unwrap();
+
+    let (_, body) = send(&mut app, http::Method::GET, "/user", &token, None).await;
+    let default_avatar = body["avatar"].clone();
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        "/user",
+        &token,
+        Some(json!({ "avatar": b"definitely not an image".to_vec() })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    // A truncated PNG is recognized but fails to decode
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        "/user",
+        &token,
+        Some(json!({ "avatar": wide_png()[..64].to_vec() })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let mut oversized = wide_png();
+    oversized.resize(1024 * 1024 + 1, 0);
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        "/user",
+        &token,
+        Some(json!({ "avatar": STANDARD.encode(&oversized) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Bodies past the limit are refused before decoding
+    oversized.resize(2 * 1024 * 1024, 0);
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        "/user",
+        &token,
+        Some(json!({ "avatar": STANDARD.encode(&oversized) })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+
+    // Rejected uploads leave the avatar and the other fields untouched
+    let (status, _) = send(
+        &mut app,
+        http::Method::PATCH,
+        "/user",
+        &token,
+        Some(json!({ "username": "renamed", "avatar": [0, 1, 2] })),
+    )
+    .await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (_, body) = send(&mut app, http::Method::GET, "/user", &token, None).await;
+    assert_eq!(body["avatar"], default_avatar);
+    assert_eq!(body["username"], "test_user");
+}
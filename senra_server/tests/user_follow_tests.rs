@@ -0,0 +1,226 @@
+mod server;
+
+use axum::body::Body;
+use axum::http::{self, Request, StatusCode};
+use axum::routing::RouterIntoService;
+use http_body_util::BodyExt;
+use serde_json::{Value, json};
+use server::{MockServer, NotebookOptions};
+use time::OffsetDateTime;
+use tower::{Service, ServiceExt};
+
+async fn send(
+    app: &mut RouterIntoService<Body>,
+    method: http::Method,
+    uri: &str,
+    token: &str,
+    body: Option<Value>,
+) -> (StatusCode, Value) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header(http::header::AUTHORIZATION, format!("Bearer {}", token))
+        .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+    let body = body.map_or(Body::empty(), |body| {
+        Body::from(serde_json::to_vec(&body).unwrap())
+    });
+    let response = ServiceExt::<Request<Body>>::ready(app)
+        .await
+        .unwrap()
+        .call(request.body(body).unwrap())
+        .await
+        .unwrap();
+
+    let status = response.status();
+    let body = response.into_body().collect().await.unwrap().to_bytes();
+    (status, serde_json::from_slice(&body).unwrap_or(Value::Null))
+}
+
+fn titles(body: &Value) -> Vec<&str> {
+    body["notebooks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|notebook| notebook["title"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_follow_and_following_feed() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let author = server
+        .create_user("author", "author@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader = server
+        .create_user("reader", "reader@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader_token = server.create_token(reader.id).await.unwrap();
+    let stranger = server
+        .create_user("stranger", "stranger@test.com", "test_password")
+        .await
+        .unwrap();
+
+    server
+        .create_notebook(author.id, NotebookOptions::new().with_title("Followed"))
+        .await
+        .unwrap();
+    server
+        .create_notebook(
+            author.id,
+            NotebookOptions::new()
+                .with_title("Private")
+                .with_visibility("private"),
+        )
+        .await
+        .unwrap();
+    server
+        .create_notebook(stranger.id, NotebookOptions::new().with_title("Stranger"))
+        .await
+        .unwrap();
+
+    let follow = format!("/user/{}/follow", author.id);
+    let (status, body) = send(&mut app, http::Method::POST, &follow, &reader_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["is_following"], true);
+    assert_eq!(body["follower_count"], 1);
+
+    // Following twice is idempotent
+    let (status, body) = send(&mut app, http::Method::POST, &follow, &reader_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["follower_count"], 1);
+
+    let own = format!("/user/{}/follow", reader.id);
+    let (status, _) = send(&mut app, http::Method::POST, &own, &reader_token, None).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let (status, _) = send(
+        &mut app,
+        http::Method::POST,
+        "/user/9999/follow",
+        &reader_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        &format!("/user/{}", author.id),
+        &reader_token,
+        None,
+    )
+    .await;
+    assert_eq!(body["follower_count"], 1);
+    assert_eq!(body["following_count"], 0);
+    assert_eq!(body["is_following"], true);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::POST,
+        "/auth/login",
+        "",
+        Some(json!({ "username": "reader", "password": "test_password" })),
+    )
+    .await;
+    assert_eq!(body["user"]["following_count"], 1);
+
+    // Only the public notebooks of followed authors are in the feed
+    let (status, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notebooks/following",
+        &reader_token,
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(titles(&body), vec!["Followed"]);
+    assert_eq!(body["total"], 1);
+
+    let (status, body) = send(&mut app, http::Method::DELETE, &follow, &reader_token, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["is_following"], false);
+    assert_eq!(body["follower_count"], 0);
+
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notebooks/following",
+        &reader_token,
+        None,
+    )
+    .await;
+    assert_eq!(body["total"], 0);
+}
+
+#[tokio::test]
+async fn test_recommendations_personalized_by_liked_tags() {
+    let mut server = MockServer::new().await;
+    let mut app = server.into_service();
+
+    let author = server
+        .create_user("author", "author@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader = server
+        .create_user("reader", "reader@test.com", "test_password")
+        .await
+        .unwrap();
+    let reader_token = server.create_token(reader.id).await.unwrap();
+
+    let updated_at = OffsetDateTime::now_utc();
+    let liked = server
+        .create_notebook_with_stats(
+            author.id,
+            NotebookOptions::new()
+                .with_title("Liked")
+                .with_tags(vec!["raymarching"]),
+            updated_at,
+            10,
+            1,
+            0,
+        )
+        .await
+        .unwrap();
+    for (title, tag) in [("Unrelated", "audio"), ("Related", "raymarching")] {
+        server
+            .create_notebook_with_stats(
+                author.id,
+                NotebookOptions::new()
+                    .with_title(title)
+                    .with_tags(vec![tag]),
+                updated_at,
+                100,
+                10,
+                0,
+            )
+            .await
+            .unwrap();
+    }
+
+    let like = format!("/notebooks/{}/like", liked.id);
+    send(&mut app, http::Method::POST, &like, &reader_token, None).await;
+    server.refresh_rankings().await.unwrap();
+
+    // Equally popular notebooks are ranked by the tags the reader liked
+    let (_, body) = send(
+        &mut app,
+        http::Method::GET,
+        "/notebooks",
+        &reader_token,
+        None,
+    )
+    .await;
+    let titles = titles(&body);
+    let related = titles.iter().position(|title| *title == "Related").unwrap();
+    let unrelated = titles
+        .iter()
+        .position(|title| *title == "Unrelated")
+        .unwrap();
+    assert!(related < unrelated, "{:?}", titles);
+}
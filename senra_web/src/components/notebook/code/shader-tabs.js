@@ -1,6 +1,13 @@
 import styles from './shader-tabs.module.css';
 import { createShaderEditor } from './shader-editor.js';
 
+/**
+ * Language a shader is written in, its `shader_type`
+ * @param {Object} shader - Shader data
+ * @returns {'wgsl' | 'glsl'} Shader language
+ */
+const shaderLanguage = (shader) => (shader.shader_type === 'glsl' ? 'glsl' : 'wgsl');
+
 /**
  * @typedef {Object} ShaderTabsOptions
  * @property {boolean} [readOnly=false] - Whether the editor is read-only
@@ -107,8 +114,8 @@ export function createShaderTabs(container, shaders, notebook, options = {}) {
         // Add shader icon based on type
         const icon = document.createElement('span');
         icon.className = styles.icon;
-        icon.textContent = shader.shader_type === 'vertex' ? '△' : '◆';
-        icon.title = shader.shader_type === 'vertex' ? 'Vertex Shader' : 'Fragment Shader';
+        icon.textContent = shaderLanguage(shader) === 'glsl' ? '△' : '◆';
+        icon.title = `${shaderLanguage(shader).toUpperCase()} Shader`;
         labelContainer.appendChild(icon);
 
         // Add shader name
@@ -121,7 +128,7 @@ export function createShaderTabs(container, shaders, notebook, options = {}) {
         // Add type indicator badge
         const typeIndicator = document.createElement('span');
         typeIndicator.className = styles.type;
-        typeIndicator.textContent = shader.shader_type === 'vertex' ? 'VERT' : 'FRAG';
+        typeIndicator.textContent = shaderLanguage(shader).toUpperCase();
         tab.appendChild(typeIndicator);
 
         tab.addEventListener('click', () => {
@@ -147,14 +154,14 @@ export function createShaderTabs(container, shaders, notebook, options = {}) {
         // Add editor info header
         const editorInfo = document.createElement('div');
         editorInfo.className = styles.info;
-        editorInfo.textContent = `${shader.name || `Shader ${shader.id}`} (${shader.shader_type === 'vertex' ? 'Vertex Shader' : 'Fragment Shader'})`;
+        editorInfo.textContent = `${shader.name || `Shader ${shader.id}`} (${shaderLanguage(shader).toUpperCase()} Shader)`;
         editorContainer.appendChild(editorInfo);
 
         contentContainer.appendChild(editorContainer);
 
         const editor = createShaderEditor(editorContainer, shader.code || '', {
             readOnly: componentOptions.readOnly,
-            language: shader.shader_type === 'fragment' ? 'wgsl' : 'wgsl',
+            language: shaderLanguage(shader),
             onChange: (content) => {
                 if (componentOptions.onChange) {
                     componentOptions.onChange({
@@ -33,7 +33,7 @@ export { createNotebookViewer } from './notebook-viewer.js';
  * @property {number} id - Shader ID
  * @property {number} notebook_id - Notebook ID
  * @property {string} name - Shader name
- * @property {string} shader_type - Shader type ('vertex' | 'fragment')
+ * @property {string} shader_type - Shader language ('wgsl' | 'glsl')
  * @property {string} code - Shader code
  */
 
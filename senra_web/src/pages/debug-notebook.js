@@ -265,28 +265,28 @@ export function createRendererTest() {
                 id: 1,
                 notebook_id: 1,
                 name: 'vertex-shader',
-                shader_type: 'vertex',
+                shader_type: 'wgsl',
                 code: DEFAULT_VERTEX_SHADER,
             },
             {
                 id: 2,
                 notebook_id: 1,
                 name: 'fragment-shader',
-                shader_type: 'fragment',
+                shader_type: 'wgsl',
                 code: DEFAULT_FRAGMENT_SHADER,
             },
             {
                 id: 3,
                 notebook_id: 1,
                 name: 'post-process-vertex',
-                shader_type: 'vertex',
+                shader_type: 'wgsl',
                 code: POST_PROCESS_VERTEX_SHADER,
             },
             {
                 id: 4,
                 notebook_id: 1,
                 name: 'post-process-fragment',
-                shader_type: 'fragment',
+                shader_type: 'wgsl',
                 code: POST_PROCESS_FRAGMENT_SHADER,
             },
         ],